@@ -0,0 +1,199 @@
+use crate::report::ReportFormat;
+
+/// 输出格式
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// 终端 ANSI 颜色
+    #[default]
+    Ansi,
+    /// tmux 状态栏 `#[fg=...]` 格式
+    Tmux,
+}
+
+impl OutputFormat {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "ansi" => Ok(OutputFormat::Ansi),
+            "tmux" => Ok(OutputFormat::Tmux),
+            _ => Err(format!("unknown format: {}", value)),
+        }
+    }
+}
+
+/// 子命令
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// 逐个诊断数据源，说明段为什么显示或不显示
+    Doctor,
+    /// 统计 `CC_STATUSLINE_TRACE` 记录的各阶段耗时
+    TraceSummarize,
+    /// 用固定输入预览所有内置主题
+    Themes,
+    /// 按项目汇总历史记录中的花费
+    Report,
+}
+
+/// 命令行参数
+#[derive(Debug, Default, PartialEq)]
+pub struct CliArgs {
+    /// 子命令，未指定时渲染 statusline
+    pub command: Option<Command>,
+    /// 从文件读取输入，`-` 表示 stdin
+    pub input: Option<String>,
+    /// 将解析后的输入以 JSON 输出到 stderr
+    pub dump_input: bool,
+    /// 不输出颜色
+    pub no_color: bool,
+    /// 颜色编码方式
+    pub format: OutputFormat,
+    /// 最大显示宽度
+    pub max_width: Option<usize>,
+    /// 以 JSON 输出渲染结果和数据源的执行结果
+    pub json: bool,
+    /// 删除数据源的缓存文件后退出
+    pub clear_cache: bool,
+    /// 检查输入的字段是否齐全、类型是否正确，不渲染
+    pub validate: bool,
+    /// 匹配 baseURL 的数据源各请求一次（不读缓存），输出结果和耗时后退出
+    pub check_providers: bool,
+    /// `report` 统计最近的天数
+    pub days: Option<u32>,
+    /// `report` 的输出格式（`report` 命令的 `--format`）
+    pub report_format: ReportFormat,
+}
+
+fn parse_width(value: &str) -> Result<usize, String> {
+    value.parse().map_err(|_| format!("invalid width: {}", value))
+}
+
+fn parse_days(value: &str) -> Result<u32, String> {
+    value.parse().ok().filter(|&days| days > 0).ok_or_else(|| format!("invalid days: {}", value))
+}
+
+/// `--format` 的值：`report` 命令中为报告格式（不接受颜色编码方式），否则为颜色编码方式
+fn parse_format(cli: &mut CliArgs, value: &str) -> Result<(), String> {
+    if cli.command == Some(Command::Report) {
+        cli.report_format = ReportFormat::parse(value)?;
+    } else {
+        cli.format = OutputFormat::parse(value)?;
+    }
+    Ok(())
+}
+
+/// 解析命令行参数（不含程序名）
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<CliArgs, String> {
+    let mut cli = CliArgs::default();
+    let mut args = args.into_iter();
+    // 子命令可以写在 `--format` 之后，解析完所有参数再按子命令解释
+    let mut format = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--input" => {
+                let path = args.next().ok_or("--input requires a file path")?;
+                cli.input = Some(path);
+            }
+            "--dump-input" => cli.dump_input = true,
+            "--no-color" => cli.no_color = true,
+            "--json" => cli.json = true,
+            "--clear-cache" => cli.clear_cache = true,
+            "--validate" => cli.validate = true,
+            "--check-providers" => cli.check_providers = true,
+            "doctor" if cli.command.is_none() => cli.command = Some(Command::Doctor),
+            "themes" if cli.command.is_none() => cli.command = Some(Command::Themes),
+            "report" if cli.command.is_none() => cli.command = Some(Command::Report),
+            "trace" if cli.command.is_none() => match args.next().as_deref() {
+                Some("summarize") => cli.command = Some(Command::TraceSummarize),
+                _ => return Err("trace requires a subcommand: summarize".to_string()),
+            },
+            "--format" => {
+                format = Some(args.next().ok_or("--format requires a value")?);
+            }
+            "--days" => {
+                let days = args.next().ok_or("--days requires a number")?;
+                cli.days = Some(parse_days(&days)?);
+            }
+            "--max-width" => {
+                let width = args.next().ok_or("--max-width requires a number")?;
+                cli.max_width = Some(parse_width(&width)?);
+            }
+            _ => {
+                if let Some(width) = arg.strip_prefix("--max-width=") {
+                    cli.max_width = Some(parse_width(width)?);
+                } else if let Some(path) = arg.strip_prefix("--input=") {
+                    cli.input = Some(path.to_string());
+                } else if let Some(value) = arg.strip_prefix("--format=") {
+                    format = Some(value.to_string());
+                } else if let Some(days) = arg.strip_prefix("--days=") {
+                    cli.days = Some(parse_days(days)?);
+                } else {
+                    return Err(format!("unknown argument: {}", arg));
+                }
+            }
+        }
+    }
+
+    if let Some(format) = format {
+        parse_format(&mut cli, &format)?;
+    }
+    Ok(cli)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<CliArgs, String> {
+        parse_args(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse(&[]).unwrap(), CliArgs::default());
+
+        let cli = parse(&["--input", "bug.json", "--dump-input", "--no-color"]).unwrap();
+        assert_eq!(cli.input.as_deref(), Some("bug.json"));
+        assert!(cli.dump_input);
+        assert!(cli.no_color);
+
+        assert_eq!(parse(&["--input=-"]).unwrap().input.as_deref(), Some("-"));
+        assert_eq!(parse(&["--format=tmux"]).unwrap().format, OutputFormat::Tmux);
+        assert_eq!(parse(&["--format", "ansi"]).unwrap().format, OutputFormat::Ansi);
+        assert_eq!(parse(&["--max-width", "80"]).unwrap().max_width, Some(80));
+        assert_eq!(parse(&["--max-width=60"]).unwrap().max_width, Some(60));
+        assert!(parse(&["--json"]).unwrap().json);
+        assert_eq!(parse(&["doctor"]).unwrap().command, Some(Command::Doctor));
+        assert_eq!(parse(&["themes", "--no-color"]).unwrap().command, Some(Command::Themes));
+        assert!(parse(&["--clear-cache"]).unwrap().clear_cache);
+        assert!(parse(&["--validate"]).unwrap().validate);
+        assert!(parse(&["--check-providers"]).unwrap().check_providers);
+        assert_eq!(parse(&["trace", "summarize"]).unwrap().command, Some(Command::TraceSummarize));
+
+        let cli = parse(&["report", "--days", "30", "--format", "csv"]).unwrap();
+        assert_eq!(cli.command, Some(Command::Report));
+        assert_eq!(cli.days, Some(30));
+        assert_eq!(cli.report_format, ReportFormat::Csv);
+        assert_eq!(cli.format, OutputFormat::Ansi);
+        assert_eq!(parse(&["report", "--format=json"]).unwrap().report_format, ReportFormat::Json);
+        assert_eq!(parse(&["report", "--days=1"]).unwrap().days, Some(1));
+        // 写在 `report` 之前的 `--format` 同样是报告格式
+        assert_eq!(parse(&["--format", "csv", "report"]).unwrap().report_format, ReportFormat::Csv);
+    }
+
+    #[test]
+    fn test_parse_args_errors() {
+        assert!(parse(&["--input"]).is_err());
+        assert!(parse(&["--bogus"]).is_err());
+        assert!(parse(&["doctor", "doctor"]).is_err());
+        assert!(parse(&["trace"]).is_err());
+        assert!(parse(&["trace", "show"]).is_err());
+        assert!(parse(&["--format=html"]).is_err());
+        assert!(parse(&["--max-width", "wide"]).is_err());
+        assert!(parse(&["report", "--format", "tmux"]).is_err());
+        assert!(parse(&["--format", "tmux", "report"]).is_err());
+        assert!(parse(&["--format=ansi", "report"]).is_err());
+        assert!(parse(&["report", "--days", "0"]).is_err());
+        assert!(parse(&["report", "--days"]).is_err());
+        assert!(parse(&["--format", "csv"]).is_err());
+    }
+}
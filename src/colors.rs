@@ -0,0 +1,526 @@
+use std::borrow::Cow;
+
+use crate::config::{Background, PalettePreset, StatuslineConfig, Style};
+use crate::render;
+
+pub const RESET: &str = "\x1b[0m";
+pub const BOLD: &str = "\x1b[1m";
+pub const DIM: &str = "\x1b[2m";
+
+pub const RED: &str = "\x1b[31m";
+pub const GREEN: &str = "\x1b[32m";
+pub const YELLOW: &str = "\x1b[33m";
+pub const BLUE: &str = "\x1b[34m";
+pub const MAGENTA: &str = "\x1b[35m";
+pub const CYAN: &str = "\x1b[36m";
+pub const BLACK: &str = "\x1b[30m";
+pub const GRAY: &str = "\x1b[90m";
+pub const ORANGE: &str = "\x1b[38;5;208m";
+
+/// Okabe-Ito 色盲友好配色的蓝、橙、朱红（256 色近似）
+pub const OKABE_BLUE: &str = "\x1b[38;5;25m";
+pub const OKABE_ORANGE: &str = "\x1b[38;5;214m";
+pub const VERMILLION: &str = "\x1b[38;5;166m";
+/// Okabe-Ito 配色的真彩色版本
+pub const OKABE_BLUE_RGB: &str = "\x1b[38;2;0;114;178m";
+pub const OKABE_ORANGE_RGB: &str = "\x1b[38;2;230;159;0m";
+pub const VERMILLION_RGB: &str = "\x1b[38;2;213;94;0m";
+/// 浅色背景使用的深色版本（256 色）
+pub const DARK_GREEN: &str = "\x1b[38;5;28m";
+pub const DARK_YELLOW: &str = "\x1b[38;5;136m";
+pub const DARK_CYAN: &str = "\x1b[38;5;30m";
+pub const DARK_GRAY: &str = "\x1b[38;5;240m";
+
+pub const BG_RED: &str = "\x1b[41m";
+pub const BG_GREEN: &str = "\x1b[42m";
+pub const BG_YELLOW: &str = "\x1b[43m";
+pub const BG_BLUE: &str = "\x1b[44m";
+pub const BG_MAGENTA: &str = "\x1b[45m";
+pub const BG_CYAN: &str = "\x1b[46m";
+pub const BG_GRAY: &str = "\x1b[100m";
+pub const BG_ORANGE: &str = "\x1b[48;5;208m";
+pub const BG_OKABE_BLUE: &str = "\x1b[48;5;25m";
+pub const BG_OKABE_ORANGE: &str = "\x1b[48;5;214m";
+pub const BG_VERMILLION: &str = "\x1b[48;5;166m";
+pub const BG_OKABE_BLUE_RGB: &str = "\x1b[48;2;0;114;178m";
+pub const BG_OKABE_ORANGE_RGB: &str = "\x1b[48;2;230;159;0m";
+pub const BG_VERMILLION_RGB: &str = "\x1b[48;2;213;94;0m";
+pub const BG_DARK_GREEN: &str = "\x1b[48;5;28m";
+pub const BG_DARK_YELLOW: &str = "\x1b[48;5;136m";
+pub const BG_DARK_CYAN: &str = "\x1b[48;5;30m";
+
+/// 终端是否声明支持真彩色（`COLORTERM=truecolor` 或 `24bit`）
+pub fn truecolor() -> bool {
+    std::env::var("COLORTERM").is_ok_and(|value| matches!(value.as_str(), "truecolor" | "24bit"))
+}
+
+/// 终端是否声明为浅色背景（`COLORFGBG`），未设置时按深色处理
+pub fn light_background() -> bool {
+    std::env::var("COLORFGBG").is_ok_and(|value| colorfgbg_is_light(&value))
+}
+
+/// `COLORFGBG` 形如 `fg;bg` 或 `fg;default;bg`，背景色为 7 或 9~15 时为浅色
+pub fn colorfgbg_is_light(value: &str) -> bool {
+    value
+        .rsplit(';')
+        .next()
+        .and_then(|bg| bg.trim().parse::<u8>().ok())
+        .is_some_and(|bg| bg == 7 || (9..=15).contains(&bg))
+}
+
+/// 阈值类指标的级别，在按阈值着色时记录到文本上，文本符号、`only_on_warn` 等按级别而不是颜色判断
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Ok,
+    Warn,
+    Crit,
+}
+
+/// 阈值类指标使用的颜色，默认正常绿色、警告黄色、严重红色
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub ok: &'static str,
+    pub warn: &'static str,
+    pub crit: &'static str,
+    /// 附在正常、警告、严重的文本后的符号，不依赖颜色（包括 `NO_COLOR`）也能区分
+    pub markers: Option<Markers>,
+    /// 浅色背景：其余段的黄色、绿色、青色和暗淡文本换成深色版本，见 [`Palette::resolve`]
+    pub light: bool,
+}
+
+/// 各级别的文本符号，None 表示该级别不加符号
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Markers {
+    pub ok: Option<char>,
+    pub warn: Option<char>,
+    pub crit: Option<char>,
+}
+
+impl Markers {
+    /// 默认符号：正常 `✓`、警告 `!`、严重 `✗`
+    pub const DEFAULT: Markers = Markers {
+        ok: Some('✓'),
+        warn: Some('!'),
+        crit: Some('✗'),
+    };
+}
+
+impl Default for Markers {
+    fn default() -> Self {
+        Markers::DEFAULT
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::dark()
+    }
+}
+
+impl Palette {
+    /// 深色背景的默认配色：绿 / 黄 / 红
+    pub const fn dark() -> Self {
+        Palette {
+            ok: GREEN,
+            warn: YELLOW,
+            crit: RED,
+            markers: None,
+            light: false,
+        }
+    }
+
+    /// 浅色背景的默认配色：深绿 / 深黄 / 红
+    pub const fn light() -> Self {
+        Palette {
+            ok: DARK_GREEN,
+            warn: DARK_YELLOW,
+            crit: RED,
+            markers: None,
+            light: true,
+        }
+    }
+
+    /// 色盲友好的 Okabe-Ito 配色：蓝 / 橙 / 朱红，并加上文本符号；`truecolor` 时使用真彩色
+    pub const fn colorblind(truecolor: bool) -> Self {
+        let (ok, warn, crit) = if truecolor {
+            (OKABE_BLUE_RGB, OKABE_ORANGE_RGB, VERMILLION_RGB)
+        } else {
+            (OKABE_BLUE, OKABE_ORANGE, VERMILLION)
+        };
+        Palette {
+            ok,
+            warn,
+            crit,
+            markers: Some(Markers::DEFAULT),
+            light: false,
+        }
+    }
+
+    /// 级别对应的颜色
+    pub fn color(&self, severity: Severity) -> &'static str {
+        match severity {
+            Severity::Ok => self.ok,
+            Severity::Warn => self.warn,
+            Severity::Crit => self.crit,
+        }
+    }
+
+    /// 文本实际输出的颜色：按阈值着色的文本取该级别的颜色，其余文本在浅色背景下把浅色换成深色版本
+    pub fn resolve(&self, color: &'static str, severity: Option<Severity>) -> &'static str {
+        if let Some(severity) = severity {
+            return self.color(severity);
+        }
+        if !self.light {
+            return color;
+        }
+        match color {
+            GREEN => DARK_GREEN,
+            YELLOW => DARK_YELLOW,
+            CYAN => DARK_CYAN,
+            DIM | GRAY => DARK_GRAY,
+            _ => color,
+        }
+    }
+
+    /// 级别对应的文本符号，未启用符号时为 None
+    pub fn marker(&self, severity: Severity) -> Option<char> {
+        let markers = self.markers?;
+        match severity {
+            Severity::Ok => markers.ok,
+            Severity::Warn => markers.warn,
+            Severity::Crit => markers.crit,
+        }
+    }
+}
+
+/// 一项指标的阈值
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Threshold {
+    /// 警告线
+    pub warn: f64,
+    /// 越低越好的指标达到该值为严重；越高越好的指标达到该值为正常
+    pub limit: f64,
+    /// 越高越好的指标，如缓存命中率
+    pub inverse: bool,
+}
+
+impl Threshold {
+    /// 越低越好的指标：达到 crit 为严重，达到 warn 为警告，否则为正常
+    pub const fn rising(warn: f64, crit: f64) -> Self {
+        Threshold {
+            warn,
+            limit: crit,
+            inverse: false,
+        }
+    }
+
+    /// 越高越好的指标：达到 good 为正常，达到 warn 为警告，否则为严重
+    pub const fn falling(warn: f64, good: f64) -> Self {
+        Threshold {
+            warn,
+            limit: good,
+            inverse: true,
+        }
+    }
+
+    /// 指标值对应的级别
+    pub fn severity(&self, value: f64) -> Severity {
+        match (self.inverse, value >= self.limit, value >= self.warn) {
+            (false, true, _) | (true, false, false) => Severity::Crit,
+            (_, false, true) => Severity::Warn,
+            _ => Severity::Ok,
+        }
+    }
+}
+
+/// 各阈值类指标的阈值
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Thresholds {
+    /// 上下文使用率（%）
+    pub context: Threshold,
+    /// 本轮上下文增长量（token）
+    pub growth: Threshold,
+    /// 缓存命中率（%），`cache_warn_pct` / `cache_good_pct` 可以覆盖
+    pub cache: Threshold,
+    /// 本轮花费（美元），达到 `turn_cost_alert` 为严重
+    pub turn_cost: Threshold,
+    /// 每个核心的平均负载（%）
+    pub load: Threshold,
+    /// 数据源额度的使用率（%）
+    pub quota: Threshold,
+}
+
+/// 内置主题共用的阈值
+pub const DEFAULT_THRESHOLDS: Thresholds = Thresholds {
+    context: Threshold::rising(60.0, 80.0),
+    growth: Threshold::rising(2_000.0, 10_000.0),
+    cache: Threshold::falling(50.0, 80.0),
+    turn_cost: Threshold::rising(0.5, 0.5),
+    load: Threshold::rising(70.0, 100.0),
+    quota: Threshold::rising(60.0, 80.0),
+};
+
+/// 数据源标签使用的图标
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Icons {
+    /// 文字标签，如 `ZAI`
+    Text,
+    /// Nerd Font 图标
+    NerdFont,
+}
+
+impl Icons {
+    /// 数据源的图标，文字标签或没有对应图标时为 None
+    pub fn provider(self, provider: &str) -> Option<&'static str> {
+        if self == Icons::Text {
+            return None;
+        }
+        match provider {
+            "zhipu" => Some("\u{f09d1}"),
+            "yunyi" => Some("\u{f0c2}"),
+            "packycode" => Some("\u{f487}"),
+            "gac" => Some("\u{f0d6}"),
+            "anthropic" => Some("\u{f06a9}"),
+            _ => None,
+        }
+    }
+}
+
+/// 颜色名称对应的 ANSI 代码（不区分大小写）
+pub fn by_name(name: &str) -> Option<&'static str> {
+    match name.to_ascii_lowercase().as_str() {
+        "red" => Some(RED),
+        "green" => Some(GREEN),
+        "yellow" => Some(YELLOW),
+        "blue" => Some(BLUE),
+        "magenta" => Some(MAGENTA),
+        "cyan" => Some(CYAN),
+        "orange" => Some(ORANGE),
+        "dim" | "gray" | "grey" => Some(DIM),
+        _ => None,
+    }
+}
+
+/// 前景色对应的背景色（DIM 等无颜色样式使用灰色背景）
+pub fn background(fg: &str) -> &'static str {
+    match fg {
+        RED => BG_RED,
+        GREEN => BG_GREEN,
+        YELLOW => BG_YELLOW,
+        BLUE => BG_BLUE,
+        MAGENTA => BG_MAGENTA,
+        CYAN => BG_CYAN,
+        ORANGE => BG_ORANGE,
+        OKABE_BLUE => BG_OKABE_BLUE,
+        OKABE_ORANGE => BG_OKABE_ORANGE,
+        VERMILLION => BG_VERMILLION,
+        OKABE_BLUE_RGB => BG_OKABE_BLUE_RGB,
+        OKABE_ORANGE_RGB => BG_OKABE_ORANGE_RGB,
+        VERMILLION_RGB => BG_VERMILLION_RGB,
+        DARK_GREEN => BG_DARK_GREEN,
+        DARK_YELLOW => BG_DARK_YELLOW,
+        DARK_CYAN => BG_DARK_CYAN,
+        _ => BG_GRAY,
+    }
+}
+
+/// 背景色对应的前景色，用于 Powerline 箭头
+pub fn foreground(bg: &str) -> &'static str {
+    match bg {
+        BG_RED => RED,
+        BG_GREEN => GREEN,
+        BG_YELLOW => YELLOW,
+        BG_BLUE => BLUE,
+        BG_MAGENTA => MAGENTA,
+        BG_CYAN => CYAN,
+        BG_ORANGE => ORANGE,
+        BG_OKABE_BLUE => OKABE_BLUE,
+        BG_OKABE_ORANGE => OKABE_ORANGE,
+        BG_VERMILLION => VERMILLION,
+        BG_OKABE_BLUE_RGB => OKABE_BLUE_RGB,
+        BG_OKABE_ORANGE_RGB => OKABE_ORANGE_RGB,
+        BG_VERMILLION_RGB => VERMILLION_RGB,
+        BG_DARK_GREEN => DARK_GREEN,
+        BG_DARK_YELLOW => DARK_YELLOW,
+        BG_DARK_CYAN => DARK_CYAN,
+        _ => GRAY,
+    }
+}
+
+/// 主题：分隔样式、阈值配色、背景、图标、分隔符与各指标阈值的组合
+///
+/// 渲染时使用的主题由配置组装，见 [`StatuslineConfig::theme`]；`themes` 子命令逐个预览内置主题。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub name: &'static str,
+    /// 分隔方式：` │ ` 或 Powerline 箭头
+    pub style: Style,
+    /// 配色的预设，与背景一起写入配置
+    pub preset: PalettePreset,
+    pub background: Background,
+    /// 正常、警告、严重三级的颜色与符号，渲染时按文本的级别取色
+    pub palette: Palette,
+    /// 数据源标签的图标
+    pub icons: Icons,
+    /// 普通样式的段分隔符
+    pub separator: Cow<'static, str>,
+    /// 各指标在何时进入警告、严重级别
+    pub thresholds: Thresholds,
+}
+
+/// 所有内置主题，按预览顺序排列
+pub const THEMES: &[Theme] = &[
+    Theme::new("default", Style::Plain, PalettePreset::Default, Background::Dark, Icons::Text),
+    Theme::new("light", Style::Plain, PalettePreset::Default, Background::Light, Icons::Text),
+    Theme::new("colorblind", Style::Plain, PalettePreset::Colorblind, Background::Dark, Icons::Text),
+    Theme::new("colorblind-light", Style::Plain, PalettePreset::Colorblind, Background::Light, Icons::Text),
+    Theme::new("nerd", Style::Plain, PalettePreset::Default, Background::Dark, Icons::NerdFont),
+    Theme::new("powerline", Style::Powerline, PalettePreset::Default, Background::Dark, Icons::Text),
+    Theme::new("powerline-light", Style::Powerline, PalettePreset::Default, Background::Light, Icons::Text),
+    Theme::new("powerline-colorblind", Style::Powerline, PalettePreset::Colorblind, Background::Dark, Icons::Text),
+    Theme::new("powerline-nerd", Style::Powerline, PalettePreset::Default, Background::Dark, Icons::NerdFont),
+];
+
+impl Theme {
+    const fn new(name: &'static str, style: Style, preset: PalettePreset, background: Background, icons: Icons) -> Self {
+        Theme {
+            name,
+            style,
+            preset,
+            background,
+            // 内置主题的背景都是确定的，预览时不使用真彩色
+            palette: preset.palette(matches!(background, Background::Light), false),
+            icons,
+            separator: Cow::Borrowed(render::SEPARATOR),
+            thresholds: DEFAULT_THRESHOLDS,
+        }
+    }
+
+    /// 把主题的各项写入配置，其余选项保持不变
+    pub fn apply(&self, config: &mut StatuslineConfig) {
+        config.style = self.style;
+        config.palette = self.preset;
+        config.background = self.background;
+        config.nerd_font = self.icons == Icons::NerdFont;
+        config.separator = Some(self.separator.to_string());
+        let cache = self.thresholds.cache;
+        config.cache_warn_pct = Some(cache.warn);
+        config.cache_good_pct = Some(cache.limit);
+        config.turn_cost_alert = Some(self.thresholds.turn_cost.limit);
+    }
+
+    /// 使用该主题需要写入 `statusline` 的选项（分隔符和阈值所有内置主题相同，不列出）
+    pub fn settings(&self) -> String {
+        let style = match self.style {
+            Style::Plain => "plain",
+            Style::Powerline => "powerline",
+        };
+        let palette = match self.preset {
+            PalettePreset::Default => "default",
+            PalettePreset::Colorblind => "colorblind",
+        };
+        let background = match self.background {
+            Background::Auto => "auto",
+            Background::Dark => "dark",
+            Background::Light => "light",
+        };
+        format!(
+            r#"{{"style": "{}", "palette": "{}", "background": "{}", "nerd_font": {}}}"#,
+            style,
+            palette,
+            background,
+            self.icons == Icons::NerdFont
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_colorfgbg() {
+        assert!(colorfgbg_is_light("0;15"));
+        assert!(colorfgbg_is_light("0;default;7"));
+        assert!(!colorfgbg_is_light("15;0"));
+        assert!(!colorfgbg_is_light("7;8"));
+        assert!(!colorfgbg_is_light("default"));
+        assert!(!colorfgbg_is_light(""));
+    }
+
+    #[test]
+    fn test_thresholds() {
+        let rising = Threshold::rising(60.0, 80.0);
+        assert_eq!(rising.severity(85.0), Severity::Crit);
+        assert_eq!(rising.severity(60.0), Severity::Warn);
+        assert_eq!(rising.severity(10.0), Severity::Ok);
+        let falling = Threshold::falling(50.0, 80.0);
+        assert_eq!(falling.severity(80.0), Severity::Ok);
+        assert_eq!(falling.severity(50.0), Severity::Warn);
+        assert_eq!(falling.severity(49.9), Severity::Crit);
+        // 警告线与严重线相同时只有两级
+        let alert = Threshold::rising(0.5, 0.5);
+        assert_eq!(alert.severity(0.5), Severity::Crit);
+        assert_eq!(alert.severity(0.49), Severity::Ok);
+
+        // 自定义配色按级别替换颜色
+        let palette = Palette {
+            ok: BLUE,
+            warn: CYAN,
+            crit: MAGENTA,
+            ..Default::default()
+        };
+        assert_eq!(palette.color(Severity::Crit), MAGENTA);
+        assert_eq!(palette.color(Severity::Warn), CYAN);
+        assert_eq!(palette.color(Severity::Ok), BLUE);
+    }
+
+    #[test]
+    fn test_provider_icons() {
+        assert_eq!(Icons::NerdFont.provider("zhipu"), Some("\u{f09d1}"));
+        assert_eq!(Icons::NerdFont.provider("openai"), None);
+        assert_eq!(Icons::Text.provider("zhipu"), None);
+    }
+
+    #[test]
+    fn test_markers_and_colors_follow_severity() {
+        // 自定义的正常颜色与普通段的颜色相同时，普通段不加符号、不按背景调整
+        let palette = Palette {
+            ok: CYAN,
+            light: true,
+            ..Palette::colorblind(false)
+        };
+        assert_eq!(palette.marker(Severity::Ok), Some('✓'));
+        assert_eq!(palette.marker(Severity::Crit), Some('✗'));
+        assert_eq!(Palette::default().marker(Severity::Crit), None);
+        assert_eq!(palette.resolve(CYAN, None), DARK_CYAN);
+        assert_eq!(palette.resolve(CYAN, Some(Severity::Ok)), CYAN);
+        // 按阈值着色的文本取当前配色中该级别的颜色，与生成段时记录的颜色无关
+        assert_eq!(palette.resolve(RED, Some(Severity::Crit)), VERMILLION);
+        assert_eq!(Palette::default().resolve(CYAN, None), CYAN);
+    }
+
+    #[test]
+    fn test_themes() {
+        let mut names: Vec<&str> = THEMES.iter().map(|theme| theme.name).collect();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), THEMES.len(), "theme names must be unique");
+
+        // 预览时显示的选项能还原出同样的配置，写入配置后组装出的主题与内置主题一致
+        for theme in THEMES {
+            let settings: serde_json::Value = serde_json::from_str(&theme.settings()).unwrap();
+            let parsed: StatuslineConfig = serde_json::from_value(settings).unwrap();
+            let mut applied = StatuslineConfig::default();
+            theme.apply(&mut applied);
+            assert_eq!(
+                (parsed.style, parsed.palette, parsed.background, parsed.nerd_font),
+                (applied.style, applied.palette, applied.background, applied.nerd_font),
+                "{}",
+                theme.name
+            );
+            assert_eq!(Theme { name: theme.name, ..applied.theme() }, *theme);
+            assert_eq!(Theme { name: theme.name, ..parsed.theme() }, *theme);
+        }
+    }
+}
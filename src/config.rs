@@ -0,0 +1,1034 @@
+use chrono::FixedOffset;
+use serde::Deserialize;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::colors::{self, Icons, Palette, Theme, Threshold, Thresholds};
+use crate::error::{self, StatuslineError};
+use crate::i18n::{Labels, Locale};
+use crate::layout;
+use crate::paths;
+use crate::render;
+
+/// 渲染样式
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Style {
+    /// 各段以 ` │ ` 分隔
+    #[default]
+    Plain,
+    /// Powerline 字体的箭头分隔、背景色块
+    Powerline,
+}
+
+/// 阈值类指标的预设配色（`statusline.palette`）
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PalettePreset {
+    /// 绿 / 黄 / 红
+    #[default]
+    Default,
+    /// Okabe-Ito 蓝 / 橙 / 朱红，文本后加上 `✓`、`!`、`✗`
+    Colorblind,
+}
+
+impl PalettePreset {
+    /// 预设在指定背景下的配色，`truecolor` 时使用真彩色
+    pub const fn palette(self, light: bool, truecolor: bool) -> Palette {
+        match self {
+            PalettePreset::Default if light => Palette::light(),
+            PalettePreset::Default => Palette::dark(),
+            PalettePreset::Colorblind => Palette {
+                light,
+                ..Palette::colorblind(truecolor)
+            },
+        }
+    }
+}
+
+/// 终端背景（`statusline.background`），决定默认配色
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Background {
+    /// 按 `COLORFGBG` 判断，未设置时按深色处理
+    #[default]
+    Auto,
+    Dark,
+    Light,
+}
+
+impl Background {
+    /// 是否为浅色背景，`auto` 时使用检测结果
+    pub fn is_light(self, detected: bool) -> bool {
+        match self {
+            Background::Auto => detected,
+            Background::Dark => false,
+            Background::Light => true,
+        }
+    }
+}
+
+/// token 数的显示格式（`statusline.token_format`）
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenFormat {
+    /// 缩写为 `k` / `M` / `B`，如 `1.2M`
+    #[default]
+    Abbrev,
+    /// 完整数字加千位分隔符，如 `1,234,567`
+    Grouped,
+    /// 完整数字，如 `1234567`
+    Raw,
+}
+
+/// token 统计的显示方式（`statusline.tokens_style`）
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TokensStyle {
+    /// 每项单独一段，如 `in:12.3k`
+    #[default]
+    Separate,
+    /// 合并为一段，如 `⛁ 12.3k↑ 1.1k↓ 48.0k⚡`（输入、输出、缓存读取），为 0 的项不显示
+    Compact,
+}
+
+/// 阈值类指标的颜色（`statusline.colors`），值为颜色名称，如 `magenta`
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct ColorsConfig {
+    /// 正常，默认 green
+    pub ok: Option<String>,
+    /// 警告，默认 yellow
+    pub warn: Option<String>,
+    /// 严重，默认 red
+    pub crit: Option<String>,
+}
+
+/// 预设配色附加的文本符号（`statusline.markers`），取第一个字符，空字符串表示不加符号
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct MarkersConfig {
+    /// 正常，默认 `✓`
+    pub ok: Option<String>,
+    /// 警告，默认 `!`
+    pub warn: Option<String>,
+    /// 严重，默认 `✗`
+    pub crit: Option<String>,
+}
+
+/// 额度类指标的显示方式
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum QuotaDisplay {
+    /// 使用率，如 `Token(5h):24%`
+    #[default]
+    Percent,
+    /// 已用量/总量，如 `Token(5h):1.2k/5.0k`
+    Absolute,
+    /// 两者都显示，如 `Token(5h):1.2k/5.0k (24%)`
+    Both,
+}
+
+/// 数据源在匹配顺序中的位置（`statusline.providers.<name>.position`）
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderPosition {
+    /// 排在内置顺序之前
+    First,
+    /// 排在内置顺序之后（包括兜底的 OpenAI 兼容接口）
+    Last,
+}
+
+/// git 段的各项功能开关（关闭后不再执行对应的 git 命令）
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct GitConfig {
+    /// 分支名
+    pub branch: bool,
+    /// 未提交文件数
+    pub dirty: bool,
+    /// 与上游的领先/落后提交数
+    pub ahead_behind: bool,
+    /// 分支名按工作区状态着色（干净绿色、有改动黄色、有冲突红色），关闭后固定为蓝色
+    pub status_color: bool,
+    /// 在仓库的子目录中时，目录段显示仓库根目录名而不是当前目录名
+    pub repo_name: bool,
+    /// 执行 git 命令前向上查找 `.git` 的最大层数，找不到时不执行任何 git 命令
+    pub search_depth: usize,
+    /// 向上查找 `.git` 时不越过 home 目录
+    pub stop_at_home: bool,
+}
+
+impl Default for GitConfig {
+    fn default() -> Self {
+        GitConfig {
+            branch: true,
+            dirty: true,
+            ahead_behind: true,
+            status_color: true,
+            repo_name: false,
+            search_depth: 16,
+            stop_at_home: true,
+        }
+    }
+}
+
+/// 单个数据源的选项（`statusline.providers.<name>`）
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct ProviderOptions {
+    /// 替换默认的前缀标签，如 `ZAI` → `Z.ai`
+    pub label: Option<String>,
+    /// 该数据源的前缀模板，覆盖 `provider_prefix`
+    pub prefix: Option<String>,
+    /// 额外匹配的 baseURL 片段，镜像站使用其他域名时配置
+    pub domains: Vec<String>,
+    /// 额度接口地址（GAC 等镜像站的站点地址与 API 地址不同）
+    pub base_url: Option<String>,
+    /// 登录 token 过期时用于换取新 token
+    pub refresh_token: Option<String>,
+    /// 额度的显示方式（目前用于智谱），接口没有返回用量时仍显示使用率
+    #[cfg_attr(not(feature = "provider-zhipu"), allow(dead_code))]
+    pub quota_display: QuotaDisplay,
+    /// 额度使用率每 10 分钟上升超过该百分点时加上 `↑↑`（目前用于智谱），默认 10，0 表示关闭
+    #[cfg_attr(not(feature = "provider-zhipu"), allow(dead_code))]
+    pub rise_alert: Option<f64>,
+    /// 显示接口返回的账号（目前用于云逸），多个账号切换时确认正在使用哪个
+    #[cfg_attr(not(feature = "provider-yunyi"), allow(dead_code))]
+    pub show_account: bool,
+    /// 调整该数据源的匹配顺序，未设置时按内置顺序
+    pub position: Option<ProviderPosition>,
+}
+
+/// 外部命令段（`statusline.segments.external` 中的一项）
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct ExternalSegment {
+    /// 可执行文件路径，`~/` 开头时相对 home 目录
+    pub command: String,
+    /// 命令参数
+    pub args: Vec<String>,
+    /// 等待输出的上限（毫秒），默认 200
+    pub timeout_ms: Option<u64>,
+    /// 保留输出中的 ANSI 颜色（默认去掉）
+    pub allow_ansi: bool,
+    /// 插入到该名称的段之后，未设置或找不到时放在最后
+    pub after: Option<String>,
+}
+
+/// 上下文使用率相关的配置（`statusline.context`）
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct ContextConfig {
+    /// 输入中没有窗口大小时按模型 id 子串推断使用的大小（最长匹配优先），优先于内置表
+    pub windows: HashMap<String, u64>,
+}
+
+/// 段相关的扩展配置（`statusline.segments`）
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct SegmentsConfig {
+    /// 由外部命令生成的段，按配置顺序插入
+    pub external: Vec<ExternalSegment>,
+}
+
+/// statusline 自定义选项（settings.json 中的 `statusline` 字段）
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct StatuslineConfig {
+    /// 上下文使用率显示的小数位数
+    pub ctx_decimals: usize,
+    /// 显示语言（en / zh），未设置时根据 LANG 推断
+    pub locale: Option<Locale>,
+    /// 渲染样式：plain / powerline
+    pub style: Style,
+    /// 不输出颜色（`--no-color` 或 `NO_COLOR` 也会开启）
+    pub no_color: bool,
+    /// 调试模式：显示输入缺失字段等诊断信息
+    pub debug: bool,
+    /// 显示运行环境（SSH / 容器 / WSL）
+    pub environment: bool,
+    /// 显示系统 1 分钟平均负载（仅 Linux / macOS）
+    pub load: bool,
+    /// 显示已配置的 MCP 服务器数量及命令缺失情况
+    pub mcp: bool,
+    /// 显示 transcript 中用户和助手的消息数
+    pub messages: bool,
+    /// transcript 超过该分钟数未更新时显示 `idle:12m`，未设置时不显示
+    pub idle_minutes: Option<u64>,
+    /// 缓存命中率低于该值时为红色，默认 50
+    pub cache_warn_pct: Option<f64>,
+    /// 缓存命中率达到该值时为绿色，默认 80
+    pub cache_good_pct: Option<f64>,
+    /// 显示本次会话的花费（`cost.total_cost_usd`），为 0 时不显示
+    pub cost: bool,
+    /// 会话花费低于该值（美元）时不显示，默认 0 即显示所有非零花费
+    pub min_cost_display: f64,
+    /// 在 cost 段显示本轮花费（距上次渲染的成本增量，按会话记录在本地）
+    pub turn_cost: bool,
+    /// 本轮花费达到该值（美元）时为红色，默认 0.50
+    pub turn_cost_alert: Option<f64>,
+    /// 显示本周花费（需要本地历史记录）
+    pub week_spend: bool,
+    /// 显示本月花费（需要本地历史记录）
+    pub month_spend: bool,
+    pub git: GitConfig,
+    /// 最大显示宽度，未设置时使用 `$COLUMNS`，都没有时不限制
+    pub max_width: Option<usize>,
+    /// 段优先级覆盖（宽度不足时优先级低的段先被丢弃）
+    pub priority: HashMap<String, i32>,
+    /// 有段被丢弃时在末尾显示 `…`
+    pub truncation_marker: bool,
+    /// 整行的最大可见宽度，超出时直接截断并以 `…` 结尾；宽度检测失败时的兜底，不设置时不限制
+    pub max_len: Option<usize>,
+    /// 放在右侧分组的段名称，如 `clock`；宽度已知时用空格推到行尾
+    pub right_segments: Vec<String>,
+    /// baseURL 不匹配已知数据源时，尝试 OpenAI 兼容的用量接口（`/v1/usage`、`/dashboard/billing/usage`）
+    pub openai_usage: bool,
+    /// 允许以明文 http 请求额度接口的主机（如内网自建网关），其余 http baseURL 升级为 https
+    pub plain_http_hosts: Vec<String>,
+    /// 段相关的扩展配置（外部命令段）
+    pub segments: SegmentsConfig,
+    /// 上下文窗口大小的推断配置
+    pub context: ContextConfig,
+    /// 阈值类指标的预设配色
+    pub palette: PalettePreset,
+    /// 检测到的终端是否支持真彩色（`COLORTERM`），不从配置读取
+    #[serde(skip)]
+    pub truecolor: bool,
+    /// 检测到的终端是否为浅色背景（`COLORFGBG`），`background` 为 `auto` 时使用，不从配置读取
+    #[serde(skip)]
+    pub light_terminal: bool,
+    /// 终端背景，浅色背景使用对比度更高的深色版本
+    pub background: Background,
+    /// 阈值类指标的颜色，覆盖预设配色中的对应项
+    pub colors: ColorsConfig,
+    /// 预设配色附加的文本符号，覆盖默认符号
+    pub markers: MarkersConfig,
+    /// 各数据源的选项，键为数据源名称（zhipu / yunyi / packycode / gac / anthropic）
+    pub providers: HashMap<String, ProviderOptions>,
+    /// 多个数据源匹配同一 baseURL 时，只显示按顺序第一个有段的数据源，默认 true；false 时全部显示
+    pub first_match_only: Option<bool>,
+    /// 数据源前缀模板，如 `[{label}]`（默认）、`{label}:`，空字符串表示不加前缀
+    pub provider_prefix: Option<String>,
+    /// 同一数据源的多项指标合并为一段，共用一个前缀
+    pub group_provider_metrics: bool,
+    /// 使用超过 60 秒的缓存时，在数据源后显示数据的更新时间，如 `(2m)`
+    pub provider_age: bool,
+    /// 数据源的百分比指标画成进度条，如 `[YUNYI] ▮▮▮▯▯ $3.80`
+    pub provider_bars: bool,
+    /// 使用 Nerd Font 图标代替数据源的默认标签
+    pub nerd_font: bool,
+    /// 同一会话两次渲染的最小间隔（毫秒），间隔内直接复用上次的输出；0 表示不启用
+    pub min_interval_ms: u64,
+    /// token 数的显示格式
+    pub token_format: TokenFormat,
+    /// token 统计分段显示还是合并为一段
+    pub tokens_style: TokensStyle,
+    /// 在 ctx 段显示本轮上下文增长量（按会话记录在本地）
+    pub ctx_growth: bool,
+    /// 显示每次渲染交替变化的心跳符号，数据源缓存超过 15 分钟未更新时标记 `~stale`
+    pub heartbeat: bool,
+    /// 在输出前加上设置终端标题的 OSC 0 序列，上下文或额度为严重级别时标题带 `⚠` 和对应的值
+    pub osc_title: bool,
+    /// 显示当前时间
+    pub clock: bool,
+    /// 时间格式（strftime），默认 `%H:%M`
+    pub clock_format: Option<String>,
+    /// 显示数据源过期时间使用的时区偏移，如 `+08:00`、`-05:00`、`UTC`；未设置时使用本地时区
+    pub timezone: Option<String>,
+    /// 指标日志路径（JSONL），每次渲染追加一行成本、token 数和上下文使用率
+    pub log: Option<String>,
+    /// 普通样式的段分隔符，可以为空字符串或 Powerline 字形，默认 ` │ `
+    pub separator: Option<String>,
+    /// 整行之前的文本，如 `❯ `
+    pub prefix: String,
+    /// 整行之后的文本
+    pub suffix: String,
+    /// 只在达到警告或严重级别时显示的段，如 `["ctx", "cache"]`；级别按各段现有的颜色阈值判断
+    pub only_on_warn: Vec<String>,
+    /// 只显示这些段并按列表顺序排列，来自 `STATUSLINE_SEGMENTS=model,ctx,git`
+    #[serde(skip)]
+    pub only_segments: Option<Vec<String>>,
+}
+
+impl StatuslineConfig {
+    /// 实际生效的最大宽度
+    pub fn effective_max_width(&self) -> Option<usize> {
+        self.max_width.or_else(|| std::env::var("COLUMNS").ok()?.trim().parse().ok())
+    }
+
+    /// 按配置组装的主题，渲染和各段的阈值都从这里读取
+    pub fn theme(&self) -> Theme {
+        let defaults = colors::DEFAULT_THRESHOLDS;
+        let cache = Threshold::falling(
+            self.cache_warn_pct.unwrap_or(defaults.cache.warn),
+            self.cache_good_pct.unwrap_or(defaults.cache.limit),
+        );
+        let alert = self.turn_cost_alert.unwrap_or(defaults.turn_cost.limit);
+        Theme {
+            name: "config",
+            style: self.style,
+            preset: self.palette,
+            background: self.background,
+            palette: self.palette(self.truecolor, self.light_terminal),
+            icons: if self.nerd_font { Icons::NerdFont } else { Icons::Text },
+            separator: self.separator.clone().map_or(Cow::Borrowed(render::SEPARATOR), Cow::Owned),
+            thresholds: Thresholds {
+                cache,
+                turn_cost: Threshold::rising(alert, alert),
+                ..defaults
+            },
+        }
+    }
+
+    /// 是否只显示第一个有段的数据源
+    pub fn first_match_only(&self) -> bool {
+        self.first_match_only.unwrap_or(true)
+    }
+
+    /// 配置的时区偏移，未设置或无法解析时为 None（使用本地时区）
+    #[cfg_attr(not(feature = "provider-yunyi"), allow(dead_code))]
+    pub fn timezone_offset(&self) -> Option<FixedOffset> {
+        let timezone = self.timezone.as_deref()?;
+        let offset = parse_offset(timezone);
+        if offset.is_none() {
+            let message = format!("invalid timezone {:?}, using local time", timezone);
+            crate::log::warn(StatuslineError::config("timezone", message));
+        }
+        offset
+    }
+
+    /// 阈值类指标使用的配色；颜色名称无法识别时使用默认颜色（读取配置时已记录），`truecolor` 时预设使用真彩色，
+    /// `light_terminal` 为检测到的终端背景，`background` 为 `auto` 时使用
+    pub fn palette(&self, truecolor: bool, light_terminal: bool) -> Palette {
+        let default = self.palette.palette(self.background.is_light(light_terminal), truecolor);
+        let color = |name: Option<&str>, default| name.and_then(colors::by_name).unwrap_or(default);
+        Palette {
+            ok: color(self.colors.ok.as_deref(), default.ok),
+            warn: color(self.colors.warn.as_deref(), default.warn),
+            crit: color(self.colors.crit.as_deref(), default.crit),
+            markers: default.markers.map(|markers| {
+                let marker = |symbol: Option<&str>, default| symbol.map_or(default, |symbol| symbol.chars().next());
+                colors::Markers {
+                    ok: marker(self.markers.ok.as_deref(), markers.ok),
+                    warn: marker(self.markers.warn.as_deref(), markers.warn),
+                    crit: marker(self.markers.crit.as_deref(), markers.crit),
+                }
+            }),
+            light: default.light,
+        }
+    }
+
+    /// 是否需要记录渲染历史
+    pub fn history_enabled(&self) -> bool {
+        self.week_spend || self.month_spend
+    }
+}
+
+impl StatuslineConfig {
+    /// 当前语言对应的文本
+    pub fn labels(&self) -> &'static Labels {
+        self.locale.unwrap_or_else(Locale::from_env).labels()
+    }
+}
+
+/// 解析 `UTC` / `Z` 或 `±HH[:MM]` 形式的时区偏移
+#[cfg_attr(not(feature = "provider-yunyi"), allow(dead_code))]
+fn parse_offset(value: &str) -> Option<FixedOffset> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("utc") || value.eq_ignore_ascii_case("z") {
+        return FixedOffset::east_opt(0);
+    }
+    let sign = match value.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let digits: String = value[1..].chars().filter(|c| *c != ':').collect();
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let (hours, minutes): (i32, i32) = match digits.len() {
+        1 | 2 => (digits.parse().ok()?, 0),
+        4 => (digits[..2].parse().ok()?, digits[2..].parse().ok()?),
+        _ => return None,
+    };
+    if hours > 14 || minutes >= 60 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Claude Code 配置文件结构
+#[derive(Debug, Deserialize, Default)]
+pub struct ClaudeConfig {
+    #[serde(rename = "baseURL")]
+    pub base_url: Option<String>,
+    #[serde(rename = "authToken")]
+    pub auth_token: Option<String>,
+    /// 固定使用的数据源名称（如 `yunyi`），跳过 baseURL 匹配；通常写在项目配置中
+    pub provider: Option<String>,
+    /// 凭据来自项目配置
+    #[serde(skip)]
+    pub project_credentials: bool,
+    /// 单独解析并叠加环境变量，见 `resolve_statusline_config`
+    #[serde(skip)]
+    pub statusline: StatuslineConfig,
+    /// 读取配置时遇到的错误（配置文件不存在除外），对应的项已退回默认值
+    #[serde(skip)]
+    pub errors: Vec<StatuslineError>,
+}
+
+/// 项目配置（`<项目>/.claude/settings.json` 与 `settings.local.json`）中可以覆盖的项
+#[derive(Debug, Deserialize, Default)]
+struct ProjectSettings {
+    #[serde(rename = "baseURL")]
+    base_url: Option<String>,
+    #[serde(rename = "authToken")]
+    auth_token: Option<String>,
+    provider: Option<String>,
+}
+
+impl ClaudeConfig {
+    /// 叠加一层项目配置，其中设置的项覆盖已有的值
+    ///
+    /// 项目中的 `baseURL` 只有与同一层的 `authToken` 一起设置时才生效，
+    /// 避免克隆的仓库把用户自己的 token 发往它指定的地址。
+    fn apply_project(&mut self, settings: ProjectSettings) {
+        match (settings.base_url, settings.auth_token) {
+            (Some(base_url), Some(auth_token)) => {
+                self.base_url = Some(base_url);
+                self.auth_token = Some(auth_token);
+                self.project_credentials = true;
+            }
+            (None, Some(auth_token)) => {
+                self.auth_token = Some(auth_token);
+                self.project_credentials = true;
+            }
+            (Some(_), None) => crate::log::warn(StatuslineError::config(
+                "baseURL",
+                "ignored project baseURL without an authToken in the same file",
+            )),
+            (None, None) => {}
+        }
+        self.provider = settings.provider.or(self.provider.take());
+    }
+
+    /// 获取自定义数据源凭据：优先配置文件，其次环境变量
+    pub fn credentials(&self) -> Option<(String, String)> {
+        self.credentials_with_source().map(|(base_url, auth_token, _)| (base_url, auth_token))
+    }
+
+    /// 凭据及其来源（`project settings`、`settings.json` 或 `environment`）
+    pub fn credentials_with_source(&self) -> Option<(String, String, &'static str)> {
+        if let (Some(base_url), Some(auth_token)) = (&self.base_url, &self.auth_token) {
+            let source = if self.project_credentials { "project settings" } else { "settings.json" };
+            return Some((base_url.clone(), auth_token.clone(), source));
+        }
+        let (base_url, auth_token) = env_credentials(|key| std::env::var(key).ok())?;
+        Some((base_url, auth_token, "environment"))
+    }
+}
+
+/// 环境变量中的凭据：token 优先取 `ANTHROPIC_AUTH_TOKEN`，其次 `ANTHROPIC_API_KEY`
+fn env_credentials(var: impl Fn(&str) -> Option<String>) -> Option<(String, String)> {
+    let base_url = var("ANTHROPIC_BASE_URL")?;
+    let auth_token = var("ANTHROPIC_AUTH_TOKEN").or_else(|| var("ANTHROPIC_API_KEY"))?;
+    Some((base_url, auth_token))
+}
+
+/// 读取 Claude Code 配置文件原始内容，文件不存在以外的错误记录到 `errors`
+fn read_settings_file(path: &Path, errors: &mut Vec<StatuslineError>) -> Option<Value> {
+    match error::read_json(path) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            if !err.is_not_found() {
+                errors.push(err);
+            }
+            None
+        }
+    }
+}
+
+/// 读取配置，优先级：环境变量 > settings.json > 默认值
+///
+/// 指定项目目录时，再依次叠加项目的 `.claude/settings.json` 和 `.claude/settings.local.json`
+/// 中的 `baseURL`、`authToken` 与 `provider`，不同项目可以使用不同的数据源。
+pub fn read_claude_config(project_dir: Option<&Path>) -> ClaudeConfig {
+    let mut errors = Vec::new();
+    let mut config = ClaudeConfig::default();
+    let mut settings = Value::Null;
+    if let Some(path) = paths::settings_path() {
+        settings = read_settings_file(&path, &mut errors).unwrap_or(Value::Null);
+        if !settings.is_null() {
+            match serde_json::from_value(settings.clone()) {
+                Ok(parsed) => config = parsed,
+                Err(err) => errors.push(StatuslineError::json(&path, err)),
+            }
+        }
+    }
+    for name in ["settings.json", "settings.local.json"] {
+        let Some(path) = project_dir.map(|dir| dir.join(".claude").join(name)) else {
+            break;
+        };
+        let Some(value) = read_settings_file(&path, &mut errors) else {
+            continue;
+        };
+        match serde_json::from_value(value) {
+            Ok(project) => config.apply_project(project),
+            Err(err) => errors.push(StatuslineError::json(&path, err)),
+        }
+    }
+
+    let file_options = settings.get("statusline").cloned().unwrap_or_default();
+    let env_options = std::env::vars().filter_map(|(key, value)| {
+        key.strip_prefix(ENV_PREFIX).map(|key| (key.to_string(), value))
+    });
+    config.statusline = resolve_statusline_config(file_options, env_options, &mut errors);
+    config.errors = errors;
+    config
+}
+
+/// 环境变量前缀，如 `STATUSLINE_CTX_DECIMALS=1`
+const ENV_PREFIX: &str = "STATUSLINE_";
+
+/// 将环境变量叠加到配置文件的 `statusline` 选项上
+///
+/// 变量名去掉前缀后转为小写作为键，`__` 表示嵌套（如 `STATUSLINE_GIT__DIRTY`）。
+/// 值能按 JSON 解析时按 JSON 处理（数字、布尔、数组），否则视为字符串；
+/// 类型不匹配的变量会被忽略，不影响其余选项，原因记录到 `errors`。
+/// `STATUSLINE_SEGMENTS` 的值不是 JSON 对象时视为逗号分隔的段名列表，见 `parse_segment_list`。
+fn resolve_statusline_config(
+    file_options: Value,
+    env_options: impl Iterator<Item = (String, String)>,
+    errors: &mut Vec<StatuslineError>,
+) -> StatuslineConfig {
+    let mut merged = match file_options {
+        Value::Object(map) => Value::Object(map),
+        _ => Value::Object(Default::default()),
+    };
+    let mut config: StatuslineConfig = match serde_json::from_value(merged.clone()) {
+        Ok(config) => config,
+        Err(err) => {
+            errors.push(StatuslineError::config("statusline", format!("{}, using defaults", err)));
+            merged = Value::Object(Default::default());
+            StatuslineConfig::default()
+        }
+    };
+
+    let mut only_segments = None;
+    for (key, raw) in env_options {
+        if key == "SEGMENTS" && !raw.trim_start().starts_with('{') {
+            only_segments = Some(parse_segment_list(&raw, errors));
+            continue;
+        }
+        let path: Vec<String> = key.to_ascii_lowercase().split("__").map(String::from).collect();
+        let parsed = serde_json::from_str(&raw).unwrap_or_else(|_| Value::String(raw.clone()));
+
+        let mut last_error = None;
+        for value in [parsed, Value::String(raw.clone())] {
+            let mut candidate = merged.clone();
+            set_path(&mut candidate, &path, value);
+            match serde_json::from_value(candidate.clone()) {
+                Ok(parsed_config) => {
+                    merged = candidate;
+                    config = parsed_config;
+                    last_error = None;
+                    break;
+                }
+                Err(err) => last_error = Some(err),
+            }
+        }
+        if let Some(err) = last_error {
+            let message = format!("invalid value {:?} ({}), ignored", raw, err);
+            errors.push(StatuslineError::config(format!("{}{}", ENV_PREFIX, key), message));
+        }
+    }
+
+    // 逐个变量合并时会重新反序列化，跳过序列化的字段最后再设置
+    config.only_segments = only_segments;
+    config.only_on_warn.retain(|name| {
+        let message = if !layout::SEGMENT_NAMES.contains(&name.as_str()) {
+            format!("unknown segment {:?}, ignored", name)
+        } else if !layout::THRESHOLD_SEGMENTS.contains(&name.as_str()) {
+            format!("segment {:?} has no thresholds, ignored", name)
+        } else {
+            return true;
+        };
+        errors.push(StatuslineError::config("statusline.only_on_warn", message));
+        false
+    });
+    let names = [("ok", &config.colors.ok), ("warn", &config.colors.warn), ("crit", &config.colors.crit)];
+    for (key, name) in names {
+        if let Some(name) = name.as_deref().filter(|name| colors::by_name(name).is_none()) {
+            let message = format!("unknown color {:?}, using default", name);
+            errors.push(StatuslineError::config(format!("statusline.colors.{}", key), message));
+        }
+    }
+    config.separator = config.separator.as_deref().map(unescape);
+    config.prefix = unescape(&config.prefix);
+    config.suffix = unescape(&config.suffix);
+    config
+}
+
+/// 展开文本中的转义序列：`\u{e0b0}`、`\n`、`\t`、`\\`；无法识别的序列原样保留
+///
+/// 环境变量和手写的 JSON 中不方便直接输入 Powerline 字形等字符。
+fn unescape(value: &str) -> String {
+    let mut out = String::new();
+    let mut rest = value;
+    while let Some(i) = rest.find('\\') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let unicode = rest
+            .strip_prefix("\\u{")
+            .and_then(|tail| tail.split_once('}'))
+            .and_then(|(hex, tail)| Some((char::from_u32(u32::from_str_radix(hex, 16).ok()?)?, tail)));
+        let (c, tail) = match unicode {
+            Some(unicode) => unicode,
+            None if rest.starts_with("\\n") => ('\n', &rest[2..]),
+            None if rest.starts_with("\\t") => ('\t', &rest[2..]),
+            None if rest.starts_with("\\\\") => ('\\', &rest[2..]),
+            None => ('\\', &rest[1..]),
+        };
+        out.push(c);
+        rest = tail;
+    }
+    out.push_str(rest);
+    out
+}
+
+/// 解析逗号分隔的段名列表，去掉空项和重复项；未知的段名忽略并记录到 `errors`
+fn parse_segment_list(raw: &str, errors: &mut Vec<StatuslineError>) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for name in raw.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+        if !layout::SEGMENT_NAMES.contains(&name) {
+            let message = format!("unknown segment {:?}, ignored", name);
+            errors.push(StatuslineError::config(format!("{}SEGMENTS", ENV_PREFIX), message));
+        } else if !names.iter().any(|existing| existing == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+/// 按路径写入嵌套的 JSON 对象
+fn set_path(target: &mut Value, path: &[String], value: Value) {
+    let Some((last, parents)) = path.split_last() else {
+        return;
+    };
+    let mut current = target;
+    for key in parents {
+        if !current.get(key).is_some_and(Value::is_object) {
+            current[key.as_str()] = Value::Object(Default::default());
+        }
+        current = &mut current[key.as_str()];
+    }
+    current[last.as_str()] = value;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_statusline_options() {
+        let json = r#"{
+            "baseURL": "https://api.z.ai/api/anthropic",
+            "statusline": {"ctx_decimals": 1, "locale": "zh"}
+        }"#;
+        let settings: Value = serde_json::from_str(json).unwrap();
+        let config: ClaudeConfig = serde_json::from_value(settings.clone()).unwrap();
+        assert_eq!(config.base_url.as_deref(), Some("https://api.z.ai/api/anthropic"));
+
+        let options = resolve_statusline_config(settings["statusline"].clone(), std::iter::empty(), &mut Vec::new());
+        assert_eq!(options.ctx_decimals, 1);
+        assert_eq!(options.locale, Some(Locale::Zh));
+
+        let options = resolve_statusline_config(Value::Null, std::iter::empty(), &mut Vec::new());
+        assert_eq!(options.ctx_decimals, 0);
+        assert!(options.git.branch && options.git.dirty && options.git.ahead_behind);
+    }
+
+    #[test]
+    fn test_provider_label_override() {
+        let file = serde_json::json!({"providers": {"zhipu": {"label": "Z.ai"}}});
+        let options = resolve_statusline_config(file, env(&[("PROVIDERS__YUNYI__LABEL", "YY")]), &mut Vec::new());
+        assert_eq!(options.providers["zhipu"].label.as_deref(), Some("Z.ai"));
+        assert_eq!(options.providers["yunyi"].label.as_deref(), Some("YY"));
+    }
+
+    #[test]
+    fn test_git_flags_partial() {
+        let file = serde_json::json!({"git": {"dirty": false}});
+        let options = resolve_statusline_config(file, env(&[("GIT__AHEAD_BEHIND", "false")]), &mut Vec::new());
+        assert!(options.git.branch);
+        assert!(!options.git.dirty);
+        assert!(!options.git.ahead_behind);
+    }
+
+    fn env(pairs: &[(&str, &str)]) -> impl Iterator<Item = (String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn test_env_overrides_file() {
+        let file = serde_json::json!({"ctx_decimals": 1, "locale": "zh"});
+        let options = resolve_statusline_config(file, env(&[("CTX_DECIMALS", "2")]), &mut Vec::new());
+        assert_eq!(options.ctx_decimals, 2);
+        assert_eq!(options.locale, Some(Locale::Zh));
+
+        // 无配置文件时仅用环境变量
+        let options = resolve_statusline_config(Value::Null, env(&[("LOCALE", "zh")]), &mut Vec::new());
+        assert_eq!(options.locale, Some(Locale::Zh));
+    }
+
+    #[test]
+    fn test_env_invalid_value_ignored() {
+        let file = serde_json::json!({"ctx_decimals": 1});
+        let mut errors = Vec::new();
+        let options = resolve_statusline_config(
+            file,
+            env(&[("CTX_DECIMALS", "many"), ("LOCALE", "en"), ("UNKNOWN", "x")]),
+            &mut errors,
+        );
+        assert_eq!(options.ctx_decimals, 1);
+        assert_eq!(options.locale, Some(Locale::En));
+        // 被忽略的变量记录为配置错误
+        let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("STATUSLINE_CTX_DECIMALS: invalid value \"many\""), "{:?}", errors);
+
+        // 配置文件中的类型错误不会静默丢弃整个配置
+        let mut errors = Vec::new();
+        let file = serde_json::json!({"ctx_decimals": "one"});
+        assert_eq!(resolve_statusline_config(file, std::iter::empty(), &mut errors).ctx_decimals, 0);
+        assert!(errors[0].to_string().starts_with("statusline: "));
+    }
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(unescape(r"\u{e0b0}"), "\u{e0b0}");
+        assert_eq!(unescape(r" \u{276F} "), " ❯ ");
+        assert_eq!(unescape(r"\n|\t|\\"), "\n|\t|\\");
+        // 无法识别的序列原样保留
+        assert_eq!(unescape(r"\u{zz} \x \u{110000}"), r"\u{zz} \x \u{110000}");
+        assert_eq!(unescape(r"trailing\"), r"trailing\");
+        assert_eq!(unescape("│"), "│");
+    }
+
+    #[test]
+    fn test_separator_and_decorations_from_env() {
+        let mut errors = Vec::new();
+        let file = serde_json::json!({"separator": "\\u{e0b1}", "prefix": "❯ "});
+        let options = resolve_statusline_config(file, env(&[("SUFFIX", r"\n")]), &mut errors);
+        assert_eq!(options.separator.as_deref(), Some("\u{e0b1}"));
+        assert_eq!(options.prefix, "❯ ");
+        assert_eq!(options.suffix, "\n");
+        assert!(errors.is_empty());
+
+        let options = resolve_statusline_config(Value::Null, env(&[("SEPARATOR", "")]), &mut errors);
+        assert_eq!(options.separator.as_deref(), Some(""));
+        assert_eq!(StatuslineConfig::default().separator, None);
+    }
+
+    #[test]
+    fn test_env_segment_list() {
+        let mut errors = Vec::new();
+        let options = resolve_statusline_config(
+            Value::Null,
+            env(&[("SEGMENTS", " ctx, model,,bogus,ctx"), ("CTX_DECIMALS", "1")]),
+            &mut errors,
+        );
+        assert_eq!(options.only_segments, Some(vec!["ctx".to_string(), "model".to_string()]));
+        assert_eq!(options.ctx_decimals, 1);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].to_string().starts_with("STATUSLINE_SEGMENTS: unknown segment \"bogus\""));
+
+        // JSON 对象仍然覆盖 `segments` 配置
+        let file = serde_json::json!({"segments": {"external": [{"command": "echo hi"}]}});
+        let segments = env(&[("SEGMENTS", r#"{"external": []}"#)]);
+        let options = resolve_statusline_config(file, segments, &mut errors);
+        assert!(options.segments.external.is_empty());
+        assert_eq!(options.only_segments, None);
+    }
+
+    #[test]
+    fn test_cache_thresholds() {
+        let options = resolve_statusline_config(Value::Null, std::iter::empty(), &mut Vec::new());
+        assert_eq!(options.theme().thresholds, colors::DEFAULT_THRESHOLDS);
+
+        let file = serde_json::json!({"cache_good_pct": 95, "turn_cost_alert": 2});
+        let options = resolve_statusline_config(file, env(&[("CACHE_WARN_PCT", "70.5")]), &mut Vec::new());
+        let thresholds = options.theme().thresholds;
+        assert_eq!(thresholds.cache, Threshold::falling(70.5, 95.0));
+        assert_eq!(thresholds.turn_cost.severity(1.5), colors::Severity::Ok);
+        assert_eq!(thresholds.context, colors::DEFAULT_THRESHOLDS.context);
+    }
+
+    #[test]
+    fn test_project_settings_override_user_settings() {
+        let user = serde_json::json!({"baseURL": "https://open.bigmodel.cn/api/anthropic", "authToken": "user"});
+        let mut config: ClaudeConfig = serde_json::from_value(user).unwrap();
+        assert_eq!(config.credentials_with_source().unwrap().2, "settings.json");
+
+        let project = serde_json::json!({"authToken": "project", "provider": "yunyi"});
+        config.apply_project(serde_json::from_value(project).unwrap());
+        let (base_url, auth_token, source) = config.credentials_with_source().unwrap();
+        assert_eq!((base_url.as_str(), auth_token.as_str()), ("https://open.bigmodel.cn/api/anthropic", "project"));
+        assert_eq!(source, "project settings");
+        assert_eq!(config.provider.as_deref(), Some("yunyi"));
+
+        let local = serde_json::json!({"baseURL": "https://yunyi.cfd/api/anthropic", "authToken": "local"});
+        config.apply_project(serde_json::from_value(local).unwrap());
+        let (base_url, auth_token, _) = config.credentials_with_source().unwrap();
+        assert_eq!((base_url.as_str(), auth_token.as_str()), ("https://yunyi.cfd/api/anthropic", "local"));
+    }
+
+    #[test]
+    fn test_project_base_url_requires_project_token() {
+        let user = serde_json::json!({"baseURL": "https://open.bigmodel.cn/api/anthropic", "authToken": "user"});
+        let mut config: ClaudeConfig = serde_json::from_value(user).unwrap();
+
+        // 只有 baseURL 的项目配置不能把用户的 token 发往其他地址
+        let project = serde_json::json!({"baseURL": "https://evil.example.com"});
+        config.apply_project(serde_json::from_value(project).unwrap());
+        let (base_url, auth_token, source) = config.credentials_with_source().unwrap();
+        assert_eq!((base_url.as_str(), auth_token.as_str()), ("https://open.bigmodel.cn/api/anthropic", "user"));
+        assert_eq!(source, "settings.json");
+    }
+
+    #[test]
+    fn test_env_credentials() {
+        fn vars<'a>(pairs: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<String> + 'a {
+            move |key| pairs.iter().find(|(name, _)| *name == key).map(|(_, value)| value.to_string())
+        }
+        let base_url = ("ANTHROPIC_BASE_URL", "https://open.bigmodel.cn/api/anthropic");
+        let credentials = |token: &str| Some((base_url.1.to_string(), token.to_string()));
+
+        let both = [base_url, ("ANTHROPIC_AUTH_TOKEN", "auth"), ("ANTHROPIC_API_KEY", "key")];
+        assert_eq!(env_credentials(vars(&both)), credentials("auth"));
+        assert_eq!(env_credentials(vars(&[base_url, ("ANTHROPIC_API_KEY", "key")])), credentials("key"));
+        assert_eq!(env_credentials(vars(&[base_url])), None);
+        assert_eq!(env_credentials(vars(&[("ANTHROPIC_API_KEY", "key")])), None);
+    }
+
+    /// 只有配置文件时的配色（深色背景、256 色）
+    fn file_palette(file: Value) -> Palette {
+        resolve_statusline_config(file, std::iter::empty(), &mut Vec::new()).palette(false, false)
+    }
+
+    #[test]
+    fn test_palette() {
+        let options = resolve_statusline_config(Value::Null, std::iter::empty(), &mut Vec::new());
+        assert_eq!(options.palette(false, false), Palette::default());
+
+        let file = serde_json::json!({"colors": {"crit": "Magenta", "ok": "nope"}});
+        let mut errors = Vec::new();
+        let options = resolve_statusline_config(file, env(&[("COLORS__WARN", "cyan")]), &mut errors);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].to_string().contains("statusline.colors.ok"), "{}", errors[0]);
+        let palette = options.palette(false, false);
+        assert_eq!(palette.crit, colors::MAGENTA);
+        assert_eq!(palette.warn, colors::CYAN);
+        // 无法识别的名称使用默认颜色
+        assert_eq!(palette.ok, colors::GREEN);
+        assert_eq!(palette.markers, None);
+
+        let file = serde_json::json!({"palette": "colorblind", "colors": {"ok": "cyan"}});
+        let palette = file_palette(file);
+        assert_eq!(palette, Palette { ok: colors::CYAN, ..Palette::colorblind(false) });
+        let options = resolve_statusline_config(Value::Null, env(&[("PALETTE", "colorblind")]), &mut Vec::new());
+        assert_eq!(options.palette(true, false).crit, colors::VERMILLION_RGB);
+
+        // 自定义符号，空字符串表示不加符号；默认配色不加符号
+        let file = serde_json::json!({"palette": "colorblind", "markers": {"ok": "", "crit": "x!"}});
+        let markers = file_palette(file).markers.unwrap();
+        assert_eq!(markers, colors::Markers { ok: None, warn: Some('!'), crit: Some('x') });
+        let file = serde_json::json!({"markers": {"ok": "+"}});
+        assert_eq!(file_palette(file).markers, None);
+    }
+
+    #[test]
+    fn test_background_palette() {
+        let options = resolve_statusline_config(Value::Null, std::iter::empty(), &mut Vec::new());
+        // auto 按检测结果选择
+        assert_eq!(options.palette(false, false), Palette::default());
+        assert_eq!(options.palette(false, true), Palette::light());
+
+        let options = resolve_statusline_config(Value::Null, env(&[("BACKGROUND", "dark")]), &mut Vec::new());
+        assert_eq!(options.palette(false, true), Palette::default());
+
+        // 自定义颜色优先于浅色配色
+        let file = serde_json::json!({"background": "light", "colors": {"warn": "yellow"}});
+        let palette = file_palette(file);
+        assert_eq!(palette, Palette { warn: colors::YELLOW, ..Palette::light() });
+        assert_eq!(palette.resolve(colors::YELLOW, Some(colors::Severity::Warn)), colors::YELLOW);
+        // 同样是黄色的普通文本（如 git 的未提交文件数）仍换成深色版本
+        assert_eq!(palette.resolve(colors::YELLOW, None), colors::DARK_YELLOW);
+        assert_eq!(palette.resolve(colors::DIM, None), colors::DARK_GRAY);
+
+        let file = serde_json::json!({"background": "light", "palette": "colorblind"});
+        let palette = file_palette(file);
+        assert_eq!(palette, Palette { light: true, ..Palette::colorblind(false) });
+    }
+
+    #[test]
+    fn test_only_on_warn() {
+        let mut errors = Vec::new();
+        let file = serde_json::json!({"only_on_warn": ["ctx", "cahce", "cache", "git"]});
+        let options = resolve_statusline_config(file, std::iter::empty(), &mut errors);
+        assert_eq!(options.only_on_warn, ["ctx", "cache"]);
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].to_string().contains("unknown segment \"cahce\""), "{}", errors[0]);
+        // 没有阈值的段永远不会进入警告级别
+        assert!(errors[1].to_string().contains("segment \"git\" has no thresholds"), "{}", errors[1]);
+        errors.clear();
+
+        let env_list = env(&[("ONLY_ON_WARN", r#"["ctx"]"#)]);
+        let options = resolve_statusline_config(Value::Null, env_list, &mut errors);
+        assert_eq!(options.only_on_warn, ["ctx"]);
+        assert!(StatuslineConfig::default().only_on_warn.is_empty());
+    }
+
+    #[test]
+    fn test_token_format() {
+        let mut errors = Vec::new();
+        let options = resolve_statusline_config(Value::Null, std::iter::empty(), &mut errors);
+        assert_eq!(options.token_format, TokenFormat::Abbrev);
+        let file = serde_json::json!({"token_format": "grouped"});
+        let options = resolve_statusline_config(file, std::iter::empty(), &mut errors);
+        assert_eq!(options.token_format, TokenFormat::Grouped);
+        let options = resolve_statusline_config(Value::Null, env(&[("TOKEN_FORMAT", "raw")]), &mut errors);
+        assert_eq!(options.token_format, TokenFormat::Raw);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_parse_offset() {
+        let east = |seconds| FixedOffset::east_opt(seconds);
+        assert_eq!(parse_offset("+08:00"), east(8 * 3600));
+        assert_eq!(parse_offset("+0530"), east(5 * 3600 + 30 * 60));
+        assert_eq!(parse_offset("-5"), east(-5 * 3600));
+        assert_eq!(parse_offset("UTC"), east(0));
+        assert_eq!(parse_offset("z"), east(0));
+        assert_eq!(parse_offset("Asia/Shanghai"), None);
+        assert_eq!(parse_offset("+25:00"), None);
+        assert_eq!(parse_offset("+08:7x"), None);
+        assert_eq!(parse_offset(""), None);
+    }
+
+    #[test]
+    fn test_set_path_nested() {
+        let mut value = serde_json::json!({"git": {"branch": true}});
+        set_path(&mut value, &["git".to_string(), "dirty".to_string()], Value::Bool(false));
+        assert_eq!(value, serde_json::json!({"git": {"branch": true, "dirty": false}}));
+    }
+}
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+
+/// 内置的上下文窗口大小，按 `model.id` 的子串匹配（不区分大小写），匹配最长的一项
+const BUILTIN_WINDOWS: &[(&str, u64)] = &[
+    ("haiku", 200_000),
+    ("sonnet", 200_000),
+    ("opus", 200_000),
+    ("claude-3-haiku", 200_000),
+    ("claude-3-5-haiku", 200_000),
+    ("claude-haiku-4", 200_000),
+    ("claude-3-5-sonnet", 200_000),
+    ("claude-3-7-sonnet", 200_000),
+    ("claude-sonnet-4", 200_000),
+    ("claude-3-opus", 200_000),
+    ("claude-opus-4", 200_000),
+];
+
+/// 1M 上下文模型的 id 后缀，如 `claude-sonnet-4-5-20250929[1m]`
+const ONE_MILLION_SUFFIX: &str = "[1m]";
+
+/// 在表中找到匹配 `model_id` 的最长子串对应的窗口大小
+fn longest_match<'a>(model_id: &str, table: impl IntoIterator<Item = (&'a str, u64)>) -> Option<u64> {
+    table
+        .into_iter()
+        .filter(|(pattern, _)| !pattern.is_empty() && model_id.contains(&pattern.to_ascii_lowercase()))
+        .max_by_key(|(pattern, _)| pattern.len())
+        .map(|(_, size)| size)
+}
+
+/// 根据模型 id 推断上下文窗口大小；用户配置的 `context.windows` 优先，其次是 `[1m]` 后缀，最后是内置表
+pub fn infer(model_id: &str, overrides: &HashMap<String, u64>) -> Option<u64> {
+    let model_id = model_id.to_ascii_lowercase();
+    longest_match(&model_id, overrides.iter().map(|(pattern, &size)| (pattern.as_str(), size)))
+        .or_else(|| model_id.ends_with(ONE_MILLION_SUFFIX).then_some(1_000_000))
+        .or_else(|| longest_match(&model_id, BUILTIN_WINDOWS.iter().copied()))
+        .filter(|&size| size > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infer_builtin_windows() {
+        let none = HashMap::new();
+        assert_eq!(infer("claude-sonnet-4-5-20250929", &none), Some(200_000));
+        assert_eq!(infer("claude-opus-4-1-20250805", &none), Some(200_000));
+        assert_eq!(infer("claude-3-5-haiku-20241022", &none), Some(200_000));
+        assert_eq!(infer("claude-3-7-sonnet-latest", &none), Some(200_000));
+        // 带 `[1m]` 后缀的 id 都是 1M 上下文，不需要逐个列出
+        assert_eq!(infer("claude-sonnet-4-5-20250929[1m]", &none), Some(1_000_000));
+        assert_eq!(infer("claude-sonnet-4-20250514[1M]", &none), Some(1_000_000));
+        assert_eq!(infer("sonnet[1m]", &none), Some(1_000_000));
+        assert_eq!(infer("claude-opus-4-6[1m]", &none), Some(1_000_000));
+        assert_eq!(infer("gpt-4o", &none), None);
+        assert_eq!(infer("", &none), None);
+    }
+
+    #[test]
+    fn test_overrides_take_precedence() {
+        let overrides = HashMap::from([
+            ("glm-4.6".to_string(), 128_000),
+            ("glm".to_string(), 64_000),
+            ("claude".to_string(), 500_000),
+            ("broken".to_string(), 0),
+        ]);
+        assert_eq!(infer("GLM-4.6", &overrides), Some(128_000));
+        assert_eq!(infer("glm-4.5-air", &overrides), Some(64_000));
+        // 用户配置的项即使更短也优先于内置表
+        assert_eq!(infer("claude-sonnet-4-5-20250929[1m]", &overrides), Some(500_000));
+        assert_eq!(infer("broken-model", &overrides), None);
+    }
+}
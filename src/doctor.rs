@@ -0,0 +1,115 @@
+use crate::config::ClaudeConfig;
+use crate::providers::{self, ProviderCheck, ProviderContext, ProviderReport};
+
+/// `doctor` 子命令的输出：凭据及其来源，以及每个数据源的诊断结果（匹配的数据源会直接请求一次）
+pub fn run(config: &ClaudeConfig) -> String {
+    let ctx = ProviderContext {
+        labels: config.statusline.labels(),
+        config: &config.statusline,
+    };
+    let mut lines: Vec<String> = config.errors.iter().map(|error| format!("config: {}", error)).collect();
+    let (base_url, auth_token) = match config.credentials_with_source() {
+        Some((base_url, auth_token, source)) => {
+            lines.push(format!("base URL: {} ({})", base_url, source));
+            let token = if auth_token.is_empty() { "empty" } else { "set" };
+            lines.push(format!("auth token: {} ({})", token, source));
+            (base_url, auth_token)
+        }
+        None => {
+            lines.push(format!("base URL: not configured, using {}", providers::ANTHROPIC_BASE_URL));
+            lines.push(
+                "auth token: not set (baseURL / authToken in settings.json, \
+                 or ANTHROPIC_BASE_URL / ANTHROPIC_AUTH_TOKEN or ANTHROPIC_API_KEY)"
+                    .to_string(),
+            );
+            (providers::ANTHROPIC_BASE_URL.to_string(), String::new())
+        }
+    };
+
+    let reports = providers::diagnose(&base_url, &auth_token, config.provider.as_deref(), &ctx);
+    if reports.is_empty() {
+        lines.push("no providers compiled in".to_string());
+    }
+    lines.extend(reports.iter().map(describe));
+    lines.join("\n")
+}
+
+/// `--check-providers` 的输出，以及是否至少有一个数据源请求成功
+pub fn check_providers(config: &ClaudeConfig) -> (String, bool) {
+    let ctx = ProviderContext {
+        labels: config.statusline.labels(),
+        config: &config.statusline,
+    };
+    let (base_url, auth_token) = config
+        .credentials()
+        .unwrap_or_else(|| (providers::ANTHROPIC_BASE_URL.to_string(), String::new()));
+    let mut lines = vec![format!("base URL: {}", base_url)];
+    if auth_token.is_empty() {
+        lines.push("auth token: not set".to_string());
+    }
+    let checks = providers::check(&base_url, &auth_token, config.provider.as_deref(), &ctx);
+    if checks.is_empty() {
+        lines.push("no provider matches the base URL".to_string());
+    }
+    lines.extend(checks.iter().map(describe_check));
+    (lines.join("\n"), checks.iter().any(|check| check.result.is_ok()))
+}
+
+/// 成功时带上最后收到的 HTTP 状态码，失败时为错误原因（含非 2xx 的状态码）
+fn describe_check(check: &ProviderCheck) -> String {
+    let result = match (&check.result, check.status) {
+        (Ok(segments), Some(status)) => format!("ok, HTTP {}, {} segment(s)", status, segments),
+        (Ok(segments), None) => format!("ok, {} segment(s)", segments),
+        (Err(error), _) => format!("failed, {}", error),
+    };
+    format!("{}: {}, {}ms", check.provider, result, check.elapsed.as_millis())
+}
+
+fn describe(report: &ProviderReport) -> String {
+    format!("{}: {}", report.provider.unwrap_or("-"), report.outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::{ProviderError, ProviderOutcome};
+
+    #[test]
+    fn test_describe() {
+        let report = |outcome| ProviderReport {
+            provider: Some("zhipu"),
+            outcome,
+        };
+        assert_eq!(describe(&report(ProviderOutcome::NotMatched)), "zhipu: not matched");
+        assert_eq!(describe(&report(ProviderOutcome::Fetched { segments: 2 })), "zhipu: matched, fetched 2 segment(s)");
+        let parse = ProviderOutcome::Failed {
+            error: ProviderError::parse("/data/limits/0", "missing field `percentage`"),
+        };
+        assert_eq!(
+            describe(&report(parse)),
+            "zhipu: matched, but the request failed: unparsable response at /data/limits/0: \
+             missing field `percentage`"
+        );
+        let status = ProviderOutcome::Failed {
+            error: ProviderError::Status { status: 401 },
+        };
+        assert_eq!(describe(&report(status)), "zhipu: matched, but the request failed: HTTP 401");
+    }
+
+    #[test]
+    fn test_describe_check() {
+        let check = |result, status| ProviderCheck {
+            provider: "zhipu",
+            result,
+            status,
+            elapsed: std::time::Duration::from_millis(153),
+        };
+        assert_eq!(describe_check(&check(Ok(2), Some(200))), "zhipu: ok, HTTP 200, 2 segment(s), 153ms");
+        assert_eq!(describe_check(&check(Ok(2), Some(203))), "zhipu: ok, HTTP 203, 2 segment(s), 153ms");
+        assert_eq!(describe_check(&check(Ok(0), None)), "zhipu: ok, 0 segment(s), 153ms");
+        let status = check(Err(ProviderError::Status { status: 502 }), Some(502));
+        assert_eq!(describe_check(&status), "zhipu: failed, HTTP 502, 153ms");
+        let timeout = check(Err(ProviderError::Timeout), None);
+        assert_eq!(describe_check(&timeout), "zhipu: failed, request timed out, 153ms");
+    }
+}
@@ -0,0 +1,173 @@
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::colors;
+use crate::render::Segment;
+
+/// 运行环境探测接口（测试时注入假实现，避免读取真实系统）
+pub trait Probe {
+    fn var(&self, key: &str) -> Option<String>;
+    fn exists(&self, path: &str) -> bool;
+    fn read(&self, path: &str) -> Option<String>;
+}
+
+/// 读取真实系统的探测器，只做环境变量和文件检查，不启动子进程
+pub struct SystemProbe;
+
+impl Probe for SystemProbe {
+    fn var(&self, key: &str) -> Option<String> {
+        std::env::var(key).ok().filter(|value| !value.is_empty())
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        Path::new(path).exists()
+    }
+
+    fn read(&self, path: &str) -> Option<String> {
+        std::fs::read_to_string(path).ok()
+    }
+}
+
+/// 当前进程所在的运行环境
+#[derive(Debug, Clone, PartialEq)]
+pub enum Environment {
+    /// 通过 SSH 登录，附带本机主机名
+    Ssh(String),
+    Container,
+    Wsl,
+}
+
+impl Environment {
+    fn color(&self) -> &'static str {
+        match self {
+            Environment::Ssh(_) => colors::YELLOW,
+            Environment::Container => colors::CYAN,
+            Environment::Wsl => colors::BLUE,
+        }
+    }
+
+    fn text(&self) -> String {
+        match self {
+            Environment::Ssh(host) if host.is_empty() => "ssh".to_string(),
+            Environment::Ssh(host) => format!("ssh:{}", host),
+            Environment::Container => "dev⬢".to_string(),
+            Environment::Wsl => "wsl".to_string(),
+        }
+    }
+
+    pub fn segment(&self) -> Segment {
+        Segment::new("env", self.color(), self.text())
+    }
+}
+
+/// 探测运行环境，SSH 优先于容器，容器优先于 WSL；本地终端返回 None
+pub fn detect(probe: &dyn Probe) -> Option<Environment> {
+    if probe.var("SSH_CONNECTION").is_some() || probe.var("SSH_TTY").is_some() {
+        return Some(Environment::Ssh(hostname(probe).unwrap_or_default()));
+    }
+
+    if probe.exists("/.dockerenv")
+        || probe.exists("/run/.containerenv")
+        || probe.var("REMOTE_CONTAINERS").is_some()
+    {
+        return Some(Environment::Container);
+    }
+
+    let is_wsl = probe
+        .read("/proc/version")
+        .is_some_and(|version| version.to_lowercase().contains("microsoft"));
+    if is_wsl {
+        return Some(Environment::Wsl);
+    }
+
+    None
+}
+
+/// 短主机名（去掉域名部分）
+fn hostname(probe: &dyn Probe) -> Option<String> {
+    let full = probe
+        .var("HOSTNAME")
+        .or_else(|| probe.read("/etc/hostname"))
+        .or_else(|| probe.read("/proc/sys/kernel/hostname"))?;
+    let short = full.trim().split('.').next()?.to_string();
+    Some(short).filter(|name| !name.is_empty())
+}
+
+/// 当前进程的运行环境，只探测一次
+pub fn current() -> Option<&'static Environment> {
+    static ENVIRONMENT: OnceLock<Option<Environment>> = OnceLock::new();
+    ENVIRONMENT.get_or_init(|| detect(&SystemProbe)).as_ref()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[derive(Default)]
+    struct FakeProbe {
+        vars: HashMap<&'static str, &'static str>,
+        files: HashMap<&'static str, &'static str>,
+    }
+
+    impl Probe for FakeProbe {
+        fn var(&self, key: &str) -> Option<String> {
+            self.vars.get(key).map(|value| value.to_string())
+        }
+
+        fn exists(&self, path: &str) -> bool {
+            self.files.contains_key(path)
+        }
+
+        fn read(&self, path: &str) -> Option<String> {
+            self.files.get(path).map(|value| value.to_string())
+        }
+    }
+
+    fn probe(vars: &[(&'static str, &'static str)], files: &[(&'static str, &'static str)]) -> FakeProbe {
+        FakeProbe {
+            vars: vars.iter().copied().collect(),
+            files: files.iter().copied().collect(),
+        }
+    }
+
+    #[test]
+    fn test_local_terminal() {
+        let local = probe(&[], &[("/proc/version", "Linux version 6.8.0-generic")]);
+        assert_eq!(detect(&local), None);
+    }
+
+    #[test]
+    fn test_ssh_with_hostname() {
+        let ssh = probe(
+            &[("SSH_CONNECTION", "10.0.0.2 52144 10.0.0.5 22")],
+            &[("/etc/hostname", "buildbox.internal\n")],
+        );
+        let env = detect(&ssh).unwrap();
+        assert_eq!(env, Environment::Ssh("buildbox".to_string()));
+        assert_eq!(env.segment().plain_text(), "ssh:buildbox");
+
+        let no_host = probe(&[("SSH_TTY", "/dev/pts/0")], &[]);
+        assert_eq!(detect(&no_host).unwrap().segment().plain_text(), "ssh");
+    }
+
+    #[test]
+    fn test_container() {
+        assert_eq!(detect(&probe(&[], &[("/.dockerenv", "")])), Some(Environment::Container));
+        assert_eq!(detect(&probe(&[], &[("/run/.containerenv", "")])), Some(Environment::Container));
+        let devcontainer = probe(&[("REMOTE_CONTAINERS", "true")], &[]);
+        assert_eq!(detect(&devcontainer).unwrap().segment().plain_text(), "dev⬢");
+    }
+
+    #[test]
+    fn test_wsl() {
+        let wsl = probe(&[], &[("/proc/version", "Linux version 5.15.90.1-microsoft-standard-WSL2")]);
+        assert_eq!(detect(&wsl), Some(Environment::Wsl));
+    }
+
+    #[test]
+    fn test_ssh_wins_over_container() {
+        let both = probe(&[("SSH_TTY", "/dev/pts/1"), ("HOSTNAME", "box")], &[("/.dockerenv", "")]);
+        assert_eq!(detect(&both), Some(Environment::Ssh("box".to_string())));
+    }
+}
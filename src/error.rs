@@ -0,0 +1,144 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::providers::ProviderError;
+
+/// 读取配置、读写缓存和请求数据源时的错误
+///
+/// 这些错误都不会中断渲染：调用方退回默认值或不显示对应的段，开启 `debug` 时输出到 stderr。
+#[derive(Debug)]
+pub enum StatuslineError {
+    /// 读写文件或创建目录失败，`action` 如 `read`、`write`
+    Io {
+        action: &'static str,
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// 文件内容不是有效的 JSON，或与期望的结构不符
+    Json { path: PathBuf, source: serde_json::Error },
+    /// 请求数据源失败
+    Network { provider: &'static str, error: ProviderError },
+    /// 配置项的值无效，已退回默认值
+    Config { key: String, message: String },
+}
+
+impl StatuslineError {
+    pub fn read(path: &Path, source: io::Error) -> Self {
+        StatuslineError::Io {
+            action: "read",
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    pub fn write(path: &Path, source: io::Error) -> Self {
+        StatuslineError::Io {
+            action: "write",
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    pub fn create(path: &Path, source: io::Error) -> Self {
+        StatuslineError::Io {
+            action: "create",
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    pub fn json(path: &Path, source: serde_json::Error) -> Self {
+        StatuslineError::Json {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    pub fn config(key: impl Into<String>, message: impl Into<String>) -> Self {
+        StatuslineError::Config {
+            key: key.into(),
+            message: message.into(),
+        }
+    }
+
+    /// 文件不存在：还没有缓存或配置文件是正常情况，不需要提示
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, StatuslineError::Io { source, .. } if source.kind() == io::ErrorKind::NotFound)
+    }
+}
+
+impl fmt::Display for StatuslineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StatuslineError::Io { action, path, source } => {
+                write!(f, "cannot {} {}: {}", action, path.display(), source)
+            }
+            StatuslineError::Json { path, source } => write!(f, "invalid JSON in {}: {}", path.display(), source),
+            StatuslineError::Network { provider, error } => write!(f, "{}: {}", provider, error),
+            StatuslineError::Config { key, message } => write!(f, "{}: {}", key, message),
+        }
+    }
+}
+
+impl std::error::Error for StatuslineError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StatuslineError::Io { source, .. } => Some(source),
+            StatuslineError::Json { source, .. } => Some(source),
+            StatuslineError::Network { error, .. } => Some(error),
+            StatuslineError::Config { .. } => None,
+        }
+    }
+}
+
+/// 读取并解析 JSON 文件
+pub fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, StatuslineError> {
+    let content = std::fs::read_to_string(path).map_err(|err| StatuslineError::read(path, err))?;
+    serde_json::from_str(&content).map_err(|err| StatuslineError::json(path, err))
+}
+
+/// 记录错误（仅调试模式输出），文件不存在时忽略
+pub fn report(error: &StatuslineError) {
+    if !error.is_not_found() {
+        crate::log::warn(error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    #[test]
+    fn test_read_json_errors() {
+        let dir = std::env::temp_dir().join(format!("cc-statusline-error-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let missing = read_json::<serde_json::Value>(&dir.join("missing.json")).unwrap_err();
+        assert!(missing.is_not_found());
+        assert!(missing.to_string().starts_with("cannot read "));
+
+        let path = dir.join("broken.json");
+        std::fs::write(&path, "{").unwrap();
+        let broken = read_json::<serde_json::Value>(&path).unwrap_err();
+        assert!(!broken.is_not_found());
+        assert!(matches!(broken, StatuslineError::Json { .. }));
+        assert!(broken.source().is_some());
+
+        std::fs::write(&path, r#"{"ok": true}"#).unwrap();
+        assert_eq!(read_json::<serde_json::Value>(&path).unwrap()["ok"], true);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_display() {
+        let error = StatuslineError::Network {
+            provider: "zhipu",
+            error: ProviderError::Status { status: 502 },
+        };
+        assert_eq!(error.to_string(), "zhipu: HTTP 502");
+        let error = StatuslineError::config("colors.ok", "unknown color \"nope\", using default");
+        assert_eq!(error.to_string(), "colors.ok: unknown color \"nope\", using default");
+    }
+}
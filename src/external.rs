@@ -0,0 +1,224 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::Duration;
+
+use crate::colors;
+use crate::config::ExternalSegment;
+use crate::render::Segment;
+use crate::text;
+
+/// 未配置 `timeout_ms` 时的等待上限
+const DEFAULT_TIMEOUT_MS: u64 = 200;
+
+/// 只使用输出的前这么多字节
+const MAX_OUTPUT_BYTES: usize = 4096;
+
+/// 段文本的最大显示宽度，超出时从中间截断
+const MAX_WIDTH: usize = 60;
+
+/// 外部命令可以输出的 JSON 格式，`color` 优先于 `severity`
+#[derive(Debug, Deserialize)]
+struct HookOutput {
+    text: String,
+    color: Option<String>,
+    severity: Option<String>,
+}
+
+/// 并发执行所有外部命令（stdin 为 `input` 的 JSON），把得到的段插入到各自配置的位置
+pub fn insert<T: Serialize>(segments: &mut Vec<Segment>, hooks: &[ExternalSegment], input: &T) {
+    if hooks.is_empty() {
+        return;
+    }
+    let Ok(input) = serde_json::to_vec(input) else {
+        return;
+    };
+    let input = input.as_slice();
+    let outputs: Vec<Option<Segment>> = std::thread::scope(|scope| {
+        let handles: Vec<_> =
+            hooks.iter().map(|hook| scope.spawn(move || segment(hook, input))).collect();
+        handles.into_iter().map(|handle| handle.join().ok().flatten()).collect()
+    });
+
+    for (hook, segment) in hooks.iter().zip(outputs) {
+        let Some(segment) = segment else {
+            continue;
+        };
+        // 同一位置的多个外部段按配置顺序排列
+        let position = hook
+            .after
+            .as_deref()
+            .and_then(|after| segments.iter().rposition(|segment| segment.name == after))
+            .map(|anchor| {
+                let following = segments[anchor + 1..].iter();
+                anchor + 1 + following.take_while(|segment| segment.name == "external").count()
+            })
+            .unwrap_or(segments.len());
+        segments.insert(position, segment);
+    }
+}
+
+/// 执行一个外部命令并生成段；超时、退出码非 0 或没有可显示的文本时返回 None
+pub fn segment(hook: &ExternalSegment, input: &[u8]) -> Option<Segment> {
+    let mut command = Command::new(expand_home(&hook.command)?);
+    command.args(&hook.args);
+    let timeout = Duration::from_millis(hook.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS));
+    let output = crate::output_within(&mut command, input, timeout)?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = &output.stdout[..output.stdout.len().min(MAX_OUTPUT_BYTES)];
+    parse_output(&String::from_utf8_lossy(stdout), hook.allow_ansi)
+}
+
+/// `~/` 开头的路径相对 home 目录
+fn expand_home(command: &str) -> Option<PathBuf> {
+    match command.strip_prefix("~/") {
+        Some(rest) => {
+            let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+            Some(PathBuf::from(home).join(rest))
+        }
+        None if command.is_empty() => None,
+        None => Some(PathBuf::from(command)),
+    }
+}
+
+/// 解析命令输出：能按 [`HookOutput`] 解析的 JSON，否则整体作为纯文本
+fn parse_output(stdout: &str, allow_ansi: bool) -> Option<Segment> {
+    let trimmed = stdout.trim();
+    let (text, color) = match serde_json::from_str::<HookOutput>(trimmed) {
+        Ok(output) => {
+            let color = output.color.as_deref().and_then(colors::by_name);
+            let severity = output.severity.as_deref().and_then(severity_color);
+            (output.text, color.or(severity).unwrap_or(colors::DIM))
+        }
+        Err(_) => (trimmed.to_string(), colors::DIM),
+    };
+    let text = sanitize(&text, allow_ansi);
+    if text::strip_ansi(&text).trim().is_empty() {
+        return None;
+    }
+    Some(Segment::new("external", color, text))
+}
+
+/// 换行和控制字符替换为空格；默认去掉 ANSI 转义序列，超出 [`MAX_WIDTH`] 时截断（截断后不保留颜色）
+fn sanitize(text: &str, allow_ansi: bool) -> String {
+    let text = if allow_ansi { text.to_string() } else { text::strip_ansi(text) };
+    let text: String = text
+        .chars()
+        .map(|c| if c.is_control() && !(allow_ansi && c == '\x1b') { ' ' } else { c })
+        .collect();
+    let text = text.split(' ').filter(|word| !word.is_empty()).collect::<Vec<_>>().join(" ");
+    if text::visible_width(&text) > MAX_WIDTH {
+        text::truncate_middle(&text::strip_ansi(&text), MAX_WIDTH)
+    } else {
+        text
+    }
+}
+
+fn severity_color(severity: &str) -> Option<&'static str> {
+    match severity.to_ascii_lowercase().as_str() {
+        "ok" | "success" => Some(colors::GREEN),
+        "info" => Some(colors::CYAN),
+        "warn" | "warning" => Some(colors::YELLOW),
+        "error" | "critical" => Some(colors::RED),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 用 sh 执行 `tests/fixtures/segments` 中的脚本（不依赖文件的可执行权限）
+    fn hook(script: &str) -> ExternalSegment {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/segments").join(script);
+        ExternalSegment {
+            command: "sh".to_string(),
+            args: vec![path.to_string_lossy().into_owned()],
+            timeout_ms: Some(2_000),
+            ..Default::default()
+        }
+    }
+
+    const INPUT: &[u8] = br#"{"session_id":"abc123","model":{"display_name":"Opus"}}"#;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_json_and_plain_output() {
+        let segment = segment(&hook("jira.sh"), INPUT).unwrap();
+        assert_eq!(segment.plain_text(), "PROJ-42 abc123");
+        assert_eq!(segment.color(), colors::YELLOW);
+
+        let plain = super::segment(&hook("plain.sh"), INPUT).unwrap();
+        assert_eq!(plain.plain_text(), "on-call: alice");
+        assert_eq!(plain.color(), colors::DIM);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_timeout_and_failure_skip_segment() {
+        let slow = ExternalSegment {
+            timeout_ms: Some(50),
+            ..hook("slow.sh")
+        };
+        let started = std::time::Instant::now();
+        assert!(segment(&slow, INPUT).is_none());
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        assert!(segment(&hook("fail.sh"), INPUT).is_none());
+        assert!(segment(&ExternalSegment::default(), INPUT).is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_garbage_output_is_sanitized() {
+        let text = segment(&hook("garbage.sh"), INPUT).unwrap().plain_text();
+        assert!(!text.contains('\x1b') && !text.contains('\n') && !text.contains('\x01'));
+        assert!(text.starts_with("red line1 line2 {not json"));
+        assert_eq!(text::visible_width(&text), MAX_WIDTH);
+
+        let colored = ExternalSegment {
+            allow_ansi: true,
+            ..hook("plain.sh")
+        };
+        assert!(segment(&colored, INPUT).is_some());
+    }
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(sanitize("\x1b[31mred\x1b[0m\r\nnext", false), "red next");
+        assert_eq!(sanitize("\x1b[31mred\x1b[0m\nnext", true), "\x1b[31mred\x1b[0m next");
+        // color 优先于 severity
+        let output = r#"{"text": "build ok", "color": "green", "severity": "error"}"#;
+        assert_eq!(parse_output(output, false).unwrap().color(), colors::GREEN);
+        assert!(parse_output("  \n\x1b[0m ", true).is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_insert_after_named_segment() {
+        let mut segments = vec![
+            Segment::new("model", colors::MAGENTA, "[Opus]"),
+            Segment::new("git", colors::BLUE, "main"),
+            Segment::new("ctx", colors::GREEN, "ctx:10%"),
+        ];
+        let hooks = [
+            ExternalSegment {
+                after: Some("git".to_string()),
+                ..hook("plain.sh")
+            },
+            ExternalSegment {
+                after: Some("git".to_string()),
+                ..hook("jira.sh")
+            },
+            ExternalSegment {
+                after: Some("missing".to_string()),
+                ..hook("plain.sh")
+            },
+        ];
+        insert(&mut segments, &hooks, &serde_json::json!({"session_id": "s1"}));
+        let texts: Vec<String> = segments.iter().map(Segment::plain_text).collect();
+        assert_eq!(texts, ["[Opus]", "main", "on-call: alice", "PROJ-42 s1", "ctx:10%", "on-call: alice"]);
+    }
+}
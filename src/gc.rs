@@ -0,0 +1,193 @@
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use crate::error::{self, StatuslineError};
+use crate::paths;
+
+/// 以修改时间记录上次清理的标记文件
+const MARKER_FILE: &str = ".last_gc";
+
+/// 两次清理的最小间隔
+const INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// 超过该时长没有更新的会话状态和数据源缓存会被删除
+const MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// 写入中断后残留的临时文件超过该时长会被删除
+const TMP_MAX_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// 一次清理删除的文件数
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct GcReport {
+    pub sessions: usize,
+    pub caches: usize,
+    pub temp_files: usize,
+}
+
+/// 距上次清理超过一天时清理 `dir`；先更新标记，多个会话同时渲染时不会重复执行
+pub fn run_daily(dir: &Path, now: SystemTime) -> Option<GcReport> {
+    if !paths::persistence_enabled() {
+        return None;
+    }
+    let marker = dir.join(MARKER_FILE);
+    let last = fs::metadata(&marker).and_then(|metadata| metadata.modified()).ok();
+    if last.is_some_and(|last| now.duration_since(last).is_ok_and(|elapsed| elapsed < INTERVAL)) {
+        return None;
+    }
+    if let Err(err) = fs::File::create(&marker).and_then(|file| file.set_modified(now)) {
+        // 无法记录时不清理，避免每次渲染都扫描目录
+        paths::write_failed(StatuslineError::write(&marker, err));
+        return None;
+    }
+    Some(collect(dir, now, &crate::providers::cache_files()))
+}
+
+/// 清理 `dir` 中本工具创建的文件，只处理已知的文件名，其他文件不动：
+///
+/// - 7 天没有更新的会话状态
+/// - 写入中断后残留超过 1 小时的临时文件（`*.tmp`，见 [`paths::write_atomic`]）
+/// - 未编译的数据源的缓存，以及 7 天没有更新的缓存（如已不再使用的 token）
+///
+/// 耗时记录由 `trace` 写入时限制行数，历史记录由 `history` 按会话压缩，都不在这里处理。
+pub fn collect(dir: &Path, now: SystemTime, cache_files: &[&str]) -> GcReport {
+    let mut report = GcReport::default();
+    let age = |path: &Path| {
+        let modified = fs::metadata(path).and_then(|metadata| metadata.modified()).ok()?;
+        Some(now.duration_since(modified).unwrap_or_default())
+    };
+
+    let sessions = files(&dir.join(paths::SESSIONS_DIR));
+    let (temp, sessions): (Vec<_>, Vec<_>) = sessions.into_iter().partition(|(_, name)| name.ends_with(".tmp"));
+    let (temp_root, root): (Vec<_>, Vec<_>) = files(dir).into_iter().partition(|(_, name)| name.ends_with(".tmp"));
+
+    for (path, _) in temp.into_iter().chain(temp_root) {
+        if age(&path).is_some_and(|age| age > TMP_MAX_AGE) && remove(&path) {
+            report.temp_files += 1;
+        }
+    }
+
+    for (path, name) in sessions {
+        if name.ends_with(".json") && age(&path).is_some_and(|age| age > MAX_AGE) && remove(&path) {
+            report.sessions += 1;
+        }
+    }
+
+    for (path, name) in root {
+        if !paths::is_cache_file(&name) {
+            continue;
+        }
+        let compiled = cache_files.iter().any(|file| paths::is_cache_of(&name, file));
+        if (!compiled || age(&path).is_some_and(|age| age > MAX_AGE)) && remove(&path) {
+            report.caches += 1;
+        }
+    }
+    report
+}
+
+/// 目录中的普通文件及其文件名，目录不存在时为空
+fn files(dir: &Path) -> Vec<(std::path::PathBuf, String)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_file()))
+        .filter_map(|entry| Some((entry.path(), entry.file_name().into_string().ok()?)))
+        .collect()
+}
+
+fn remove(path: &Path) -> bool {
+    match fs::remove_file(path) {
+        Ok(()) => true,
+        Err(err) => {
+            error::report(&StatuslineError::Io {
+                action: "remove",
+                path: path.to_path_buf(),
+                source: err,
+            });
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    fn touch(path: &Path, modified: SystemTime) {
+        fs::write(path, "{}").unwrap();
+        fs::File::options().write(true).open(path).unwrap().set_modified(modified).unwrap();
+    }
+
+    #[test]
+    fn test_collect() {
+        let dir = std::env::temp_dir().join(format!("cc-statusline-gc-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let sessions = dir.join(paths::SESSIONS_DIR);
+        fs::create_dir_all(&sessions).unwrap();
+        let now = SystemTime::now();
+        let old = now - 8 * DAY;
+
+        touch(&sessions.join("fresh.json"), now - DAY);
+        touch(&sessions.join("old.json"), old);
+        touch(&sessions.join("old.json.42.tmp"), now - DAY);
+        touch(&sessions.join("writing.json.43.tmp"), now);
+        touch(&sessions.join("notes.txt"), old);
+        touch(&dir.join(".zhipu_cache.json"), now);
+        touch(&dir.join(".zhipu_cache.0123456789abcdef.json"), old);
+        touch(&dir.join(".gac_cache.json"), now);
+        touch(&dir.join(".other_cache.json"), old);
+        touch(&dir.join("settings.json"), old);
+        touch(&dir.join("statusline_mcp.json"), old);
+        touch(&dir.join("statusline_git.json.44.tmp"), now - DAY);
+        touch(&dir.join("statusline_history.jsonl.45.tmp"), now - DAY);
+        touch(&dir.join("statusline_git.json.46.tmp"), now);
+        // 历史记录和耗时记录不在这里截断
+        touch(&dir.join(paths::HISTORY_FILE), old);
+        touch(&dir.join(paths::TRACE_FILE), old);
+
+        let report = collect(&dir, now, &[".zhipu_cache.json"]);
+        assert_eq!(
+            report,
+            GcReport {
+                sessions: 1,
+                caches: 2,
+                temp_files: 3
+            }
+        );
+        let mut left: Vec<String> = files(&dir).into_iter().chain(files(&sessions)).map(|(_, name)| name).collect();
+        left.sort();
+        assert_eq!(
+            left,
+            [
+                ".other_cache.json",
+                ".zhipu_cache.json",
+                "fresh.json",
+                "notes.txt",
+                "settings.json",
+                "statusline_git.json.46.tmp",
+                "statusline_history.jsonl",
+                "statusline_mcp.json",
+                "statusline_trace.jsonl",
+                "writing.json.43.tmp"
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_run_daily() {
+        let dir = std::env::temp_dir().join(format!("cc-statusline-gc-daily-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let now = SystemTime::now();
+
+        assert!(run_daily(&dir, now).is_some());
+        assert!(run_daily(&dir, now + Duration::from_secs(60)).is_none());
+        assert!(run_daily(&dir, now + DAY + Duration::from_secs(1)).is_some());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,345 @@
+use chrono::{DateTime, Datelike, Duration, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+use crate::error::StatuslineError;
+use crate::paths::{self, history_path};
+
+/// 允许的时钟偏差：未来时间在此范围内视为当前时间
+const MAX_CLOCK_SKEW_MINUTES: i64 = 10;
+
+/// 文件每增长这么多字节检查一次是否需要压缩，平时追加不读取整个文件
+const COMPACT_EVERY_BYTES: u64 = 64 * 1024;
+
+/// 当前的历史记录格式版本
+///
+/// 1：只有时间、会话和成本（没有 `version` 字段）；2：增加项目目录、token 总数和会话时长。
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// 每次渲染写入的一条历史记录
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct HistoryRecord {
+    #[serde(default = "legacy_version")]
+    pub version: u32,
+    pub timestamp: DateTime<Utc>,
+    pub session_id: String,
+    pub cost_usd: f64,
+    /// 会话所在的项目目录
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_dir: Option<String>,
+    /// 会话累计的输入 + 输出 token 数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_tokens: Option<u64>,
+    /// 会话累计时长
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+}
+
+/// 没有 `version` 字段的记录是第 1 版
+fn legacy_version() -> u32 {
+    1
+}
+
+impl HistoryRecord {
+    /// 升级到当前版本：旧记录缺少的字段为 None（报告中归入未知项目）
+    pub fn migrate(mut self) -> Self {
+        self.version = SCHEMA_VERSION;
+        self
+    }
+}
+
+/// 按周、按月汇总的花费
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Spend {
+    pub week: f64,
+    pub month: f64,
+}
+
+/// 追加一条历史记录（失败时只在调试模式下提示，并在本次运行中不再写入状态文件）
+pub fn append(record: &HistoryRecord) {
+    if !paths::persistence_enabled() {
+        return;
+    }
+    if let Err(err) = append_to(&history_path(), record, COMPACT_EVERY_BYTES) {
+        paths::write_failed(err);
+    }
+}
+
+/// 追加一条记录；文件大小跨过 `compact_every` 的整数倍时检查是否需要压缩
+fn append_to(path: &Path, record: &HistoryRecord, compact_every: u64) -> Result<(), StatuslineError> {
+    let Ok(json) = serde_json::to_string(record) else {
+        return Ok(());
+    };
+    let (before, after) = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| {
+            let before = file.metadata()?.len();
+            writeln!(file, "{}", json)?;
+            Ok((before, file.metadata()?.len()))
+        })
+        .map_err(|err| StatuslineError::write(path, err))?;
+    if before / compact_every == after / compact_every {
+        return Ok(());
+    }
+    compact(path)
+}
+
+/// 每个会话只保留最后一条记录（汇总时也只取最后一条），按记录原有的先后顺序重写文件
+///
+/// 同一会话的记录不超过平均两条时不重写。经 [`paths::write_atomic_if`] 写入，
+/// 重命名前文件大小有变化（其他渲染在此期间追加了记录）时放弃本次压缩，避免丢掉新记录。
+/// 无法解析的行一并丢弃，旧版本的记录升级到 [`SCHEMA_VERSION`]。
+fn compact(path: &Path) -> Result<(), StatuslineError> {
+    let content = fs::read_to_string(path).map_err(|err| StatuslineError::read(path, err))?;
+    let lines: Vec<&str> = content.lines().collect();
+    let mut last: HashMap<String, (usize, HistoryRecord)> = HashMap::new();
+    for (i, line) in lines.iter().enumerate() {
+        if let Ok(record) = serde_json::from_str::<HistoryRecord>(line) {
+            last.insert(record.session_id.clone(), (i, record));
+        }
+    }
+    if lines.len() <= last.len() * 2 {
+        return Ok(());
+    }
+
+    let mut kept: Vec<(usize, HistoryRecord)> = last.into_values().collect();
+    kept.sort_unstable_by_key(|(i, _)| *i);
+    let mut compacted = String::new();
+    for (i, record) in kept {
+        if record.version == SCHEMA_VERSION {
+            compacted.push_str(lines[i]);
+        } else if let Ok(json) = serde_json::to_string(&record.migrate()) {
+            compacted.push_str(&json);
+        }
+        compacted.push('\n');
+    }
+    let unchanged = || Ok(fs::metadata(path)?.len() == content.len() as u64);
+    paths::write_atomic_if(path, compacted, unchanged).map_err(|err| StatuslineError::write(path, err))
+}
+
+/// 读取历史文件并汇总本周、本月花费（本地时间）
+pub fn load_spend() -> Option<Spend> {
+    let file = fs::File::open(history_path()).ok()?;
+    Some(aggregate(BufReader::new(file), chrono::Local::now()))
+}
+
+/// 逐行汇总历史记录
+///
+/// 每个会话只取文件中最后一条记录（成本是累计值），按其时间戳所在的
+/// ISO 周和自然月归类。略微超前的时间戳按当前时间处理，明显超前的记录被忽略。
+pub fn aggregate<R: BufRead, Tz: TimeZone>(reader: R, now: DateTime<Tz>) -> Spend {
+    let mut latest: HashMap<String, (DateTime<Utc>, f64)> = HashMap::new();
+    for line in reader.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if let Ok(record) = serde_json::from_str::<HistoryRecord>(&line) {
+            latest.insert(record.session_id, (record.timestamp, record.cost_usd));
+        }
+    }
+
+    let now_utc = now.with_timezone(&Utc);
+    let tz = now.timezone();
+    let today = now.date_naive();
+    let mut spend = Spend::default();
+
+    for (timestamp, cost) in latest.into_values() {
+        let timestamp = if timestamp > now_utc {
+            if timestamp - now_utc > Duration::minutes(MAX_CLOCK_SKEW_MINUTES) {
+                continue;
+            }
+            now_utc
+        } else {
+            timestamp
+        };
+        let date = timestamp.with_timezone(&tz).date_naive();
+
+        if date.iso_week() == today.iso_week() {
+            spend.week += cost;
+        }
+        if date.year() == today.year() && date.month() == today.month() {
+            spend.month += cost;
+        }
+    }
+
+    spend
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+
+    fn line(timestamp: &str, session_id: &str, cost_usd: f64) -> String {
+        let record = HistoryRecord {
+            version: SCHEMA_VERSION,
+            timestamp: DateTime::parse_from_rfc3339(timestamp).unwrap().with_timezone(&Utc),
+            session_id: session_id.to_string(),
+            cost_usd,
+            project_dir: None,
+            total_tokens: None,
+            duration_ms: None,
+        };
+        serde_json::to_string(&record).unwrap()
+    }
+
+    fn now(timestamp: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(timestamp).unwrap()
+    }
+
+    #[test]
+    fn test_latest_record_per_session_wins() {
+        let history = [
+            line("2026-03-11T09:00:00+08:00", "a", 1.0),
+            line("2026-03-11T10:00:00+08:00", "a", 2.5),
+            line("2026-03-11T11:00:00+08:00", "b", 4.0),
+        ]
+        .join("\n");
+        let spend = aggregate(history.as_bytes(), now("2026-03-12T12:00:00+08:00"));
+        assert_eq!(spend, Spend { week: 6.5, month: 6.5 });
+    }
+
+    #[test]
+    fn test_week_and_month_boundaries() {
+        // 2026-03-30 是周一：上周日的记录只计入本月，2 月的记录都不计入
+        let history = [
+            line("2026-02-28T23:00:00+08:00", "feb", 10.0),
+            line("2026-03-29T23:30:00+08:00", "last-week", 3.0),
+            line("2026-03-30T00:10:00+08:00", "this-week", 2.0),
+        ]
+        .join("\n");
+        let spend = aggregate(history.as_bytes(), now("2026-03-31T12:00:00+08:00"));
+        assert_eq!(spend, Spend { week: 2.0, month: 5.0 });
+
+        // 跨月的同一周：4 月 1 日时本周包含 3 月 30 日，本月不包含
+        let spend = aggregate(history.as_bytes(), now("2026-04-01T12:00:00+08:00"));
+        assert_eq!(spend, Spend { week: 2.0, month: 0.0 });
+    }
+
+    #[test]
+    fn test_boundaries_use_local_time() {
+        // UTC 时间在 3 月，东八区本地时间已是 4 月
+        let history = line("2026-03-31T17:00:00Z", "a", 1.0);
+        let spend = aggregate(history.as_bytes(), now("2026-04-01T09:00:00+08:00"));
+        assert_eq!(spend.month, 1.0);
+    }
+
+    #[test]
+    fn test_clock_skew() {
+        let history = [
+            line("2026-03-31T23:58:00+08:00", "slightly-future", 1.0),
+            line("2026-04-02T00:00:00+08:00", "far-future", 5.0),
+        ]
+        .join("\n");
+        let spend = aggregate(history.as_bytes(), now("2026-03-31T23:55:00+08:00"));
+        assert_eq!(spend, Spend { week: 1.0, month: 1.0 });
+    }
+
+    #[test]
+    fn test_repeated_renders_count_each_session_once() {
+        let dir = std::env::temp_dir().join(format!("cc-statusline-history-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("statusline_history.jsonl");
+        let _ = fs::remove_file(&path);
+
+        let record = |session_id: &str, cost_usd: f64| HistoryRecord {
+            version: SCHEMA_VERSION,
+            timestamp: DateTime::parse_from_rfc3339("2026-03-11T10:00:00+08:00").unwrap().with_timezone(&Utc),
+            session_id: session_id.to_string(),
+            cost_usd,
+            project_dir: Some("/work/project".to_string()),
+            total_tokens: None,
+            duration_ms: None,
+        };
+        // 多个 hook 事件先后触发渲染，同一会话的记录重复写入；每次追加都跨过 1 字节的检查间隔
+        for round in 1..=10 {
+            for session_id in ["a", "b", "c"] {
+                append_to(&path, &record(session_id, f64::from(round)), 1).unwrap();
+                append_to(&path, &record(session_id, f64::from(round)), 1).unwrap();
+            }
+            let lines = fs::read_to_string(&path).unwrap().lines().count();
+            assert!(lines <= 6, "round {}: {} lines", round, lines);
+        }
+        // 没有跨过检查间隔时只追加，不读取和重写文件
+        let count = || fs::read_to_string(&path).unwrap().lines().count();
+        let before = count();
+        for _ in 0..3 {
+            append_to(&path, &record("a", 10.0), COMPACT_EVERY_BYTES).unwrap();
+        }
+        assert_eq!(count(), before + 3);
+
+        let content = fs::read_to_string(&path).unwrap();
+        let spend = aggregate(content.as_bytes(), now("2026-03-12T12:00:00+08:00"));
+        assert_eq!(spend, Spend { week: 30.0, month: 30.0 });
+        // 压缩后每个会话保留最后一条，不留下临时文件
+        compact(&path).unwrap();
+        let lines: Vec<HistoryRecord> = fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert!(lines.len() <= 6);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_compact_keeps_last_record_in_order() {
+        let dir = std::env::temp_dir().join(format!("cc-statusline-compact-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("history.jsonl");
+        let history = [
+            line("2026-03-11T09:00:00+08:00", "a", 1.0),
+            line("2026-03-11T09:01:00+08:00", "a", 1.5),
+            line("2026-03-11T09:02:00+08:00", "b", 0.5),
+            "not json".to_string(),
+            line("2026-03-11T09:03:00+08:00", "a", 2.0),
+        ];
+        fs::write(&path, history.join("\n")).unwrap();
+        compact(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), format!("{}\n{}\n", history[2], history[4]));
+
+        // 不超过会话数两倍时不重写
+        let unchanged = [history[0].clone(), history[2].clone(), history[4].clone()].join("\n");
+        fs::write(&path, &unchanged).unwrap();
+        compact(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), unchanged);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_compact_migrates_legacy_records() {
+        let dir = std::env::temp_dir().join(format!("cc-statusline-history-migrate-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("history.jsonl");
+        let legacy = |cost_usd: f64| {
+            format!(r#"{{"timestamp":"2026-03-11T01:00:00Z","session_id":"old","cost_usd":{}}}"#, cost_usd)
+        };
+        let current = line("2026-03-11T09:02:00+08:00", "new", 0.5);
+        let history = [legacy(0.5), legacy(1.0), legacy(1.5), legacy(2.0), current.clone()];
+        let record: HistoryRecord = serde_json::from_str(&history[0]).unwrap();
+        assert_eq!(record.version, 1);
+        assert_eq!(record.project_dir, None);
+
+        fs::write(&path, history.join("\n")).unwrap();
+        compact(&path).unwrap();
+        let expected = concat!(
+            r#"{"version":2,"timestamp":"2026-03-11T01:00:00Z","#,
+            r#""session_id":"old","cost_usd":2.0}"#
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), format!("{}\n{}\n", expected, current));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_skips_invalid_lines() {
+        let history = format!("not json\n{}\n{{\"partial\": ", line("2026-03-11T10:00:00+08:00", "a", 2.0));
+        let spend = aggregate(history.as_bytes(), now("2026-03-11T12:00:00+08:00"));
+        assert_eq!(spend, Spend { week: 2.0, month: 2.0 });
+    }
+}
@@ -0,0 +1,195 @@
+use serde::Deserialize;
+
+/// 显示语言
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    En,
+    Zh,
+}
+
+impl Locale {
+    /// 根据 LC_ALL / LC_MESSAGES / LANG 推断语言，无法识别时默认英文
+    pub fn from_env() -> Self {
+        let lang = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|key| std::env::var(key).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        Self::from_lang(&lang)
+    }
+
+    fn from_lang(lang: &str) -> Self {
+        if lang.to_ascii_lowercase().starts_with("zh") {
+            Locale::Zh
+        } else {
+            Locale::En
+        }
+    }
+
+    pub fn labels(self) -> &'static Labels {
+        match self {
+            Locale::En => &EN,
+            Locale::Zh => &ZH,
+        }
+    }
+}
+
+/// 所有用户可见的文本，按语言区分；数据源的标签只在编译了对应数据源时读取
+#[derive(Debug)]
+pub struct Labels {
+    pub model_missing: &'static str,
+    pub ctx: &'static str,
+    pub input: &'static str,
+    pub output: &'static str,
+    pub cache: &'static str,
+    pub cached: &'static str,
+    pub agent: &'static str,
+    /// 消息数段中用户和助手的标签，如 `user:12 asst:11`
+    pub user: &'static str,
+    pub assistant: &'static str,
+    /// transcript 长时间未更新时的标记，如 `idle:12m`
+    pub idle: &'static str,
+    /// 扩展思考开启时的标记
+    pub thinking: &'static str,
+    /// 超过 200k token 时的警告
+    pub exceeds_200k: &'static str,
+    pub errors: &'static str,
+    pub load: &'static str,
+    pub mcp: &'static str,
+    pub week: &'static str,
+    pub month: &'static str,
+    pub git_merge: &'static str,
+    pub git_rebase: &'static str,
+    #[cfg_attr(not(feature = "provider-zhipu"), allow(dead_code))]
+    pub zhipu_tag: &'static str,
+    #[cfg_attr(not(feature = "provider-zhipu"), allow(dead_code))]
+    pub zhipu_token: &'static str,
+    #[cfg_attr(not(feature = "provider-zhipu"), allow(dead_code))]
+    pub zhipu_mcp: &'static str,
+    #[cfg_attr(not(feature = "provider-yunyi"), allow(dead_code))]
+    pub yunyi_tag: &'static str,
+    #[cfg_attr(not(feature = "provider-yunyi"), allow(dead_code))]
+    pub yunyi_remaining: &'static str,
+    #[cfg_attr(not(feature = "provider-yunyi"), allow(dead_code))]
+    pub yunyi_daily: &'static str,
+    #[cfg_attr(not(feature = "provider-yunyi"), allow(dead_code))]
+    pub yunyi_pack: &'static str,
+    #[cfg_attr(not(feature = "provider-yunyi"), allow(dead_code))]
+    pub yunyi_expires: &'static str,
+    #[cfg_attr(not(feature = "provider-yunyi"), allow(dead_code))]
+    pub yunyi_pack_expires: &'static str,
+    /// 剩余时间的前缀，如 `in 3h20m`
+    #[cfg_attr(not(feature = "provider-yunyi"), allow(dead_code))]
+    pub yunyi_in: &'static str,
+    /// 账号段的前缀，如 `acct:user@example.com`
+    #[cfg_attr(not(feature = "provider-yunyi"), allow(dead_code))]
+    pub account: &'static str,
+    #[cfg_attr(not(feature = "provider-packycode"), allow(dead_code))]
+    pub packycode_tag: &'static str,
+    #[cfg_attr(not(feature = "provider-gac"), allow(dead_code))]
+    pub gac_tag: &'static str,
+    #[cfg_attr(not(feature = "provider-gac"), allow(dead_code))]
+    pub gac_auth_failed: &'static str,
+    #[cfg_attr(not(feature = "provider-anthropic"), allow(dead_code))]
+    pub ratelimit_tag: &'static str,
+    #[cfg_attr(not(feature = "provider-anthropic"), allow(dead_code))]
+    pub ratelimit_requests: &'static str,
+    #[cfg_attr(not(feature = "provider-anthropic"), allow(dead_code))]
+    pub ratelimit_tokens: &'static str,
+    #[cfg_attr(not(feature = "provider-openai"), allow(dead_code))]
+    pub openai_tag: &'static str,
+}
+
+pub static EN: Labels = Labels {
+    model_missing: "?model",
+    ctx: "ctx",
+    input: "in",
+    output: "out",
+    cache: "cache",
+    cached: "cached",
+    agent: "agent",
+    user: "user",
+    assistant: "asst",
+    idle: "idle",
+    thinking: "think",
+    exceeds_200k: ">200k",
+    errors: "err",
+    load: "load",
+    mcp: "mcp",
+    week: "wk",
+    month: "mo",
+    git_merge: "!merge",
+    git_rebase: "!rebase",
+    zhipu_tag: "ZAI",
+    zhipu_token: "Token(5h)",
+    zhipu_mcp: "MCP(1mo)",
+    yunyi_tag: "YUNYI",
+    yunyi_remaining: "Rem:",
+    yunyi_daily: "day",
+    yunyi_pack: "pack",
+    yunyi_expires: "Exp",
+    yunyi_pack_expires: "PackExp",
+    yunyi_in: "in ",
+    account: "acct:",
+    packycode_tag: "PC",
+    gac_tag: "GAC",
+    gac_auth_failed: "auth✗",
+    ratelimit_tag: "API",
+    ratelimit_requests: "req",
+    ratelimit_tokens: "tok",
+    openai_tag: "OAI",
+};
+
+pub static ZH: Labels = Labels {
+    model_missing: "?model",
+    ctx: "ctx",
+    input: "in",
+    output: "out",
+    cache: "cache",
+    cached: "缓存",
+    agent: "agent",
+    user: "用户",
+    assistant: "助手",
+    idle: "空闲",
+    thinking: "思考",
+    exceeds_200k: ">200k",
+    errors: "err",
+    load: "负载",
+    mcp: "mcp",
+    week: "周",
+    month: "月",
+    git_merge: "!merge",
+    git_rebase: "!rebase",
+    zhipu_tag: "ZAI",
+    zhipu_token: "Token(5h)",
+    zhipu_mcp: "MCP(1月)",
+    yunyi_tag: "YUNYI",
+    yunyi_remaining: "剩余",
+    yunyi_daily: "日",
+    yunyi_pack: "包",
+    yunyi_expires: "Exp",
+    yunyi_pack_expires: "包Exp",
+    yunyi_in: "剩",
+    account: "账号:",
+    packycode_tag: "PC",
+    gac_tag: "GAC",
+    gac_auth_failed: "auth✗",
+    ratelimit_tag: "API",
+    ratelimit_requests: "req",
+    ratelimit_tokens: "tok",
+    openai_tag: "OAI",
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_lang() {
+        assert_eq!(Locale::from_lang("zh_CN.UTF-8"), Locale::Zh);
+        assert_eq!(Locale::from_lang("en_US.UTF-8"), Locale::En);
+        assert_eq!(Locale::from_lang("C"), Locale::En);
+        assert_eq!(Locale::from_lang(""), Locale::En);
+    }
+}
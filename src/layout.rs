@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+
+use crate::colors;
+use crate::render::{Segment, Span};
+use crate::text::{truncate_middle, visible_width};
+
+/// 所有内置段的名称，按默认显示顺序排列
+pub const SEGMENT_NAMES: &[&str] = &[
+    "model",
+    "thinking",
+    "exceeds_200k",
+    "dir",
+    "git",
+    "agent",
+    "messages",
+    "idle",
+    "errors",
+    "ctx",
+    "tokens",
+    "cache",
+    "cache_ratio",
+    "env",
+    "platform",
+    "mcp",
+    "load",
+    "clock",
+    "cost",
+    "week_spend",
+    "month_spend",
+    "zhipu_token",
+    "zhipu_mcp",
+    "yunyi_account",
+    "yunyi_quota",
+    "yunyi_expiry",
+    "packycode_quota",
+    "gac_quota",
+    "gac_auth",
+    "anthropic_ratelimit",
+    "openai_spend",
+    "external",
+    "heartbeat",
+];
+
+/// 按阈值分级的段，可以用于 `only_on_warn`
+pub const THRESHOLD_SEGMENTS: &[&str] = &[
+    "ctx",
+    "cache",
+    "load",
+    "cost",
+    "zhipu_token",
+    "zhipu_mcp",
+    "yunyi_quota",
+    "packycode_quota",
+    "gac_quota",
+    "anthropic_ratelimit",
+];
+
+/// 可以从中间截断的文本段（数值类的段截断后没有意义）
+const TEXT_SEGMENTS: &[&str] = &["model", "dir", "git", "agent"];
+
+/// 文本段截断后至少保留的宽度
+const MIN_TRUNCATED_WIDTH: usize = 6;
+
+/// 段的默认优先级，数值越大越晚被丢弃
+pub fn default_priority(name: &str) -> i32 {
+    match name {
+        "ctx" => 100,
+        "model" => 90,
+        "errors" => 75,
+        "git" => 70,
+        "dir" => 60,
+        "env" => 55,
+        "zhipu_token" | "zhipu_mcp" | "yunyi_quota" | "packycode_quota" | "gac_quota" | "gac_auth"
+        | "anthropic_ratelimit" => 50,
+        "agent" | "mcp" => 45,
+        "cache" => 40,
+        "cache_ratio" => 35,
+        "tokens" => 30,
+        "week_spend" | "month_spend" => 20,
+        "load" => 15,
+        "yunyi_expiry" | "version" => 10,
+        "clock" => 5,
+        _ => 25,
+    }
+}
+
+/// 宽度受限时的截断规则
+pub struct FitOptions<'a> {
+    pub max_width: usize,
+    /// 用户覆盖的优先级
+    pub priorities: &'a HashMap<String, i32>,
+    /// 有段被丢弃时在末尾保留一个暗色 `…`
+    pub marker: bool,
+}
+
+impl FitOptions<'_> {
+    fn priority(&self, segment: &Segment) -> i32 {
+        self.priorities
+            .get(segment.name)
+            .copied()
+            .unwrap_or_else(|| default_priority(segment.name))
+    }
+}
+
+/// 让渲染结果不超过最大宽度
+///
+/// 按优先级从低到高整段丢弃（同优先级先丢靠后的段）；当剩余超出部分可以通过
+/// 截断优先级最低的文本段来消化时，改为从中间截断该段并停止丢弃。
+pub fn fit_width(
+    mut segments: Vec<Segment>,
+    options: &FitOptions,
+    render: impl Fn(&[Segment]) -> String,
+) -> Vec<Segment> {
+    let marker = Segment::new("truncated", colors::DIM, "…");
+    let mut dropped = false;
+
+    let width_of = |segments: &[Segment], dropped: bool| {
+        if dropped && options.marker {
+            let mut with_marker = segments.to_vec();
+            with_marker.push(marker.clone());
+            visible_width(&render(&with_marker))
+        } else {
+            visible_width(&render(segments))
+        }
+    };
+
+    loop {
+        let width = width_of(&segments, dropped);
+        if width <= options.max_width || segments.is_empty() {
+            break;
+        }
+        let overflow = width - options.max_width;
+
+        // 优先尝试截断优先级最低的文本段
+        let text_index = segments
+            .iter()
+            .enumerate()
+            .filter(|(_, segment)| TEXT_SEGMENTS.contains(&segment.name))
+            .min_by_key(|(i, segment)| (options.priority(segment), std::cmp::Reverse(*i)))
+            .map(|(i, _)| i);
+        if let Some(i) = text_index {
+            let text = segments[i].plain_text();
+            let text_width = visible_width(&text);
+            if text_width >= overflow + MIN_TRUNCATED_WIDTH {
+                let truncated = truncate_middle(&text, text_width - overflow);
+                let segment = &segments[i];
+                segments[i] = Segment {
+                    spans: vec![Span {
+                        color: segment.color(),
+                        text: truncated,
+                        severity: segment.spans.first().and_then(|span| span.severity),
+                    }],
+                    ..segment.clone()
+                };
+                continue;
+            }
+        }
+
+        let lowest = segments
+            .iter()
+            .enumerate()
+            .min_by_key(|(i, segment)| (options.priority(segment), std::cmp::Reverse(*i)))
+            .map(|(i, _)| i);
+        if let Some(i) = lowest {
+            segments.remove(i);
+            dropped = true;
+        }
+    }
+
+    if dropped && options.marker && !segments.is_empty() {
+        segments.push(marker);
+    }
+    segments
+}
+
+/// 只保留 `names` 中的段并按 `names` 的顺序排列，同名的多个段保持原有的相对顺序
+pub fn select(segments: Vec<Segment>, names: &[String]) -> Vec<Segment> {
+    let mut selected: Vec<Segment> = segments
+        .into_iter()
+        .filter(|segment| names.iter().any(|name| name == segment.name))
+        .collect();
+    // 稳定排序，同名段不会交换位置
+    selected.sort_by_key(|segment| names.iter().position(|name| name == segment.name));
+    selected
+}
+
+/// 用空格把右侧分组推到 `width` 列的末尾；两组之间至少留一个空格，放不下时返回 None
+pub fn align_right(left: &str, right: &str, width: usize) -> Option<String> {
+    let used = visible_width(left) + visible_width(right);
+    let padding = width.checked_sub(used).filter(|&padding| padding >= 1)?;
+    Some(format!("{}{}{}", left, " ".repeat(padding), right))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::render_plain;
+
+    /// 宽度依次递减的固定段
+    fn fixture() -> Vec<Segment> {
+        vec![
+            Segment::new("model", colors::MAGENTA, "[Claude Opus 4]"),
+            Segment::new("dir", colors::CYAN, "my-project"),
+            Segment::new("git", colors::BLUE, "main"),
+            Segment::new("ctx", colors::GREEN, "ctx:42%"),
+            Segment::new("tokens", colors::DIM, "in:1k"),
+            Segment::new("yunyi_expiry", colors::DIM, "Exp"),
+        ]
+    }
+
+    fn names(max_width: usize, priorities: &HashMap<String, i32>, marker: bool) -> Vec<String> {
+        let options = FitOptions {
+            max_width,
+            priorities,
+            marker,
+        };
+        fit_width(fixture(), &options, render_plain)
+            .iter()
+            .map(|segment| format!("{}={}", segment.name, segment.plain_text()))
+            .collect()
+    }
+
+    #[test]
+    fn test_fits_without_changes() {
+        // 全部宽度：15+10+4+7+5+3 + 5*3 = 59
+        let all = names(59, &HashMap::new(), true);
+        assert_eq!(all.len(), 6);
+        assert_eq!(visible_width(&render_plain(&fixture())), 59);
+    }
+
+    #[test]
+    fn test_drop_order() {
+        let none = HashMap::new();
+        // 丢弃 yunyi_expiry（优先级 10）
+        assert_eq!(
+            names(53, &none, false),
+            ["model=[Claude Opus 4]", "dir=my-project", "git=main", "ctx=ctx:42%", "tokens=in:1k"]
+        );
+        // 再丢弃 tokens（30），超出 1 列由 dir 截断消化
+        assert_eq!(
+            names(44, &none, false),
+            ["model=[Claude Opus 4]", "dir=my-p…ject", "git=main", "ctx=ctx:42%"]
+        );
+        // dir 截断后仍放不下时整段丢弃，git 太短无法截断，继续丢弃
+        assert_eq!(names(28, &none, false), ["model=[Claude Opus 4]", "ctx=ctx:42%"]);
+        // 只剩最重要的段
+        assert_eq!(names(12, &none, false), ["ctx=ctx:42%"]);
+    }
+
+    #[test]
+    fn test_priority_override_and_marker() {
+        let priorities = HashMap::from([("yunyi_expiry".to_string(), 95)]);
+        assert_eq!(
+            names(47, &priorities, true),
+            ["model=[Claude Opus 4]", "git=main", "ctx=ctx:42%", "yunyi_expiry=Exp", "truncated=…"]
+        );
+    }
+
+    #[test]
+    fn test_select() {
+        let mut segments = fixture();
+        segments.push(Segment::new("git", colors::YELLOW, "+2"));
+        let wanted = ["ctx", "git", "model", "clock"].map(String::from);
+        let selected: Vec<String> = select(segments, &wanted)
+            .iter()
+            .map(|segment| format!("{}={}", segment.name, segment.plain_text()))
+            .collect();
+        assert_eq!(selected, ["ctx=ctx:42%", "git=main", "git=+2", "model=[Claude Opus 4]"]);
+        assert!(select(fixture(), &[]).is_empty());
+    }
+
+    #[test]
+    fn test_align_right() {
+        assert_eq!(align_right("left", "14:32", 15).unwrap(), "left      14:32");
+        // 按可见宽度计算，ANSI 转义序列不占宽度
+        let colored = "\x1b[32mctx:42%\x1b[0m";
+        let line = align_right(colored, "14:32", 20).unwrap();
+        assert_eq!(visible_width(&line), 20);
+        assert!(line.ends_with("      14:32"));
+        // 至少一个空格，放不下时不返回负的填充
+        assert_eq!(align_right("left", "14:32", 10).unwrap(), "left 14:32");
+        assert!(align_right("left", "14:32", 9).is_none());
+        assert!(align_right("left", "14:32", 3).is_none());
+    }
+}
@@ -0,0 +1,74 @@
+use crate::colors::Threshold;
+use crate::i18n::Labels;
+use crate::render::Segment;
+
+/// 系统 1 分钟平均负载；Linux 读取 `/proc/loadavg`，macOS 使用 `sysctl`，其他平台为 None
+#[cfg(target_os = "linux")]
+pub fn load_average() -> Option<f64> {
+    parse_proc_loadavg(&std::fs::read_to_string("/proc/loadavg").ok()?)
+}
+
+#[cfg(target_os = "macos")]
+pub fn load_average() -> Option<f64> {
+    let output = std::process::Command::new("sysctl").args(["-n", "vm.loadavg"]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_sysctl_loadavg(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn load_average() -> Option<f64> {
+    None
+}
+
+/// `/proc/loadavg` 的格式：`0.52 0.58 0.59 1/467 12345`
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_proc_loadavg(content: &str) -> Option<f64> {
+    content.split_whitespace().next()?.parse().ok()
+}
+
+/// `sysctl -n vm.loadavg` 的格式：`{ 1.23 1.45 1.50 }`
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn parse_sysctl_loadavg(content: &str) -> Option<f64> {
+    content.trim().trim_start_matches('{').split_whitespace().next()?.parse().ok()
+}
+
+/// 负载段，按每个核心的负载和主题阈值着色，默认达到 70% 为黄色，满载为红色
+pub fn segment(load: f64, cores: usize, threshold: Threshold, labels: &Labels) -> Segment {
+    let per_core = load / cores.max(1) as f64 * 100.0;
+    let severity = threshold.severity(per_core);
+    Segment::graded("load", severity, format!("{}:{:.2}", labels.load, load))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colors;
+
+    #[test]
+    fn test_parse_loadavg() {
+        assert_eq!(parse_proc_loadavg("0.52 0.58 0.59 1/467 12345\n"), Some(0.52));
+        assert_eq!(parse_proc_loadavg(""), None);
+        assert_eq!(parse_sysctl_loadavg("{ 1.23 1.45 1.50 }\n"), Some(1.23));
+        assert_eq!(parse_sysctl_loadavg("{ }"), None);
+    }
+
+    #[test]
+    fn test_segment_color_by_cores() {
+        let color = |load, cores| segment(load, cores, colors::DEFAULT_THRESHOLDS.load, &crate::i18n::EN).color();
+        let load = segment(1.234, 8, colors::DEFAULT_THRESHOLDS.load, &crate::i18n::EN);
+        assert_eq!(load.plain_text(), "load:1.23");
+        assert_eq!(load.color(), colors::GREEN);
+        assert_eq!(color(6.0, 8), colors::YELLOW);
+        assert_eq!(color(9.5, 8), colors::RED);
+        // 核心数未知时按单核计算
+        assert_eq!(color(1.0, 0), colors::RED);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_load_average_on_linux() {
+        assert!(load_average().is_some_and(|load| load >= 0.0));
+    }
+}
@@ -0,0 +1,66 @@
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// 是否输出调试日志（对应 `statusline.debug`）
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// 暂存中的日志，为 None 时直接输出
+static HELD: Mutex<Option<Vec<String>>> = Mutex::new(None);
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// 调试模式下把警告写到 stderr，不影响 stdout 上的 statusline
+pub fn warn(message: impl Display) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let line = format!("cc-statusline: {}", message);
+    if let Ok(mut held) = HELD.lock() {
+        if let Some(held) = held.as_mut() {
+            held.push(line);
+            return;
+        }
+    }
+    eprintln!("{}", line);
+}
+
+/// 暂存之后的日志，直到调用 [`release`]
+pub fn hold() {
+    if let Ok(mut held) = HELD.lock() {
+        held.get_or_insert_with(Vec::new);
+    }
+}
+
+/// 结束暂存并返回暂存的日志，`emit` 为 true 时同时输出
+pub fn release(emit: bool) -> Vec<String> {
+    let held = HELD.lock().ok().and_then(|mut held| held.take()).unwrap_or_default();
+    if emit {
+        for line in &held {
+            eprintln!("{}", line);
+        }
+    }
+    held
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hold_and_release() {
+        set_enabled(true);
+        hold();
+        warn("first");
+        warn(format_args!("second {}", 2));
+        // 其他测试可能同时写日志，只检查包含本测试的两条
+        let held = release(false);
+        assert!(held.contains(&"cc-statusline: first".to_string()));
+        assert!(held.contains(&"cc-statusline: second 2".to_string()));
+        // 结束暂存后不再收集
+        assert!(release(false).is_empty());
+        set_enabled(false);
+    }
+}
@@ -1,28 +1,61 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::io::{self, Read};
-use std::process::Command;
+use std::process::{Command, Stdio};
 use std::fs;
 use std::path::PathBuf;
+use std::time::Duration;
 
+mod cli;
+mod colors;
+mod config;
+mod context_window;
+mod doctor;
+mod environment;
+mod error;
+mod external;
+mod gc;
+mod history;
+mod i18n;
+mod layout;
+mod load;
+mod log;
+mod mcp;
+mod metrics;
 mod providers;
-use providers::providers;
+mod paths;
+mod platform;
+mod render;
+mod report;
+mod session;
+mod text;
+mod trace;
+mod transcript;
+mod validate;
+use cli::OutputFormat;
+use colors::{Severity, Theme};
+use config::{read_claude_config, ClaudeConfig, GitConfig, Style, TokenFormat, TokensStyle};
+use i18n::Labels;
+use providers::ProviderContext;
+use render::Segment;
+use session::SessionState;
 
 /// 模型信息
-#[derive(Debug, Deserialize, Default)]
+#[derive(Debug, Deserialize, Serialize, Default)]
 pub struct Model {
     pub id: Option<String>,
     pub display_name: Option<String>,
 }
 
 /// 工作区信息
-#[derive(Debug, Deserialize, Default)]
+#[derive(Debug, Deserialize, Serialize, Default)]
 pub struct Workspace {
     pub current_dir: Option<String>,
     pub project_dir: Option<String>,
 }
 
 /// 成本统计
-#[derive(Debug, Deserialize, Default)]
+#[derive(Debug, Deserialize, Serialize, Default)]
 pub struct Cost {
     pub total_cost_usd: Option<f64>,
     pub total_duration_ms: Option<u64>,
@@ -32,7 +65,7 @@ pub struct Cost {
 }
 
 /// 当前使用情况
-#[derive(Debug, Deserialize, Default)]
+#[derive(Debug, Deserialize, Serialize, Default)]
 pub struct CurrentUsage {
     pub input_tokens: Option<u64>,
     pub output_tokens: Option<u64>,
@@ -41,7 +74,7 @@ pub struct CurrentUsage {
 }
 
 /// 上下文窗口信息
-#[derive(Debug, Deserialize, Default)]
+#[derive(Debug, Deserialize, Serialize, Default)]
 pub struct ContextWindow {
     pub total_input_tokens: Option<u64>,
     pub total_output_tokens: Option<u64>,
@@ -51,14 +84,41 @@ pub struct ContextWindow {
     pub current_usage: Option<CurrentUsage>,
 }
 
+/// 上下文使用率：优先 `used_percentage`，不存在或为 0 时按 `(输入 + 输出) / 窗口大小 * 100` 计算
+fn resolve_context_pct(cw: &ContextWindow) -> Option<f64> {
+    if let Some(used_pct) = cw.used_percentage.filter(|&used_pct| used_pct > 0.0) {
+        return Some(used_pct);
+    }
+    let total_in = cw.total_input_tokens?;
+    let total_out = cw.total_output_tokens?;
+    let window_size = cw.context_window_size.filter(|&window_size| window_size > 0)?;
+    Some(((total_in + total_out) as f64 / window_size as f64) * 100.0)
+}
+
+/// API 错误统计（预留字段，Claude Code 暂未提供）
+#[derive(Debug, Deserialize, Serialize, Default)]
+pub struct ErrorStats {
+    pub count: Option<u64>,
+    pub last_message: Option<String>,
+}
+
+/// 字段为 `null` 时使用默认值，而不是整个输入解析失败
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
 /// 输出样式
-#[derive(Debug, Deserialize, Default)]
+#[derive(Debug, Deserialize, Serialize, Default)]
 pub struct OutputStyle {
     pub name: Option<String>,
 }
 
 /// Claude Code Statusline 输入数据结构
-#[derive(Debug, Deserialize, Default)]
+#[derive(Debug, Deserialize, Serialize, Default)]
 pub struct StatusInput {
     pub hook_event_name: Option<String>,
     pub session_id: Option<String>,
@@ -75,50 +135,139 @@ pub struct StatusInput {
     pub context_window: ContextWindow,
     #[serde(default)]
     pub output_style: OutputStyle,
+    /// 预留字段，可能以 `null` 出现，与缺失同样处理
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub errors: ErrorStats,
+    /// 当前子代理（新版本 Claude Code 可能提供，字符串或对象）
+    pub agent: Option<serde_json::Value>,
+    /// 本次请求的 token 数是否超过 200k（新版本 Claude Code 提供）
+    pub exceeds_200k_tokens: Option<bool>,
+    /// 扩展思考状态（新版本 Claude Code 可能提供，布尔值或对象）
+    pub thinking: Option<serde_json::Value>,
 }
 
-/// ANSI 颜色代码
-mod colors {
-    pub const RESET: &str = "\x1b[0m";
-    pub const BOLD: &str = "\x1b[1m";
-    pub const DIM: &str = "\x1b[2m";
+impl StatusInput {
+    /// git 命令使用的目录：优先 `workspace.current_dir`，没有时使用顶层的 `cwd`
+    fn git_dir(&self) -> Option<&str> {
+        self.workspace.current_dir.as_deref().or(self.cwd.as_deref())
+    }
+
+    /// transcript 文件路径：相对路径按 `cwd`（没有时为 `workspace.current_dir`）解析，而不是进程的当前目录
+    fn resolved_transcript_path(&self) -> Option<String> {
+        let path = std::path::Path::new(self.transcript_path.as_deref()?);
+        let base = self.cwd.as_deref().or(self.workspace.current_dir.as_deref());
+        match base {
+            Some(base) if path.is_relative() => {
+                Some(PathBuf::from(base).join(path).to_string_lossy().into_owned())
+            }
+            _ => self.transcript_path.clone(),
+        }
+    }
+
+    /// 项目目录：优先 `workspace.project_dir`，没有时使用 [`StatusInput::git_dir`]
+    fn project_dir(&self) -> Option<&str> {
+        self.workspace.project_dir.as_deref().or(self.git_dir())
+    }
+
+    /// 上下文使用率：先按 [`resolve_context_pct`] 计算，
+    /// 输入中没有 `context_window_size` 时按模型 id 推断窗口大小（见 [`context_window::infer`]），
+    /// 返回值的第二项表示使用率是否为推断得到的估计值。
+    fn context_percentage(&self, windows: &HashMap<String, u64>) -> Option<(f64, bool)> {
+        let context = &self.context_window;
+        if let Some(percentage) = resolve_context_pct(context) {
+            return Some((percentage, false));
+        }
+        if context.context_window_size.is_some() {
+            return None;
+        }
+        let total_in = context.total_input_tokens?;
+        let total_out = context.total_output_tokens?;
+        let window_size = context_window::infer(self.model.id.as_deref()?, windows)?;
+        if window_size > 0 {
+            Some((((total_in + total_out) as f64 / window_size as f64) * 100.0, true))
+        } else {
+            None
+        }
+    }
+
+    /// 扩展思考是否开启：`true`、`{"enabled": true}` 或 `{"type": "enabled"}`
+    fn thinking_enabled(&self) -> bool {
+        match self.thinking.as_ref() {
+            Some(serde_json::Value::Bool(enabled)) => *enabled,
+            Some(serde_json::Value::Object(map)) => {
+                map.get("enabled").and_then(serde_json::Value::as_bool).unwrap_or(false)
+                    || map.get("type").and_then(serde_json::Value::as_str) == Some("enabled")
+            }
+            _ => false,
+        }
+    }
+
+    /// 输入中携带的子代理名称
+    fn agent_name(&self) -> Option<String> {
+        match self.agent.as_ref()? {
+            serde_json::Value::String(name) => Some(name.clone()),
+            serde_json::Value::Object(map) => ["name", "subagent_type", "type"]
+                .iter()
+                .find_map(|key| map.get(*key)?.as_str())
+                .map(String::from),
+            _ => None,
+        }
+    }
+}
 
-    pub const RED: &str = "\x1b[31m";
-    pub const GREEN: &str = "\x1b[32m";
-    pub const YELLOW: &str = "\x1b[33m";
-    pub const BLUE: &str = "\x1b[34m";
-    pub const MAGENTA: &str = "\x1b[35m";
-    pub const CYAN: &str = "\x1b[36m";
+/// 根据使用百分比返回当前主题下的级别
+fn context_severity(theme: &Theme, percentage: f64) -> Severity {
+    theme.thresholds.context.severity(percentage)
 }
 
-/// 根据使用百分比返回对应颜色
-fn get_context_color(percentage: f64) -> &'static str {
-    if percentage >= 80.0 {
-        colors::RED
-    } else if percentage >= 60.0 {
-        colors::YELLOW
-    } else {
+/// 根据模型 id 选择模型名称的颜色，未知模型使用紫色
+fn get_model_color(model_id: Option<&str>) -> &'static str {
+    let id = model_id.unwrap_or_default().to_ascii_lowercase();
+    if id.contains("sonnet") {
+        colors::BLUE
+    } else if id.contains("haiku") {
         colors::GREEN
+    } else {
+        colors::MAGENTA
     }
 }
 
-/// 格式化成本显示
+/// 格式化百分比（向下取整，避免显示值高于实际值）
+fn format_percentage(percentage: f64, decimals: usize) -> String {
+    let factor = 10f64.powi(decimals as i32);
+    // 加上极小量抵消浮点误差（如 79.6 * 10 = 795.999...）
+    let floored = (percentage * factor + 1e-9).floor() / factor;
+    format!("{:.*}", decimals, floored)
+}
+
+/// 格式化成本显示：小额保留更多小数，1 美元以上保留两位并加千位分隔符
+///
+/// 负数或非有限值（有问题的中转站会返回）显示为 0.00。
 fn format_cost(cost: f64) -> String {
+    if !cost.is_finite() || cost < 0.0 {
+        log::warn(format_args!("invalid cost {}, showing 0.00", cost));
+        return "0.00".to_string();
+    }
     if cost < 0.01 {
-        format!("{:.4}", cost)
-    } else if cost < 1.0 {
-        format!("{:.3}", cost)
-    } else {
-        format!("{:.2}", cost)
+        return format!("{:.4}", cost);
     }
+    if cost < 1.0 {
+        return format!("{:.3}", cost);
+    }
+    let formatted = format!("{:.2}", cost);
+    let (integer, fraction) = formatted.split_once('.').unwrap_or((&formatted, "00"));
+    // 超出 u64 的金额不分组
+    let grouped = integer.parse().map_or_else(|_| integer.to_string(), group_thousands);
+    format!("{}.{}", grouped, fraction)
 }
 
-/// 从路径中提取目录名
-fn get_dir_name(path: &str) -> &str {
-    std::path::Path::new(path)
+/// 从路径中提取目录名，去掉其中的控制字符
+fn get_dir_name(path: &str) -> String {
+    let name = std::path::Path::new(path)
         .file_name()
         .and_then(|s| s.to_str())
-        .unwrap_or(path)
+        .unwrap_or(if path.starts_with('/') { "" } else { path });
+    text::strip_control(name)
 }
 
 /// 获取当前 git 分支名
@@ -126,48 +275,278 @@ fn get_git_branch(cwd: Option<&str>) -> Option<String> {
     let cwd = cwd?; // 如果没有工作目录,直接返回 None
 
     let output = Command::new("git")
-        .args(&["branch", "--show-current"])
+        .args(["branch", "--show-current"])
         .current_dir(cwd)
         .output()
         .ok()?;
 
     if output.status.success() {
-        let branch = String::from_utf8(output.stdout).ok()?;
-        let branch = branch.trim();
-        if !branch.is_empty() {
-            return Some(branch.to_string());
+        return parse_branch(output.stdout);
+    }
+    None
+}
+
+/// 解析 `git branch --show-current` 的输出；去掉控制字符，避免向终端注入控制序列
+fn parse_branch(stdout: Vec<u8>) -> Option<String> {
+    let branch = String::from_utf8(stdout).ok()?;
+    let branch = text::strip_control(branch.trim());
+    let branch = branch.trim();
+    (!branch.is_empty()).then(|| branch.to_string())
+}
+
+/// 不在仓库中的检查结果的缓存时长，期间同一目录不再查找 `.git`
+const NO_REPO_CACHE_SECS: i64 = 60;
+
+/// 从工作目录向上查找工作区根目录（包含 `.git` 目录或 worktree 的 `.git` 文件的目录，
+/// 与 `git rev-parse --show-toplevel` 一致）
+///
+/// 最多查找 `git.search_depth` 层；开启 `git.stop_at_home` 时到 `home` 为止，`home` 本身仍会检查。
+fn find_repo_root<'a>(
+    cwd: &'a std::path::Path,
+    git: &GitConfig,
+    home: Option<&std::path::Path>,
+) -> Option<&'a std::path::Path> {
+    let home = home.filter(|_| git.stop_at_home);
+    for dir in cwd.ancestors().take(git.search_depth + 1) {
+        if dir.join(".git").exists() {
+            return Some(dir);
+        }
+        if home.is_some_and(|home| dir == home) {
+            break;
         }
     }
     None
 }
 
-/// 获取未提交的文件数量
-fn get_uncommitted_files(cwd: Option<&str>) -> Option<usize> {
+/// 用户的 home 目录，查找仓库时不越过它
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(PathBuf::from)
+}
+
+/// `dir` 是否在缓存有效期内被记录为不在仓库中
+fn cached_no_repo(path: &std::path::Path, dir: &str, now: chrono::DateTime<chrono::Utc>) -> bool {
+    let Ok(content) = fs::read_to_string(path) else {
+        return false;
+    };
+    let Ok(checked) = serde_json::from_str::<HashMap<String, chrono::DateTime<chrono::Utc>>>(&content)
+    else {
+        return false;
+    };
+    checked
+        .get(dir)
+        .is_some_and(|checked_at| now.signed_duration_since(*checked_at).num_seconds() < NO_REPO_CACHE_SECS)
+}
+
+/// 记录 `dir` 不在仓库中，同时清理过期的记录；失败时只在调试模式下提示
+fn record_no_repo(path: &std::path::Path, dir: &str, now: chrono::DateTime<chrono::Utc>) {
+    if !paths::persistence_enabled() {
+        return;
+    }
+    let mut checked: HashMap<String, chrono::DateTime<chrono::Utc>> = fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    checked.retain(|_, checked_at| now.signed_duration_since(*checked_at).num_seconds() < NO_REPO_CACHE_SECS);
+    checked.insert(dir.to_string(), now);
+    let Ok(json) = serde_json::to_string(&checked) else {
+        return;
+    };
+    if let Err(err) = paths::write_atomic(path, json) {
+        paths::write_failed(error::StatuslineError::write(path, err));
+    }
+}
+
+/// 目录是否在 git 仓库中；不在时 60 秒内不再查找（很少有人在会话中途 `git init`）
+///
+/// 缓存只为少启动 git 子进程，所有 git 命令都关闭时直接查找 `.git`，不读写缓存文件。
+fn inside_repo(cwd: Option<&str>, git: &GitConfig) -> bool {
+    let Some(dir) = cwd.map(PathBuf::from).or_else(|| std::env::current_dir().ok()) else {
+        return false;
+    };
+    let search = || find_repo_root(&dir, git, home_dir().as_deref()).is_some();
+    if !(git.branch || git.dirty || git.ahead_behind) {
+        return search();
+    }
+    let key = dir.to_string_lossy().into_owned();
+    let cache_path = paths::git_cache_path();
+    let now = chrono::Utc::now();
+    if cached_no_repo(&cache_path, &key, now) {
+        return false;
+    }
+    let found = search();
+    if !found {
+        record_no_repo(&cache_path, &key, now);
+    }
+    found
+}
+
+/// 从工作目录向上查找 git 目录（兼容 worktree 的 `.git` 文件）
+fn find_git_dir(cwd: &str, git: &GitConfig) -> Option<PathBuf> {
+    let root = find_repo_root(std::path::Path::new(cwd), git, home_dir().as_deref())?;
+    let dot_git = root.join(".git");
+    if dot_git.is_dir() {
+        return Some(dot_git);
+    }
+    // worktree/submodule: 内容形如 "gitdir: /path/to/git/dir"
+    let content = fs::read_to_string(&dot_git).ok()?;
+    let git_dir = content.trim().strip_prefix("gitdir:")?.trim();
+    Some(root.join(git_dir))
+}
+
+/// 进行中的 git 操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GitState {
+    Merge,
+    Rebase,
+}
+
+impl GitState {
+    fn label(self, labels: &Labels) -> &'static str {
+        match self {
+            GitState::Merge => labels.git_merge,
+            GitState::Rebase => labels.git_rebase,
+        }
+    }
+}
+
+/// 获取进行中的 git 操作状态（merge/rebase），正常情况下返回 None
+fn get_git_state(cwd: Option<&str>, git: &GitConfig) -> Option<GitState> {
+    let git_dir = find_git_dir(cwd?, git)?;
+
+    if git_dir.join("MERGE_HEAD").exists() {
+        Some(GitState::Merge)
+    } else if git_dir.join("rebase-merge").exists() || git_dir.join("rebase-apply").exists() {
+        Some(GitState::Rebase)
+    } else {
+        None
+    }
+}
+
+/// `git status` 的等待上限，大仓库超时后不显示改动数，分支保持蓝色
+const GIT_STATUS_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// 工作区状态：有改动的文件数（含冲突）和其中冲突的文件数
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct WorktreeStatus {
+    changed: usize,
+    conflicted: usize,
+}
+
+impl WorktreeStatus {
+    /// 分支名的颜色：干净为绿色，有改动为黄色，有冲突为红色
+    fn branch_color(self) -> &'static str {
+        if self.conflicted > 0 {
+            colors::RED
+        } else if self.changed > 0 {
+            colors::YELLOW
+        } else {
+            colors::GREEN
+        }
+    }
+}
+
+/// 获取工作区状态，无法确定（不是仓库、超时）时返回 None
+fn get_worktree_status(cwd: Option<&str>) -> Option<WorktreeStatus> {
+    let mut command = Command::new("git");
+    command.args(["status", "--porcelain"]).current_dir(cwd?);
+    read_worktree_status(command, GIT_STATUS_TIMEOUT)
+}
+
+/// 执行输出 porcelain 格式的命令并解析
+fn read_worktree_status(mut command: Command, timeout: Duration) -> Option<WorktreeStatus> {
+    let output = output_within(&mut command, &[], timeout)?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(parse_porcelain(&String::from_utf8(output.stdout).ok()?))
+}
+
+/// 执行命令（`input` 非空时写入 stdin）并在 `timeout` 内等待输出，超时时结束进程并返回 None
+fn output_within(command: &mut Command, input: &[u8], timeout: Duration) -> Option<std::process::Output> {
+    let stdin = if input.is_empty() { Stdio::null() } else { Stdio::piped() };
+    let mut child = command
+        .stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    // 在单独的线程读写，避免输入输出较多时填满管道互相阻塞
+    if let Some(mut stdin) = child.stdin.take() {
+        let input = input.to_vec();
+        std::thread::spawn(move || {
+            use std::io::Write;
+            let _ = stdin.write_all(&input);
+        });
+    }
+    let mut stdout = child.stdout.take()?;
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = stdout.read_to_end(&mut buf);
+        let _ = sender.send(buf);
+    });
+
+    match receiver.recv_timeout(timeout) {
+        Ok(stdout) => Some(std::process::Output {
+            status: child.wait().ok()?,
+            stdout,
+            stderr: Vec::new(),
+        }),
+        Err(_) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            None
+        }
+    }
+}
+
+/// 解析 `git status --porcelain` 的输出，`UU`、`AA` 等状态码表示冲突
+fn parse_porcelain(status: &str) -> WorktreeStatus {
+    let mut worktree = WorktreeStatus::default();
+    for line in status.lines().filter(|line| !line.is_empty()) {
+        worktree.changed += 1;
+        if matches!(line.get(..2), Some("DD" | "AU" | "UD" | "UA" | "DU" | "AA" | "UU")) {
+            worktree.conflicted += 1;
+        }
+    }
+    worktree
+}
+
+/// 获取相对上游分支的领先/落后提交数，没有上游时返回 None
+fn get_ahead_behind(cwd: Option<&str>) -> Option<(u64, u64)> {
     let cwd = cwd?;
 
     let output = Command::new("git")
-        .args(&["status", "--porcelain"])
+        .args(["rev-list", "--left-right", "--count", "HEAD...@{upstream}"])
         .current_dir(cwd)
         .output()
         .ok()?;
 
-    if output.status.success() {
-        let status = String::from_utf8(output.stdout).ok()?;
-        let count = status.lines().filter(|line| !line.is_empty()).count();
-        if count > 0 {
-            return Some(count);
-        }
+    if !output.status.success() {
+        return None;
     }
-    None
+    let counts = String::from_utf8(output.stdout).ok()?;
+    parse_ahead_behind(&counts)
+}
+
+/// 解析 `git rev-list --left-right --count` 的输出（"领先\t落后"）
+fn parse_ahead_behind(counts: &str) -> Option<(u64, u64)> {
+    let mut fields = counts.split_whitespace();
+    let ahead = fields.next()?.parse().ok()?;
+    let behind = fields.next()?.parse().ok()?;
+    Some((ahead, behind))
 }
 
-/// 格式化会话时长
+/// 格式化时长，超过一天时以天和小时显示
 fn format_duration(ms: u64) -> String {
     let seconds = ms / 1000;
     let minutes = seconds / 60;
     let hours = minutes / 60;
+    let days = hours / 24;
 
-    if hours > 0 {
+    if days > 0 {
+        format!("{}d{}h", days, hours % 24)
+    } else if hours > 0 {
         format!("{}h{}m", hours, minutes % 60)
     } else if minutes > 0 {
         format!("{}m", minutes)
@@ -176,6 +555,133 @@ fn format_duration(ms: u64) -> String {
     }
 }
 
+/// 格式化 token 数，超过 1000 时以 k 为单位，超过一百万时以 M 为单位，超过十亿时以 B 为单位
+///
+/// 全部使用整数运算（保留一位小数，四舍五入），避免大数转 f64 时丢失精度。
+fn format_tokens(tokens: u64, format: TokenFormat) -> String {
+    match format {
+        TokenFormat::Abbrev => {}
+        TokenFormat::Grouped => return group_thousands(tokens),
+        TokenFormat::Raw => return tokens.to_string(),
+    }
+    if tokens < 1000 {
+        return tokens.to_string();
+    }
+    // 四舍五入后达到 1000.0 的进位到下一个单位
+    let (divisor, unit) = if tokens < 999_950 {
+        (100, "k")
+    } else if tokens < 999_950_000 {
+        (100_000, "M")
+    } else {
+        (100_000_000, "B")
+    };
+    let tenths = tokens / divisor + u64::from(tokens % divisor >= divisor / 2);
+    format!("{}.{}{}", tenths / 10, tenths % 10, unit)
+}
+
+/// 每三位插入一个逗号，如 `1,234,567`
+fn group_thousands(value: u64) -> String {
+    let digits = value.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    // 距末尾的位数是 3 的倍数时插入逗号
+    let offset = digits.len() % 3;
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && i % 3 == offset {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// token 统计段使用的数量
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct TokenCounts {
+    input: Option<u64>,
+    output: Option<u64>,
+    cache_read: Option<u64>,
+    /// 来自会话累计值（`total_input_tokens` / `total_output_tokens`），而不是最近一次请求
+    totals: bool,
+}
+
+impl TokenCounts {
+    /// 优先使用最近一次请求的 `current_usage`，没有时退回会话累计值
+    fn from_context(context: &ContextWindow) -> Self {
+        match &context.current_usage {
+            Some(usage) => TokenCounts {
+                input: usage.input_tokens,
+                output: usage.output_tokens,
+                cache_read: usage.cache_read_input_tokens,
+                totals: false,
+            },
+            None => TokenCounts {
+                input: context.total_input_tokens,
+                output: context.total_output_tokens,
+                cache_read: None,
+                totals: true,
+            },
+        }
+    }
+}
+
+/// token 统计段；会话累计值前面加上暗色的 `Σ`，如 `Σ in:1.2M out:45.0k`
+fn tokens_segment(
+    context: &ContextWindow,
+    statusline: &config::StatuslineConfig,
+    labels: &Labels,
+) -> Option<Segment> {
+    let counts = TokenCounts::from_context(context);
+    let format = statusline.token_format;
+    match statusline.tokens_style {
+        TokensStyle::Compact => compact_tokens_segment(&counts, format),
+        TokensStyle::Separate if !counts.totals => {
+            let text = format!("{}:{}", labels.input, format_tokens(counts.input?, format));
+            Some(Segment::new("tokens", colors::DIM, text))
+        }
+        TokensStyle::Separate => {
+            let parts = [(labels.input, counts.input), (labels.output, counts.output)];
+            let mut segment = Segment::new("tokens", colors::DIM, "Σ");
+            let mut empty = true;
+            for (label, tokens) in parts {
+                if let Some(tokens) = tokens {
+                    let text = format!(" {}:{}", label, format_tokens(tokens, format));
+                    segment = segment.with(colors::DIM, text);
+                    empty = false;
+                }
+            }
+            (!empty).then_some(segment)
+        }
+    }
+}
+
+/// 合并的 token 统计段，按输入、输出、缓存读取的顺序排列，如 `⛁ 12.3k↑ 1.1k↓ 48.0k⚡`；都为 0 时为 None
+fn compact_tokens_segment(counts: &TokenCounts, format: TokenFormat) -> Option<Segment> {
+    let parts = [
+        (counts.input, colors::CYAN, "↑"),
+        (counts.output, colors::GREEN, "↓"),
+        (counts.cache_read, colors::BLUE, "⚡"),
+    ];
+    let glyph = if counts.totals { "⛁ Σ" } else { "⛁" };
+    let mut segment = Segment::new("tokens", colors::DIM, glyph);
+    let mut empty = true;
+    for (tokens, color, marker) in parts {
+        let Some(tokens) = tokens.filter(|&tokens| tokens > 0) else {
+            continue;
+        };
+        segment = segment
+            .with(colors::DIM, " ")
+            .with(color, format_tokens(tokens, format))
+            .with(colors::DIM, marker);
+        empty = false;
+    }
+    (!empty).then_some(segment)
+}
+
+/// 上下文增长量在当前主题下的级别，默认 2k 以下正常，10k 以下警告，否则严重
+fn growth_severity(theme: &Theme, delta: u64) -> Severity {
+    theme.thresholds.growth.severity(delta as f64)
+}
+
 /// 计算缓存命中率
 fn calculate_cache_hit_rate(usage: &CurrentUsage) -> Option<f64> {
     let cache_read = usage.cache_read_input_tokens?;
@@ -189,201 +695,1602 @@ fn calculate_cache_hit_rate(usage: &CurrentUsage) -> Option<f64> {
     Some(hit_rate)
 }
 
-/// Claude Code 配置文件结构
-#[derive(Debug, Deserialize)]
-struct ClaudeConfig {
-    #[serde(rename = "baseURL")]
-    base_url: Option<String>,
-    #[serde(rename = "authToken")]
-    auth_token: Option<String>,
+/// 计算缓存读取与创建的比例（每创建 1 个 token 读取多少个）
+fn calculate_cache_ratio(usage: &CurrentUsage) -> Option<f64> {
+    let cache_read = usage.cache_read_input_tokens?;
+    let cache_creation = usage.cache_creation_input_tokens?;
+
+    if cache_creation == 0 {
+        return None;
+    }
+
+    Some(cache_read as f64 / cache_creation as f64)
+}
+
+/// 计算当前上下文中来自缓存读取的比例（百分比）
+fn calculate_cached_share(usage: &CurrentUsage) -> Option<f64> {
+    let cache_read = usage.cache_read_input_tokens?;
+    let total = usage.input_tokens? + cache_read + usage.cache_creation_input_tokens?;
+
+    if total == 0 {
+        return None;
+    }
+
+    Some((cache_read as f64 / total as f64) * 100.0)
 }
 
-/// 从 Claude Code 配置文件读取配置
-fn read_claude_config() -> Option<(String, String)> {
-    let home = std::env::var("HOME")
-        .or_else(|_| std::env::var("USERPROFILE"))
-        .ok()?;
-    let config_path = PathBuf::from(home).join(".claude").join("settings.json");
+/// 渲染时依赖外部环境的数据来源
+///
+/// `main` 使用 [`StatuslineOptions::system`] 读取真实的 git、数据源等信息，
+/// 测试中可以替换为返回固定值的函数，使整行输出可预测。
+struct StatuslineOptions<'a> {
+    /// 按会话保存的状态（上下文增长量等）
+    session: &'a SessionState,
+    /// 读取目录的 git 信息
+    git: fn(Option<&str>, &GitConfig) -> GitInfo,
+    /// 自定义数据源的段
+    providers: fn(&ClaudeConfig, &ProviderContext) -> Vec<Segment>,
+    /// 运行环境（SSH / 容器 / WSL）
+    environment: fn() -> Option<environment::Environment>,
+    /// 云平台（Bedrock / Vertex）
+    platform: fn() -> Option<platform::Platform>,
+    /// 从 transcript 中查找运行中的子代理
+    subagent: fn(&str) -> Option<String>,
+    /// 按角色统计 transcript 中的消息数
+    messages: fn(&str) -> Option<transcript::MessageCounts>,
+    /// transcript 距上次修改的时长
+    idle: fn(&str) -> Option<std::time::Duration>,
+    /// 本周/本月花费
+    spend: fn() -> Option<history::Spend>,
+    /// 进程当前目录
+    process_dir: fn() -> Option<String>,
+    /// 按格式输出的本地时间，格式无效时为 None
+    clock: fn(&str) -> Option<String>,
+    /// 系统平均负载与 CPU 核心数
+    load: fn() -> Option<(f64, usize)>,
+    /// 项目目录对应的 MCP 服务器状态
+    mcp: fn(Option<&str>) -> Option<mcp::McpStatus>,
+}
 
-    let content = fs::read_to_string(config_path).ok()?;
-    let config: ClaudeConfig = serde_json::from_str(&content).ok()?;
+impl<'a> StatuslineOptions<'a> {
+    fn system(session: &'a SessionState) -> Self {
+        StatuslineOptions {
+            session,
+            git: read_git_info,
+            providers: provider_segments,
+            environment: || environment::current().cloned(),
+            platform: platform::current,
+            subagent: |path| {
+                transcript::active_subagent(&transcript::read_tail_entries(std::path::Path::new(path)))
+            },
+            messages: |path| transcript::count_messages(std::path::Path::new(path), transcript::COUNT_BUDGET),
+            idle: |path| fs::metadata(path).ok()?.modified().ok()?.elapsed().ok(),
+            spend: history::load_spend,
+            process_dir,
+            clock: format_local_time,
+            load: || {
+                let cores = std::thread::available_parallelism().map_or(1, |cores| cores.get());
+                Some((load::load_average()?, cores))
+            },
+            mcp: mcp::status,
+        }
+    }
+}
 
-    let base_url = config.base_url?;
-    let auth_token = config.auth_token?;
+/// 第一个匹配 baseURL 的数据源的段；未配置中转地址时按官方 API 处理
+fn provider_segments(config: &ClaudeConfig, ctx: &ProviderContext) -> Vec<Segment> {
+    let (base_url, auth_token) = config
+        .credentials()
+        .unwrap_or_else(|| (providers::ANTHROPIC_BASE_URL.to_string(), String::new()));
+    trace::phase("providers", || providers::segments(&base_url, &auth_token, config.provider.as_deref(), ctx))
+}
 
-    Some((base_url, auth_token))
+/// 使用 Bedrock / Vertex 时 baseURL 不指向中转站，跳过凭据查找和 baseURL 匹配；固定的 `provider` 仍然生效
+fn skip_providers(config: &ClaudeConfig, platform: Option<platform::Platform>) -> bool {
+    platform.is_some() && config.provider.is_none()
 }
 
 /// 构建 statusline 输出
-fn build_statusline(input: &StatusInput) -> String {
-    let mut parts = Vec::new();
+fn build_statusline(input: &StatusInput, config: &ClaudeConfig, options: &StatuslineOptions) -> String {
+    let segments = collect_segments(input, config, options);
+    render_statusline(segments, config, session::heartbeat(options.session))
+}
 
-    // 模型名称
-    if let Some(ref name) = input.model.display_name {
-        parts.push(format!(
-            "{}{}[{}]{}",
-            colors::BOLD,
-            colors::MAGENTA,
-            name,
-            colors::RESET
-        ));
-    }
+/// 收集各段（不含心跳符号）
+fn collect_segments(input: &StatusInput, config: &ClaudeConfig, options: &StatuslineOptions) -> Vec<Segment> {
+    let labels = config.statusline.labels();
+    let mut segments = input_segments(input, config, options, labels);
 
-    // 当前目录
-    if let Some(ref dir) = input.workspace.current_dir {
-        let dir_name = get_dir_name(dir);
-        parts.push(format!(
-            "{}{}{}",
-            colors::CYAN,
-            dir_name,
-            colors::RESET
-        ));
-    }
-
-    // Git 分支
-    if let Some(branch) = get_git_branch(input.workspace.current_dir.as_deref()) {
-        parts.push(format!(
-            "{}{}{}",
-            colors::BLUE,
-            branch,
-            colors::RESET
-        ));
-    }
-
-    // 上下文使用率
-    let percentage = if let Some(used_pct) = input.context_window.used_percentage {
-        if used_pct > 0.0 {
-            Some(used_pct)
-        } else {
-            None
-        }
-    } else {
-        None
-    }.or_else(|| {
-        // 如果 used_percentage 不存在或为 0，则从 token 数计算
-        let total_in = input.context_window.total_input_tokens?;
-        let total_out = input.context_window.total_output_tokens?;
-        let window_size = input.context_window.context_window_size?;
-        if window_size > 0 {
-            Some(((total_in + total_out) as f64 / window_size as f64) * 100.0)
-        } else {
-            None
-        }
-    });
+    // 自定义数据源（放在最后）；云平台按 `options.platform` 判断，与模型名称使用同一来源
+    if !skip_providers(config, (options.platform)()) {
+        let provider_ctx = ProviderContext {
+            labels,
+            config: &config.statusline,
+        };
+        segments.extend((options.providers)(config, &provider_ctx));
+    }
 
-    if let Some(percentage) = percentage {
-        let color = get_context_color(percentage);
-        parts.push(format!(
-            "{}ctx:{:.0}%{}",
-            color,
-            percentage,
-            colors::RESET
-        ));
+    // 什么都没有时至少显示模型和当前目录，避免状态栏看起来像坏了
+    if segments.is_empty() {
+        segments = fallback_segments(input, config, options, labels);
+    } else if config.statusline.debug && input.model.display_name.is_none() {
+        // 调试模式下提示输入中没有模型名称
+        segments.insert(0, Segment::new("model", colors::DIM, labels.model_missing));
     }
 
-    // Token 统计
-    if let Some(ref usage) = input.context_window.current_usage {
-        if let Some(input_tokens) = usage.input_tokens {
-            let formatted = if input_tokens >= 1000 {
-                format!("{:.1}k", input_tokens as f64 / 1000.0)
-            } else {
-                format!("{}", input_tokens)
-            };
-            parts.push(format!(
-                "{}in:{}{}",
-                colors::DIM,
-                formatted,
-                colors::RESET
-            ));
-        }
+    // 外部命令段，插入到配置的位置
+    external::insert(&mut segments, &config.statusline.segments.external, input);
+    mark_thresholds(&mut segments, &config.statusline.theme().palette);
+    hide_normal(&mut segments, &config.statusline.only_on_warn);
+    segments
+}
 
-        // 缓存命中率
-        if let Some(hit_rate) = calculate_cache_hit_rate(usage) {
-            if hit_rate > 0.0 {
-                let color = if hit_rate >= 80.0 {
-                    colors::GREEN
-                } else if hit_rate >= 50.0 {
-                    colors::YELLOW
-                } else {
-                    colors::RED
-                };
-                parts.push(format!(
-                    "{}cache:{:.0}%{}",
-                    color,
-                    hit_rate,
-                    colors::RESET
-                ));
+/// 去掉 `only_on_warn` 中处于正常级别的段：段中没有达到警告级别的文本时视为正常
+fn hide_normal(segments: &mut Vec<Segment>, names: &[String]) {
+    segments.retain(|segment| {
+        !names.iter().any(|name| name == segment.name)
+            || segment.spans.iter().any(|span| span.severity >= Some(Severity::Warn))
+    });
+}
+
+/// 配色启用文本符号时，在按阈值着色的文本后加上对应级别的符号
+///
+/// 同一段中同一级别只在最后一处加符号，如 `ctx:≈65%!` 而不是 `ctx:!≈65%!`。
+fn mark_thresholds(segments: &mut [Segment], palette: &colors::Palette) {
+    for segment in segments.iter_mut() {
+        let mut marked: Vec<Severity> = Vec::new();
+        for span in segment.spans.iter_mut().rev() {
+            let Some(severity) = span.severity.filter(|severity| !marked.contains(severity)) else {
+                continue;
+            };
+            if let Some(marker) = palette.marker(severity) {
+                span.text.push(marker);
+                marked.push(severity);
             }
         }
     }
+}
+
+/// 加上心跳符号后按配置的样式和宽度渲染
+fn render_statusline(mut segments: Vec<Segment>, config: &ClaudeConfig, heartbeat: Option<&str>) -> String {
+    // 心跳符号放在最后，输出变化时交替变化，状态栏停止刷新时可以看出来
+    if let Some(glyph) = heartbeat.filter(|_| config.statusline.heartbeat) {
+        segments.push(Segment::new("heartbeat", colors::DIM, glyph));
+    }
+    if let Some(names) = &config.statusline.only_segments {
+        segments = layout::select(segments, names);
+    }
 
-    // 自定义数据源（放在最后）
-    if let Some((base_url, auth_token)) = read_claude_config().or_else(|| {
-        let base_url = std::env::var("ANTHROPIC_BASE_URL").ok()?;
-        let auth_token = std::env::var("ANTHROPIC_AUTH_TOKEN").ok()?;
-        Some((base_url, auth_token))
-    }) {
-        for provider in providers() {
-            if provider.matches(&base_url) {
-                parts.extend(provider.get_parts(&base_url, &auth_token));
-                break;
+    // 按主题的配色确定各段文本的颜色
+    let theme = config.statusline.theme();
+    for span in segments.iter_mut().flat_map(|segment| segment.spans.iter_mut()) {
+        span.color = theme.palette.resolve(span.color, span.severity);
+    }
+
+    // 没有文本的段不输出，两侧也不加分隔符
+    segments.retain(|segment| !segment.plain_text().is_empty());
+
+    let separator = theme.separator.as_ref();
+    let style = theme.style;
+    let no_color = config.statusline.no_color;
+    let render = |segments: &[Segment]| match (style, no_color) {
+        (Style::Plain, _) => render::render_plain_with(segments, separator),
+        (Style::Powerline, false) => render::render_powerline(segments),
+        (Style::Powerline, true) => render::render_powerline_no_color(segments),
+    };
+
+    // 右侧分组：宽度已知且两组放得下时用空格推到行尾，否则按单组排列；前后缀占用的宽度不参与排列
+    let decoration_width = decoration_width(&config.statusline);
+    let max_width = config
+        .statusline
+        .effective_max_width()
+        .map(|max_width| max_width.saturating_sub(decoration_width));
+    let right_names = &config.statusline.right_segments;
+    if let Some(max_width) = max_width.filter(|_| !right_names.is_empty()) {
+        let (right, left): (Vec<Segment>, Vec<Segment>) = segments
+            .iter()
+            .cloned()
+            .partition(|segment| right_names.iter().any(|name| name == segment.name));
+        if !right.is_empty() && !left.is_empty() {
+            if let Some(line) = layout::align_right(&render(&left), &render(&right), max_width) {
+                return line;
             }
         }
     }
 
-    parts.join(" │ ")
+    // 宽度受限时按优先级丢弃或截断
+    if let Some(max_width) = max_width {
+        let options = layout::FitOptions {
+            max_width,
+            priorities: &config.statusline.priority,
+            marker: config.statusline.truncation_marker,
+        };
+        segments = layout::fit_width(segments, &options, render);
+    }
+
+    render(&segments)
 }
 
-fn main() {
-    // 从 stdin 读取 JSON 输入
-    let mut input_str = String::new();
-    if io::stdin().read_to_string(&mut input_str).is_err() {
-        println!("Error reading stdin");
-        return;
+/// 前后缀在 statusline 所在行占用的宽度：前缀只计最后一个换行之后的部分，后缀只计第一个换行之前的部分
+fn decoration_width(statusline: &config::StatuslineConfig) -> usize {
+    let prefix = statusline.prefix.rsplit('\n').next().unwrap_or_default();
+    let suffix = statusline.suffix.split('\n').next().unwrap_or_default();
+    text::visible_width(prefix) + text::visible_width(suffix)
+}
+
+/// 组装最终输出：设置了 `max_len` 时先截断，再加上前后缀（截断不会切掉后缀）
+fn finish_line(line: String, statusline: &config::StatuslineConfig) -> String {
+    let line = match statusline.max_len {
+        // 宽度检测失败时的兜底：直接截断整行
+        Some(max_len) => text::truncate_end(&line, max_len.saturating_sub(decoration_width(statusline))),
+        None => line,
+    };
+    format!("{}{}{}", statusline.prefix, line, statusline.suffix)
+}
+
+/// 目录的 git 信息，关闭的功能对应字段为 None
+#[derive(Debug, Default, Clone, PartialEq)]
+struct GitInfo {
+    branch: Option<String>,
+    status: Option<WorktreeStatus>,
+    ahead_behind: Option<(u64, u64)>,
+    state: Option<GitState>,
+    /// 工作区根目录（开启 `git.repo_name` 时读取）
+    root: Option<String>,
+}
+
+/// 读取 git 信息，只执行开启的功能对应的 git 命令
+fn read_git_info(cwd: Option<&str>, git: &GitConfig) -> GitInfo {
+    // 不在仓库中时不启动任何 git 子进程
+    if !trace::phase("git:detect", || inside_repo(cwd, git)) {
+        return GitInfo::default();
     }
+    GitInfo {
+        branch: if git.branch { trace::phase("git:branch", || get_git_branch(cwd)) } else { None },
+        status: if git.dirty { trace::phase("git:status", || get_worktree_status(cwd)) } else { None },
+        ahead_behind: if git.ahead_behind {
+            trace::phase("git:ahead_behind", || get_ahead_behind(cwd))
+        } else {
+            None
+        },
+        state: trace::phase("git:state", || get_git_state(cwd, git)),
+        root: if git.repo_name {
+            let home = home_dir();
+            cwd.and_then(|cwd| find_repo_root(std::path::Path::new(cwd), git, home.as_deref()))
+                .map(|root| root.to_string_lossy().into_owned())
+        } else {
+            None
+        },
+    }
+}
 
-    // 解析 JSON
-    let input: StatusInput = match serde_json::from_str(&input_str) {
-        Ok(data) => data,
-        Err(_) => {
-            println!("Error parsing JSON");
-            return;
+/// 构建 git 段；开启 `git.status_color` 时分支名按工作区状态着色，状态未知时为蓝色
+fn git_segment(info: &GitInfo, git: &GitConfig, labels: &Labels) -> Option<Segment> {
+    let mut spans: Vec<(&'static str, String)> = Vec::new();
+
+    if let Some(ref branch) = info.branch {
+        let color = match info.status {
+            Some(status) if git.status_color => status.branch_color(),
+            _ => colors::BLUE,
+        };
+        spans.push((color, branch.clone()));
+    }
+    if let Some(status) = info.status.filter(|status| status.changed > 0) {
+        spans.push((colors::YELLOW, format!("±{}", status.changed)));
+    }
+    if let Some((ahead, behind)) = info.ahead_behind {
+        let mut text = String::new();
+        if ahead > 0 {
+            text.push_str(&format!("↑{}", ahead));
         }
-    };
+        if behind > 0 {
+            text.push_str(&format!("↓{}", behind));
+        }
+        if !text.is_empty() {
+            spans.push((colors::CYAN, text));
+        }
+    }
+    // rebase 期间处于 detached HEAD，可能只有状态标记
+    if let Some(state) = info.state {
+        spans.push((colors::RED, state.label(labels).to_string()));
+    }
 
-    // 输出 statusline
-    println!("{}", build_statusline(&input));
+    let mut spans = spans.into_iter();
+    let (color, text) = spans.next()?;
+    Some(spans.fold(Segment::new("git", color, text), |segment, (color, text)| {
+        segment.with(color, format!(" {}", text))
+    }))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// 按 strftime 格式输出本地时间；chrono 遇到无效格式时返回错误（`to_string` 会 panic）
+fn format_local_time(format: &str) -> Option<String> {
+    use std::fmt::Write;
 
-    #[test]
-    fn test_get_dir_name() {
-        assert_eq!(get_dir_name("/Users/test/project"), "project");
-        assert_eq!(get_dir_name("project"), "project");
-        assert_eq!(get_dir_name("/"), "");
+    let mut text = String::new();
+    write!(text, "{}", chrono::Local::now().format(format)).ok()?;
+    Some(text)
+}
+
+/// 进程当前目录，作为输入中没有目录时的最后手段
+fn process_dir() -> Option<String> {
+    std::env::var("PWD")
+        .ok()
+        .filter(|pwd| !pwd.is_empty())
+        .or_else(|| Some(std::env::current_dir().ok()?.to_string_lossy().into_owned()))
+}
+
+/// 消息数段，如 `user:12 asst:11`；只统计了部分时数字后加 `+`
+fn messages_segment(counts: transcript::MessageCounts, labels: &Labels) -> Segment {
+    let more = if counts.partial { "+" } else { "" };
+    let text = format!(
+        "{}:{}{} {}:{}{}",
+        labels.user, counts.user, more, labels.assistant, counts.assistant, more
+    );
+    Segment::new("messages", colors::DIM, text)
+}
+
+/// 输入几乎为空时的兜底显示：模型（未知时为 Claude）、进程目录及其 git 信息
+fn fallback_segments(
+    input: &StatusInput,
+    config: &ClaudeConfig,
+    options: &StatuslineOptions,
+    labels: &Labels,
+) -> Vec<Segment> {
+    let model = input.model.display_name.as_deref().unwrap_or("Claude");
+    let color = get_model_color(input.model.id.as_deref());
+    let mut segments = vec![Segment::new("model", color, format!("[{}]", model)).bold()];
+
+    if let Some(dir) = (options.process_dir)() {
+        let git = (options.git)(Some(&dir), &config.statusline.git);
+        segments.push(Segment::new("dir", colors::CYAN, get_dir_name(git.root.as_deref().unwrap_or(&dir))));
+        segments.extend(git_segment(&git, &config.statusline.git, labels));
     }
+    segments
+}
 
-    #[test]
-    fn test_format_cost() {
-        assert_eq!(format_cost(0.0001), "0.0001");
-        assert_eq!(format_cost(0.123), "0.123");
-        assert_eq!(format_cost(1.5), "1.50");
+/// 根据输入数据构建各段（不含自定义数据源）
+fn input_segments(
+    input: &StatusInput,
+    config: &ClaudeConfig,
+    options: &StatuslineOptions,
+    labels: &Labels,
+) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let theme = config.statusline.theme();
+
+    // 模型名称；Bedrock / Vertex 的模型 ID 转成简短名称
+    let platform = (options.platform)();
+    if let Some(ref name) = input.model.display_name {
+        let color = get_model_color(input.model.id.as_deref());
+        let name = platform.and_then(|_| platform::prettify_model_id(name)).unwrap_or_else(|| name.clone());
+        segments.push(Segment::new("model", color, format!("[{}]", name)).bold());
     }
 
-    #[test]
-    fn test_get_context_color() {
-        assert_eq!(get_context_color(90.0), colors::RED);
-        assert_eq!(get_context_color(70.0), colors::YELLOW);
-        assert_eq!(get_context_color(30.0), colors::GREEN);
+    // 扩展思考与超过 200k token 的标记
+    if input.thinking_enabled() {
+        segments.push(Segment::new("thinking", colors::MAGENTA, labels.thinking));
+    }
+    if input.exceeds_200k_tokens == Some(true) {
+        segments.push(Segment::graded("exceeds_200k", Severity::Warn, labels.exceeds_200k));
     }
 
-    #[test]
-    fn test_parse_input() {
-        let json = r#"{
-            "hook_event_name": "Status",
+    // 当前目录；开启 `git.repo_name` 时在仓库内显示仓库根目录名
+    let git = (options.git)(input.git_dir(), &config.statusline.git);
+    if let Some(ref dir) = input.workspace.current_dir {
+        segments.push(Segment::new("dir", colors::CYAN, get_dir_name(git.root.as_deref().unwrap_or(dir))));
+    }
+
+    // Git 分支、未提交文件数、领先/落后（merge/rebase 进行中时附带红色状态标记）
+    if let Some(git) = git_segment(&git, &config.statusline.git, labels) {
+        segments.push(git);
+    }
+
+    // 运行环境（SSH / 容器 / WSL）
+    if config.statusline.environment {
+        if let Some(env) = (options.environment)() {
+            segments.push(env.segment());
+        }
+    }
+    if let Some(platform) = platform {
+        segments.push(platform.segment());
+    }
+
+    // 系统负载
+    if config.statusline.load {
+        if let Some((load, cores)) = (options.load)() {
+            segments.push(load::segment(load, cores, theme.thresholds.load, labels));
+        }
+    }
+
+    // 子代理：优先使用输入字段，否则从 transcript 中查找运行中的 Task
+    let transcript_path = input.resolved_transcript_path();
+    let agent = input.agent_name().or_else(|| (options.subagent)(transcript_path.as_deref()?));
+    if let Some(agent) = agent {
+        segments.push(Segment::new("agent", colors::CYAN, format!("{}:{}", labels.agent, agent)));
+    }
+
+    // 用户和助手的消息数，只在开启时读取 transcript
+    if config.statusline.messages {
+        let counts = transcript_path.as_deref().and_then(|path| (options.messages)(path));
+        if let Some(counts) = counts {
+            segments.push(messages_segment(counts, labels));
+        }
+    }
+
+    // transcript 超过阈值未更新时显示空闲时长
+    if let Some(minutes) = config.statusline.idle_minutes {
+        let idle = transcript_path.as_deref().and_then(|path| (options.idle)(path));
+        if let Some(idle) = idle.filter(|idle| idle.as_secs() >= minutes * 60) {
+            let text = format!("{}:{}", labels.idle, format_duration(idle.as_millis() as u64));
+            segments.push(Segment::new("idle", colors::YELLOW, text));
+        }
+    }
+
+    // MCP 服务器状态
+    if config.statusline.mcp {
+        let project = input.project_dir();
+        if let Some(segment) = (options.mcp)(project).and_then(|status| mcp::segment(status, labels)) {
+            segments.push(segment);
+        }
+    }
+
+    // API 错误数
+    if let Some(count) = input.errors.count.filter(|&count| count > 0) {
+        segments.push(Segment::new("errors", colors::RED, format!("{}:{}", labels.errors, count)));
+    }
+
+    // 上下文使用率，按模型推断窗口大小得到的估计值前加 `≈`
+    if let Some((percentage, estimated)) = input.context_percentage(&config.statusline.context.windows) {
+        let severity = context_severity(&theme, percentage);
+        let formatted = format!("{}%", format_percentage(percentage, config.statusline.ctx_decimals));
+        let mut segment = if estimated {
+            Segment::graded("ctx", severity, format!("{}:", labels.ctx))
+                .with(colors::DIM, "≈")
+                .with_graded(severity, formatted)
+        } else {
+            Segment::graded("ctx", severity, format!("{}:{}", labels.ctx, formatted))
+        };
+        // 上下文较少时缓存占比意义不大，超过 20% 才显示
+        let cached = input.context_window.current_usage.as_ref().and_then(calculate_cached_share);
+        if let Some(cached) = cached.filter(|_| percentage > 20.0) {
+            segment = segment.with(colors::DIM, format!(" ({} {:.0}%)", labels.cached, cached));
+        }
+        // 本轮上下文增长量
+        if let Some(delta) = options.session.context_delta.filter(|_| config.statusline.ctx_growth) {
+            let delta_text = format!("+{}", format_tokens(delta, config.statusline.token_format));
+            segment = segment
+                .with(colors::DIM, " (")
+                .with_graded(growth_severity(&theme, delta), delta_text)
+                .with(colors::DIM, ")");
+        }
+        segments.push(segment);
+    }
+
+    // Token 统计；没有 current_usage 时使用会话累计值
+    segments.extend(tokens_segment(&input.context_window, &config.statusline, labels));
+
+    // 缓存相关的指标只按最近一次请求计算，会话累计值没有缓存的拆分
+    if let Some(ref usage) = input.context_window.current_usage {
+        // 缓存命中率
+        if let Some(hit_rate) = calculate_cache_hit_rate(usage) {
+            if hit_rate > 0.0 {
+                let severity = theme.thresholds.cache.severity(hit_rate);
+                segments.push(Segment::graded("cache", severity, format!("{}:{:.0}%", labels.cache, hit_rate)));
+            }
+        }
+
+        // 缓存读取/创建比例，越高说明缓存越划算
+        if let Some(ratio) = calculate_cache_ratio(usage) {
+            segments.push(Segment::new(
+                "cache_ratio",
+                colors::DIM,
+                format!("{} {:.0}:1", labels.cache, ratio),
+            ));
+        }
+    }
+
+    // 本次会话的花费，为 0 或低于 `min_cost_display` 时不显示
+    if config.statusline.cost {
+        let min_cost = config.statusline.min_cost_display;
+        if let Some(cost) = input.cost.total_cost_usd.filter(|&cost| cost > 0.0 && cost >= min_cost) {
+            let mut segment = Segment::new("cost", colors::DIM, format!("${}", format_cost(cost)));
+            // 本轮花费，超过阈值时为红色
+            if let Some(delta) = options.session.cost_delta.filter(|_| config.statusline.turn_cost) {
+                let text = format!(" +${}", format_cost(delta));
+                segment = match theme.thresholds.turn_cost.severity(delta) {
+                    Severity::Ok => segment.with(colors::DIM, text),
+                    severity => segment.with_graded(severity, text),
+                };
+            }
+            segments.push(segment);
+        }
+    }
+
+    // 本周/本月花费（来自本地历史记录）
+    if config.statusline.history_enabled() {
+        if let Some(spend) = (options.spend)() {
+            if config.statusline.week_spend {
+                let text = format!("{}:${}", labels.week, format_cost(spend.week));
+                segments.push(Segment::new("week_spend", colors::DIM, text));
+            }
+            if config.statusline.month_spend {
+                let text = format!("{}:${}", labels.month, format_cost(spend.month));
+                segments.push(Segment::new("month_spend", colors::DIM, text));
+            }
+        }
+    }
+
+    // 当前时间
+    if config.statusline.clock {
+        let format = config.statusline.clock_format.as_deref().unwrap_or("%H:%M");
+        if let Some(time) = (options.clock)(format) {
+            segments.push(Segment::new("clock", colors::DIM, time));
+        }
+    }
+
+    segments
+}
+
+/// 是否为状态栏事件；其他 hook 事件触发的渲染不写历史记录，避免重复记录
+fn is_status_event(input: &StatusInput) -> bool {
+    input.hook_event_name.as_deref().is_none_or(|event| event == "Status")
+}
+
+/// 记录本次渲染的会话成本
+fn record_history(input: &StatusInput) {
+    if let (Some(session_id), Some(cost_usd)) = (&input.session_id, input.cost.total_cost_usd) {
+        let context = &input.context_window;
+        history::append(&history::HistoryRecord {
+            version: history::SCHEMA_VERSION,
+            timestamp: chrono::Utc::now(),
+            session_id: session_id.clone(),
+            cost_usd,
+            project_dir: input.project_dir().map(String::from),
+            total_tokens: context.total_input_tokens.zip(context.total_output_tokens).map(|(i, o)| i + o),
+            duration_ms: input.cost.total_duration_ms,
+        });
+    }
+}
+
+/// 把本次渲染的成本、token 数和上下文使用率追加到指标日志
+fn record_metrics(input: &StatusInput, config: &ClaudeConfig, path: &str) {
+    metrics::append(
+        std::path::Path::new(path),
+        &metrics::MetricsRecord {
+            timestamp: chrono::Utc::now(),
+            session_id: input.session_id.clone(),
+            model: input.model.id.clone(),
+            cost_usd: input.cost.total_cost_usd,
+            input_tokens: input.context_window.total_input_tokens,
+            output_tokens: input.context_window.total_output_tokens,
+            context_pct: input
+                .context_percentage(&config.statusline.context.windows)
+                .map(|(percentage, _)| percentage),
+        },
+    );
+}
+
+/// 进入严重级别时写入终端标题的段
+const TITLE_SEGMENTS: &[&str] = &[
+    "ctx",
+    "zhipu_token",
+    "zhipu_mcp",
+    "yunyi_quota",
+    "packycode_quota",
+    "gac_quota",
+    "anthropic_ratelimit",
+];
+
+/// 终端标题的最大宽度
+const TITLE_MAX_WIDTH: usize = 60;
+
+/// 开启 `osc_title` 时的终端标题：上下文或额度为严重级别时如 `⚠ ctx:91% — myproject`，否则为项目名
+///
+/// 只取严重级别的文本，去掉控制字符，不超过 60 列。
+fn terminal_title(segments: &[Segment], input: &StatusInput, config: &ClaudeConfig) -> Option<String> {
+    if !config.statusline.osc_title {
+        return None;
+    }
+    let critical: Vec<String> = segments
+        .iter()
+        .filter(|segment| TITLE_SEGMENTS.contains(&segment.name))
+        .map(|segment| {
+            let spans = segment.spans.iter().filter(|span| span.severity == Some(Severity::Crit));
+            spans.map(|span| span.text.as_str()).collect::<String>()
+        })
+        .filter(|text| !text.is_empty())
+        .collect();
+    let project = input.project_dir().map(get_dir_name).filter(|name| !name.is_empty());
+    let title = match (critical.is_empty(), project) {
+        (true, project) => project?.to_string(),
+        (false, Some(project)) => format!("⚠ {} — {}", critical.join(" · "), project),
+        (false, None) => format!("⚠ {}", critical.join(" · ")),
+    };
+    let title: String = title.chars().filter(|c| !c.is_control()).collect();
+    Some(text::strip_ansi(&text::truncate_end(&title, TITLE_MAX_WIDTH)))
+}
+
+/// 渲染结果
+struct RenderedLine {
+    line: String,
+    /// 终端标题（开启 `osc_title` 时），复用上次的输出时为 None
+    title: Option<String>,
+    /// 输出与上次相比是否变化
+    changed: bool,
+}
+
+/// 渲染 statusline，并维护按会话保存的状态
+///
+/// 配置了 `min_interval_ms` 时，同一会话在间隔内复用上次的输出；
+/// 开启 `ctx_growth` 时记录上下文 token 总数以计算增长量；
+/// 开启 `turn_cost` 时记录会话成本以计算本轮花费；
+/// 开启 `heartbeat` 时记录渲染次数和时间；
+/// 需要历史记录时只在会话成本变化后追加（只记录状态栏事件）；
+/// 开启 `heartbeat` 或 `debug` 时记录输出的哈希以判断是否变化。
+fn render_line(input: &StatusInput, config: &ClaudeConfig) -> RenderedLine {
+    let min_interval_ms = config.statusline.min_interval_ms;
+    let ctx_growth = config.statusline.ctx_growth;
+    let turn_cost = config.statusline.turn_cost;
+    let heartbeat = config.statusline.heartbeat;
+    let history = config.statusline.history_enabled() && is_status_event(input);
+    let detect_changes = heartbeat || config.statusline.debug;
+    let Some(session_id) = input
+        .session_id
+        .as_deref()
+        .filter(|_| min_interval_ms > 0 || ctx_growth || turn_cost || history || detect_changes)
+    else {
+        let session = SessionState::default();
+        let options = StatuslineOptions::system(&session);
+        let segments = collect_segments(input, config, &options);
+        let title = terminal_title(&segments, input, config);
+        let line = render_statusline(segments, config, session::heartbeat(&session));
+        return RenderedLine { line, title, changed: true };
+    };
+
+    let now = chrono::Utc::now();
+    let mut state = session::load(session_id);
+    if min_interval_ms > 0 {
+        if let Some(output) = session::reusable_output(&state, now, min_interval_ms) {
+            return RenderedLine {
+                line: output.to_string(),
+                title: None,
+                changed: false,
+            };
+        }
+    }
+
+    // 成本没有变化时历史记录不会有新内容
+    if let Some(cost_usd) = input.cost.total_cost_usd.filter(|_| history) {
+        if session::update_recorded_cost(&mut state, cost_usd) {
+            record_history(input);
+        }
+    }
+
+    if ctx_growth {
+        let context = &input.context_window;
+        if let (Some(total_in), Some(total_out)) = (context.total_input_tokens, context.total_output_tokens) {
+            session::update_context(&mut state, total_in + total_out);
+        }
+    }
+
+    if let Some(cost_usd) = input.cost.total_cost_usd.filter(|_| turn_cost) {
+        session::update_cost(&mut state, cost_usd);
+    }
+
+    let segments = collect_segments(input, config, &StatuslineOptions::system(&state));
+    let title = terminal_title(&segments, input, config);
+    let changed = !detect_changes || {
+        let hash = paths::fnv1a(&render::render_plain(&segments));
+        session::update_line_hash(&mut state, hash)
+    };
+    if heartbeat {
+        session::record_render(&mut state, now, changed);
+    }
+
+    let line = render_statusline(segments, config, session::heartbeat(&state));
+    if min_interval_ms > 0 {
+        state.last_render = Some(session::LastRender {
+            rendered_at: now,
+            output: line.clone(),
+        });
+    }
+    session::save(session_id, &state);
+    RenderedLine { line, title, changed }
+}
+
+/// 执行渲染，panic 时输出兜底行而不是让进程退出（否则 Claude Code 的状态栏什么都不显示）
+///
+/// panic 信息和调用栈写到调试日志。测试中不替换 panic hook，其他测试的 panic 照常输出。
+fn render_guarded(input: &StatusInput, render: impl FnOnce() -> RenderedLine) -> RenderedLine {
+    #[cfg(not(test))]
+    let hook = std::panic::take_hook();
+    #[cfg(not(test))]
+    std::panic::set_hook(Box::new(|info| {
+        log::warn(format_args!("panic: {}\n{}", info, std::backtrace::Backtrace::force_capture()));
+    }));
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(render));
+    #[cfg(not(test))]
+    std::panic::set_hook(hook);
+    result.unwrap_or_else(|_| RenderedLine {
+        line: panic_line(input),
+        title: None,
+        changed: true,
+    })
+}
+
+/// panic 后的兜底行：直接取输入中的模型和目录名，都没有时显示暗色的 `statusline error`
+fn panic_line(input: &StatusInput) -> String {
+    let mut segments = Vec::new();
+    if let Some(ref name) = input.model.display_name {
+        let color = get_model_color(input.model.id.as_deref());
+        segments.push(Segment::new("model", color, format!("[{}]", name)).bold());
+    }
+    if let Some(dir) = input.git_dir() {
+        segments.push(Segment::new("dir", colors::CYAN, get_dir_name(dir)));
+    }
+    if segments.is_empty() {
+        segments.push(Segment::new("error", colors::DIM, "statusline error"));
+    }
+    render::render_plain(&segments)
+}
+
+/// 读取原始输入：指定文件或 stdin（`-`）
+fn read_input(path: Option<&str>) -> io::Result<String> {
+    match path {
+        Some(path) if path != "-" => fs::read_to_string(path),
+        _ => {
+            let mut input_str = String::new();
+            io::stdin().read_to_string(&mut input_str)?;
+            Ok(input_str)
+        }
+    }
+}
+
+/// `themes` 子命令：用固定的输入按每个内置主题渲染一次，上一行是主题名和对应的 `statusline` 选项
+fn theme_gallery(no_color: bool) -> String {
+    let input: StatusInput = serde_json::from_value(serde_json::json!({
+        "model": {"id": "claude-opus-4-1-20250805", "display_name": "Opus"},
+        "workspace": {"current_dir": "/work/project"},
+        "errors": {"count": 2},
+        "cost": {"total_cost_usd": 1.25},
+        "context_window": {
+            "used_percentage": 64.5,
+            "current_usage": {
+                "input_tokens": 24000,
+                "cache_read_input_tokens": 20000,
+                "cache_creation_input_tokens": 4600
+            }
+        }
+    }))
+    .unwrap_or_default();
+    let session = SessionState {
+        context_tokens: Some(125_000),
+        context_delta: Some(3_100),
+        ..Default::default()
+    };
+    let options = StatuslineOptions {
+        session: &session,
+        git: |_, _| GitInfo {
+            branch: Some("main".to_string()),
+            status: Some(WorktreeStatus {
+                changed: 3,
+                conflicted: 0,
+            }),
+            ahead_behind: Some((2, 1)),
+            ..Default::default()
+        },
+        providers: |_, ctx| {
+            let severity = colors::DEFAULT_THRESHOLDS.quota.severity(64.0);
+            vec![Segment::graded("zhipu_token", severity, format!("{} Token(5h):64%", ctx.prefix("zhipu", "ZAI")))]
+        },
+        environment: || None,
+        platform: || None,
+        subagent: |_| None,
+        messages: |_| None,
+        idle: |_| None,
+        spend: || None,
+        process_dir: || Some("/work/project".to_string()),
+        clock: |_| Some("14:32".to_string()),
+        load: || None,
+        mcp: |_| None,
+    };
+
+    let mut gallery = Vec::new();
+    for theme in colors::THEMES {
+        let mut config = ClaudeConfig::default();
+        config.statusline.locale = Some(i18n::Locale::En);
+        config.statusline.cost = true;
+        config.statusline.ctx_growth = true;
+        config.statusline.clock = true;
+        config.statusline.no_color = no_color;
+        theme.apply(&mut config.statusline);
+        config.statusline.truecolor = colors::truecolor();
+        let line = build_statusline(&input, &config, &options);
+        let line = format_output(line, OutputFormat::Ansi, no_color);
+        gallery.push(format!("{}  {}\n{}", theme.name, theme.settings(), line));
+    }
+    gallery.join("\n\n")
+}
+
+/// 输入无法读取或解析为 JSON 时的退出码（stdout 输出空行）；`--validate` 发现输入无法渲染时也使用该退出码
+const EXIT_INPUT_ERROR: i32 = 2;
+
+/// 配置有误时的退出码；有问题的项退回默认值，仍正常输出
+const EXIT_CONFIG_ERROR: i32 = 3;
+
+/// `--check-providers` 没有一个数据源请求成功时的退出码
+const EXIT_CHECK_FAILED: i32 = 1;
+
+/// 命令行参数无法识别时的退出码（stdout 输出空行）
+const EXIT_USAGE_ERROR: i32 = 4;
+
+fn main() {
+    let cli = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(err) => {
+            println!();
+            eprintln!("{}", err);
+            std::process::exit(EXIT_USAGE_ERROR);
+        }
+    };
+
+    if cli.clear_cache {
+        let dir = paths::cache_dir();
+        match paths::clear_caches(dir) {
+            Ok(removed) => println!("removed {} cache file(s) from {}", removed, dir.display()),
+            Err(err) => eprintln!("{}", err),
+        }
+        return;
+    }
+
+    if cli.check_providers {
+        let config = read_claude_config(std::env::current_dir().ok().as_deref());
+        let (report, ok) = doctor::check_providers(&config);
+        println!("{}", report);
+        if !ok {
+            std::process::exit(EXIT_CHECK_FAILED);
+        }
+        return;
+    }
+
+    if cli.command == Some(cli::Command::TraceSummarize) {
+        let path = paths::trace_path();
+        let summaries = trace::summarize(&fs::read_to_string(&path).unwrap_or_default());
+        if summaries.is_empty() {
+            println!("no trace records in {}", path.display());
+        } else {
+            println!("{}", trace::format_summary(&summaries));
+        }
+        return;
+    }
+
+    if cli.command == Some(cli::Command::Report) {
+        let days = cli.days.unwrap_or(report::DEFAULT_DAYS);
+        let since = chrono::Utc::now() - chrono::Duration::days(i64::from(days));
+        let content = fs::read_to_string(paths::history_path()).unwrap_or_default();
+        println!("{}", report::format(&report::build(content.as_bytes(), since), cli.report_format));
+        return;
+    }
+
+    if cli.command == Some(cli::Command::Themes) {
+        println!("{}", theme_gallery(cli.no_color || std::env::var_os("NO_COLOR").is_some()));
+        return;
+    }
+
+    if cli.command == Some(cli::Command::Doctor) {
+        println!("{}", doctor::run(&read_claude_config(std::env::current_dir().ok().as_deref())));
+        return;
+    }
+
+    trace::init();
+
+    // 读取 JSON 输入（默认 stdin）
+    // 出错时输出空行，原因写到 stderr，不污染状态栏
+    let input_str = match trace::phase("stdin", || read_input(cli.input.as_deref())) {
+        Ok(input_str) => input_str,
+        Err(err) => {
+            println!();
+            eprintln!("Error reading input: {}", err);
+            std::process::exit(EXIT_INPUT_ERROR);
+        }
+    };
+
+    // 逐个字段检查输入，不渲染；无法渲染时以非零状态退出
+    if cli.validate {
+        let report = validate::validate(&input_str);
+        println!("{}", report);
+        if !report.is_valid() {
+            std::process::exit(EXIT_INPUT_ERROR);
+        }
+        return;
+    }
+
+    // 解析 JSON
+    let input: StatusInput = match trace::phase("parse", || serde_json::from_str(&input_str)) {
+        Ok(data) => data,
+        Err(err) => {
+            println!();
+            eprintln!("Error parsing JSON: {}", err);
+            std::process::exit(EXIT_INPUT_ERROR);
+        }
+    };
+
+    if cli.dump_input {
+        if let Ok(json) = serde_json::to_string_pretty(&input) {
+            eprintln!("{}", json);
+        }
+    }
+
+    let project_dir = input.project_dir().map(std::path::Path::new);
+    let mut config = trace::phase("config", || read_claude_config(project_dir));
+    if cli.max_width.is_some() {
+        config.statusline.max_width = cli.max_width;
+    }
+    if cli.no_color || std::env::var_os("NO_COLOR").is_some() {
+        config.statusline.no_color = true;
+    }
+    log::set_enabled(config.statusline.debug);
+    for error in &config.errors {
+        log::warn(error);
+    }
+    config.statusline.truecolor = colors::truecolor();
+    config.statusline.light_terminal = colors::light_background();
+    if let Some(path) = config.statusline.log.as_deref().filter(|path| !path.is_empty()) {
+        record_metrics(&input, &config, path);
+    }
+    // 输出与上次相同时不输出渲染过程中的调试日志
+    log::hold();
+    let RenderedLine { line, title, changed } = trace::phase("render", || {
+        render_guarded(&input, || render_line(&input, &config))
+    });
+    log::release(changed);
+    let line = finish_line(line, &config.statusline);
+
+    // 输出 statusline
+    let line = trace::phase("format", || format_output(line, cli.format, config.statusline.no_color));
+    // 终端标题只用于 ANSI 输出
+    let title = title.filter(|_| cli.format == OutputFormat::Ansi && !config.statusline.no_color);
+    if cli.json {
+        // 数据源没有执行（如复用上次的输出）时 provider 为 null
+        println!("{}", serde_json::json!({ "line": line, "provider": providers::last_report() }));
+    } else if let Some(title) = title {
+        // OSC 0 设置终端标题，放在 statusline 文本之前
+        println!("\x1b]0;{}\x07{}", title, line);
+    } else {
+        println!("{}", line);
+    }
+    trace::finish(&paths::trace_path());
+    // 输出后再清理，不影响渲染耗时
+    gc::run_daily(paths::cache_dir(), std::time::SystemTime::now());
+    if !config.errors.is_empty() {
+        std::process::exit(EXIT_CONFIG_ERROR);
+    }
+}
+
+/// 按输出格式转换，关闭颜色时去掉 ANSI 转义序列
+fn format_output(line: String, format: OutputFormat, no_color: bool) -> String {
+    match (format, no_color) {
+        (OutputFormat::Ansi, false) => line,
+        (OutputFormat::Ansi, true) => text::strip_ansi(&line),
+        (OutputFormat::Tmux, false) => text::to_tmux(&line),
+        (OutputFormat::Tmux, true) => text::to_tmux(&text::strip_ansi(&line)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 不读取 git、数据源等外部环境的渲染选项
+    fn fixed_options(session: &SessionState) -> StatuslineOptions<'_> {
+        StatuslineOptions {
+            session,
+            git: |_, _| GitInfo::default(),
+            providers: |_, _| Vec::new(),
+            environment: || None,
+            platform: || None,
+            subagent: |_| None,
+            messages: |_| Some(transcript::MessageCounts { user: 12, assistant: 11, partial: false }),
+            idle: |_| Some(std::time::Duration::from_secs(12 * 60 + 30)),
+            spend: || None,
+            process_dir: || Some("/work/project".to_string()),
+            clock: |format| Some(format.replace("%H", "14").replace("%M", "32").replace("%S", "05")),
+            load: || Some((3.5, 4)),
+            mcp: |_| Some(mcp::McpStatus { total: 3, missing: 1 }),
+        }
+    }
+
+    /// 渲染输入中的各段，返回名为 `name` 的段
+    fn find_segment(
+        input: &StatusInput,
+        config: &ClaudeConfig,
+        options: &StatuslineOptions,
+        name: &str,
+    ) -> Option<Segment> {
+        input_segments(input, config, options, &i18n::EN).into_iter().find(|segment| segment.name == name)
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(42_000), "42s");
+        assert_eq!(format_duration(5 * 60_000), "5m");
+        assert_eq!(format_duration(200 * 60_000), "3h20m");
+        assert_eq!(format_duration(47 * 3_600_000 + 59 * 60_000), "1d23h");
+    }
+
+    #[test]
+    fn test_parse_branch() {
+        assert_eq!(parse_branch(b"main\n".to_vec()).as_deref(), Some("main"));
+        assert_eq!(parse_branch(b"feat/\x1b[2Jx\n".to_vec()).as_deref(), Some("feat/[2Jx"));
+        assert_eq!(parse_branch("fix\u{200B}\n".as_bytes().to_vec()).as_deref(), Some("fix"));
+        assert_eq!(parse_branch(b"\x1b\n".to_vec()), None);
+        assert_eq!(parse_branch(b"\n".to_vec()), None);
+        assert_eq!(parse_branch(vec![0xff]), None);
+    }
+
+    #[test]
+    fn test_get_dir_name() {
+        assert_eq!(get_dir_name("/Users/test/project"), "project");
+        assert_eq!(get_dir_name("project"), "project");
+        assert_eq!(get_dir_name("/"), "");
+        assert_eq!(get_dir_name("/work/a\x1b]0;pwned\x07b"), "a]0;pwnedb");
+    }
+
+    #[test]
+    fn test_format_cost() {
+        assert_eq!(format_cost(0.0), "0.0000");
+        assert_eq!(format_cost(0.0001), "0.0001");
+        assert_eq!(format_cost(0.009), "0.0090");
+        assert_eq!(format_cost(0.01), "0.010");
+        assert_eq!(format_cost(0.123), "0.123");
+        assert_eq!(format_cost(0.999), "0.999");
+        assert_eq!(format_cost(1.0), "1.00");
+        assert_eq!(format_cost(1.5), "1.50");
+        assert_eq!(format_cost(123.456789), "123.46");
+        assert_eq!(format_cost(999.99), "999.99");
+        // 四舍五入后进位到四位数时也加分隔符
+        assert_eq!(format_cost(999.999), "1,000.00");
+        assert_eq!(format_cost(1_234.56), "1,234.56");
+        assert_eq!(format_cost(123_456.7), "123,456.70");
+        assert_eq!(format_cost(1_234_567.891), "1,234,567.89");
+        assert_eq!(format_cost(-0.5), "0.00");
+        assert_eq!(format_cost(f64::NAN), "0.00");
+        assert_eq!(format_cost(f64::INFINITY), "0.00");
+    }
+
+    #[test]
+    fn test_parse_ahead_behind() {
+        assert_eq!(parse_ahead_behind("2\t1\n"), Some((2, 1)));
+        assert_eq!(parse_ahead_behind("0\t0"), Some((0, 0)));
+        assert_eq!(parse_ahead_behind(""), None);
+    }
+
+    #[test]
+    fn test_git_segment_disabled_features() {
+        let root = std::env::temp_dir().join(format!("cc-statusline-git-flags-{}", std::process::id()));
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join(".git").join("MERGE_HEAD"), "abc").unwrap();
+        let cwd = root.to_str();
+
+        // 所有 git 命令都关闭时仍显示不依赖子进程的状态标记
+        let off = GitConfig {
+            branch: false,
+            dirty: false,
+            ahead_behind: false,
+            status_color: false,
+            repo_name: false,
+            ..GitConfig::default()
+        };
+        let segment = git_segment(&read_git_info(cwd, &off), &off, &i18n::EN).unwrap();
+        assert_eq!(segment.plain_text(), "!merge");
+        assert_eq!(segment.color(), colors::RED);
+
+        fs::remove_dir_all(&root).unwrap();
+        assert!(git_segment(&read_git_info(cwd, &off), &off, &i18n::EN).is_none());
+        // 不执行 git 命令时不缓存不在仓库中的结果，重新创建后立即可见
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join(".git").join("MERGE_HEAD"), "abc").unwrap();
+        assert!(git_segment(&read_git_info(cwd, &off), &off, &i18n::EN).is_some());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_parse_porcelain() {
+        assert_eq!(parse_porcelain(""), WorktreeStatus::default());
+        let status = parse_porcelain(" M src/main.rs\nA  src/new.rs\n?? notes.txt\n");
+        assert_eq!(status, WorktreeStatus { changed: 3, conflicted: 0 });
+        let status = parse_porcelain("UU src/main.rs\nAA Cargo.lock\nDU old.rs\n M README.md\n");
+        assert_eq!(status, WorktreeStatus { changed: 4, conflicted: 3 });
+    }
+
+    #[test]
+    fn test_branch_color_by_status() {
+        let git = GitConfig::default();
+        let branch_color = |status: Option<WorktreeStatus>, git: &GitConfig| {
+            let info = GitInfo {
+                branch: Some("main".to_string()),
+                status,
+                ..Default::default()
+            };
+            git_segment(&info, git, &i18n::EN).unwrap().color()
+        };
+        let dirty = WorktreeStatus { changed: 2, conflicted: 0 };
+        let conflicted = WorktreeStatus { changed: 2, conflicted: 1 };
+
+        assert_eq!(branch_color(Some(WorktreeStatus::default()), &git), colors::GREEN);
+        assert_eq!(branch_color(Some(dirty), &git), colors::YELLOW);
+        assert_eq!(branch_color(Some(conflicted), &git), colors::RED);
+        // 状态未知（超时或关闭了 dirty）时保持蓝色
+        assert_eq!(branch_color(None, &git), colors::BLUE);
+
+        let always_blue = GitConfig {
+            status_color: false,
+            ..GitConfig::default()
+        };
+        assert_eq!(branch_color(Some(conflicted), &always_blue), colors::BLUE);
+    }
+
+    /// 用 shell 命令代替 git，验证输出解析与超时
+    #[cfg(unix)]
+    #[test]
+    fn test_read_worktree_status_with_fake_command() {
+        let fake = |script: &str| {
+            let mut command = Command::new("sh");
+            command.args(["-c", script]);
+            command
+        };
+        let timeout = Duration::from_secs(5);
+
+        assert_eq!(read_worktree_status(fake("true"), timeout), Some(WorktreeStatus::default()));
+        let dirty = read_worktree_status(fake("printf ' M a.rs\\n?? b.rs\\n'"), timeout);
+        assert_eq!(dirty, Some(WorktreeStatus { changed: 2, conflicted: 0 }));
+        let conflicted = read_worktree_status(fake("printf 'UU a.rs\\n M b.rs\\n'"), timeout);
+        assert_eq!(conflicted, Some(WorktreeStatus { changed: 2, conflicted: 1 }));
+        assert_eq!(read_worktree_status(fake("exit 128"), timeout), None);
+
+        // 超时时结束进程，不等待其退出
+        let started = std::time::Instant::now();
+        let mut slow = Command::new("sleep");
+        slow.arg("5");
+        assert_eq!(read_worktree_status(slow, Duration::from_millis(50)), None);
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_calculate_cache_ratio() {
+        let usage = |read: Option<u64>, creation: Option<u64>| CurrentUsage {
+            cache_read_input_tokens: read,
+            cache_creation_input_tokens: creation,
+            ..Default::default()
+        };
+        assert_eq!(calculate_cache_ratio(&usage(Some(50_000), Some(10_000))), Some(5.0));
+        assert_eq!(calculate_cache_ratio(&usage(Some(0), Some(10_000))), Some(0.0));
+        assert_eq!(calculate_cache_ratio(&usage(Some(50_000), Some(0))), None);
+        assert_eq!(calculate_cache_ratio(&usage(None, Some(10_000))), None);
+        assert_eq!(calculate_cache_ratio(&usage(Some(50_000), None)), None);
+    }
+
+    #[test]
+    fn test_calculate_cached_share() {
+        let usage = |input: Option<u64>, read: Option<u64>, creation: Option<u64>| CurrentUsage {
+            input_tokens: input,
+            cache_read_input_tokens: read,
+            cache_creation_input_tokens: creation,
+            ..Default::default()
+        };
+        // 典型的多轮对话：大部分上下文来自缓存
+        let share = calculate_cached_share(&usage(Some(2_400), Some(118_000), Some(4_600))).unwrap();
+        assert!((share - 94.4).abs() < 0.01);
+        // 首轮：全部是新写入的缓存
+        assert_eq!(calculate_cached_share(&usage(Some(12), Some(0), Some(24_000))), Some(0.0));
+        assert_eq!(calculate_cached_share(&usage(Some(0), Some(0), Some(0))), None);
+        assert_eq!(calculate_cached_share(&usage(None, Some(1_000), Some(0))), None);
+        assert_eq!(calculate_cached_share(&usage(Some(100), None, Some(0))), None);
+        assert_eq!(calculate_cached_share(&usage(Some(100), Some(1_000), None)), None);
+    }
+
+    #[test]
+    fn test_ctx_cached_share_rendering() {
+        let config = ClaudeConfig::default();
+        let payload = |used: f64| -> StatusInput {
+            serde_json::from_value(serde_json::json!({
+                "context_window": {
+                    "used_percentage": used,
+                    "current_usage": {
+                        "input_tokens": 20_000,
+                        "cache_read_input_tokens": 64_000,
+                        "cache_creation_input_tokens": 16_000
+                    }
+                }
+            }))
+            .unwrap()
+        };
+        let ctx = |input: &StatusInput| {
+            find_segment(input, &config, &fixed_options(&SessionState::default()), "ctx").unwrap()
+        };
+
+        let segment = ctx(&payload(72.0));
+        assert_eq!(segment.plain_text(), "ctx:72% (cached 64%)");
+        assert_eq!(segment.spans[1].color, colors::DIM);
+        assert_eq!(ctx(&payload(15.0)).plain_text(), "ctx:15%");
+
+        let input: StatusInput =
+            serde_json::from_value(serde_json::json!({"context_window": {"used_percentage": 72.0}})).unwrap();
+        assert_eq!(ctx(&input).plain_text(), "ctx:72%");
+    }
+
+    #[test]
+    fn test_context_percentage_fallback() {
+        let windows = HashMap::new();
+        let percentage = |context: serde_json::Value| {
+            let input: StatusInput =
+                serde_json::from_value(serde_json::json!({"context_window": context})).unwrap();
+            input.context_percentage(&windows)
+        };
+
+        // 只有 used_percentage
+        assert_eq!(percentage(serde_json::json!({"used_percentage": 42.5})), Some((42.5, false)));
+
+        // 只有 token 数和窗口大小：(输入 + 输出) / 窗口 * 100
+        let tokens = serde_json::json!({
+            "total_input_tokens": 30_000,
+            "total_output_tokens": 20_000,
+            "context_window_size": 200_000
+        });
+        assert_eq!(percentage(tokens.clone()), Some((25.0, false)));
+
+        // used_percentage 为 0 时按 token 数计算
+        let mut zero = tokens;
+        zero["used_percentage"] = serde_json::json!(0.0);
+        assert_eq!(percentage(zero), Some((25.0, false)));
+
+        // 都没有时不显示 ctx 段
+        assert_eq!(percentage(serde_json::json!({})), None);
+        assert_eq!(percentage(serde_json::json!({"total_input_tokens": 30_000})), None);
+        let empty_window = serde_json::json!({
+            "total_input_tokens": 30_000,
+            "total_output_tokens": 20_000,
+            "context_window_size": 0
+        });
+        assert_eq!(percentage(empty_window), None);
+
+        let cw = |context: serde_json::Value| -> ContextWindow { serde_json::from_value(context).unwrap() };
+        assert_eq!(resolve_context_pct(&cw(serde_json::json!({"used_percentage": 42.5}))), Some(42.5));
+        let tokens = serde_json::json!({
+            "total_input_tokens": 150_000,
+            "total_output_tokens": 50_000,
+            "context_window_size": 1_000_000
+        });
+        assert_eq!(resolve_context_pct(&cw(tokens)), Some(20.0));
+        // 没有窗口大小时不推断
+        let no_window = serde_json::json!({"total_input_tokens": 150_000, "total_output_tokens": 50_000});
+        assert_eq!(resolve_context_pct(&cw(no_window)), None);
+        let input: StatusInput = serde_json::from_str("{}").unwrap();
+        let session = SessionState::default();
+        let segments = input_segments(&input, &ClaudeConfig::default(), &fixed_options(&session), &i18n::EN);
+        assert!(segments.iter().all(|segment| segment.name != "ctx"));
+    }
+
+    #[test]
+    fn test_ctx_estimated_from_model() {
+        let mut config = ClaudeConfig::default();
+        let payload = |model_id: &str, window_size: Option<u64>| -> StatusInput {
+            serde_json::from_value(serde_json::json!({
+                "model": {"id": model_id},
+                "context_window": {
+                    "total_input_tokens": 90_000,
+                    "total_output_tokens": 10_000,
+                    "context_window_size": window_size
+                }
+            }))
+            .unwrap()
+        };
+        let ctx = |input: &StatusInput, config: &ClaudeConfig| {
+            find_segment(input, config, &fixed_options(&SessionState::default()), "ctx")
+        };
+
+        // 输入中有窗口大小时不是估计值
+        let explicit = ctx(&payload("claude-sonnet-4-5-20250929", Some(400_000)), &config).unwrap();
+        assert_eq!(explicit.plain_text(), "ctx:25%");
+
+        let estimated = ctx(&payload("claude-sonnet-4-5-20250929", None), &config).unwrap();
+        assert_eq!(estimated.plain_text(), "ctx:≈50%");
+        assert_eq!(estimated.spans[1].color, colors::DIM);
+        assert_eq!(estimated.color(), colors::GREEN);
+        let one_million = ctx(&payload("claude-sonnet-4-5-20250929[1m]", None), &config).unwrap();
+        assert_eq!(one_million.plain_text(), "ctx:≈10%");
+
+        // 未知模型不显示，配置后按配置推断
+        assert!(ctx(&payload("glm-4.6", None), &config).is_none());
+        config.statusline.context.windows.insert("glm-4.6".to_string(), 128_000);
+        assert_eq!(ctx(&payload("glm-4.6", None), &config).unwrap().plain_text(), "ctx:≈78%");
+    }
+
+    #[test]
+    fn test_ctx_growth_rendering() {
+        let mut config = ClaudeConfig::default();
+        config.statusline.ctx_growth = true;
+        let input: StatusInput =
+            serde_json::from_value(serde_json::json!({"context_window": {"used_percentage": 72.0}})).unwrap();
+        let ctx = |session: &SessionState| {
+            find_segment(&input, &config, &fixed_options(session), "ctx").unwrap()
+        };
+
+        let grown = SessionState {
+            context_tokens: Some(150_000),
+            context_delta: Some(3_100),
+            ..Default::default()
+        };
+        let segment = ctx(&grown);
+        assert_eq!(segment.plain_text(), "ctx:72% (+3.1k)");
+        assert_eq!(segment.spans[2].color, colors::YELLOW);
+        assert_eq!(growth_severity(&colors::THEMES[0], 800), Severity::Ok);
+        assert_eq!(growth_severity(&colors::THEMES[0], 12_000), Severity::Crit);
+
+        // 首次渲染或压缩后没有增长量
+        assert_eq!(ctx(&SessionState::default()).plain_text(), "ctx:72%");
+    }
+
+    #[test]
+    fn test_heartbeat_segment() {
+        let input: StatusInput =
+            serde_json::from_value(serde_json::json!({"model": {"display_name": "Opus"}})).unwrap();
+        let mut config = ClaudeConfig::default();
+        config.statusline.locale = Some(i18n::Locale::En);
+        let render = |config: &ClaudeConfig, renders: u64| {
+            let session = SessionState {
+                renders,
+                ..Default::default()
+            };
+            text::strip_ansi(&build_statusline(&input, config, &fixed_options(&session)))
+        };
+
+        // 未开启时不显示
+        assert!(!render(&config, 1).contains('·'));
+
+        config.statusline.heartbeat = true;
+        assert_eq!(render(&config, 1), "[Opus] │ ·");
+        assert_eq!(render(&config, 2), "[Opus] │ ∘");
+        assert_eq!(render(&config, 3), "[Opus] │ ·");
+        // 没有会话状态时不显示
+        assert!(!render(&config, 0).contains(['·', '∘']));
+    }
+
+    #[test]
+    fn test_errors_segment() {
+        let config = ClaudeConfig::default();
+        let errors = |json: serde_json::Value| {
+            let input: StatusInput = serde_json::from_value(json).unwrap();
+            find_segment(&input, &config, &fixed_options(&SessionState::default()), "errors")
+        };
+
+        let payload = serde_json::json!({"errors": {"count": 3, "last_message": "overloaded"}});
+        let segment = errors(payload).unwrap();
+        assert_eq!(segment.plain_text(), "err:3");
+        assert_eq!(segment.color(), colors::RED);
+        assert!(errors(serde_json::json!({"errors": {"count": 0}})).is_none());
+        assert!(errors(serde_json::json!({"errors": {}})).is_none());
+        assert!(errors(serde_json::json!({})).is_none());
+        assert!(errors(serde_json::json!({"errors": null})).is_none());
+    }
+
+    #[test]
+    fn test_format_tokens() {
+        assert_eq!(format_tokens(0, TokenFormat::Abbrev), "0");
+        assert_eq!(format_tokens(999, TokenFormat::Abbrev), "999");
+        assert_eq!(format_tokens(1000, TokenFormat::Abbrev), "1.0k");
+        assert_eq!(format_tokens(15_200, TokenFormat::Abbrev), "15.2k");
+        assert_eq!(format_tokens(15_250, TokenFormat::Abbrev), "15.3k");
+        assert_eq!(format_tokens(999_949, TokenFormat::Abbrev), "999.9k");
+        assert_eq!(format_tokens(999_950, TokenFormat::Abbrev), "1.0M");
+        assert_eq!(format_tokens(1_234_567, TokenFormat::Abbrev), "1.2M");
+        assert_eq!(format_tokens(1_843_200, TokenFormat::Abbrev), "1.8M");
+        assert_eq!(format_tokens(999_949_999, TokenFormat::Abbrev), "999.9M");
+        assert_eq!(format_tokens(999_950_000, TokenFormat::Abbrev), "1.0B");
+        assert_eq!(format_tokens(1_250_000_000, TokenFormat::Abbrev), "1.3B");
+        assert_eq!(format_tokens(u64::MAX, TokenFormat::Abbrev), "18446744073.7B");
+    }
+
+    #[test]
+    fn test_format_tokens_grouped_and_raw() {
+        let cases = [
+            (0, "0", "0"),
+            (999, "999", "999"),
+            (1000, "1,000", "1000"),
+            (15_250, "15,250", "15250"),
+            (999_950, "999,950", "999950"),
+            (1_234_567, "1,234,567", "1234567"),
+            (u64::MAX, "18,446,744,073,709,551,615", "18446744073709551615"),
+        ];
+        for (tokens, grouped, raw) in cases {
+            assert_eq!(format_tokens(tokens, TokenFormat::Grouped), grouped);
+            assert_eq!(format_tokens(tokens, TokenFormat::Raw), raw);
+        }
+    }
+
+    #[test]
+    fn test_format_percentage() {
+        // 79.6% 不应显示为 80%，颜色仍按原始值判断
+        assert_eq!(format_percentage(79.6, 0), "79");
+        assert_eq!(context_severity(&colors::THEMES[0], 79.6), Severity::Warn);
+        assert_eq!(format_percentage(79.6, 1), "79.6");
+        assert_eq!(format_percentage(79.96, 1), "79.9");
+        assert_eq!(format_percentage(42.0, 2), "42.00");
+    }
+
+    #[test]
+    fn test_get_model_color() {
+        assert_eq!(get_model_color(Some("claude-opus-4-1-20250805")), colors::MAGENTA);
+        assert_eq!(get_model_color(Some("claude-sonnet-4-5-20250929")), colors::BLUE);
+        assert_eq!(get_model_color(Some("claude-3-5-haiku-20241022")), colors::GREEN);
+        assert_eq!(get_model_color(Some("glm-4.6")), colors::MAGENTA);
+        assert_eq!(get_model_color(None), colors::MAGENTA);
+    }
+
+    #[test]
+    fn test_context_severity() {
+        assert_eq!(context_severity(&colors::THEMES[0], 90.0), Severity::Crit);
+        assert_eq!(context_severity(&colors::THEMES[0], 70.0), Severity::Warn);
+        assert_eq!(context_severity(&colors::THEMES[0], 30.0), Severity::Ok);
+    }
+
+    #[test]
+    fn test_get_git_state() {
+        let root = std::env::temp_dir().join(format!("cc-statusline-git-state-{}", std::process::id()));
+        let git_dir = root.join(".git");
+        let sub_dir = root.join("src");
+        fs::create_dir_all(&git_dir).unwrap();
+        fs::create_dir_all(&sub_dir).unwrap();
+        let sub = sub_dir.to_str().unwrap();
+        let git = GitConfig::default();
+
+        assert_eq!(get_git_state(Some(sub), &git), None);
+
+        fs::write(git_dir.join("MERGE_HEAD"), "abc").unwrap();
+        assert_eq!(get_git_state(Some(sub), &git), Some(GitState::Merge));
+        fs::remove_file(git_dir.join("MERGE_HEAD")).unwrap();
+
+        fs::create_dir_all(git_dir.join("rebase-merge")).unwrap();
+        assert_eq!(get_git_state(Some(sub), &git), Some(GitState::Rebase));
+
+        assert_eq!(get_git_state(None, &git), None);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    /// 包含自定义数据源段的快照输入
+    #[cfg(all(feature = "provider-zhipu", feature = "provider-yunyi"))]
+    fn snapshot_line(labels: &'static Labels) -> String {
+        use providers::yunyi::{YunyiProvider, YunyiUsageCache};
+        use providers::zhipu::{QuotaLimit, ZhipuProvider, ZhipuUsageCache};
+
+        let json = r#"{
+            "model": {"display_name": "Opus"},
+            "context_window": {
+                "used_percentage": 42.5,
+                "current_usage": {"input_tokens": 15200, "cache_read_input_tokens": 13000}
+            }
+        }"#;
+        let input: StatusInput = serde_json::from_str(json).unwrap();
+        let mut config = ClaudeConfig::default();
+        // 固定时区，结果不依赖运行环境
+        config.statusline.timezone = Some("+08:00".to_string());
+        let ctx = ProviderContext {
+            labels,
+            config: &config.statusline,
+        };
+        let mut segments = input_segments(&input, &config, &fixed_options(&SessionState::default()), labels);
+
+        let limit = |limit_type: &str, percentage: f64| QuotaLimit {
+            limit_type: limit_type.to_string(),
+            percentage,
+            current_value: None,
+            usage: None,
+        };
+        let zhipu = ZhipuUsageCache {
+            token_limit: Some(limit("TOKENS_LIMIT", 37.0)),
+            mcp_limit: Some(limit("TIME_LIMIT", 12.0)),
+            timestamp: chrono::Utc::now(),
+            previous_token: None,
+            earlier_token: None,
+        };
+        segments.extend(ctx.decorate(&ZhipuProvider, ZhipuProvider.render_segments(&zhipu, &ctx)));
+
+        let yunyi = YunyiUsageCache {
+            daily_used: None,
+            daily_quota: Some(1000),
+            daily_spent: None,
+            daily_total_spent: Some(400),
+            expires_at: Some("2026-01-10T04:30:00Z".to_string()),
+            request_count: None,
+            daily_request_count: None,
+            quota_pack: Some(1000),
+            quota_pack_remaining: Some(500),
+            quota_pack_expires_at: Some("2026-02-01T00:00:00Z".to_string()),
+            account: None,
+            timestamp: chrono::Utc::now(),
+        };
+        let now = "2026-03-01T00:00:00Z".parse().unwrap();
+        segments.extend(ctx.decorate(&YunyiProvider, YunyiProvider.render_segments(&yunyi, now, &ctx)));
+
+        text::strip_ansi(&render::render_plain(&segments))
+    }
+
+    #[cfg(all(feature = "provider-zhipu", feature = "provider-yunyi"))]
+    #[test]
+    fn test_snapshot_locales() {
+        assert_eq!(
+            snapshot_line(&i18n::EN),
+            "[Opus] │ ctx:42% │ in:15.2k │ cache:86% │ [ZAI] Token(5h):37% │ [ZAI] MCP(1mo):12% \
+             │ [YUNYI] Rem:55% $11.00(day$6.00+pack$5.00) │ [YUNYI] Exp:01-10 12:30 pack:02-01 08:00"
+        );
+        assert_eq!(
+            snapshot_line(&i18n::ZH),
+            "[Opus] │ ctx:42% │ in:15.2k │ cache:86% │ [ZAI] Token(5h):37% │ [ZAI] MCP(1月):12% \
+             │ [YUNYI] 剩余55% $11.00(日$6.00+包$5.00) │ [YUNYI] Exp:01-10 12:30 包:02-01 08:00"
+        );
+    }
+
+    #[test]
+    fn test_empty_payload_fallback() {
+        let input: StatusInput = serde_json::from_str("{}").unwrap();
+        let mut config = ClaudeConfig::default();
+        config.statusline.locale = Some(i18n::Locale::En);
+        let session = SessionState::default();
+        let line = text::strip_ansi(&build_statusline(&input, &config, &fixed_options(&session)));
+        assert_eq!(line, "[Claude] │ project");
+    }
+
+    #[test]
+    fn test_pinned_git_and_provider_segments() {
+        let input: StatusInput = serde_json::from_value(serde_json::json!({
+            "model": {"display_name": "Opus"},
+            "workspace": {"current_dir": "/work/project"},
+            "context_window": {"used_percentage": 42.0}
+        }))
+        .unwrap();
+        let mut config = ClaudeConfig::default();
+        config.statusline.locale = Some(i18n::Locale::En);
+        let session = SessionState::default();
+        let options = StatuslineOptions {
+            git: |_, _| GitInfo {
+                branch: Some("main".to_string()),
+                status: Some(WorktreeStatus {
+                    changed: 3,
+                    conflicted: 0,
+                }),
+                ahead_behind: Some((2, 0)),
+                ..Default::default()
+            },
+            providers: |_, ctx| {
+                vec![Segment::new("quota", colors::GREEN, format!("[{}] 80%", ctx.labels.gac_tag))]
+            },
+            ..fixed_options(&session)
+        };
+
+        let line = text::strip_ansi(&build_statusline(&input, &config, &options));
+        assert_eq!(line, "[Opus] │ project │ main ±3 ↑2 │ ctx:42% │ [GAC] 80%");
+    }
+
+    #[test]
+    fn test_missing_model_placeholder_in_debug() {
+        let input: StatusInput =
+            serde_json::from_str(r#"{"context_window": {"used_percentage": 30}}"#).unwrap();
+        let mut config = ClaudeConfig::default();
+        config.statusline.locale = Some(i18n::Locale::En);
+        config.statusline.max_width = Some(200);
+        let session = SessionState::default();
+        let options = fixed_options(&session);
+        assert_eq!(text::strip_ansi(&build_statusline(&input, &config, &options)), "ctx:30%");
+
+        config.statusline.debug = true;
+        let line = build_statusline(&input, &config, &options);
+        assert_eq!(text::strip_ansi(&line), "?model │ ctx:30%");
+    }
+
+    #[test]
+    fn test_parse_input() {
+        let json = r#"{
+            "hook_event_name": "Status",
             "model": {"display_name": "Opus"},
             "workspace": {"current_dir": "/test/project"},
             "context_window": {"used_percentage": 42.5},
@@ -393,5 +2300,911 @@ mod tests {
         let input: StatusInput = serde_json::from_str(json).unwrap();
         assert_eq!(input.model.display_name, Some("Opus".to_string()));
         assert_eq!(input.context_window.used_percentage, Some(42.5));
+        assert_eq!(input.agent_name(), None);
+    }
+
+    #[test]
+    fn test_agent_field_passthrough() {
+        let input: StatusInput =
+            serde_json::from_str(r#"{"agent": "code-reviewer", "some_future_field": [1, 2]}"#).unwrap();
+        assert_eq!(input.agent_name(), Some("code-reviewer".to_string()));
+
+        let input: StatusInput =
+            serde_json::from_str(r#"{"agent": {"name": "Explore", "id": 3}}"#).unwrap();
+        assert_eq!(input.agent_name(), Some("Explore".to_string()));
+
+        let input: StatusInput = serde_json::from_str(r#"{"agent": 42}"#).unwrap();
+        assert_eq!(input.agent_name(), None);
+    }
+
+    #[test]
+    fn test_thinking_and_200k_fields() {
+        let input: StatusInput = serde_json::from_str(r#"{"model": {"display_name": "Opus"}}"#).unwrap();
+        assert_eq!(input.exceeds_200k_tokens, None);
+        assert!(!input.thinking_enabled());
+
+        let thinking_enabled = |thinking: &str| {
+            let input: StatusInput = serde_json::from_str(&format!(r#"{{"thinking": {}}}"#, thinking)).unwrap();
+            input.thinking_enabled()
+        };
+        for thinking in [r#"true"#, r#"{"enabled": true}"#, r#"{"type": "enabled", "budget_tokens": 8000}"#] {
+            assert!(thinking_enabled(thinking), "{}", thinking);
+        }
+        for thinking in [r#"false"#, r#"{"type": "disabled"}"#, r#""on""#] {
+            assert!(!thinking_enabled(thinking), "{}", thinking);
+        }
+
+        let input: StatusInput = serde_json::from_str(r#"{"exceeds_200k_tokens": true}"#).unwrap();
+        assert_eq!(input.exceeds_200k_tokens, Some(true));
+    }
+
+    #[test]
+    fn test_unknown_top_level_fields_are_ignored() {
+        // 不启用 deny_unknown_fields：新版本 Claude Code 增加的字段不能让旧版本解析失败
+        let json = r#"{"model": {"display_name": "Opus"}, "future_field": {"nested": [1]}, "another": 3}"#;
+        let input: StatusInput = serde_json::from_str(json).unwrap();
+        assert_eq!(input.model.display_name.as_deref(), Some("Opus"));
+    }
+
+    /// 与 `src/snapshots/<name>.snap` 比较；设置 `UPDATE_SNAPSHOTS=1` 时改为写入新的快照
+    fn assert_snapshot(name: &str, actual: &str) {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src").join("snapshots");
+        let path = dir.join(format!("{}.snap", name));
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(&path, actual).unwrap();
+            return;
+        }
+        let expected = fs::read_to_string(&path)
+            .unwrap_or_else(|_| panic!("missing snapshot {}, rerun with UPDATE_SNAPSHOTS=1", path.display()));
+        assert_eq!(actual, expected, "snapshot {} changed, rerun with UPDATE_SNAPSHOTS=1", name);
+    }
+
+    /// 按 plain/powerline 两种样式、开关颜色各渲染一次，ESC 写成 `\e` 便于阅读快照
+    fn render_variants(
+        json: serde_json::Value,
+        options: &StatuslineOptions,
+        configure: impl Fn(&mut config::StatuslineConfig),
+    ) -> String {
+        let input: StatusInput = serde_json::from_value(json).unwrap();
+        let variants = [
+            ("plain", Style::Plain, false),
+            ("plain no-color", Style::Plain, true),
+            ("powerline", Style::Powerline, false),
+            ("powerline no-color", Style::Powerline, true),
+        ];
+        let mut snapshot = String::new();
+        for (name, style, no_color) in variants {
+            let mut config = ClaudeConfig::default();
+            configure(&mut config.statusline);
+            config.statusline.locale = Some(i18n::Locale::En);
+            config.statusline.style = style;
+            config.statusline.no_color = no_color;
+            let line = finish_line(build_statusline(&input, &config, options), &config.statusline);
+            let line = format_output(line, OutputFormat::Ansi, no_color);
+            snapshot.push_str(&format!("-- {} --\n{}\n", name, line.replace('\x1b', "\\e")));
+        }
+        snapshot
+    }
+
+    /// 包含大部分段的会话状态和输入
+    fn full_line_session() -> SessionState {
+        SessionState {
+            context_tokens: Some(125_000),
+            context_delta: Some(3_100),
+            ..Default::default()
+        }
+    }
+
+    fn full_line_options(session: &SessionState) -> StatuslineOptions<'_> {
+        StatuslineOptions {
+            git: |_, _| GitInfo {
+                branch: Some("main".to_string()),
+                status: Some(WorktreeStatus {
+                    changed: 3,
+                    conflicted: 0,
+                }),
+                ahead_behind: Some((2, 1)),
+                ..Default::default()
+            },
+            providers: |_, _| vec![Segment::new("zhipu_token", colors::YELLOW, "[ZAI] Token(5h):64%")],
+            ..fixed_options(session)
+        }
+    }
+
+    fn full_line_json() -> serde_json::Value {
+        serde_json::json!({
+            "model": {"id": "claude-opus-4-1-20250805", "display_name": "Opus"},
+            "workspace": {"current_dir": "/work/project"},
+            "agent": "code-reviewer",
+            "errors": {"count": 2},
+            "context_window": {
+                "used_percentage": 64.5,
+                "current_usage": {
+                    "input_tokens": 128000,
+                    "cache_read_input_tokens": 118000,
+                    "cache_creation_input_tokens": 4600
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn test_snapshot_full_line() {
+        let session = full_line_session();
+        let options = full_line_options(&session);
+        let rendered = render_variants(full_line_json(), &options, |config| config.ctx_growth = true);
+        assert_snapshot("full_line", &rendered);
+    }
+
+    #[test]
+    fn test_snapshot_themes() {
+        assert_snapshot("themes", &theme_gallery(true));
+    }
+
+    #[test]
+    fn test_snapshot_backgrounds() {
+        let session = full_line_session();
+        let options = full_line_options(&session);
+        let mut snapshot = String::new();
+        for background in [config::Background::Dark, config::Background::Light] {
+            snapshot.push_str(&format!("== {:?} ==\n", background));
+            snapshot.push_str(&render_variants(full_line_json(), &options, |config| {
+                config.ctx_growth = true;
+                config.background = background;
+            }));
+        }
+        assert_snapshot("backgrounds", &snapshot);
+    }
+
+    #[test]
+    fn test_snapshot_token_counts() {
+        // 没有 used_percentage 时按 token 数计算，超过 80% 为红色
+        let session = SessionState::default();
+        let json = serde_json::json!({
+            "model": {"id": "claude-sonnet-4-5-20250929", "display_name": "Sonnet"},
+            "workspace": {"current_dir": "/work/project"},
+            "context_window": {
+                "total_input_tokens": 150000,
+                "total_output_tokens": 20000,
+                "context_window_size": 200000
+            }
+        });
+        let rendered = render_variants(json, &fixed_options(&session), |_| {});
+        assert_snapshot("token_counts", &rendered);
+    }
+
+    #[test]
+    fn test_snapshot_right_aligned() {
+        let session = SessionState::default();
+        let json = serde_json::json!({
+            "model": {"display_name": "Opus"},
+            "workspace": {"current_dir": "/work/project"},
+            "context_window": {"used_percentage": 42}
+        });
+        let options = fixed_options(&session);
+        let mut snapshot = String::new();
+        // 足够宽、刚好放下、放不下两组时退回单组布局
+        for width in [60, 38, 30] {
+            snapshot.push_str(&format!("== width {} ==\n", width));
+            snapshot.push_str(&render_variants(json.clone(), &options, |config| {
+                config.clock = true;
+                config.right_segments = vec!["clock".to_string()];
+                config.max_width = Some(width);
+            }));
+        }
+        assert_snapshot("right_aligned", &snapshot);
+    }
+
+    #[test]
+    fn test_snapshot_separators() {
+        let session = SessionState::default();
+        // 没有文本的段两侧不加分隔符
+        let options = StatuslineOptions {
+            providers: |_, _| vec![Segment::new("zhipu_token", colors::GREEN, "")],
+            ..fixed_options(&session)
+        };
+        let json = serde_json::json!({
+            "model": {"display_name": "Opus"},
+            "workspace": {"current_dir": "/work/project"},
+            "context_window": {"used_percentage": 42}
+        });
+        let mut snapshot = String::new();
+        let cases: [(&str, Option<&str>, &str, &str); 4] = [
+            ("default", None, "", ""),
+            ("custom separator", Some(" › "), "", ""),
+            ("empty separator", Some(""), "", ""),
+            ("prefixed", None, "❯ ", " ·"),
+        ];
+        for (name, separator, prefix, suffix) in cases {
+            snapshot.push_str(&format!("== {} ==\n", name));
+            snapshot.push_str(&render_variants(json.clone(), &options, |config| {
+                config.separator = separator.map(String::from);
+                config.prefix = prefix.to_string();
+                config.suffix = suffix.to_string();
+            }));
+        }
+        assert_snapshot("separators", &snapshot);
+    }
+
+    #[test]
+    fn test_snapshot_tokens_compact() {
+        let session = SessionState::default();
+        let cases = [
+            ("full", (60_000, 1_100, 48_000)),
+            ("partial", (0, 950, 0)),
+            ("all zero", (0, 0, 0)),
+        ];
+        let mut snapshot = String::new();
+        for (name, (input, output, cache_read)) in cases {
+            let usage = serde_json::json!({
+                "input_tokens": input,
+                "output_tokens": output,
+                "cache_read_input_tokens": cache_read
+            });
+            let json = serde_json::json!({
+                "model": {"display_name": "Opus"},
+                "context_window": {"current_usage": usage}
+            });
+            snapshot.push_str(&format!("== {} ==\n", name));
+            snapshot.push_str(&render_variants(json, &fixed_options(&session), |config| {
+                config.tokens_style = TokensStyle::Compact;
+            }));
+        }
+        assert_snapshot("tokens_compact", &snapshot);
+    }
+
+    #[test]
+    fn test_snapshot_colorblind() {
+        let session = SessionState::default();
+        let options = StatuslineOptions {
+            providers: |_, _| {
+                let severity = colors::DEFAULT_THRESHOLDS.quota.severity(65.0);
+                vec![Segment::new("zhipu_token", colors::DIM, "[ZAI] ").with_graded(severity, "Token(5h):65%")]
+            },
+            ..fixed_options(&session)
+        };
+        let json = serde_json::json!({
+            "model": {"display_name": "Opus"},
+            "context_window": {
+                "used_percentage": 85,
+                "current_usage": {"input_tokens": 100000, "cache_read_input_tokens": 90000}
+            }
+        });
+        let rendered = render_variants(json, &options, |config| {
+            config.palette = config::PalettePreset::Colorblind;
+        });
+        assert_snapshot("colorblind", &rendered);
+    }
+
+    #[test]
+    fn test_snapshot_minimal_and_empty() {
+        let session = SessionState::default();
+        let json = serde_json::json!({"context_window": {"used_percentage": 30}});
+        assert_snapshot("ctx_only", &render_variants(json, &fixed_options(&session), |_| {}));
+        let json = serde_json::json!({});
+        assert_snapshot("empty_payload", &render_variants(json, &fixed_options(&session), |_| {}));
+    }
+
+    /// 智谱的两项指标，经过前缀模板处理
+    #[cfg(feature = "provider-zhipu")]
+    fn zhipu_metrics(_: &ClaudeConfig, ctx: &ProviderContext) -> Vec<Segment> {
+        use providers::zhipu::{QuotaLimit, ZhipuProvider, ZhipuUsageCache};
+
+        let limit = |limit_type: &str, percentage: f64| QuotaLimit {
+            limit_type: limit_type.to_string(),
+            percentage,
+            current_value: None,
+            usage: None,
+        };
+        let usage = ZhipuUsageCache {
+            token_limit: Some(limit("TOKENS_LIMIT", 37.0)),
+            mcp_limit: Some(limit("TIME_LIMIT", 85.0)),
+            timestamp: chrono::Utc::now(),
+            previous_token: None,
+            earlier_token: None,
+        };
+        ctx.decorate(&ZhipuProvider, ZhipuProvider.render_segments(&usage, ctx))
+    }
+
+    #[cfg(feature = "provider-zhipu")]
+    #[test]
+    fn test_snapshot_provider_prefix_styles() {
+        let session = SessionState::default();
+        let options = StatuslineOptions {
+            providers: zhipu_metrics,
+            ..fixed_options(&session)
+        };
+        let json = || serde_json::json!({"model": {"display_name": "GLM"}});
+        assert_snapshot("provider_ungrouped", &render_variants(json(), &options, |_| {}));
+        let grouped = render_variants(json(), &options, |config| config.group_provider_metrics = true);
+        assert_snapshot("provider_grouped", &grouped);
+        let bare = render_variants(json(), &options, |config| {
+            config.group_provider_metrics = true;
+            config.provider_prefix = Some(String::new());
+        });
+        assert_snapshot("provider_empty_prefix", &bare);
+    }
+
+    #[test]
+    fn test_clock_segment() {
+        let mut config = ClaudeConfig::default();
+        let input: StatusInput = serde_json::from_str(r#"{"model": {"display_name": "Opus"}}"#).unwrap();
+        let session = SessionState::default();
+        let clock = |config: &ClaudeConfig| {
+            find_segment(&input, config, &fixed_options(&session), "clock")
+        };
+        assert!(clock(&config).is_none());
+
+        config.statusline.clock = true;
+        let segment = clock(&config).unwrap();
+        assert_eq!(segment.plain_text(), "14:32");
+        assert_eq!(segment.color(), colors::DIM);
+
+        config.statusline.clock_format = Some("%H:%M:%S".to_string());
+        assert_eq!(clock(&config).unwrap().plain_text(), "14:32:05");
+    }
+
+    #[test]
+    fn test_format_local_time() {
+        let time = format_local_time("%H:%M").unwrap();
+        assert_eq!(time.len(), 5);
+        assert_eq!(&time[2..3], ":");
+        // 无效格式不会 panic
+        assert_eq!(format_local_time("%Q"), None);
+    }
+
+    #[test]
+    fn test_load_segment_opt_in() {
+        let mut config = ClaudeConfig::default();
+        let input: StatusInput = serde_json::from_str(r#"{"model": {"display_name": "Opus"}}"#).unwrap();
+        let session = SessionState::default();
+        let load = |config: &ClaudeConfig| {
+            find_segment(&input, config, &fixed_options(&session), "load")
+        };
+        assert!(load(&config).is_none());
+
+        config.statusline.load = true;
+        let segment = load(&config).unwrap();
+        assert_eq!(segment.plain_text(), "load:3.50");
+        assert_eq!(segment.color(), colors::YELLOW);
+    }
+
+    #[test]
+    fn test_cost_segment_threshold() {
+        let mut config = ClaudeConfig::default();
+        let session = SessionState::default();
+        let cost = |config: &ClaudeConfig, usd: f64| {
+            let json = serde_json::json!({"cost": {"total_cost_usd": usd}});
+            let input: StatusInput = serde_json::from_value(json).unwrap();
+            find_segment(&input, config, &fixed_options(&session), "cost").map(|segment| segment.plain_text())
+        };
+        assert_eq!(cost(&config, 0.5), None);
+
+        // 默认显示所有非零花费
+        config.statusline.cost = true;
+        assert_eq!(cost(&config, 0.0), None);
+        assert_eq!(cost(&config, 0.004).as_deref(), Some("$0.0040"));
+        assert_eq!(cost(&config, 1.25).as_deref(), Some("$1.25"));
+
+        config.statusline.min_cost_display = 0.01;
+        assert_eq!(cost(&config, 0.004), None);
+        assert_eq!(cost(&config, 0.01).as_deref(), Some("$0.010"));
+    }
+
+    #[test]
+    fn test_turn_cost_rendering() {
+        let mut config = ClaudeConfig::default();
+        config.statusline.cost = true;
+        config.statusline.turn_cost = true;
+        let input: StatusInput =
+            serde_json::from_value(serde_json::json!({"cost": {"total_cost_usd": 2.4}})).unwrap();
+        let cost = |config: &ClaudeConfig, session: &SessionState| {
+            find_segment(&input, config, &fixed_options(session), "cost").unwrap()
+        };
+        let turn = |delta: f64| SessionState {
+            cost_total: Some(2.4),
+            cost_delta: Some(delta),
+            ..Default::default()
+        };
+
+        // 首次渲染或成本倒退时没有增量
+        assert_eq!(cost(&config, &SessionState::default()).plain_text(), "$2.40");
+
+        let segment = cost(&config, &turn(0.08));
+        assert_eq!(segment.plain_text(), "$2.40 +$0.080");
+        assert_eq!(segment.spans[1].color, colors::DIM);
+
+        let segment = cost(&config, &turn(0.8));
+        assert_eq!(segment.plain_text(), "$2.40 +$0.800");
+        assert_eq!(segment.spans[1].color, colors::RED);
+        config.statusline.turn_cost_alert = Some(1.0);
+        assert_eq!(cost(&config, &turn(0.8)).spans[1].color, colors::DIM);
+
+        config.statusline.turn_cost = false;
+        assert_eq!(cost(&config, &turn(0.8)).plain_text(), "$2.40");
+    }
+
+    #[test]
+    fn test_git_falls_back_to_cwd() {
+        let config = ClaudeConfig::default();
+        let session = SessionState::default();
+        let options = StatuslineOptions {
+            // 用分支名记录 git 命令收到的目录
+            git: |cwd, _| GitInfo {
+                branch: cwd.map(String::from),
+                ..Default::default()
+            },
+            ..fixed_options(&session)
+        };
+        let git = |json: &str| {
+            let input: StatusInput = serde_json::from_str(json).unwrap();
+            find_segment(&input, &config, &options, "git").map(|segment| segment.plain_text())
+        };
+
+        assert_eq!(git(r#"{"cwd": "/work/repo"}"#).as_deref(), Some("/work/repo"));
+        let both = r#"{"cwd": "/work/repo", "workspace": {"current_dir": "/work/project"}}"#;
+        assert_eq!(git(both).as_deref(), Some("/work/project"));
+        assert_eq!(git("{}"), None);
+    }
+
+    #[test]
+    fn test_search_repo_root() {
+        let dir = std::env::temp_dir().join(format!("cc-statusline-repo-search-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let nested = dir.join("repo/src/foo/bar");
+        fs::create_dir_all(&nested).unwrap();
+        fs::create_dir_all(dir.join("repo/.git")).unwrap();
+        fs::create_dir_all(dir.join("plain/src")).unwrap();
+        fs::create_dir_all(dir.join("worktree/src")).unwrap();
+        fs::write(dir.join("worktree/.git"), "gitdir: ../repo/.git/worktrees/wt\n").unwrap();
+
+        let search = |cwd: &std::path::Path, depth: usize, home: Option<&std::path::Path>| {
+            let git = GitConfig {
+                search_depth: depth,
+                ..Default::default()
+            };
+            find_repo_root(cwd, &git, home).map(std::path::Path::to_path_buf)
+        };
+        // 仓库、非仓库、worktree 的 `.git` 文件
+        assert_eq!(search(&nested, 16, None), Some(dir.join("repo")));
+        assert_eq!(search(&dir.join("plain/src"), 16, None), None);
+        assert_eq!(search(&dir.join("worktree/src"), 16, None), Some(dir.join("worktree")));
+
+        // 超过层数限制时不再查找
+        assert_eq!(search(&nested, 2, None), None);
+        assert_eq!(search(&nested, 3, None), Some(dir.join("repo")));
+        // 不越过 home 目录，home 本身仍会检查
+        assert_eq!(search(&nested, 16, Some(&dir.join("repo/src"))), None);
+        assert_eq!(search(&nested, 16, Some(&dir.join("repo"))), Some(dir.join("repo")));
+        let unlimited = GitConfig {
+            stop_at_home: false,
+            ..Default::default()
+        };
+        let home = dir.join("repo/src");
+        assert_eq!(find_repo_root(&nested, &unlimited, Some(&home)), Some(dir.join("repo").as_path()));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_no_repo_cache() {
+        let dir = std::env::temp_dir().join(format!("cc-statusline-no-repo-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("git.json");
+        let _ = fs::remove_file(&path);
+        let now: chrono::DateTime<chrono::Utc> = "2025-01-15T10:00:00Z".parse().unwrap();
+        let later = |secs| now + chrono::Duration::seconds(secs);
+
+        assert!(!cached_no_repo(&path, "/tmp/plain", now));
+        record_no_repo(&path, "/tmp/plain", now);
+        assert!(cached_no_repo(&path, "/tmp/plain", later(59)));
+        assert!(!cached_no_repo(&path, "/tmp/other", later(59)));
+        // 60 秒后重新查找
+        assert!(!cached_no_repo(&path, "/tmp/plain", later(60)));
+
+        // 写入时清理过期的记录
+        record_no_repo(&path, "/tmp/other", later(90));
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(!content.contains("/tmp/plain"));
+        assert!(cached_no_repo(&path, "/tmp/other", later(90)));
+        // 不留下临时文件
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_find_repo_root() {
+        let dir = std::env::temp_dir().join(format!("cc-statusline-repo-root-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let nested = dir.join("repo/src/foo/bar");
+        fs::create_dir_all(&nested).unwrap();
+        fs::create_dir_all(dir.join("repo/.git")).unwrap();
+        // worktree 的 `.git` 是文件
+        fs::create_dir_all(dir.join("worktree/src")).unwrap();
+        fs::write(dir.join("worktree/.git"), "gitdir: ../repo/.git/worktrees/wt\n").unwrap();
+
+        let git = GitConfig::default();
+        let root = |path: std::path::PathBuf| find_repo_root(&path, &git, None).map(std::path::Path::to_path_buf);
+        assert_eq!(root(nested.clone()), Some(dir.join("repo")));
+        assert_eq!(root(dir.join("repo")), Some(dir.join("repo")));
+        assert_eq!(root(dir.join("worktree/src")), Some(dir.join("worktree")));
+        assert_eq!(find_git_dir(nested.to_str().unwrap(), &git), Some(dir.join("repo/.git")));
+        assert_eq!(
+            find_git_dir(dir.join("worktree/src").to_str().unwrap(), &git),
+            Some(dir.join("worktree/../repo/.git/worktrees/wt"))
+        );
+
+        // 目录段：开启后显示仓库根目录名，仓库外仍为当前目录名
+        let mut config = ClaudeConfig::default();
+        config.statusline.git.repo_name = true;
+        let session = SessionState::default();
+        let dir_name = |config: &ClaudeConfig, current_dir: &std::path::Path| {
+            let json = serde_json::json!({"workspace": {"current_dir": current_dir}});
+            let input: StatusInput = serde_json::from_value(json).unwrap();
+            let options = StatuslineOptions {
+                git: read_git_info,
+                ..fixed_options(&session)
+            };
+            find_segment(&input, config, &options, "dir").map(|segment| segment.plain_text())
+        };
+        assert_eq!(dir_name(&config, &nested).as_deref(), Some("repo"));
+        assert_eq!(dir_name(&config, &dir.join("worktree/src")).as_deref(), Some("worktree"));
+        assert_eq!(dir_name(&config, &dir).as_deref(), Some(get_dir_name(dir.to_str().unwrap()).as_str()));
+        config.statusline.git.repo_name = false;
+        assert_eq!(dir_name(&config, &nested).as_deref(), Some("bar"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_messages_segment_opt_in() {
+        let mut config = ClaudeConfig::default();
+        let session = SessionState::default();
+        let messages = |config: &ClaudeConfig, json: &str| {
+            let input: StatusInput = serde_json::from_str(json).unwrap();
+            find_segment(&input, config, &fixed_options(&session), "messages").map(|segment| segment.plain_text())
+        };
+        let with_transcript = r#"{"transcript_path": "/tmp/session.jsonl"}"#;
+        assert_eq!(messages(&config, with_transcript), None);
+
+        config.statusline.messages = true;
+        assert_eq!(messages(&config, with_transcript).as_deref(), Some("user:12 asst:11"));
+        assert_eq!(messages(&config, "{}"), None);
+
+        let partial = transcript::MessageCounts { user: 3, assistant: 2, partial: true };
+        assert_eq!(messages_segment(partial, &i18n::ZH).plain_text(), "用户:3+ 助手:2+");
+    }
+
+    #[test]
+    fn test_render_guarded_recovers_from_panic() {
+        let mut config = ClaudeConfig::default();
+        config.statusline.load = true;
+        let session = SessionState::default();
+        let render = |input: &StatusInput, config: &ClaudeConfig| {
+            render_guarded(input, || {
+                // 注入会 panic 的段（输入几乎为空时走兜底路径，读取进程目录时 panic）
+                let options = StatuslineOptions {
+                    load: || panic!("injected"),
+                    process_dir: || panic!("injected"),
+                    ..fixed_options(&session)
+                };
+                let segments = collect_segments(input, config, &options);
+                RenderedLine {
+                    line: render_statusline(segments, config, None),
+                    title: None,
+                    changed: true,
+                }
+            })
+        };
+
+        let json = r#"{"model": {"display_name": "Opus"}, "workspace": {"current_dir": "/work/project"}}"#;
+        let input: StatusInput = serde_json::from_str(json).unwrap();
+        let rendered = render(&input, &config);
+        assert_eq!(text::strip_ansi(&rendered.line), "[Opus] │ project");
+        assert!(rendered.changed);
+
+        let input: StatusInput = serde_json::from_str("{}").unwrap();
+        let rendered = render(&input, &config);
+        assert_eq!(rendered.line, format!("{}statusline error{}", colors::DIM, colors::RESET));
+
+        // 没有 panic 时原样返回
+        config.statusline.load = false;
+        let json = r#"{"model": {"display_name": "Opus"}, "workspace": {"current_dir": "/work/other"}}"#;
+        let input: StatusInput = serde_json::from_str(json).unwrap();
+        assert!(text::strip_ansi(&render(&input, &config).line).starts_with("[Opus] │ other"));
+    }
+
+    #[test]
+    fn test_idle_segment() {
+        let mut config = ClaudeConfig::default();
+        let session = SessionState::default();
+        let idle = |config: &ClaudeConfig, json: &str| {
+            let input: StatusInput = serde_json::from_str(json).unwrap();
+            find_segment(&input, config, &fixed_options(&session), "idle").map(|segment| segment.plain_text())
+        };
+        let with_transcript = r#"{"transcript_path": "/tmp/session.jsonl"}"#;
+        // 默认关闭
+        assert_eq!(idle(&config, with_transcript), None);
+
+        config.statusline.idle_minutes = Some(10);
+        assert_eq!(idle(&config, with_transcript).as_deref(), Some("idle:12m"));
+        // 没有 transcript 时不显示
+        assert_eq!(idle(&config, "{}"), None);
+
+        // 未超过阈值
+        config.statusline.idle_minutes = Some(15);
+        assert_eq!(idle(&config, with_transcript), None);
+    }
+
+    #[test]
+    fn test_tokens_fallback_to_totals() {
+        let session = SessionState::default();
+        let texts = |context_window: serde_json::Value, style: TokensStyle| {
+            let input: StatusInput =
+                serde_json::from_value(serde_json::json!({"context_window": context_window})).unwrap();
+            let mut config = ClaudeConfig::default();
+            config.statusline.tokens_style = style;
+            input_segments(&input, &config, &fixed_options(&session), &i18n::EN)
+                .iter()
+                .filter(|segment| segment.name == "tokens" || segment.name.starts_with("cache"))
+                .map(Segment::plain_text)
+                .collect::<Vec<_>>()
+        };
+        let usage = serde_json::json!({
+            "input_tokens": 15_200,
+            "output_tokens": 800,
+            "cache_read_input_tokens": 9_000
+        });
+        let totals = serde_json::json!({"total_input_tokens": 1_234_567, "total_output_tokens": 45_000});
+
+        // 只有最近一次请求的用量
+        let current = serde_json::json!({"current_usage": usage});
+        assert_eq!(texts(current.clone(), TokensStyle::Separate), ["in:15.2k", "cache:59%"]);
+        assert_eq!(texts(current, TokensStyle::Compact), ["⛁ 15.2k↑ 800↓ 9.0k⚡", "cache:59%"]);
+
+        // 只有会话累计值：加上 Σ，不计算缓存命中率
+        assert_eq!(texts(totals.clone(), TokensStyle::Separate), ["Σ in:1.2M out:45.0k"]);
+        assert_eq!(texts(totals.clone(), TokensStyle::Compact), ["⛁ Σ 1.2M↑ 45.0k↓"]);
+        let input_only = serde_json::json!({"total_input_tokens": 5_000});
+        assert_eq!(texts(input_only, TokensStyle::Separate), ["Σ in:5.0k"]);
+
+        // 两者都有时优先使用最近一次请求的用量
+        let mut both = totals;
+        both["current_usage"] = usage;
+        assert_eq!(texts(both, TokensStyle::Separate), ["in:15.2k", "cache:59%"]);
+
+        assert!(texts(serde_json::json!({}), TokensStyle::Separate).is_empty());
+        assert!(texts(serde_json::json!({}), TokensStyle::Compact).is_empty());
+    }
+
+    #[test]
+    fn test_resolved_transcript_path() {
+        let resolve = |json: serde_json::Value| {
+            serde_json::from_value::<StatusInput>(json).unwrap().resolved_transcript_path()
+        };
+        let relative = "sessions/abc.jsonl";
+        let path = resolve(serde_json::json!({"transcript_path": relative, "cwd": "/work/project"}));
+        assert_eq!(path.as_deref(), Some("/work/project/sessions/abc.jsonl"));
+        let path = resolve(serde_json::json!({
+            "transcript_path": relative,
+            "workspace": {"current_dir": "/work/other"}
+        }));
+        assert_eq!(path.as_deref(), Some("/work/other/sessions/abc.jsonl"));
+        // 绝对路径和没有工作目录时原样使用
+        let path = resolve(serde_json::json!({"transcript_path": "/tmp/abc.jsonl", "cwd": "/work/project"}));
+        assert_eq!(path.as_deref(), Some("/tmp/abc.jsonl"));
+        assert_eq!(resolve(serde_json::json!({"transcript_path": relative})).as_deref(), Some(relative));
+        assert_eq!(resolve(serde_json::json!({"cwd": "/work/project"})), None);
+
+        // 读取 transcript 的功能使用解析后的路径
+        let mut config = ClaudeConfig::default();
+        config.statusline.messages = true;
+        let session = SessionState::default();
+        let options = StatuslineOptions {
+            messages: |path| {
+                let counts = transcript::MessageCounts { user: 3, assistant: 2, partial: false };
+                (path == "/work/project/sessions/abc.jsonl").then_some(counts)
+            },
+            ..fixed_options(&session)
+        };
+        let json = serde_json::json!({"transcript_path": relative, "cwd": "/work/project"});
+        let input: StatusInput = serde_json::from_value(json).unwrap();
+        let segments = input_segments(&input, &config, &options, &i18n::EN);
+        assert!(segments.iter().any(|segment| segment.name == "messages"));
+    }
+
+    #[test]
+    fn test_finish_line() {
+        let mut statusline = config::StatuslineConfig::default();
+        assert_eq!(finish_line("[Opus]".to_string(), &statusline), "[Opus]");
+
+        statusline.prefix = "「".to_string();
+        statusline.suffix = "」".to_string();
+        assert_eq!(decoration_width(&statusline), 4);
+        assert_eq!(finish_line("[Opus]".to_string(), &statusline), "「[Opus]」");
+
+        // 换行之前的前缀和换行之后的后缀不占 statusline 所在行的宽度
+        statusline.prefix = "prompt\n".to_string();
+        statusline.suffix = " ·\nnext".to_string();
+        assert_eq!(decoration_width(&statusline), 2);
+        statusline.max_len = Some(6);
+        assert_eq!(finish_line("[Opus] │ ctx".to_string(), &statusline), "prompt\n[Op…\x1b[0m ·\nnext");
+    }
+
+    #[test]
+    fn test_platform_segments() {
+        let session = SessionState::default();
+        let id = "anthropic.claude-sonnet-4-5-20250929-v1:0";
+        let input: StatusInput =
+            serde_json::from_value(serde_json::json!({"model": {"id": id, "display_name": id}})).unwrap();
+        let texts = |options: &StatuslineOptions| -> Vec<String> {
+            input_segments(&input, &ClaudeConfig::default(), options, &i18n::EN)
+                .iter()
+                .map(Segment::plain_text)
+                .collect()
+        };
+        let bedrock = StatuslineOptions {
+            platform: || Some(platform::Platform::Bedrock),
+            ..fixed_options(&session)
+        };
+        assert_eq!(texts(&bedrock), ["[Sonnet 4.5]", "aws"]);
+        // 未使用云平台时原样显示
+        assert_eq!(texts(&fixed_options(&session)), ["[anthropic.claude-sonnet-4-5-20250929-v1:0]"]);
+    }
+
+    #[test]
+    fn test_skip_providers() {
+        let (bedrock, vertex) = (Some(platform::Platform::Bedrock), Some(platform::Platform::Vertex));
+        let mut config = ClaudeConfig::default();
+        assert!(!skip_providers(&config, None));
+        assert!(skip_providers(&config, bedrock));
+        assert!(skip_providers(&config, vertex));
+        // 固定的数据源不受影响
+        config.provider = Some("yunyi".to_string());
+        assert!(!skip_providers(&config, bedrock));
+        assert!(!skip_providers(&config, vertex));
+
+        // 渲染时按注入的平台判断，不读取真实环境变量
+        let session = SessionState::default();
+        let input: StatusInput = serde_json::from_str(r#"{"model": {"display_name": "Opus"}}"#).unwrap();
+        let options = |platform: fn() -> Option<platform::Platform>| StatuslineOptions {
+            providers: |_, _| vec![Segment::new("zhipu_token", colors::GREEN, "[ZAI] Token(5h):12%")],
+            platform,
+            ..fixed_options(&session)
+        };
+        let names = |config: &ClaudeConfig, options: &StatuslineOptions| {
+            collect_segments(&input, config, options).iter().map(|segment| segment.name).collect::<Vec<_>>()
+        };
+        config.provider = None;
+        assert_eq!(names(&config, &options(|| Some(platform::Platform::Vertex))), ["model", "platform"]);
+        assert_eq!(names(&config, &options(|| None)), ["model", "zhipu_token"]);
+    }
+
+    #[test]
+    fn test_terminal_title() {
+        let mut config = ClaudeConfig::default();
+        let input: StatusInput =
+            serde_json::from_str(r#"{"workspace": {"project_dir": "/work/myproject"}}"#).unwrap();
+        let ctx = |pct: f64| {
+            Segment::graded("ctx", context_severity(&colors::THEMES[0], pct), format!("ctx:{}%", pct))
+                .with(colors::DIM, " (cached 40%)")
+        };
+        let critical = [ctx(91.0), Segment::graded("zhipu_token", Severity::Crit, "[ZAI] Token(5h):97%")];
+        assert_eq!(terminal_title(&critical, &input, &config), None);
+
+        config.statusline.osc_title = true;
+        assert_eq!(
+            terminal_title(&critical, &input, &config).as_deref(),
+            Some("⚠ ctx:91% · [ZAI] Token(5h):97% — myproject")
+        );
+        // 不是严重级别时只有项目名；其余红色的段不计入
+        let normal = [ctx(42.0), Segment::new("errors", colors::RED, "err:2")];
+        assert_eq!(terminal_title(&normal, &input, &config).as_deref(), Some("myproject"));
+        assert_eq!(terminal_title(&normal, &StatusInput::default(), &config), None);
+        let title = terminal_title(&critical[..1], &StatusInput::default(), &config);
+        assert_eq!(title.as_deref(), Some("⚠ ctx:91%"));
+
+        // 去掉控制字符，不超过 60 列
+        let project_dir = format!("/work/{}\x07\x1b", "p".repeat(80));
+        let json = serde_json::json!({"workspace": {"project_dir": project_dir}});
+        let input: StatusInput = serde_json::from_value(json).unwrap();
+        let title = terminal_title(&critical, &input, &config).unwrap();
+        assert_eq!(text::visible_width(&title), 60);
+        assert!(title.ends_with("ppp…"));
+        assert!(!title.chars().any(char::is_control));
+    }
+
+    #[test]
+    fn test_history_only_for_status_events() {
+        let event = |json: &str| is_status_event(&serde_json::from_str(json).unwrap());
+        assert!(event("{}"));
+        assert!(event(r#"{"hook_event_name": "Status"}"#));
+        assert!(!event(r#"{"hook_event_name": "PostToolUse"}"#));
+    }
+
+    #[test]
+    fn test_mcp_segment_opt_in() {
+        let mut config = ClaudeConfig::default();
+        let input: StatusInput = serde_json::from_str(r#"{"model": {"display_name": "Opus"}}"#).unwrap();
+        let session = SessionState::default();
+        let mcp = |config: &ClaudeConfig| {
+            find_segment(&input, config, &fixed_options(&session), "mcp")
+        };
+        assert!(mcp(&config).is_none());
+
+        config.statusline.mcp = true;
+        assert_eq!(mcp(&config).unwrap().plain_text(), "mcp:2/3");
+    }
+
+    #[test]
+    fn test_cache_hit_rate_thresholds() {
+        let input: StatusInput = serde_json::from_str(
+            r#"{"context_window": {"current_usage": {"input_tokens": 1000, "cache_read_input_tokens": 700}}}"#,
+        )
+        .unwrap();
+        let session = SessionState::default();
+        let cache_color = |config: &ClaudeConfig| {
+            find_segment(&input, config, &fixed_options(&session), "cache").map(|segment| segment.color())
+        };
+
+        let mut config = ClaudeConfig::default();
+        assert_eq!(cache_color(&config), Some(colors::YELLOW));
+        config.statusline.cache_good_pct = Some(70.0);
+        assert_eq!(cache_color(&config), Some(colors::GREEN));
+        config.statusline.cache_warn_pct = Some(75.0);
+        config.statusline.cache_good_pct = Some(90.0);
+        assert_eq!(cache_color(&config), Some(colors::RED));
+    }
+
+    #[test]
+    fn test_hide_normal() {
+        let segments = || {
+            vec![
+                Segment::new("model", colors::MAGENTA, "[Opus]"),
+                Segment::graded("ctx", context_severity(&colors::THEMES[0], 42.0), "ctx:42%"),
+                Segment::graded("cache", Severity::Ok, "cache:95%"),
+                Segment::new("zhipu_token", colors::DIM, "[GLM] ").with_graded(Severity::Crit, "Token(5h):91%"),
+                // 与警告颜色相同、但不是按阈值着色的文本不算异常
+                Segment::new("cost", colors::YELLOW, "$1.20"),
+            ]
+        };
+        let names = |segments: &[Segment]| segments.iter().map(|segment| segment.name).collect::<Vec<_>>();
+
+        let mut quiet = segments();
+        let quiet_names = ["ctx", "cache", "zhipu_token", "cost"].map(String::from);
+        hide_normal(&mut quiet, &quiet_names);
+        assert_eq!(names(&quiet), ["model", "zhipu_token"]);
+
+        // 达到警告级别时显示
+        let mut warn = segments();
+        warn[1] = Segment::graded("ctx", context_severity(&colors::THEMES[0], 65.0), "ctx:65%");
+        hide_normal(&mut warn, &["ctx".to_string()]);
+        assert_eq!(names(&warn), ["model", "ctx", "cache", "zhipu_token", "cost"]);
+
+        let mut all = segments();
+        hide_normal(&mut all, &[]);
+        assert_eq!(all.len(), 5);
+    }
+
+    #[test]
+    fn test_mark_thresholds() {
+        let palette = colors::Palette::colorblind(false);
+        let mut segments = vec![
+            Segment::graded("ctx", context_severity(&colors::THEMES[0], 90.0), "ctx:90%"),
+            Segment::graded("load", colors::DEFAULT_THRESHOLDS.load.severity(75.0), "load:6.00"),
+            Segment::graded("cache", Severity::Ok, "cache:95%"),
+            Segment::new("zhipu", colors::DIM, "[GLM] ").with_graded(Severity::Warn, "Token(5h):65%"),
+            Segment::graded("ctx", Severity::Warn, "ctx:").with(colors::DIM, "≈").with_graded(Severity::Warn, "65%"),
+            // 与正常颜色相同的普通段不加符号
+            Segment::new("dir", palette.ok, "project"),
+        ];
+        mark_thresholds(&mut segments, &colors::Palette::default());
+        assert_eq!(segments[0].plain_text(), "ctx:90%");
+
+        mark_thresholds(&mut segments, &palette);
+        let texts: Vec<String> = segments.iter().map(Segment::plain_text).collect();
+        assert_eq!(
+            texts,
+            ["ctx:90%✗", "load:6.00!", "cache:95%✓", "[GLM] Token(5h):65%!", "ctx:≈65%!", "project"]
+        );
     }
 }
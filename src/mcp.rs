@@ -0,0 +1,236 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsStr;
+use std::fs;
+use std::path::Path;
+
+use crate::colors;
+use crate::error::StatuslineError;
+use crate::i18n::Labels;
+use crate::paths::{self, claude_json_path, mcp_cache_path};
+use crate::render::Segment;
+
+/// 检查结果的缓存时长
+const CACHE_MAX_AGE_SECS: i64 = 60;
+
+/// 已配置的 MCP 服务器数量，以及其中命令找不到的 stdio 服务器数量
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub struct McpStatus {
+    pub total: usize,
+    pub missing: usize,
+}
+
+/// 单个 MCP 服务器配置，只关心类型和命令
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+struct ServerConfig {
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    command: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct ProjectConfig {
+    #[serde(default, rename = "mcpServers")]
+    mcp_servers: BTreeMap<String, ServerConfig>,
+}
+
+/// `~/.claude.json`（全局与按项目的 `mcpServers`）和项目 `.mcp.json`（只有 `mcpServers`）共用的结构
+#[derive(Debug, Deserialize, Default)]
+struct McpConfig {
+    #[serde(default, rename = "mcpServers")]
+    mcp_servers: BTreeMap<String, ServerConfig>,
+    #[serde(default)]
+    projects: HashMap<String, ProjectConfig>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct McpCache {
+    project: Option<String>,
+    status: Option<McpStatus>,
+    timestamp: DateTime<Utc>,
+}
+
+/// 从配置文件内容中取出对当前项目生效的服务器，同名时项目配置覆盖全局配置
+fn parse_servers(content: &str, project: Option<&str>) -> BTreeMap<String, ServerConfig> {
+    let Ok(mut config) = serde_json::from_str::<McpConfig>(content) else {
+        return BTreeMap::new();
+    };
+    if let Some(project) = project.and_then(|project| config.projects.remove(project)) {
+        config.mcp_servers.extend(project.mcp_servers);
+    }
+    config.mcp_servers
+}
+
+/// 在 PATH 中查找命令（只检查文件，不启动进程）；命令带路径分隔符时直接检查该路径
+fn command_exists(command: &str, path_var: Option<&OsStr>) -> bool {
+    let is_executable = |path: &Path| {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::metadata(path)
+                .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+        }
+        #[cfg(not(unix))]
+        {
+            path.is_file()
+                || ["exe", "cmd", "bat"].iter().any(|ext| path.with_extension(ext).is_file())
+        }
+    };
+    if command.contains('/') || command.contains('\\') {
+        return is_executable(Path::new(command));
+    }
+    path_var.is_some_and(|path_var| {
+        std::env::split_paths(path_var).any(|dir| is_executable(&dir.join(command)))
+    })
+}
+
+/// 统计服务器数量和命令缺失的 stdio 服务器；http / sse 服务器只计入总数
+fn check(servers: &BTreeMap<String, ServerConfig>, path_var: Option<&OsStr>) -> McpStatus {
+    let missing = servers
+        .values()
+        .filter(|server| server.kind.as_deref().is_none_or(|kind| kind == "stdio"))
+        .filter(|server| match server.command.as_deref() {
+            Some(command) => !command_exists(command, path_var),
+            None => true,
+        })
+        .count();
+    McpStatus {
+        total: servers.len(),
+        missing,
+    }
+}
+
+/// 读取两个配置文件并检查命令，没有配置任何服务器时为 None
+fn read_status(project: Option<&str>) -> Option<McpStatus> {
+    let mut servers = claude_json_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|content| parse_servers(&content, project))
+        .unwrap_or_default();
+    let project_file = project.map(|project| Path::new(project).join(".mcp.json"));
+    if let Some(content) = project_file.and_then(|path| fs::read_to_string(path).ok()) {
+        servers.extend(parse_servers(&content, None));
+    }
+    if servers.is_empty() {
+        return None;
+    }
+    Some(check(&servers, std::env::var_os("PATH").as_deref()))
+}
+
+/// 当前项目的 MCP 状态，同一项目 60 秒内复用上次的检查结果
+pub fn status(project: Option<&str>) -> Option<McpStatus> {
+    let cache_path = mcp_cache_path();
+    let cached = fs::read_to_string(&cache_path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<McpCache>(&content).ok())
+        .filter(|cache| cache.project.as_deref() == project)
+        .filter(|cache| {
+            Utc::now().signed_duration_since(cache.timestamp).num_seconds() < CACHE_MAX_AGE_SECS
+        });
+    if let Some(cache) = cached {
+        return cache.status;
+    }
+
+    let status = read_status(project);
+    let cache = McpCache {
+        project: project.map(String::from),
+        status,
+        timestamp: Utc::now(),
+    };
+    if let Some(json) = serde_json::to_string(&cache).ok().filter(|_| paths::persistence_enabled()) {
+        if let Err(err) = fs::write(&cache_path, json) {
+            paths::write_failed(StatuslineError::write(&cache_path, err));
+        }
+    }
+    status
+}
+
+/// 全部可用时显示 `mcp:N`（绿色），有命令缺失时显示 `mcp:可用数/总数`（黄色）
+pub fn segment(status: McpStatus, labels: &Labels) -> Option<Segment> {
+    if status.total == 0 {
+        return None;
+    }
+    Some(if status.missing == 0 {
+        Segment::new("mcp", colors::GREEN, format!("{}:{}", labels.mcp, status.total))
+    } else {
+        let available = status.total - status.missing;
+        Segment::new("mcp", colors::YELLOW, format!("{}:{}/{}", labels.mcp, available, status.total))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn fixture(name: &str) -> String {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/mcp").join(name);
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn test_parse_claude_json() {
+        let content = fixture("claude.json");
+        let servers = parse_servers(&content, Some("/work/project"));
+        assert_eq!(servers.keys().collect::<Vec<_>>(), ["context7", "filesystem", "github", "linear"]);
+        // 项目配置覆盖同名的全局配置
+        assert_eq!(servers["filesystem"].command.as_deref(), Some("/opt/mcp/filesystem"));
+        assert_eq!(servers["linear"].kind.as_deref(), Some("http"));
+
+        let global = parse_servers(&content, Some("/work/other"));
+        assert_eq!(global.keys().collect::<Vec<_>>(), ["context7", "filesystem"]);
+        assert_eq!(global["filesystem"].command.as_deref(), Some("npx"));
+    }
+
+    #[test]
+    fn test_parse_project_mcp_json() {
+        let servers = parse_servers(&fixture("mcp.json"), None);
+        assert_eq!(servers.keys().collect::<Vec<_>>(), ["postgres", "sentry"]);
+        assert_eq!(servers["postgres"].command.as_deref(), Some("postgres-mcp"));
+        assert_eq!(servers["sentry"].kind.as_deref(), Some("sse"));
+
+        assert!(parse_servers("not json", None).is_empty());
+        assert!(parse_servers("{}", None).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_commands_on_path() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("cc-statusline-mcp-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let tool = dir.join("postgres-mcp");
+        fs::write(&tool, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&tool, fs::Permissions::from_mode(0o755)).unwrap();
+        // 没有执行权限的文件不算
+        fs::write(dir.join("not-executable"), "").unwrap();
+
+        let path_var = std::env::join_paths([PathBuf::from("/nonexistent"), dir.clone()]).unwrap();
+        let path_var = Some(path_var.as_os_str());
+        assert!(command_exists("postgres-mcp", path_var));
+        assert!(command_exists(tool.to_str().unwrap(), None));
+        assert!(!command_exists("not-executable", path_var));
+        assert!(!command_exists("postgres-mcp", None));
+
+        let mut servers = parse_servers(&fixture("mcp.json"), None);
+        assert_eq!(check(&servers, path_var), McpStatus { total: 2, missing: 0 });
+        servers.extend(parse_servers(&fixture("claude.json"), Some("/work/project")));
+        assert_eq!(check(&servers, path_var), McpStatus { total: 6, missing: 2 });
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_segment() {
+        let segment = segment(McpStatus { total: 3, missing: 0 }, &crate::i18n::EN).unwrap();
+        assert_eq!(segment.plain_text(), "mcp:3");
+        assert_eq!(segment.color(), colors::GREEN);
+
+        let partial = super::segment(McpStatus { total: 3, missing: 1 }, &crate::i18n::EN).unwrap();
+        assert_eq!(partial.plain_text(), "mcp:2/3");
+        assert_eq!(partial.color(), colors::YELLOW);
+
+        assert!(super::segment(McpStatus { total: 0, missing: 0 }, &crate::i18n::EN).is_none());
+    }
+}
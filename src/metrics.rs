@@ -0,0 +1,72 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+use crate::error::{self, StatuslineError};
+
+/// 每次渲染追加到 `statusline.log` 的一行指标，用于事后统计用量
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct MetricsRecord {
+    pub timestamp: DateTime<Utc>,
+    pub session_id: Option<String>,
+    pub model: Option<String>,
+    pub cost_usd: Option<f64>,
+    pub input_tokens: Option<u64>,
+    pub output_tokens: Option<u64>,
+    pub context_pct: Option<f64>,
+}
+
+/// 追加一行指标；尽力而为，失败时只在调试模式下提示，不影响 statusline 输出
+pub fn append(path: &Path, record: &MetricsRecord) {
+    let Ok(mut json) = serde_json::to_string(record) else {
+        return;
+    };
+    json.push('\n');
+    // 整行一次写入，多个会话同时追加时不会交错
+    let result = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(json.as_bytes()));
+    if let Err(err) = result {
+        error::report(&StatuslineError::write(path, err));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_lines() {
+        let dir = std::env::temp_dir().join(format!("cc-statusline-metrics-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("metrics.jsonl");
+        let _ = std::fs::remove_file(&path);
+
+        let record = MetricsRecord {
+            timestamp: "2025-01-15T10:00:00Z".parse().unwrap(),
+            session_id: Some("abc".to_string()),
+            model: Some("claude-opus-4-1".to_string()),
+            cost_usd: Some(1.25),
+            input_tokens: Some(15_200),
+            output_tokens: None,
+            context_pct: Some(42.5),
+        };
+        append(&path, &record);
+        append(&path, &MetricsRecord { cost_usd: Some(1.5), ..record.clone() });
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let records: Vec<MetricsRecord> =
+            content.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0], record);
+        assert_eq!(records[1].cost_usd, Some(1.5));
+
+        // 无法写入时静默忽略
+        append(&dir.join("missing").join("metrics.jsonl"), &record);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,444 @@
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+use crate::error::{self, StatuslineError};
+
+/// 替代 `~/.claude` 的目录，便于测试和便携安装
+pub const HOME_ENV: &str = "CC_STATUSLINE_HOME";
+
+/// 替代 `~/.claude/statusline/` 存放缓存和状态文件的目录（如 `~/.claude` 只读时指向 `/tmp`）
+pub const STATE_DIR_ENV: &str = "CC_STATUSLINE_STATE_DIR";
+
+/// `~/.claude` 下存放本工具文件的子目录
+const STATE_DIR: &str = "statusline";
+
+/// 本次运行中状态目录已无法写入，不再尝试写入缓存和状态文件
+static PERSISTENCE_DISABLED: AtomicBool = AtomicBool::new(false);
+
+pub const HISTORY_FILE: &str = "statusline_history.jsonl";
+pub const SESSIONS_DIR: &str = "statusline_sessions";
+pub const TRACE_FILE: &str = "statusline_trace.jsonl";
+pub const MCP_FILE: &str = "statusline_mcp.json";
+pub const GIT_FILE: &str = "statusline_git.json";
+
+/// 各数据源的缓存文件名，不论是否编译了该数据源；迁移和清理只处理这些文件及其按 token 区分的版本，
+/// 其他工具写入的 `*_cache.json` 不动
+pub const PROVIDER_CACHE_FILES: [&str; 5] = [
+    ".zhipu_cache.json",
+    ".yunyi_cache.json",
+    ".packycode_cache.json",
+    ".gac_cache.json",
+    ".openai_usage_cache.json",
+];
+
+/// 外部 hook 写入的限流信息，不是本工具创建的文件，留在 `~/.claude/` 下
+#[cfg(feature = "provider-anthropic")]
+const RATELIMIT_FILE: &str = ".ratelimit_cache.json";
+
+/// Claude Code 配置目录：`$CC_STATUSLINE_HOME`，否则为 `~/.claude`
+///
+/// 使用 `var_os` 读取，home 路径不是合法 UTF-8 时也能正常工作。
+fn claude_dir() -> Option<PathBuf> {
+    resolve_claude_dir(
+        std::env::var_os(HOME_ENV),
+        std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")),
+    )
+}
+
+fn resolve_claude_dir(override_dir: Option<OsString>, home: Option<OsString>) -> Option<PathBuf> {
+    let non_empty = |value: OsString| Some(value).filter(|value| !value.is_empty());
+    if let Some(dir) = override_dir.and_then(non_empty) {
+        return Some(PathBuf::from(dir));
+    }
+    home.and_then(non_empty).map(|home| PathBuf::from(home).join(".claude"))
+}
+
+/// Claude Code 配置文件 `settings.json`
+pub fn settings_path() -> Option<PathBuf> {
+    Some(claude_dir()?.join("settings.json"))
+}
+
+/// Claude Code 配置目录（规范化后），首次调用时创建；没有 home 目录时使用系统临时目录
+pub fn claude_home() -> &'static Path {
+    static CLAUDE_HOME: OnceLock<PathBuf> = OnceLock::new();
+    CLAUDE_HOME.get_or_init(|| {
+        let dir = claude_dir().unwrap_or_else(std::env::temp_dir);
+        prepare_dir(&dir).unwrap_or_else(|err| {
+            error::report(&err);
+            dir
+        })
+    })
+}
+
+/// 本工具的缓存和状态文件目录：`$CC_STATUSLINE_STATE_DIR`，否则为 `~/.claude/statusline/`
+///
+/// 默认目录首次创建时迁移旧版本放在 `~/.claude/` 下的文件；目录无法创建时本次运行不再写入状态文件。
+pub fn cache_dir() -> &'static Path {
+    static CACHE_DIR: OnceLock<PathBuf> = OnceLock::new();
+    CACHE_DIR.get_or_init(|| {
+        if let Some(dir) = std::env::var_os(STATE_DIR_ENV).filter(|dir| !dir.is_empty()) {
+            let dir = PathBuf::from(dir);
+            return prepare_dir(&dir).unwrap_or_else(|err| {
+                write_failed(err);
+                dir
+            });
+        }
+        let home = claude_home();
+        let dir = home.join(STATE_DIR);
+        let migrate = !dir.exists();
+        match prepare_dir(&dir) {
+            Ok(dir) => {
+                if migrate {
+                    migrate_legacy_files(home, &dir);
+                }
+                dir
+            }
+            Err(err) => {
+                write_failed(err);
+                dir
+            }
+        }
+    })
+}
+
+/// 确保目录存在，并返回规范化后的路径（跟随符号链接）
+fn prepare_dir(dir: &Path) -> Result<PathBuf, StatuslineError> {
+    fs::create_dir_all(dir).map_err(|err| StatuslineError::create(dir, err))?;
+    Ok(fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf()))
+}
+
+/// 是否可以写入缓存和状态文件；写入失败过一次后本次运行返回 false，各功能照常渲染，只是不跨次保留状态
+pub fn persistence_enabled() -> bool {
+    !PERSISTENCE_DISABLED.load(Ordering::Relaxed)
+}
+
+/// 记录状态文件写入失败（只读文件系统、磁盘已满等）并关闭本次运行的持久化，只在第一次失败时提示
+pub fn write_failed(err: StatuslineError) {
+    disable(&PERSISTENCE_DISABLED, &err);
+}
+
+/// 设置 `flag`，第一次设置时在调试模式下提示，返回是否为第一次
+fn disable(flag: &AtomicBool, err: &StatuslineError) -> bool {
+    if flag.swap(true, Ordering::Relaxed) {
+        return false;
+    }
+    crate::log::warn(format_args!("{}; state will not be saved during this run", err));
+    true
+}
+
+/// 花费历史记录
+pub fn history_path() -> PathBuf {
+    cache_dir().join(HISTORY_FILE)
+}
+
+/// 按会话保存的状态文件所在目录
+pub fn sessions_dir() -> PathBuf {
+    cache_dir().join(SESSIONS_DIR)
+}
+
+/// Claude Code 的全局状态文件 `~/.claude.json`（设置 `CC_STATUSLINE_HOME` 时为该目录下的 `.claude.json`）
+pub fn claude_json_path() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os(HOME_ENV).filter(|dir| !dir.is_empty()) {
+        return Some(PathBuf::from(dir).join(".claude.json"));
+    }
+    Some(claude_dir()?.with_file_name(".claude.json"))
+}
+
+/// `CC_STATUSLINE_TRACE=1` 时各阶段耗时的记录
+pub fn trace_path() -> PathBuf {
+    cache_dir().join(TRACE_FILE)
+}
+
+/// MCP 状态检查结果的缓存
+pub fn mcp_cache_path() -> PathBuf {
+    cache_dir().join(MCP_FILE)
+}
+
+/// 不在 git 仓库中的目录及检查时间
+pub fn git_cache_path() -> PathBuf {
+    cache_dir().join(GIT_FILE)
+}
+
+/// 外部 hook 写入的 Anthropic 限流信息 `~/.claude/.ratelimit_cache.json`
+#[cfg(feature = "provider-anthropic")]
+pub fn ratelimit_path() -> PathBuf {
+    claude_home().join(RATELIMIT_FILE)
+}
+
+/// 把旧版本放在 `~/.claude/` 下的文件移到 `to`；外部 hook 写入的限流信息等其他文件保留在原处
+fn migrate_legacy_files(from: &Path, to: &Path) {
+    let Ok(entries) = fs::read_dir(from) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+        let legacy = [HISTORY_FILE, SESSIONS_DIR, TRACE_FILE, MCP_FILE].contains(&name)
+            || is_cache_file(name);
+        if !legacy {
+            continue;
+        }
+        // 多个进程同时迁移时，已被其他进程移走的文件报 NotFound，不需要提示；
+        // 目标目录不存在同样报 NotFound，这时源文件还在，需要提示
+        match fs::rename(entry.path(), to.join(name)) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound && !entry.path().exists() => {}
+            Err(err) => crate::log::warn(StatuslineError::Io {
+                action: "move",
+                path: entry.path(),
+                source: err,
+            }),
+            Ok(()) => {}
+        }
+    }
+}
+
+/// 先写入同目录下的临时文件 `<文件名>.<pid>.tmp` 再重命名，并发渲染不会读到半个文件；失败时删除临时文件
+///
+/// 中断后残留的临时文件由 `gc` 清理。
+pub fn write_atomic(path: &Path, content: impl AsRef<[u8]>) -> io::Result<()> {
+    write_atomic_if(path, content, || Ok(true))
+}
+
+/// 同 [`write_atomic`]，但在重命名前调用 `unchanged`，返回 false（如目标文件已被其他进程修改）时放弃写入
+pub fn write_atomic_if(
+    path: &Path,
+    content: impl AsRef<[u8]>,
+    unchanged: impl FnOnce() -> io::Result<bool>,
+) -> io::Result<()> {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.tmp", std::process::id()));
+    let tmp = path.with_file_name(name);
+    let result = fs::write(&tmp, content).and_then(|_| match unchanged()? {
+        true => fs::rename(&tmp, path),
+        false => fs::remove_file(&tmp),
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// 字符串的 FNV-1a 哈希，不同版本的程序对同样的输入得到同样的值，可用于文件名和保存的状态
+pub fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// 数据源的缓存文件：[`PROVIDER_CACHE_FILES`] 中的文件，以及按 token 区分的 `.<名称>_cache.<哈希>.json`
+pub fn is_cache_file(name: &str) -> bool {
+    PROVIDER_CACHE_FILES.iter().any(|file| is_cache_of(name, file))
+}
+
+/// `name` 是否为缓存文件 `file`，或其按 token 区分的 `<stem>.<哈希>.json`
+pub fn is_cache_of(name: &str, file: &str) -> bool {
+    let stem = file.strip_suffix(".json").unwrap_or(file);
+    name == file
+        || name
+            .strip_prefix(stem)
+            .and_then(|rest| rest.strip_prefix('.'))
+            .and_then(|rest| rest.strip_suffix(".json"))
+            .is_some_and(|hash| !hash.is_empty() && hash.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// 删除 `dir` 中所有数据源的缓存文件（见 [`is_cache_file`]），返回删除的数量；单个文件删除失败时跳过
+pub fn clear_caches(dir: &Path) -> Result<usize, StatuslineError> {
+    let entries = fs::read_dir(dir).map_err(|err| StatuslineError::read(dir, err))?;
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_file() || !path.file_name().and_then(|name| name.to_str()).is_some_and(is_cache_file) {
+            continue;
+        }
+        match fs::remove_file(&path) {
+            Ok(()) => removed += 1,
+            Err(err) => error::report(&StatuslineError::Io {
+                action: "remove",
+                path,
+                source: err,
+            }),
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fnv1a() {
+        let hash = fnv1a("[Opus] │ ctx:42%");
+        assert_eq!(hash, fnv1a("[Opus] │ ctx:42%"));
+        assert_ne!(hash, fnv1a("[Opus] │ ctx:43%"));
+        // 保存在会话文件和缓存文件名中，结果不能随程序版本变化
+        assert_eq!(fnv1a(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a("a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn test_write_atomic() {
+        let dir = std::env::temp_dir().join(format!("cc-statusline-atomic-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("state.json");
+
+        write_atomic(&path, "first").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first");
+        // 放弃写入时保留原内容，都不留下临时文件
+        write_atomic_if(&path, "second", || Ok(false)).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        assert!(write_atomic(&dir.join("missing/state.json"), "x").is_err());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_override_wins_over_home() {
+        let dir = resolve_claude_dir(Some("/opt/statusline".into()), Some("/home/me".into()));
+        assert_eq!(dir, Some(PathBuf::from("/opt/statusline")));
+
+        let dir = resolve_claude_dir(Some("".into()), Some("/home/me".into()));
+        assert_eq!(dir, Some(PathBuf::from("/home/me/.claude")));
+        assert_eq!(resolve_claude_dir(None, None), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_home() {
+        use std::os::unix::ffi::OsStringExt;
+
+        let home = OsString::from_vec(b"/home/caf\xe9".to_vec());
+        let dir = resolve_claude_dir(None, Some(home)).unwrap();
+        assert!(dir.to_str().is_none());
+        assert!(dir.ends_with(".claude"));
+    }
+
+    #[test]
+    fn test_prepare_dir_creates_on_demand() {
+        let root = std::env::temp_dir().join(format!("cc-statusline-paths-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let dir = prepare_dir(&root.join("nested").join(".claude")).unwrap();
+        assert!(dir.is_dir());
+        assert_eq!(dir, fs::canonicalize(root.join("nested/.claude")).unwrap());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_prepare_dir_under_a_file() {
+        let file = std::env::temp_dir().join(format!("cc-statusline-not-a-dir-{}", std::process::id()));
+        fs::write(&file, "").unwrap();
+        let err = prepare_dir(&file.join("state")).unwrap_err();
+        assert!(err.to_string().starts_with("cannot create "), "{}", err);
+        fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn test_disable_reports_once() {
+        let flag = AtomicBool::new(false);
+        let path = Path::new("/ro/state.json");
+        let err = || StatuslineError::write(path, std::io::ErrorKind::ReadOnlyFilesystem.into());
+        assert!(disable(&flag, &err()));
+        assert!(flag.load(Ordering::Relaxed));
+        assert!(!disable(&flag, &err()));
+    }
+
+    #[test]
+    fn test_is_cache_file() {
+        assert!(is_cache_of(".zhipu_cache.json", ".zhipu_cache.json"));
+        assert!(is_cache_of(".zhipu_cache.0123456789abcdef.json", ".zhipu_cache.json"));
+        assert!(!is_cache_of(".zhipu_cache.backup.json", ".zhipu_cache.json"));
+        assert!(!is_cache_of(".gac_cache.json", ".zhipu_cache.json"));
+        assert!(is_cache_file(".openai_usage_cache.0123456789abcdef.json"));
+        assert!(!is_cache_file(".other_cache.json"));
+        assert!(!is_cache_file(".ratelimit_cache.json"));
+    }
+
+    #[test]
+    fn test_clear_caches() {
+        let dir = std::env::temp_dir().join(format!("cc-statusline-clear-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("statusline_sessions")).unwrap();
+        let files = [
+            ".zhipu_cache.json",
+            ".gac_cache.0123456789abcdef.json",
+            ".ratelimit_cache.json",
+            ".other_cache.json",
+            "settings.json",
+            "statusline_history.jsonl",
+            "statusline_mcp.json",
+        ];
+        for name in files {
+            fs::write(dir.join(name), "{}").unwrap();
+        }
+
+        // 只删除本工具的缓存，其他工具写入的 `*_cache.json` 保留
+        assert_eq!(clear_caches(&dir).unwrap(), 2);
+        let mut left: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        left.sort();
+        assert_eq!(
+            left,
+            [
+                ".other_cache.json",
+                ".ratelimit_cache.json",
+                "settings.json",
+                "statusline_history.jsonl",
+                "statusline_mcp.json",
+                "statusline_sessions"
+            ]
+        );
+        assert_eq!(clear_caches(&dir).unwrap(), 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(clear_caches(&dir).unwrap_err().is_not_found());
+    }
+
+    #[test]
+    fn test_migrate_legacy_files() {
+        let root = std::env::temp_dir().join(format!("cc-statusline-migrate-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let to = root.join(STATE_DIR);
+        fs::create_dir_all(root.join(SESSIONS_DIR)).unwrap();
+        fs::create_dir_all(&to).unwrap();
+        fs::write(root.join(SESSIONS_DIR).join("abc.json"), "{}").unwrap();
+        let foreign = [".ratelimit_cache.json", ".other_cache.json", "settings.json", "notes_cache.txt"];
+        for name in [HISTORY_FILE, MCP_FILE, ".zhipu_cache.json"].into_iter().chain(foreign) {
+            fs::write(root.join(name), "{}").unwrap();
+        }
+
+        migrate_legacy_files(&root, &to);
+        for name in [HISTORY_FILE, MCP_FILE, ".zhipu_cache.json"] {
+            assert!(to.join(name).is_file(), "{} not moved", name);
+            assert!(!root.join(name).exists());
+        }
+        assert!(to.join(SESSIONS_DIR).join("abc.json").is_file());
+        // 外部 hook 和其他工具写入的文件不移动
+        for name in foreign {
+            assert!(root.join(name).is_file(), "{} moved", name);
+        }
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_prepare_dir_follows_symlink() {
+        let root = std::env::temp_dir().join(format!("cc-statusline-symlink-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let target = root.join("dotfiles").join("claude");
+        fs::create_dir_all(&target).unwrap();
+        std::os::unix::fs::symlink(&target, root.join(".claude")).unwrap();
+
+        assert_eq!(prepare_dir(&root.join(".claude")).unwrap(), fs::canonicalize(&target).unwrap());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
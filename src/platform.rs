@@ -0,0 +1,138 @@
+use std::sync::OnceLock;
+
+use crate::colors;
+use crate::environment::{Probe, SystemProbe};
+use crate::render::Segment;
+
+/// Claude Code 直连的云平台（`CLAUDE_CODE_USE_BEDROCK` / `CLAUDE_CODE_USE_VERTEX`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    Bedrock,
+    Vertex,
+}
+
+impl Platform {
+    fn tag(self) -> &'static str {
+        match self {
+            Platform::Bedrock => "aws",
+            Platform::Vertex => "gcp",
+        }
+    }
+
+    pub fn segment(self) -> Segment {
+        Segment::new("platform", colors::DIM, self.tag())
+    }
+}
+
+/// 按环境变量判断云平台，两者都开启时按 Bedrock 处理；值为 `1` / `true` / `yes` / `on` 时视为开启
+pub fn detect(probe: &dyn Probe) -> Option<Platform> {
+    let enabled = |key: &str| {
+        let value = probe.var(key).unwrap_or_default().trim().to_lowercase();
+        matches!(value.as_str(), "1" | "true" | "yes" | "on")
+    };
+    if enabled("CLAUDE_CODE_USE_BEDROCK") {
+        Some(Platform::Bedrock)
+    } else if enabled("CLAUDE_CODE_USE_VERTEX") {
+        Some(Platform::Vertex)
+    } else {
+        None
+    }
+}
+
+/// 当前进程使用的云平台，只探测一次
+pub fn current() -> Option<Platform> {
+    static PLATFORM: OnceLock<Option<Platform>> = OnceLock::new();
+    *PLATFORM.get_or_init(|| detect(&SystemProbe))
+}
+
+/// 把 Bedrock / Vertex 的模型 ID 转成简短名称，如 `anthropic.claude-sonnet-4-5-20250929-v1:0` → `Sonnet 4.5`
+///
+/// 支持带区域前缀的推理配置文件、完整 ARN、旧的 `claude-3-5-sonnet` 写法和 Vertex 的 `@日期` 后缀；
+/// 不是 Claude 模型 ID 时返回 None。
+pub fn prettify_model_id(id: &str) -> Option<String> {
+    let id = id.rsplit('/').next()?;
+    let id = id.split([':', '@']).next()?;
+    let rest = &id[id.find("claude-")? + "claude-".len()..];
+
+    let mut family = None;
+    let mut version = Vec::new();
+    for part in rest.split('-') {
+        match part {
+            "opus" | "sonnet" | "haiku" => family = Some(part),
+            // 跳过日期（20250929）和 v1 之类的修订号
+            _ if part.len() <= 2 && !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()) => {
+                version.push(part)
+            }
+            _ => {}
+        }
+    }
+
+    let family = family?;
+    let name = format!("{}{}", family[..1].to_uppercase(), &family[1..]);
+    if version.is_empty() {
+        Some(name)
+    } else {
+        Some(format!("{} {}", name, version.join(".")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 只提供环境变量的探测器
+    struct Vars(&'static [(&'static str, &'static str)]);
+
+    impl Probe for Vars {
+        fn var(&self, key: &str) -> Option<String> {
+            self.0.iter().find(|(name, _)| *name == key).map(|(_, value)| value.to_string())
+        }
+
+        fn exists(&self, _: &str) -> bool {
+            false
+        }
+
+        fn read(&self, _: &str) -> Option<String> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect(&Vars(&[])), None);
+        assert_eq!(detect(&Vars(&[("CLAUDE_CODE_USE_BEDROCK", "1")])), Some(Platform::Bedrock));
+        assert_eq!(detect(&Vars(&[("CLAUDE_CODE_USE_VERTEX", "true")])), Some(Platform::Vertex));
+        assert_eq!(detect(&Vars(&[("CLAUDE_CODE_USE_BEDROCK", "0"), ("CLAUDE_CODE_USE_VERTEX", "")])), None);
+        let both = Vars(&[("CLAUDE_CODE_USE_BEDROCK", "YES"), ("CLAUDE_CODE_USE_VERTEX", "1")]);
+        assert_eq!(detect(&both), Some(Platform::Bedrock));
+    }
+
+    #[test]
+    fn test_segment() {
+        assert_eq!(Platform::Bedrock.segment().plain_text(), "aws");
+        assert_eq!(Platform::Vertex.segment().plain_text(), "gcp");
+    }
+
+    #[test]
+    fn test_prettify_model_id() {
+        let cases = [
+            ("anthropic.claude-sonnet-4-5-20250929-v1:0", "Sonnet 4.5"),
+            ("us.anthropic.claude-opus-4-1-20250805-v1:0", "Opus 4.1"),
+            ("anthropic.claude-3-5-sonnet-20241022-v2:0", "Sonnet 3.5"),
+            ("anthropic.claude-3-haiku-20240307-v1:0", "Haiku 3"),
+            ("anthropic.claude-opus-4-20250514-v1:0", "Opus 4"),
+            (
+                "arn:aws:bedrock:us-east-1:123456789012:inference-profile/us.anthropic.claude-sonnet-4-20250514-v1:0",
+                "Sonnet 4",
+            ),
+            ("claude-sonnet-4-5@20250929", "Sonnet 4.5"),
+            ("claude-3-7-sonnet@20250219", "Sonnet 3.7"),
+        ];
+        for (id, expected) in cases {
+            assert_eq!(prettify_model_id(id).as_deref(), Some(expected), "{}", id);
+        }
+        assert_eq!(prettify_model_id("Sonnet 4.5"), None);
+        assert_eq!(prettify_model_id("anthropic.claude-instant-v1"), None);
+        assert_eq!(prettify_model_id("meta.llama3-70b-instruct-v1:0"), None);
+    }
+}
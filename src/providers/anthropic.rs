@@ -0,0 +1,185 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use super::{AuthScheme, Provider, ProviderContext};
+use crate::error;
+use crate::i18n::Labels;
+use crate::paths;
+use crate::render::Segment;
+
+/// 限流信息文件超过该时长视为过期
+const RATELIMIT_MAX_AGE_SECS: i64 = 120;
+
+/// 外部 hook 写入的 Anthropic 限流信息（`~/.claude/.ratelimit_cache.json`）
+///
+/// 字段对应 `anthropic-ratelimit-*` 响应头，所有字段都是可选的；
+/// `updated_at` 缺失时以文件修改时间判断是否过期。
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct RateLimitCache {
+    pub requests_limit: Option<u64>,
+    pub requests_remaining: Option<u64>,
+    pub tokens_limit: Option<u64>,
+    pub tokens_remaining: Option<u64>,
+    /// 限额重置时间（RFC3339）
+    pub reset_at: Option<DateTime<Utc>>,
+    /// 写入时间（RFC3339）
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+pub struct AnthropicProvider;
+
+impl AnthropicProvider {
+    fn cache_path(&self) -> PathBuf {
+        paths::ratelimit_path()
+    }
+
+    fn read_cache(&self, now: DateTime<Utc>) -> Option<RateLimitCache> {
+        let cache_path = self.cache_path();
+        let cache: RateLimitCache = error::read_json(&cache_path).map_err(|err| error::report(&err)).ok()?;
+        let modified = fs::metadata(&cache_path).ok()?.modified().ok()?;
+        self.is_fresh(&cache, modified.into(), now).then_some(cache)
+    }
+
+    /// 写入时间距今不超过 2 分钟时才使用
+    fn is_fresh(&self, cache: &RateLimitCache, modified: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        let updated_at = cache.updated_at.unwrap_or(modified);
+        now.signed_duration_since(updated_at).num_seconds() <= RATELIMIT_MAX_AGE_SECS
+    }
+
+    pub(crate) fn render_segments(
+        &self,
+        cache: &RateLimitCache,
+        now: DateTime<Utc>,
+        ctx: &ProviderContext,
+    ) -> Vec<Segment> {
+        let labels = ctx.labels;
+        let remaining_pct = |remaining: Option<u64>, limit: Option<u64>| -> Option<f64> {
+            let limit = limit.filter(|&limit| limit > 0)?;
+            Some(remaining? as f64 / limit as f64 * 100.0)
+        };
+        let requests_pct = remaining_pct(cache.requests_remaining, cache.requests_limit);
+        let tokens_pct = remaining_pct(cache.tokens_remaining, cache.tokens_limit);
+
+        let mut parts = Vec::new();
+        if let Some(pct) = requests_pct {
+            parts.push(format!("{}:{:.0}%", labels.ratelimit_requests, pct));
+        }
+        if let Some(pct) = tokens_pct {
+            parts.push(format!("{}:{:.0}%", labels.ratelimit_tokens, pct));
+        }
+        if parts.is_empty() {
+            return Vec::new();
+        }
+        if let Some(reset_at) = cache.reset_at {
+            let until_reset = reset_at.signed_duration_since(now).num_milliseconds();
+            if until_reset > 0 {
+                parts.push(format!("↻{}", crate::format_duration(until_reset as u64)));
+            }
+        }
+
+        // 按剩余比例较低的一项着色
+        let lowest = requests_pct.into_iter().chain(tokens_pct).fold(f64::MAX, f64::min);
+        let severity = ctx.quota_severity(100.0 - lowest);
+        vec![Segment::graded("anthropic_ratelimit", severity, parts.join(" "))]
+    }
+}
+
+impl Provider for AnthropicProvider {
+    fn name(&self) -> &'static str {
+        "anthropic"
+    }
+
+    fn label(&self, labels: &'static Labels) -> &'static str {
+        labels.ratelimit_tag
+    }
+
+    fn matches(&self, base_url: &str) -> bool {
+        base_url.contains("api.anthropic.com")
+    }
+
+    fn auth_scheme(&self) -> AuthScheme {
+        AuthScheme::XApiKey
+    }
+
+    /// 限流信息由外部 hook 写入，不需要请求网络
+    fn fetches(&self) -> bool {
+        false
+    }
+
+    fn cached_segments(&self, _auth_token: &str, ctx: &ProviderContext) -> Option<Vec<Segment>> {
+        let now = Utc::now();
+        match self.read_cache(now) {
+            Some(cache) => Some(self.render_segments(&cache, now, ctx)),
+            None => Some(Vec::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colors;
+    use crate::config::StatuslineConfig;
+    use crate::providers::en_ctx;
+
+    fn time(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_ratelimit_render() {
+        let cache: RateLimitCache = serde_json::from_str(
+            r#"{
+                "requests_limit": 1000, "requests_remaining": 450,
+                "tokens_limit": 400000, "tokens_remaining": 312000,
+                "reset_at": "2026-05-01T10:03:00Z"
+            }"#,
+        )
+        .unwrap();
+        let config = StatuslineConfig::default();
+        let segments = AnthropicProvider.render_segments(&cache, time("2026-05-01T10:00:00Z"), &en_ctx(&config));
+        assert_eq!(segments[0].plain_text(), "req:45% tok:78% ↻3m");
+        assert_eq!(segments[0].color(), colors::GREEN);
+    }
+
+    #[test]
+    fn test_ratelimit_partial_fields() {
+        let now = time("2026-05-01T10:00:00Z");
+        let cache = RateLimitCache {
+            tokens_limit: Some(100),
+            tokens_remaining: Some(10),
+            // 已过的重置时间不显示
+            reset_at: Some(time("2026-05-01T09:59:00Z")),
+            ..Default::default()
+        };
+        let config = StatuslineConfig::default();
+        let segments = AnthropicProvider.render_segments(&cache, now, &en_ctx(&config));
+        assert_eq!(segments[0].plain_text(), "tok:10%");
+        assert_eq!(segments[0].color(), colors::RED);
+
+        // 只有剩余量没有上限时无法计算比例
+        let cache = RateLimitCache {
+            requests_remaining: Some(10),
+            ..Default::default()
+        };
+        assert!(AnthropicProvider.render_segments(&cache, now, &en_ctx(&config)).is_empty());
+    }
+
+    #[test]
+    fn test_ratelimit_staleness() {
+        let now = time("2026-05-01T10:00:00Z");
+        let modified = time("2026-05-01T09:59:30Z");
+        let fresh = RateLimitCache::default();
+        assert!(AnthropicProvider.is_fresh(&fresh, modified, now));
+        assert!(!AnthropicProvider.is_fresh(&fresh, time("2026-05-01T09:57:00Z"), now));
+
+        // updated_at 优先于文件修改时间
+        let stale = RateLimitCache {
+            updated_at: Some(time("2026-05-01T09:55:00Z")),
+            ..Default::default()
+        };
+        assert!(!AnthropicProvider.is_fresh(&stale, modified, now));
+    }
+}
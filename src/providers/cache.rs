@@ -0,0 +1,136 @@
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::error::{self, StatuslineError};
+use crate::paths;
+
+/// 缓存目录中按 token 区分的缓存文件，如 `.zhipu_cache.json` 变为 `.zhipu_cache.<token 哈希>.json`
+///
+/// 不同项目使用不同的 token 时各自的缓存互不覆盖，文件名中只有哈希，不含 token 本身。
+pub fn cache_path(file_name: &str, auth_token: &str) -> PathBuf {
+    let file_name = match file_name.strip_suffix(".json") {
+        Some(stem) if !auth_token.is_empty() => {
+            format!("{}.{:016x}.json", stem, paths::fnv1a(auth_token))
+        }
+        _ => file_name.to_string(),
+    };
+    paths::cache_dir().join(file_name)
+}
+
+/// 读取缓存目录中 `auth_token` 对应的 JSON 文件
+pub fn read_cache<T: DeserializeOwned>(file_name: &str, auth_token: &str) -> Option<T> {
+    read_json(&cache_path(file_name, auth_token))
+}
+
+/// 写入缓存目录中 `auth_token` 对应的 JSON 文件（失败时只在调试模式下提示）
+#[cfg(any(
+    feature = "provider-zhipu",
+    feature = "provider-yunyi",
+    feature = "provider-packycode",
+    feature = "provider-openai"
+))]
+pub fn write_cache<T: Serialize>(file_name: &str, auth_token: &str, value: &T) {
+    write_json(&cache_path(file_name, auth_token), value);
+}
+
+/// 读取指定路径的 JSON 文件（文件不存在以外的失败只在调试模式下提示）
+pub fn read_json<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    error::read_json(path).map_err(|err| error::report(&err)).ok()
+}
+
+/// 写入指定路径的 JSON 文件（失败时只在调试模式下提示，并在本次运行中不再写入状态文件）
+#[cfg(any(
+    feature = "provider-zhipu",
+    feature = "provider-yunyi",
+    feature = "provider-packycode",
+    feature = "provider-openai"
+))]
+pub fn write_json<T: Serialize>(path: &Path, value: &T) {
+    write_json_with(path, value, |path, json| fs::write(path, json));
+}
+
+/// 写入含有 token 的 JSON 文件，Unix 下权限为 0600，其他用户不可读
+#[cfg(feature = "provider-gac")]
+pub fn write_private_json<T: Serialize>(path: &Path, value: &T) {
+    write_json_with(path, value, write_private);
+}
+
+fn write_json_with<T: Serialize>(path: &Path, value: &T, write: impl FnOnce(&Path, String) -> io::Result<()>) {
+    if !paths::persistence_enabled() {
+        return;
+    }
+    match serde_json::to_string(value) {
+        Ok(json) => {
+            if let Err(err) = write(path, json) {
+                paths::write_failed(StatuslineError::write(path, err));
+            }
+        }
+        Err(err) => error::report(&StatuslineError::json(path, err)),
+    }
+}
+
+/// 以 0600 权限写入文件；创建时的权限只对新文件生效，旧版本写入的文件也改为 0600
+#[cfg(feature = "provider-gac")]
+fn write_private(path: &Path, content: String) -> io::Result<()> {
+    use std::io::Write;
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        let file = options.open(path)?;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        (&file).write_all(content.as_bytes())
+    }
+    #[cfg(not(unix))]
+    options.open(path)?.write_all(content.as_bytes())
+}
+
+/// 缓存时间距今不足 `max_age` 时视为有效
+pub fn is_fresh(timestamp: DateTime<Utc>, max_age: chrono::Duration) -> bool {
+    Utc::now().signed_duration_since(timestamp) < max_age
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_path_per_token() {
+        let file_name = |auth_token| {
+            let path = cache_path(".zhipu_cache.json", auth_token);
+            path.file_name().unwrap().to_string_lossy().into_owned()
+        };
+        assert_eq!(file_name(""), ".zhipu_cache.json");
+
+        let first = file_name("sk-first");
+        assert!(first.starts_with(".zhipu_cache.") && first.ends_with(".json"));
+        assert!(!first.contains("sk-first"));
+        assert_eq!(first, file_name("sk-first"));
+        assert_ne!(first, file_name("sk-second"));
+    }
+
+    #[cfg(all(unix, feature = "provider-gac"))]
+    #[test]
+    fn test_write_private_json() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("cc-statusline-private-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(".gac_cache.json");
+        // 旧版本按默认权限写入的文件也收紧为 0600
+        fs::write(&path, "{}").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_private_json(&path, &serde_json::json!({"access_token": "secret"}));
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert_eq!(fs::read_to_string(&path).unwrap(), r#"{"access_token":"secret"}"#);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
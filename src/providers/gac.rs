@@ -0,0 +1,312 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use super::cache::{cache_path, is_fresh, read_cache, read_json, write_private_json};
+use super::{http, AuthScheme, Fetch, Provider, ProviderContext, ProviderError};
+use crate::colors;
+use crate::i18n::Labels;
+use crate::render::Segment;
+
+pub(crate) const CACHE_FILE: &str = ".gac_cache.json";
+
+/// 未配置 `providers.gac.base_url` 时使用的站点地址
+const DEFAULT_API_BASE: &str = "https://gaccode.com";
+
+/// GAC 缓存：额度与刷新后得到的 access token
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct GacCache {
+    /// 刷新得到的 access token，优先于 authToken 使用
+    pub access_token: Option<String>,
+    pub balance: Option<f64>,
+    pub credit_cap: Option<f64>,
+    /// 额度的获取时间
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+/// 额度查询结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct GacBalance {
+    pub balance: f64,
+    pub credit_cap: Option<f64>,
+}
+
+pub struct GacProvider;
+
+/// 401 视为 token 失效
+fn unauthorized(error: ProviderError) -> ProviderError {
+    match error {
+        ProviderError::Status { status: 401 } => ProviderError::Unauthorized,
+        error => error,
+    }
+}
+
+impl GacProvider {
+    async fn fetch_balance(&self, api_base: &str, token: &str) -> Result<GacBalance, ProviderError> {
+        let url = format!("{}/api/credits/balance", api_base);
+        let request = http::get(&url, self.auth_scheme(), token)?.header("Accept", "application/json");
+        let body = http::parse_body(&http::send(request).await.map_err(unauthorized)?)?;
+        Ok(GacBalance {
+            balance: http::field(&body, "/balance")?,
+            credit_cap: http::field(&body, "/creditCap").ok(),
+        })
+    }
+
+    /// 用 refresh token 换取新的 access token
+    async fn refresh_access_token(&self, api_base: &str, refresh_token: &str) -> Result<String, ProviderError> {
+        #[derive(Deserialize)]
+        struct ApiResponse {
+            #[serde(alias = "accessToken")]
+            access_token: String,
+        }
+
+        let url = format!("{}/auth/refresh", api_base);
+        let body = serde_json::json!({ "refresh_token": refresh_token });
+        let response = http::send(http::post_json(&url, &body)?).await?;
+        let api_response: ApiResponse = http::field(&http::parse_body(&response)?, "")?;
+        Ok(api_response.access_token)
+    }
+
+    /// 缓存中 1 分钟内的额度
+    pub(crate) fn cached_balance(&self, cache_path: &Path) -> Option<GacBalance> {
+        let cache: GacCache = read_json(cache_path)?;
+        let balance = cache.balance?;
+        is_fresh(cache.timestamp?, chrono::Duration::minutes(1)).then_some(GacBalance {
+            balance,
+            credit_cap: cache.credit_cap,
+        })
+    }
+
+    /// 查询额度；返回 401 时刷新一次 token 并重试，新 token 写入缓存文件。
+    /// 没有 refresh token 或刷新失败时返回 [`ProviderError::Unauthorized`]
+    pub(crate) async fn fetch_balance_with_refresh(
+        &self,
+        api_base: &str,
+        auth_token: &str,
+        refresh_token: Option<&str>,
+        cache_path: &Path,
+    ) -> Result<GacBalance, ProviderError> {
+        let mut cache: GacCache = read_json(cache_path).unwrap_or_default();
+        let token = cache.access_token.clone().unwrap_or_else(|| auth_token.to_string());
+        let balance = match self.fetch_balance(api_base, &token).await {
+            Err(ProviderError::Unauthorized) => {
+                let refresh_token = refresh_token.ok_or(ProviderError::Unauthorized)?;
+                let new_token = self
+                    .refresh_access_token(api_base, refresh_token)
+                    .await
+                    .map_err(|_| ProviderError::Unauthorized)?;
+                cache.access_token = Some(new_token.clone());
+                write_private_json(cache_path, &cache);
+                self.fetch_balance(api_base, &new_token).await?
+            }
+            result => result?,
+        };
+
+        cache.balance = Some(balance.balance);
+        cache.credit_cap = balance.credit_cap;
+        cache.timestamp = Some(Utc::now());
+        write_private_json(cache_path, &cache);
+        Ok(balance)
+    }
+
+    pub(crate) fn render_segments(&self, balance: &GacBalance, ctx: &ProviderContext) -> Vec<Segment> {
+        let (severity, text) = match balance.credit_cap.filter(|&cap| cap > 0.0) {
+            Some(cap) => {
+                let remaining_pct = balance.balance / cap * 100.0;
+                // 默认剩余 40% 及以下为警告，20% 及以下为严重
+                let severity = ctx.quota_severity(100.0 - remaining_pct);
+                (severity, format!("{:.0}/{:.0}", balance.balance, cap))
+            }
+            None => (colors::Severity::Ok, format!("{:.0}", balance.balance)),
+        };
+        vec![Segment::graded("gac_quota", severity, text)]
+    }
+}
+
+impl Provider for GacProvider {
+    fn name(&self) -> &'static str {
+        "gac"
+    }
+
+    fn label(&self, labels: &'static Labels) -> &'static str {
+        labels.gac_tag
+    }
+
+    fn matches(&self, base_url: &str) -> bool {
+        base_url.contains("gaccode.com")
+    }
+
+    fn auth_scheme(&self) -> AuthScheme {
+        AuthScheme::Bearer
+    }
+
+    fn cache_updated_at(&self, auth_token: &str) -> Option<DateTime<Utc>> {
+        read_cache::<GacCache>(CACHE_FILE, auth_token)?.timestamp
+    }
+
+    fn cached_segments(&self, auth_token: &str, ctx: &ProviderContext) -> Option<Vec<Segment>> {
+        let balance = self.cached_balance(&cache_path(CACHE_FILE, auth_token))?;
+        Some(self.render_segments(&balance, ctx))
+    }
+
+    /// token 失效且无法刷新时提示需要重新登录
+    fn failure_segments(&self, error: &ProviderError, ctx: &ProviderContext) -> Vec<Segment> {
+        match error {
+            ProviderError::Unauthorized => vec![Segment::new("gac_auth", colors::DIM, ctx.labels.gac_auth_failed)],
+            _ => Vec::new(),
+        }
+    }
+
+    fn fetch_segments<'a>(
+        &'a self,
+        _base_url: &'a str,
+        auth_token: &'a str,
+        ctx: &'a ProviderContext,
+    ) -> Fetch<'a> {
+        Box::pin(async move {
+            let options = ctx.options(self.name());
+            let api_base = options
+                .and_then(|options| options.base_url.as_deref())
+                .unwrap_or(DEFAULT_API_BASE)
+                .trim_end_matches('/');
+            let refresh_token = options.and_then(|options| options.refresh_token.as_deref());
+            let cache_file = cache_path(CACHE_FILE, auth_token);
+            let balance = self.fetch_balance_with_refresh(api_base, auth_token, refresh_token, &cache_file).await?;
+            Ok(self.render_segments(&balance, ctx))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::StatuslineConfig;
+    use crate::providers::en_ctx;
+    use crate::providers::http::mock_server;
+    use std::path::PathBuf;
+
+    fn fetch(
+        api_base: &str,
+        auth_token: &str,
+        refresh_token: Option<&str>,
+        cache_path: &Path,
+    ) -> Result<GacBalance, ProviderError> {
+        let future = GacProvider.fetch_balance_with_refresh(api_base, auth_token, refresh_token, cache_path);
+        http::fetch_all(vec![future]).pop().flatten().unwrap()
+    }
+
+    fn temp_cache(name: &str) -> PathBuf {
+        let file_name = format!("cc-statusline-gac-{}-{}.json", name, std::process::id());
+        let path = std::env::temp_dir().join(file_name);
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_refresh_on_unauthorized() {
+        let (address, server) = mock_server(vec![
+            (401, r#"{"error":"token expired"}"#),
+            (200, r#"{"access_token":"fresh"}"#),
+            (200, r#"{"balance":1234,"creditCap":5000}"#),
+        ]);
+        let cache_path = temp_cache("refresh");
+
+        let balance = fetch(&address, "stale", Some("refresh-me"), &cache_path).unwrap();
+        assert_eq!(
+            balance,
+            GacBalance {
+                balance: 1234.0,
+                credit_cap: Some(5000.0)
+            }
+        );
+        assert_eq!(
+            server.join().unwrap(),
+            vec![
+                "GET /api/credits/balance HTTP/1.1 Bearer stale",
+                "POST /auth/refresh HTTP/1.1",
+                "GET /api/credits/balance HTTP/1.1 Bearer fresh",
+            ]
+        );
+
+        let cache: GacCache = read_json(&cache_path).unwrap();
+        assert_eq!(cache.access_token.as_deref(), Some("fresh"));
+        assert_eq!(cache.balance, Some(1234.0));
+
+        let segments = GacProvider.render_segments(&balance, &en_ctx(&StatuslineConfig::default()));
+        assert_eq!(segments[0].plain_text(), "1234/5000");
+        assert_eq!(segments[0].color(), colors::YELLOW);
+        std::fs::remove_file(&cache_path).unwrap();
+    }
+
+    #[test]
+    fn test_refresh_failure_shows_auth_marker() {
+        let (address, server) = mock_server(vec![
+            (401, r#"{"error":"token expired"}"#),
+            (401, r#"{"error":"refresh token revoked"}"#),
+        ]);
+        let cache_path = temp_cache("failure");
+
+        let error = fetch(&address, "stale", Some("revoked"), &cache_path).unwrap_err();
+        assert_eq!(error, ProviderError::Unauthorized);
+        assert_eq!(server.join().unwrap().len(), 2);
+
+        let config = StatuslineConfig::default();
+        let segments = GacProvider.failure_segments(&error, &en_ctx(&config));
+        assert_eq!(segments[0].plain_text(), "auth✗");
+        assert_eq!(segments[0].color(), colors::DIM);
+        let _ = std::fs::remove_file(&cache_path);
+    }
+
+    #[test]
+    fn test_failures_without_auth_marker() {
+        let config = StatuslineConfig::default();
+        let cache_path = temp_cache("unavailable");
+
+        let (address, server) = mock_server(vec![(401, r#"{"error":"token expired"}"#)]);
+        let error = fetch(&address, "stale", None, &cache_path).unwrap_err();
+        assert_eq!(error, ProviderError::Unauthorized);
+        assert_eq!(server.join().unwrap().len(), 1);
+
+        let (address, server) = mock_server(vec![(502, "bad gateway")]);
+        let error = fetch(&address, "token", None, &cache_path).unwrap_err();
+        assert_eq!(error, ProviderError::Status { status: 502 });
+        assert!(GacProvider.failure_segments(&error, &en_ctx(&config)).is_empty());
+        server.join().unwrap();
+
+        let (address, server) = mock_server(vec![(200, r#"{"credits":12}"#)]);
+        let error = fetch(&address, "token", None, &cache_path).unwrap_err();
+        assert_eq!(error, ProviderError::parse("/balance", "missing"));
+        server.join().unwrap();
+        let _ = std::fs::remove_file(&cache_path);
+    }
+
+    #[test]
+    fn test_fresh_cache_skips_network() {
+        let cache_path = temp_cache("fresh");
+        let cache = GacCache {
+            balance: Some(42.0),
+            timestamp: Some(Utc::now()),
+            ..Default::default()
+        };
+        write_private_json(&cache_path, &cache);
+
+        // 命中缓存时不请求网络，也不创建运行时
+        assert_eq!(
+            GacProvider.cached_balance(&cache_path),
+            Some(GacBalance {
+                balance: 42.0,
+                credit_cap: None
+            })
+        );
+        assert_eq!(http::runtimes_created(), 0);
+
+        let stale = GacCache {
+            timestamp: Some(Utc::now() - chrono::Duration::minutes(5)),
+            ..cache
+        };
+        write_private_json(&cache_path, &stale);
+        assert_eq!(GacProvider.cached_balance(&cache_path), None);
+        std::fs::remove_file(&cache_path).unwrap();
+    }
+}
@@ -0,0 +1,359 @@
+use reqwest::{Client, RequestBuilder};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::cell::Cell;
+use std::future::{poll_fn, Future};
+use std::pin::Pin;
+use std::task::Poll;
+use std::time::Duration;
+
+use super::{AuthScheme, ProviderError};
+
+/// 整个请求阶段的超时，所有数据源共用，避免拖慢 statusline 渲染
+const FETCH_DEADLINE: Duration = Duration::from_secs(3);
+
+tokio::task_local! {
+    /// 当前请求过程中最后收到的 HTTP 状态码，由 [`with_status`] 提供
+    static LAST_STATUS: Cell<Option<u16>>;
+}
+
+#[cfg(test)]
+thread_local! {
+    /// 当前线程创建运行时的次数，测试用于确认命中缓存时没有创建运行时
+    static RUNTIMES_CREATED: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+impl AuthScheme {
+    /// 认证头的名称和值
+    pub fn header(self, auth_token: &str) -> (&'static str, String) {
+        match self {
+            AuthScheme::Raw => ("Authorization", auth_token.to_string()),
+            AuthScheme::Bearer if auth_token.to_ascii_lowercase().starts_with("bearer ") => {
+                ("Authorization", auth_token.to_string())
+            }
+            AuthScheme::Bearer => ("Authorization", format!("Bearer {}", auth_token)),
+            AuthScheme::XApiKey => ("x-api-key", auth_token.to_string()),
+        }
+    }
+
+    /// 给请求加上认证头
+    pub fn apply(self, request: RequestBuilder, auth_token: &str) -> RequestBuilder {
+        let (name, value) = self.header(auth_token);
+        request.header(name, value)
+    }
+}
+
+fn request_error(err: reqwest::Error) -> ProviderError {
+    ProviderError::Request {
+        message: err.to_string(),
+    }
+}
+
+/// 构造带认证头的 GET 请求
+pub fn get(url: &str, scheme: AuthScheme, auth_token: &str) -> Result<RequestBuilder, ProviderError> {
+    let client = Client::builder().build().map_err(request_error)?;
+    Ok(scheme.apply(client.get(url), auth_token))
+}
+
+/// 构造 JSON 请求体的 POST 请求（不带认证头）
+#[cfg(feature = "provider-gac")]
+pub fn post_json<T: serde::Serialize>(url: &str, body: &T) -> Result<RequestBuilder, ProviderError> {
+    let client = Client::builder().build().map_err(request_error)?;
+    Ok(client.post(url).json(body))
+}
+
+/// 发送请求并读取响应体，非 2xx 状态码视为失败
+pub async fn send(request: RequestBuilder) -> Result<String, ProviderError> {
+    let response = request.send().await.map_err(request_error)?;
+    let status = response.status();
+    let _ = LAST_STATUS.try_with(|last| last.set(Some(status.as_u16())));
+    if !status.is_success() {
+        return Err(ProviderError::Status {
+            status: status.as_u16(),
+        });
+    }
+    response.text().await.map_err(request_error)
+}
+
+/// 执行一个数据源的请求过程，同时返回其中最后收到的 HTTP 状态码（没有收到响应时为 None）
+pub async fn with_status<F: Future>(future: F) -> (F::Output, Option<u16>) {
+    LAST_STATUS
+        .scope(Cell::new(None), async move {
+            let output = future.await;
+            (output, LAST_STATUS.with(Cell::get))
+        })
+        .await
+}
+
+/// 把响应体解析为 JSON
+pub fn parse_body(body: &str) -> Result<Value, ProviderError> {
+    serde_json::from_str(body).map_err(|err| ProviderError::parse("", err))
+}
+
+/// 取出 `pointer`（JSON Pointer，如 `/data/limits`）处的值并反序列化，出错时报告该路径
+pub fn field<T: DeserializeOwned>(value: &Value, pointer: &str) -> Result<T, ProviderError> {
+    let field = value.pointer(pointer).ok_or_else(|| ProviderError::parse(pointer, "missing"))?;
+    T::deserialize(field).map_err(|err| ProviderError::parse(pointer, err))
+}
+
+/// 逐项反序列化 `pointer` 处的数组，出错时路径精确到下标
+#[cfg(feature = "provider-zhipu")]
+pub fn items<T: DeserializeOwned>(value: &Value, pointer: &str) -> Result<Vec<T>, ProviderError> {
+    let items: Vec<Value> = field(value, pointer)?;
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| T::deserialize(item).map_err(|err| ProviderError::parse(&format!("{}/{}", pointer, i), err)))
+        .collect()
+}
+
+/// 在一个临时的单线程运行时中并发执行所有请求，共用 [`FETCH_DEADLINE`]
+///
+/// 超时时已完成的结果照常返回，未完成的为 None。只在确实需要请求网络时调用，
+/// 命中缓存的渲染不会创建运行时。
+pub fn fetch_all<F: Future>(futures: Vec<F>) -> Vec<Option<F::Output>> {
+    fetch_all_within(futures, FETCH_DEADLINE)
+}
+
+fn fetch_all_within<F: Future>(futures: Vec<F>, deadline: Duration) -> Vec<Option<F::Output>> {
+    let mut outputs: Vec<Option<F::Output>> = futures.iter().map(|_| None).collect();
+    if futures.is_empty() {
+        return outputs;
+    }
+    let Ok(runtime) = tokio::runtime::Builder::new_current_thread().enable_all().build() else {
+        return outputs;
+    };
+    #[cfg(test)]
+    RUNTIMES_CREATED.with(|count| count.set(count.get() + 1));
+
+    let mut futures: Vec<Pin<Box<F>>> = futures.into_iter().map(Box::pin).collect();
+    let join = poll_fn(|cx| {
+        let mut pending = false;
+        for (future, output) in futures.iter_mut().zip(outputs.iter_mut()) {
+            if output.is_none() {
+                match future.as_mut().poll(cx) {
+                    Poll::Ready(value) => *output = Some(value),
+                    Poll::Pending => pending = true,
+                }
+            }
+        }
+        if pending {
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    });
+    let _ = runtime.block_on(async { tokio::time::timeout(deadline, join).await });
+    outputs
+}
+
+/// 当前线程创建过的运行时数量
+#[cfg(test)]
+pub fn runtimes_created() -> usize {
+    RUNTIMES_CREATED.with(|count| count.get())
+}
+
+/// 从 baseURL 中取出 `scheme://host[:port]`，去掉路径
+///
+/// 明文 `http://` 会升级为 `https://`，避免 token 以明文发送；`plain_http_hosts`
+/// 中的主机（如内网自建网关）保持 http。非默认端口原样保留。
+#[cfg(any(feature = "provider-zhipu", feature = "provider-packycode", feature = "provider-openai"))]
+pub fn base_domain(base_url: &str, plain_http_hosts: &[String]) -> Option<String> {
+    let parsed_url = base_url.parse::<reqwest::Url>().ok()?;
+    let host = parsed_url.host_str()?;
+    let scheme = match parsed_url.scheme() {
+        "http" if !plain_http_hosts.iter().any(|allowed| allowed.eq_ignore_ascii_case(host)) => {
+            "https"
+        }
+        scheme => scheme,
+    };
+    Some(match parsed_url.port() {
+        Some(port) => format!("{}://{}:{}", scheme, host, port),
+        None => format!("{}://{}", scheme, host),
+    })
+}
+
+/// 按顺序返回预设响应的本地 HTTP 服务，供各数据源的测试使用
+///
+/// 线程结束时返回收到的请求，每个请求为请求行加上 Authorization，如 `GET /api/usage HTTP/1.1 Bearer sk-test`。
+#[cfg(all(test, any(feature = "provider-gac", feature = "provider-openai")))]
+pub(crate) fn mock_server(responses: Vec<(u16, &'static str)>) -> (String, std::thread::JoinHandle<Vec<String>>) {
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = format!("http://{}", listener.local_addr().unwrap());
+    let handle = std::thread::spawn(move || {
+        let mut requests = Vec::new();
+        for (status, body) in responses {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut authorization = String::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                let (name, value) = line.split_once(':').unwrap();
+                match name.to_ascii_lowercase().as_str() {
+                    "authorization" => authorization = value.trim().to_string(),
+                    "content-length" => content_length = value.trim().parse().unwrap(),
+                    _ => {}
+                }
+            }
+            let mut request_body = vec![0; content_length];
+            reader.read_exact(&mut request_body).unwrap();
+            let request = format!("{} {}", request_line.trim_end(), authorization);
+            requests.push(request.trim_end().to_string());
+
+            let response = format!(
+                "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+                 Connection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+        }
+        requests
+    });
+    (address, handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auth_scheme_header() {
+        assert_eq!(AuthScheme::Raw.header("abc"), ("Authorization", "abc".to_string()));
+        assert_eq!(AuthScheme::Bearer.header("abc"), ("Authorization", "Bearer abc".to_string()));
+        assert_eq!(AuthScheme::Bearer.header("bearer abc"), ("Authorization", "bearer abc".to_string()));
+        assert_eq!(AuthScheme::XApiKey.header("abc"), ("x-api-key", "abc".to_string()));
+    }
+
+    /// 每个连接等待 `delay` 后返回 200 的本地 HTTP 服务
+    fn slow_server(connections: usize, delay: Duration) -> String {
+        use std::io::{BufRead, BufReader, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for _ in 0..connections {
+                let (stream, _) = listener.accept().unwrap();
+                std::thread::spawn(move || {
+                    let mut reader = BufReader::new(stream);
+                    let mut line = String::new();
+                    while reader.read_line(&mut line).unwrap() > 2 {
+                        line.clear();
+                    }
+                    std::thread::sleep(delay);
+                    let response = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok";
+                    let _ = reader.get_mut().write_all(response.as_bytes());
+                });
+            }
+        });
+        address
+    }
+
+    async fn fetch_text(url: String) -> Option<String> {
+        send(get(&url, AuthScheme::Bearer, "token").ok()?).await.ok()
+    }
+
+    #[test]
+    fn test_fetches_run_concurrently() {
+        let delay = Duration::from_millis(400);
+        let address = slow_server(3, delay);
+        let started = std::time::Instant::now();
+        let outputs = fetch_all((0..3).map(|_| fetch_text(address.clone())).collect());
+        assert_eq!(outputs, vec![Some(Some("ok".to_string())); 3]);
+        // 依次请求至少需要 3 倍的延迟
+        assert!(started.elapsed() < delay * 2, "{:?}", started.elapsed());
+        assert_eq!(runtimes_created(), 1);
+    }
+
+    #[test]
+    fn test_shared_deadline() {
+        let address = slow_server(2, Duration::from_secs(2));
+        let fast = Box::pin(async { Some("cached".to_string()) }) as Pin<Box<dyn Future<Output = _>>>;
+        let slow = Box::pin(fetch_text(address.clone()));
+        let started = std::time::Instant::now();
+        let outputs = fetch_all_within(vec![fast, slow], Duration::from_millis(200));
+        assert_eq!(outputs, vec![Some(Some("cached".to_string())), None]);
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_with_status_reports_last_response() {
+        let address = slow_server(1, Duration::ZERO);
+        let fetched = with_status(fetch_text(address));
+        let unsent = with_status(async { None::<String> });
+        let outputs = fetch_all(vec![Box::pin(fetched) as Pin<Box<dyn Future<Output = _>>>, Box::pin(unsent)]);
+        assert_eq!(outputs, [Some((Some("ok".to_string()), Some(200))), Some((None, None))]);
+    }
+
+    #[test]
+    fn test_no_runtime_without_fetches() {
+        let outputs = fetch_all(Vec::<std::future::Ready<()>>::new());
+        assert!(outputs.is_empty());
+        assert_eq!(runtimes_created(), 0);
+    }
+
+    #[cfg(feature = "provider-zhipu")]
+    #[test]
+    fn test_parse_error_paths() {
+        #[derive(Debug, serde::Deserialize)]
+        struct Limit {
+            #[allow(dead_code)]
+            percentage: f64,
+        }
+
+        let body = parse_body(r#"{"data":{"limits":[{"percentage":1},{"percentage":"x"}]}}"#).unwrap();
+        let error = items::<Limit>(&body, "/data/limits").unwrap_err();
+        assert!(matches!(error, ProviderError::Parse { ref path, .. } if path == "/data/limits/1"), "{:?}", error);
+        assert_eq!(items::<Limit>(&body, "/data/quota").unwrap_err(), ProviderError::parse("/data/quota", "missing"));
+        assert_eq!(field::<f64>(&body, "/data/limits/0/percentage"), Ok(1.0));
+
+        let error = parse_body("<html>").unwrap_err();
+        assert!(matches!(error, ProviderError::Parse { ref path, .. } if path.is_empty()), "{:?}", error);
+    }
+
+    #[cfg(any(feature = "provider-zhipu", feature = "provider-packycode", feature = "provider-openai"))]
+    #[test]
+    fn test_base_domain() {
+        let base = |url: &str| base_domain(url, &[]);
+        let zhipu = Some("https://open.bigmodel.cn");
+        assert_eq!(base("https://open.bigmodel.cn/api/anthropic").as_deref(), zhipu);
+        assert_eq!(base("https://open.bigmodel.cn/api/anthropic/").as_deref(), zhipu);
+        assert_eq!(base("https://open.bigmodel.cn/").as_deref(), zhipu);
+        // 默认端口不重复写出
+        assert_eq!(base("https://open.bigmodel.cn:443/api").as_deref(), zhipu);
+        let gateway = base("https://gateway.lan:8443/api/anthropic");
+        assert_eq!(gateway.as_deref(), Some("https://gateway.lan:8443"));
+        assert_eq!(base("not a url"), None);
+    }
+
+    #[cfg(any(feature = "provider-zhipu", feature = "provider-packycode", feature = "provider-openai"))]
+    #[test]
+    fn test_base_domain_plain_http_hosts() {
+        let allowed = ["gateway.lan".to_string(), "10.0.0.5".to_string()];
+        assert_eq!(
+            base_domain("http://gateway.lan:8080/api/anthropic/", &allowed).as_deref(),
+            Some("http://gateway.lan:8080")
+        );
+        assert_eq!(base_domain("http://10.0.0.5/api", &allowed).as_deref(), Some("http://10.0.0.5"));
+        // 主机名不区分大小写（解析后为小写）
+        let upper = base_domain("http://GATEWAY.lan:8080", &allowed);
+        assert_eq!(upper.as_deref(), Some("http://gateway.lan:8080"));
+        // 不在列表中的主机升级为 https，端口保留
+        assert_eq!(
+            base_domain("http://other.lan:8080/api/anthropic", &allowed).as_deref(),
+            Some("https://other.lan:8080")
+        );
+        assert_eq!(base_domain("http://other.lan/api", &[]).as_deref(), Some("https://other.lan"));
+    }
+}
@@ -0,0 +1,1109 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::config::{ProviderOptions, ProviderPosition, StatuslineConfig};
+use crate::error::StatuslineError;
+use crate::colors::Severity;
+use crate::i18n::Labels;
+use crate::render::Segment;
+
+// 每个数据源由同名 cargo feature 控制（`provider-zhipu` 等），
+// 共享的缓存与 HTTP 辅助函数放在 `cache` / `http` 中
+#[cfg(feature = "provider-anthropic")]
+pub mod anthropic;
+#[cfg(feature = "network")]
+mod cache;
+#[cfg(feature = "network")]
+mod http;
+#[cfg(feature = "provider-gac")]
+pub mod gac;
+#[cfg(feature = "provider-openai")]
+pub mod openai;
+#[cfg(feature = "provider-packycode")]
+pub mod packycode;
+#[cfg(feature = "provider-yunyi")]
+pub mod yunyi;
+#[cfg(feature = "provider-zhipu")]
+pub mod zhipu;
+
+/// 官方 API 地址，未配置 baseURL 时使用
+pub const ANTHROPIC_BASE_URL: &str = "https://api.anthropic.com";
+
+/// 默认的前缀模板，`{label}` 替换为数据源标签
+pub const DEFAULT_PREFIX: &str = "[{label}]";
+
+/// 开启 `heartbeat` 时，缓存超过该时长未更新的数据源段标记为 `~stale`
+const STALE_AFTER_MINUTES: i64 = 15;
+
+/// 开启 `provider_age` 时，使用超过该时长的缓存才显示数据的更新时间
+const AGE_AFTER_SECS: i64 = 60;
+
+/// 数据源渲染时使用的文本与配置
+pub struct ProviderContext<'a> {
+    pub labels: &'static Labels,
+    pub config: &'a StatuslineConfig,
+}
+
+impl ProviderContext<'_> {
+    /// 数据源的标签：用户配置的 `label` 优先，其次是主题的图标
+    pub fn label<'b>(&'b self, provider: &str, default: &'b str) -> &'b str {
+        match self.options(provider).and_then(|options| options.label.as_deref()) {
+            Some(label) => label,
+            None => self.config.theme().icons.provider(provider).unwrap_or(default),
+        }
+    }
+
+    /// 额度使用率（%）在当前主题下的级别
+    #[cfg_attr(
+        not(any(
+            feature = "provider-zhipu",
+            feature = "provider-yunyi",
+            feature = "provider-packycode",
+            feature = "provider-gac",
+            feature = "provider-anthropic"
+        )),
+        allow(dead_code)
+    )]
+    pub fn quota_severity(&self, used_pct: f64) -> Severity {
+        self.config.theme().thresholds.quota.severity(used_pct)
+    }
+
+    /// 按模板生成数据源的前缀，模板为空时返回空字符串
+    pub fn prefix(&self, provider: &str, default_label: &str) -> String {
+        let template = self
+            .options(provider)
+            .and_then(|options| options.prefix.as_deref())
+            .or(self.config.provider_prefix.as_deref())
+            .unwrap_or(DEFAULT_PREFIX);
+        template.replace("{label}", self.label(provider, default_label))
+    }
+
+    /// 给数据源的各项指标加上前缀；开启 `group_provider_metrics` 时合并为一段，只保留一个前缀
+    pub fn decorate(&self, provider: &dyn Provider, metrics: Vec<Segment>) -> Vec<Segment> {
+        let prefix = self.prefix(provider.name(), provider.label(self.labels));
+        let with_prefix = |mut segment: Segment| {
+            if let Some(first) = segment.spans.first_mut().filter(|_| !prefix.is_empty()) {
+                first.text = format!("{} {}", prefix, first.text);
+            }
+            segment
+        };
+        let bars = self.config.provider_bars;
+        let metrics = metrics.into_iter().map(|metric| if bars { metric.draw_bar() } else { metric });
+        if !self.config.group_provider_metrics {
+            return metrics.map(with_prefix).collect();
+        }
+
+        let mut metrics = metrics;
+        let Some(first) = metrics.next() else {
+            return Vec::new();
+        };
+        // 合并后的段沿用第一项的名称（决定宽度不足时的优先级），各项保留自己的颜色
+        let grouped = metrics.fold(first, |grouped, metric| {
+            metric.spans.into_iter().enumerate().fold(grouped, |grouped, (i, span)| {
+                let text = if i == 0 { format!(" {}", span.text) } else { span.text };
+                grouped.with(span.color, text)
+            })
+        });
+        vec![with_prefix(grouped)]
+    }
+
+    /// 数据源的用户配置
+    pub fn options(&self, provider: &str) -> Option<&ProviderOptions> {
+        self.config.providers.get(provider)
+    }
+
+    /// baseURL 是否命中用户为该数据源配置的 `domains`
+    pub fn matches_domains(&self, provider: &str, base_url: &str) -> bool {
+        self.options(provider)
+            .is_some_and(|options| options.domains.iter().any(|domain| base_url.contains(domain.as_str())))
+    }
+}
+
+/// 数据源请求失败的原因
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ProviderError {
+    /// baseURL 无法解析出主机
+    #[cfg_attr(
+        not(any(feature = "provider-zhipu", feature = "provider-packycode", feature = "provider-openai")),
+        allow(dead_code)
+    )]
+    InvalidUrl,
+    /// 网络错误（连接失败、读取响应失败等）
+    #[cfg_attr(not(feature = "network"), allow(dead_code))]
+    Request { message: String },
+    /// 超过共用的请求时限
+    Timeout,
+    /// 接口返回非 2xx 状态码
+    #[cfg_attr(not(feature = "network"), allow(dead_code))]
+    Status { status: u16 },
+    /// token 失效且无法刷新
+    #[cfg_attr(not(feature = "provider-gac"), allow(dead_code))]
+    Unauthorized,
+    /// 响应无法解析；`path` 为出错位置的 JSON Pointer，整个响应不是 JSON 时为空
+    Parse { path: String, message: String },
+    /// 没有编译请求网络的支持
+    #[cfg_attr(feature = "network", allow(dead_code))]
+    NotCompiled,
+}
+
+impl ProviderError {
+    #[cfg_attr(not(feature = "network"), allow(dead_code))]
+    pub fn parse(path: &str, message: impl fmt::Display) -> Self {
+        ProviderError::Parse {
+            path: path.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProviderError::InvalidUrl => write!(f, "invalid base URL"),
+            ProviderError::Request { message } => write!(f, "request failed: {}", message),
+            ProviderError::Timeout => write!(f, "request timed out"),
+            ProviderError::Status { status } => write!(f, "HTTP {}", status),
+            ProviderError::Unauthorized => write!(f, "unauthorized (token expired and refresh failed)"),
+            ProviderError::Parse { path, message } if path.is_empty() => {
+                write!(f, "unparsable response: {}", message)
+            }
+            ProviderError::Parse { path, message } => write!(f, "unparsable response at {}: {}", path, message),
+            ProviderError::NotCompiled => write!(f, "network support not compiled in"),
+        }
+    }
+}
+
+impl std::error::Error for ProviderError {}
+
+/// 数据源段的获取结果，说明段为什么显示或不显示
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ProviderOutcome {
+    /// 没有数据源匹配 baseURL
+    NotMatched,
+    /// 需要在配置中开启的数据源未开启
+    Disabled,
+    /// 缓存失效，但没有可用于请求的 token
+    NoCredentials,
+    /// 使用有效的缓存
+    Cached { segments: usize },
+    /// 请求成功
+    Fetched { segments: usize },
+    /// 缓存失效且请求失败
+    Failed { error: ProviderError },
+}
+
+impl fmt::Display for ProviderOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProviderOutcome::NotMatched => write!(f, "not matched"),
+            ProviderOutcome::Disabled => write!(f, "disabled in config"),
+            ProviderOutcome::NoCredentials => write!(f, "matched, but no auth token is configured"),
+            ProviderOutcome::Cached { segments } => write!(f, "matched, {} segment(s) from cache", segments),
+            ProviderOutcome::Fetched { segments } => write!(f, "matched, fetched {} segment(s)", segments),
+            ProviderOutcome::Failed { error } => write!(f, "matched, but the request failed: {}", error),
+        }
+    }
+}
+
+/// 一次渲染中数据源的执行结果，`provider` 为匹配的数据源名称
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProviderReport {
+    pub provider: Option<&'static str>,
+    #[serde(flatten)]
+    pub outcome: ProviderOutcome,
+}
+
+/// 最近一次 [`segments`] 的执行结果，供 `--json` 输出
+static LAST_REPORT: Mutex<Option<ProviderReport>> = Mutex::new(None);
+
+/// 本次运行中数据源的执行结果；复用上次输出等没有执行数据源时为 None
+pub fn last_report() -> Option<ProviderReport> {
+    LAST_REPORT.lock().ok()?.clone()
+}
+
+/// 认证 token 的发送方式（请求头的构造见 `http` 模块）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthScheme {
+    /// `Authorization: <token>`
+    #[cfg_attr(not(feature = "provider-zhipu"), allow(dead_code))]
+    Raw,
+    /// `Authorization: Bearer <token>`，token 已带前缀时不重复添加
+    #[cfg_attr(
+        not(any(
+            feature = "provider-yunyi",
+            feature = "provider-packycode",
+            feature = "provider-gac",
+            feature = "provider-openai"
+        )),
+        allow(dead_code)
+    )]
+    Bearer,
+    /// `x-api-key: <token>`
+    #[cfg_attr(not(feature = "provider-anthropic"), allow(dead_code))]
+    XApiKey,
+}
+
+pub trait Provider {
+    fn name(&self) -> &'static str;
+    /// 默认的前缀标签，如 `ZAI`
+    fn label(&self, labels: &'static Labels) -> &'static str;
+    fn matches(&self, base_url: &str) -> bool;
+    /// 是否参与匹配，需要用户显式开启的数据源返回配置中的开关
+    fn enabled(&self, _ctx: &ProviderContext) -> bool {
+        true
+    }
+    /// 请求额度接口时使用的认证方式
+    #[cfg_attr(not(feature = "network"), allow(dead_code))]
+    fn auth_scheme(&self) -> AuthScheme;
+    /// 缓存的更新时间（不论是否有效），没有缓存文件的数据源返回 None；
+    /// 缓存文件按 `auth_token` 区分，见 `cache::cache_path`
+    fn cache_updated_at(&self, _auth_token: &str) -> Option<DateTime<Utc>> {
+        None
+    }
+    /// 是否需要请求网络；不请求的数据源只使用 [`Provider::cached_segments`]，也不需要 token
+    fn fetches(&self) -> bool {
+        true
+    }
+    /// 缓存有效时的各项指标，不含前缀（前缀由 [`ProviderContext::decorate`] 统一添加）；
+    /// 返回 None 表示需要请求网络
+    fn cached_segments(&self, auth_token: &str, ctx: &ProviderContext) -> Option<Vec<Segment>>;
+    /// 请求失败时仍要显示的指标（如需要重新登录的提示），默认不显示
+    fn failure_segments(&self, _error: &ProviderError, _ctx: &ProviderContext) -> Vec<Segment> {
+        Vec::new()
+    }
+    /// 请求额度接口得到各项指标，在 `http::fetch_all` 创建的运行时中执行
+    #[cfg_attr(not(feature = "network"), allow(dead_code))]
+    fn fetch_segments<'a>(
+        &'a self,
+        _base_url: &'a str,
+        _auth_token: &'a str,
+        _ctx: &'a ProviderContext,
+    ) -> Fetch<'a> {
+        Box::pin(std::future::ready(Ok(Vec::new())))
+    }
+}
+
+/// 数据源的请求过程
+pub type Fetch<'a> = Pin<Box<dyn Future<Output = Result<Vec<Segment>, ProviderError>> + 'a>>;
+
+/// 一个数据源的请求结果
+struct Fetched {
+    /// 超过时限的为 None
+    result: Option<Result<Vec<Segment>, ProviderError>>,
+    /// 请求耗时，超时的为等待的时长
+    elapsed: Duration,
+    /// 最后收到的 HTTP 状态码，没有收到响应时为 None
+    status: Option<u16>,
+}
+
+/// 按 feature 组装已编译的数据源，匹配时按此顺序
+pub fn providers() -> Vec<&'static dyn Provider> {
+    vec![
+        #[cfg(feature = "provider-zhipu")]
+        &zhipu::ZhipuProvider,
+        #[cfg(feature = "provider-yunyi")]
+        &yunyi::YunyiProvider,
+        #[cfg(feature = "provider-packycode")]
+        &packycode::PackycodeProvider,
+        #[cfg(feature = "provider-gac")]
+        &gac::GacProvider,
+        #[cfg(feature = "provider-anthropic")]
+        &anthropic::AnthropicProvider,
+        // 兜底的 OpenAI 兼容用量接口，必须排在最后
+        #[cfg(feature = "provider-openai")]
+        &openai::OpenaiProvider,
+    ]
+}
+
+/// 已编译的数据源在缓存目录中写入的缓存文件名（不含按 token 区分的哈希）
+pub fn cache_files() -> Vec<&'static str> {
+    vec![
+        #[cfg(feature = "provider-zhipu")]
+        zhipu::CACHE_FILE,
+        #[cfg(feature = "provider-yunyi")]
+        yunyi::CACHE_FILE,
+        #[cfg(feature = "provider-packycode")]
+        packycode::CACHE_FILE,
+        #[cfg(feature = "provider-gac")]
+        gac::CACHE_FILE,
+        #[cfg(feature = "provider-openai")]
+        openai::CACHE_FILE,
+    ]
+}
+
+/// 按用户配置的 `position` 调整匹配顺序：`first` 的排到最前，`last` 的排到最后，其余保持内置顺序
+fn ordered<'a>(providers: &[&'a dyn Provider], ctx: &ProviderContext) -> Vec<&'a dyn Provider> {
+    let mut ordered = providers.to_vec();
+    ordered.sort_by_key(|provider| match ctx.options(provider.name()).and_then(|options| options.position) {
+        Some(ProviderPosition::First) => 0,
+        None => 1,
+        Some(ProviderPosition::Last) => 2,
+    });
+    ordered
+}
+
+/// 数据源是否匹配 baseURL（内置规则或用户配置的 `domains`）
+fn matches(provider: &dyn Provider, base_url: &str, ctx: &ProviderContext) -> bool {
+    provider.matches(base_url) || ctx.matches_domains(provider.name(), base_url)
+}
+
+/// 匹配 baseURL 的数据源的段（已加前缀），执行结果见 [`last_report`]
+///
+/// 数据源按 [`ordered`] 的顺序匹配。开启 `first_match_only`（默认）时依次获取，
+/// 第一个有段的数据源之后不再继续；关闭时所有匹配的数据源并发获取，按顺序排列。
+/// `pin` 为配置中固定使用的数据源名称，设置时跳过 baseURL 匹配，但仍检查数据源是否开启。
+pub fn segments(base_url: &str, auth_token: &str, pin: Option<&str>, ctx: &ProviderContext) -> Vec<Segment> {
+    let (segments, report) = resolve(&providers(), base_url, auth_token, pin, ctx);
+    if let Ok(mut last) = LAST_REPORT.lock() {
+        *last = Some(report);
+    }
+    segments
+}
+
+fn resolve(
+    providers: &[&dyn Provider],
+    base_url: &str,
+    auth_token: &str,
+    pin: Option<&str>,
+    ctx: &ProviderContext,
+) -> (Vec<Segment>, ProviderReport) {
+    let matched: Vec<&dyn Provider> = match pin {
+        Some(pin) => {
+            let pinned = providers.iter().copied().find(|provider| provider.name() == pin);
+            if pinned.is_none() {
+                crate::log::warn(StatuslineError::config("provider", format!("unknown provider {:?}", pin)));
+            }
+            pinned.into_iter().filter(|provider| provider.enabled(ctx)).collect()
+        }
+        None => ordered(providers, ctx)
+            .into_iter()
+            .filter(|provider| provider.enabled(ctx))
+            .filter(|provider| matches(*provider, base_url, ctx))
+            .collect(),
+    };
+
+    let results: Vec<(&dyn Provider, Vec<Segment>, ProviderOutcome)> = if ctx.config.first_match_only() {
+        let mut results = Vec::new();
+        for provider in matched {
+            let Some((metrics, outcome)) = metrics(&[provider], base_url, auth_token, ctx).pop() else {
+                continue;
+            };
+            let found = !metrics.is_empty();
+            results.push((provider, metrics, outcome));
+            if found {
+                break;
+            }
+        }
+        results
+    } else {
+        let metrics = metrics(&matched, base_url, auth_token, ctx);
+        matched
+            .into_iter()
+            .zip(metrics)
+            .map(|(provider, (metrics, outcome))| (provider, metrics, outcome))
+            .collect()
+    };
+
+    // 报告第一个有段的数据源，都没有段时报告第一个匹配的
+    let reported = results.iter().position(|(_, metrics, _)| !metrics.is_empty()).unwrap_or(0);
+    let report = match results.get(reported) {
+        Some((provider, _, outcome)) => ProviderReport {
+            provider: Some(provider.name()),
+            outcome: outcome.clone(),
+        },
+        None => ProviderReport {
+            provider: None,
+            outcome: ProviderOutcome::NotMatched,
+        },
+    };
+    let segments = results
+        .into_iter()
+        .flat_map(|(provider, metrics, outcome)| {
+            let mut segments = ctx.decorate(provider, metrics);
+            let cached = matches!(outcome, ProviderOutcome::Cached { .. });
+            if ctx.config.provider_age && cached {
+                segments = mark_age(segments, provider.cache_updated_at(auth_token), Utc::now());
+            }
+            if !ctx.config.heartbeat {
+                return segments;
+            }
+            mark_stale(segments, provider.cache_updated_at(auth_token), Utc::now())
+        })
+        .collect();
+    (segments, report)
+}
+
+/// 依次检查每个已编译的数据源：是否开启、是否匹配（或是否为固定使用的数据源），
+/// 匹配的数据源不读缓存、直接请求一次
+pub fn diagnose(base_url: &str, auth_token: &str, pin: Option<&str>, ctx: &ProviderContext) -> Vec<ProviderReport> {
+    let providers = ordered(&providers(), ctx);
+    let mut reports: Vec<ProviderReport> = providers
+        .iter()
+        .map(|provider| {
+            let skipped = match pin {
+                Some(pin) if provider.name() != pin => Some(ProviderOutcome::NotMatched),
+                _ if !provider.enabled(ctx) => Some(ProviderOutcome::Disabled),
+                Some(_) => None,
+                None => (!matches(*provider, base_url, ctx)).then_some(ProviderOutcome::NotMatched),
+            };
+            let outcome = skipped.unwrap_or_else(|| {
+                if !provider.fetches() {
+                    let segments = provider.cached_segments(auth_token, ctx).unwrap_or_default();
+                    ProviderOutcome::Cached { segments: segments.len() }
+                } else if auth_token.is_empty() {
+                    ProviderOutcome::NoCredentials
+                } else {
+                    ProviderOutcome::Fetched { segments: 0 }
+                }
+            });
+            ProviderReport {
+                provider: Some(provider.name()),
+                outcome,
+            }
+        })
+        .collect();
+
+    let pending: Vec<usize> = (0..reports.len())
+        .filter(|&i| matches!(reports[i].outcome, ProviderOutcome::Fetched { .. }))
+        .collect();
+    let selected: Vec<&dyn Provider> = pending.iter().map(|&i| providers[i]).collect();
+    for (i, fetched) in pending.into_iter().zip(fetch(&selected, base_url, auth_token, ctx)) {
+        reports[i].outcome = match fetched.result {
+            Some(Ok(segments)) => ProviderOutcome::Fetched { segments: segments.len() },
+            Some(Err(error)) => ProviderOutcome::Failed { error },
+            None => ProviderOutcome::Failed {
+                error: ProviderError::Timeout,
+            },
+        };
+    }
+    reports
+}
+
+/// `--check-providers` 中一个数据源的请求结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProviderCheck {
+    pub provider: &'static str,
+    /// 成功时为得到的段数
+    pub result: Result<usize, ProviderError>,
+    /// 最后收到的 HTTP 状态码，没有收到响应时为 None
+    pub status: Option<u16>,
+    /// 请求耗时，超时的为等待的时长
+    pub elapsed: Duration,
+}
+
+/// 已开启且匹配 baseURL 的需要请求网络的数据源（`pin` 为固定使用的数据源）各请求一次，不读缓存
+pub fn check(
+    base_url: &str,
+    auth_token: &str,
+    pin: Option<&str>,
+    ctx: &ProviderContext,
+) -> Vec<ProviderCheck> {
+    run_checks(&providers(), base_url, auth_token, pin, ctx)
+}
+
+fn run_checks(
+    providers: &[&dyn Provider],
+    base_url: &str,
+    auth_token: &str,
+    pin: Option<&str>,
+    ctx: &ProviderContext,
+) -> Vec<ProviderCheck> {
+    let selected: Vec<&dyn Provider> = ordered(providers, ctx)
+        .into_iter()
+        .filter(|provider| provider.fetches() && provider.enabled(ctx))
+        .filter(|provider| match pin {
+            Some(pin) => provider.name() == pin,
+            None => matches(*provider, base_url, ctx),
+        })
+        .collect();
+    selected
+        .iter()
+        .zip(fetch(&selected, base_url, auth_token, ctx))
+        .map(|(provider, fetched)| ProviderCheck {
+            provider: provider.name(),
+            result: fetched.result.unwrap_or(Err(ProviderError::Timeout)).map(|segments| segments.len()),
+            status: fetched.status,
+            elapsed: fetched.elapsed,
+        })
+        .collect()
+}
+
+/// 缓存超过 [`STALE_AFTER_MINUTES`] 未更新时，在每个段后加上灰色的 `~stale`
+fn mark_stale(segments: Vec<Segment>, updated_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Vec<Segment> {
+    let max_age = chrono::Duration::minutes(STALE_AFTER_MINUTES);
+    if updated_at.is_none_or(|updated_at| now.signed_duration_since(updated_at) <= max_age) {
+        return segments;
+    }
+    segments.into_iter().map(|segment| segment.with(crate::colors::DIM, " ~stale")).collect()
+}
+
+/// 缓存超过 [`AGE_AFTER_SECS`] 时，在数据源的最后一段后加上灰色的缓存时长，如 `(2m)`
+fn mark_age(segments: Vec<Segment>, updated_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Vec<Segment> {
+    let age = updated_at.and_then(|updated_at| now.signed_duration_since(updated_at).to_std().ok());
+    let Some(age) = age else {
+        return segments;
+    };
+    if age.as_secs() <= AGE_AFTER_SECS as u64 {
+        return segments;
+    }
+    let mut segments = segments;
+    if let Some(last) = segments.pop() {
+        let age = crate::format_duration(age.as_millis() as u64);
+        segments.push(last.with(crate::colors::DIM, format!(" ({})", age)));
+    }
+    segments
+}
+
+/// 各数据源的指标和执行结果：先读缓存，缓存失效的数据源在同一个运行时中并发请求
+fn metrics(
+    providers: &[&dyn Provider],
+    base_url: &str,
+    auth_token: &str,
+    ctx: &ProviderContext,
+) -> Vec<(Vec<Segment>, ProviderOutcome)> {
+    let mut metrics: Vec<Option<(Vec<Segment>, ProviderOutcome)>> = providers
+        .iter()
+        .map(|provider| {
+            if let Some(segments) = provider.cached_segments(auth_token, ctx) {
+                let outcome = ProviderOutcome::Cached { segments: segments.len() };
+                Some((segments, outcome))
+            } else if !provider.fetches() {
+                Some((Vec::new(), ProviderOutcome::Cached { segments: 0 }))
+            } else if auth_token.is_empty() {
+                Some((Vec::new(), ProviderOutcome::NoCredentials))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    // 没有需要请求的数据源时不创建运行时
+    let stale: Vec<usize> = (0..providers.len()).filter(|&i| metrics[i].is_none()).collect();
+    let selected: Vec<&dyn Provider> = stale.iter().map(|&i| providers[i]).collect();
+    for (i, fetched) in stale.into_iter().zip(fetch(&selected, base_url, auth_token, ctx)) {
+        let result = fetched.result.unwrap_or(Err(ProviderError::Timeout));
+        metrics[i] = Some(match result {
+            Ok(segments) => {
+                let outcome = ProviderOutcome::Fetched { segments: segments.len() };
+                (segments, outcome)
+            }
+            Err(error) => {
+                crate::log::warn(StatuslineError::Network {
+                    provider: providers[i].name(),
+                    error: error.clone(),
+                });
+                (providers[i].failure_segments(&error, ctx), ProviderOutcome::Failed { error })
+            }
+        });
+    }
+    metrics.into_iter().flatten().collect()
+}
+
+/// 在同一个运行时中并发请求各个数据源，超过时限的为 None；同时返回各数据源的请求耗时
+#[cfg(feature = "network")]
+fn fetch(
+    providers: &[&dyn Provider],
+    base_url: &str,
+    auth_token: &str,
+    ctx: &ProviderContext,
+) -> Vec<Fetched> {
+    let start = std::time::Instant::now();
+    let fetches: Vec<Pin<Box<dyn Future<Output = _> + '_>>> = providers
+        .iter()
+        .map(|provider| -> Pin<Box<dyn Future<Output = _> + '_>> {
+            let fetch = provider.fetch_segments(base_url, auth_token, ctx);
+            Box::pin(async move {
+                let start = std::time::Instant::now();
+                let (result, status) = http::with_status(fetch).await;
+                // 超时被取消的请求不记录耗时
+                if crate::trace::enabled() {
+                    crate::trace::record(&format!("provider:{}", provider.name()), start.elapsed());
+                }
+                Fetched {
+                    result: Some(result),
+                    elapsed: start.elapsed(),
+                    status,
+                }
+            })
+        })
+        .collect();
+    http::fetch_all(fetches)
+        .into_iter()
+        .map(|fetched| {
+            fetched.unwrap_or_else(|| Fetched {
+                result: None,
+                elapsed: start.elapsed(),
+                status: None,
+            })
+        })
+        .collect()
+}
+
+/// 没有编译需要请求网络的数据源：每个请求都报告未编译，而不是当作超时
+#[cfg(not(feature = "network"))]
+fn fetch(
+    providers: &[&dyn Provider],
+    _: &str,
+    _: &str,
+    _: &ProviderContext,
+) -> Vec<Fetched> {
+    providers
+        .iter()
+        .map(|_| Fetched {
+            result: Some(Err(ProviderError::NotCompiled)),
+            elapsed: Duration::ZERO,
+            status: None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub(crate) fn en_ctx(config: &StatuslineConfig) -> ProviderContext<'_> {
+    ProviderContext {
+        labels: &crate::i18n::EN,
+        config,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colors;
+
+    /// 固定返回两项指标的数据源
+    struct FakeProvider;
+
+    impl Provider for FakeProvider {
+        fn name(&self) -> &'static str {
+            "zhipu"
+        }
+
+        fn label(&self, labels: &'static Labels) -> &'static str {
+            labels.zhipu_tag
+        }
+
+        fn matches(&self, _base_url: &str) -> bool {
+            true
+        }
+
+        fn auth_scheme(&self) -> AuthScheme {
+            AuthScheme::Raw
+        }
+
+        fn cached_segments(&self, _auth_token: &str, _ctx: &ProviderContext) -> Option<Vec<Segment>> {
+            Some(vec![
+                Segment::new("zhipu_token", colors::YELLOW, "tok:37%"),
+                Segment::new("zhipu_mcp", colors::GREEN, "mcp:12%"),
+            ])
+        }
+    }
+
+    fn render(config: &StatuslineConfig) -> Vec<String> {
+        let ctx = en_ctx(config);
+        let (metrics, _) = metrics(&[&FakeProvider], "", "", &ctx).remove(0);
+        ctx.decorate(&FakeProvider, metrics).iter().map(Segment::plain_text).collect()
+    }
+
+    #[test]
+    fn test_prefix_templates() {
+        let mut config = StatuslineConfig::default();
+        assert_eq!(render(&config), ["[ZAI] tok:37%", "[ZAI] mcp:12%"]);
+
+        config.provider_prefix = Some("{label}:".to_string());
+        assert_eq!(render(&config), ["ZAI: tok:37%", "ZAI: mcp:12%"]);
+
+        config.provider_prefix = Some(String::new());
+        assert_eq!(render(&config), ["tok:37%", "mcp:12%"]);
+
+        // 数据源自己的模板和标签优先
+        config.providers.insert(
+            "zhipu".to_string(),
+            ProviderOptions {
+                label: Some("Z.ai".to_string()),
+                prefix: Some("<{label}>".to_string()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(render(&config), ["<Z.ai> tok:37%", "<Z.ai> mcp:12%"]);
+    }
+
+    #[test]
+    fn test_grouped_metrics() {
+        let mut config = StatuslineConfig {
+            group_provider_metrics: true,
+            ..Default::default()
+        };
+        let ctx = en_ctx(&config);
+        let segments = ctx.decorate(&FakeProvider, FakeProvider.cached_segments("", &ctx).unwrap());
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].name, "zhipu_token");
+        assert_eq!(segments[0].plain_text(), "[ZAI] tok:37% mcp:12%");
+        assert_eq!(segments[0].spans[1].color, colors::GREEN);
+        assert!(ctx.decorate(&FakeProvider, Vec::new()).is_empty());
+
+        config.provider_prefix = Some(String::new());
+        assert_eq!(render(&config), ["tok:37% mcp:12%"]);
+    }
+
+    #[test]
+    fn test_mark_stale_after_fifteen_minutes() {
+        let now: DateTime<Utc> = "2026-05-01T10:00:00Z".parse().unwrap();
+        let render = |updated_at: Option<&str>| -> Vec<String> {
+            let segments = vec![
+                Segment::new("zhipu_token", colors::YELLOW, "[ZAI] tok:37%"),
+                Segment::new("zhipu_mcp", colors::GREEN, "[ZAI] mcp:12%"),
+            ];
+            let updated_at = updated_at.map(|s| s.parse().unwrap());
+            mark_stale(segments, updated_at, now).iter().map(Segment::plain_text).collect()
+        };
+
+        assert_eq!(render(Some("2026-05-01T09:45:01Z")), ["[ZAI] tok:37%", "[ZAI] mcp:12%"]);
+        assert_eq!(render(Some("2026-05-01T09:45:00Z")), ["[ZAI] tok:37%", "[ZAI] mcp:12%"]);
+        assert_eq!(render(Some("2026-05-01T09:44:59Z")), ["[ZAI] tok:37% ~stale", "[ZAI] mcp:12% ~stale"]);
+        // 没有缓存时间的数据源不标记
+        assert_eq!(render(None), ["[ZAI] tok:37%", "[ZAI] mcp:12%"]);
+
+        let segments = vec![Segment::new("gac_quota", colors::GREEN, "80")];
+        let stale = mark_stale(segments, Some(now - chrono::Duration::hours(1)), now);
+        assert_eq!(stale[0].spans[1].color, colors::DIM);
+    }
+
+    #[test]
+    fn test_mark_age_after_a_minute() {
+        let now: DateTime<Utc> = "2026-05-01T10:00:00Z".parse().unwrap();
+        let render = |updated_at: Option<&str>| -> Vec<String> {
+            let segments = vec![
+                Segment::new("zhipu_token", colors::YELLOW, "[ZAI] tok:37%"),
+                Segment::new("zhipu_mcp", colors::GREEN, "[ZAI] mcp:12%"),
+            ];
+            let updated_at = updated_at.map(|s| s.parse().unwrap());
+            mark_age(segments, updated_at, now).iter().map(Segment::plain_text).collect()
+        };
+
+        assert_eq!(render(Some("2026-05-01T09:59:00Z")), ["[ZAI] tok:37%", "[ZAI] mcp:12%"]);
+        // 只在最后一段后显示
+        assert_eq!(render(Some("2026-05-01T09:58:59Z")), ["[ZAI] tok:37%", "[ZAI] mcp:12% (1m)"]);
+        assert_eq!(render(Some("2026-05-01T08:30:00Z")), ["[ZAI] tok:37%", "[ZAI] mcp:12% (1h30m)"]);
+        // 没有缓存时间或时间在未来时不显示
+        assert_eq!(render(None), ["[ZAI] tok:37%", "[ZAI] mcp:12%"]);
+        assert_eq!(render(Some("2026-05-01T10:05:00Z")), ["[ZAI] tok:37%", "[ZAI] mcp:12%"]);
+
+        let hour_ago = Some(now - chrono::Duration::hours(1));
+        let aged = mark_age(vec![Segment::new("gac_quota", colors::GREEN, "80")], hour_ago, now);
+        assert_eq!(aged[0].spans[1].color, colors::DIM);
+        assert!(mark_age(Vec::new(), hour_ago, now).is_empty());
+    }
+
+    #[test]
+    fn test_cache_files_are_known() {
+        // 迁移和清理只认识列表中的文件，新增数据源时需要加入
+        for file in cache_files() {
+            assert!(crate::paths::PROVIDER_CACHE_FILES.contains(&file), "{} missing", file);
+        }
+    }
+
+    #[test]
+    fn test_nerd_font_glyphs() {
+        let mut config = StatuslineConfig {
+            nerd_font: true,
+            ..Default::default()
+        };
+        assert_eq!(render(&config)[0], "[\u{f09d1}] tok:37%");
+
+        // 自定义标签不会被图标替换
+        config.providers.insert(
+            "zhipu".to_string(),
+            ProviderOptions {
+                label: Some("Z.ai".to_string()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(render(&config)[0], "[Z.ai] tok:37%");
+    }
+
+    /// 需要开启 `openai_usage` 才参与匹配的数据源
+    struct OptInProvider;
+
+    impl Provider for OptInProvider {
+        fn name(&self) -> &'static str {
+            "opt_in"
+        }
+
+        fn label(&self, _labels: &'static Labels) -> &'static str {
+            "OPT"
+        }
+
+        fn matches(&self, _base_url: &str) -> bool {
+            true
+        }
+
+        fn enabled(&self, ctx: &ProviderContext) -> bool {
+            ctx.config.openai_usage
+        }
+
+        fn auth_scheme(&self) -> AuthScheme {
+            AuthScheme::Bearer
+        }
+
+        fn cached_segments(&self, _auth_token: &str, _ctx: &ProviderContext) -> Option<Vec<Segment>> {
+            Some(vec![Segment::new("opt_in", colors::GREEN, "used:1")])
+        }
+    }
+
+    #[test]
+    fn test_pinned_provider_must_be_enabled() {
+        let pinned = |config: &StatuslineConfig| {
+            resolve(&[&OptInProvider], "https://proxy.example.com", "token", Some("opt_in"), &en_ctx(config))
+        };
+        let mut config = StatuslineConfig::default();
+        let (segments, report) = pinned(&config);
+        assert!(segments.is_empty());
+        assert_eq!(report.provider, None);
+
+        config.openai_usage = true;
+        let (segments, report) = pinned(&config);
+        assert_eq!(segments.iter().map(Segment::plain_text).collect::<Vec<_>>(), ["[OPT] used:1"]);
+        assert_eq!(report.provider, Some("opt_in"));
+    }
+
+    #[cfg(not(feature = "network"))]
+    #[test]
+    fn test_checks_without_network_support() {
+        let config = StatuslineConfig { openai_usage: true, ..Default::default() };
+        let checks = run_checks(&[&OptInProvider], "", "token", None, &en_ctx(&config));
+        assert_eq!(checks[0].result, Err(ProviderError::NotCompiled));
+        assert_eq!(checks[0].result.as_ref().unwrap_err().to_string(), "network support not compiled in");
+    }
+
+    /// 缓存总是失效、需要请求网络的数据源
+    #[cfg(feature = "network")]
+    struct StaleProvider;
+
+    #[cfg(feature = "network")]
+    impl Provider for StaleProvider {
+        fn name(&self) -> &'static str {
+            "stale"
+        }
+
+        fn label(&self, _labels: &'static Labels) -> &'static str {
+            "STALE"
+        }
+
+        fn matches(&self, _base_url: &str) -> bool {
+            true
+        }
+
+        fn auth_scheme(&self) -> AuthScheme {
+            AuthScheme::Bearer
+        }
+
+        fn cached_segments(&self, _auth_token: &str, _ctx: &ProviderContext) -> Option<Vec<Segment>> {
+            None
+        }
+
+        fn fetch_segments<'a>(&'a self, base_url: &'a str, _: &'a str, _: &'a ProviderContext) -> Fetch<'a> {
+            Box::pin(async move { Ok(vec![Segment::new("stale", colors::GREEN, base_url)]) })
+        }
+    }
+
+    /// 请求总是失败的数据源，失败时显示错误
+    #[cfg(feature = "network")]
+    struct FailingProvider(ProviderError);
+
+    #[cfg(feature = "network")]
+    impl Provider for FailingProvider {
+        fn name(&self) -> &'static str {
+            "failing"
+        }
+
+        fn label(&self, _labels: &'static Labels) -> &'static str {
+            "FAIL"
+        }
+
+        fn matches(&self, base_url: &str) -> bool {
+            base_url.contains("failing")
+        }
+
+        fn auth_scheme(&self) -> AuthScheme {
+            AuthScheme::Bearer
+        }
+
+        fn cached_segments(&self, _auth_token: &str, _ctx: &ProviderContext) -> Option<Vec<Segment>> {
+            None
+        }
+
+        fn failure_segments(&self, error: &ProviderError, _ctx: &ProviderContext) -> Vec<Segment> {
+            vec![Segment::new("failing", colors::DIM, error.to_string())]
+        }
+
+        fn fetch_segments<'a>(&'a self, _: &'a str, _: &'a str, _: &'a ProviderContext) -> Fetch<'a> {
+            Box::pin(std::future::ready(Err(self.0.clone())))
+        }
+    }
+
+    #[cfg(feature = "network")]
+    #[test]
+    fn test_runtime_only_for_stale_providers() {
+        let config = StatuslineConfig::default();
+        let ctx = en_ctx(&config);
+        assert_eq!(metrics(&[&FakeProvider], "fetched", "token", &ctx)[0].0.len(), 2);
+        assert_eq!(http::runtimes_created(), 0);
+        // 没有 token 时不请求
+        metrics(&[&StaleProvider], "fetched", "", &ctx);
+        assert_eq!(http::runtimes_created(), 0);
+
+        // 多个数据源需要请求时共用一个运行时
+        let metrics = metrics(&[&StaleProvider, &FakeProvider, &StaleProvider], "fetched", "token", &ctx);
+        assert_eq!(http::runtimes_created(), 1);
+        let texts: Vec<Vec<String>> = metrics
+            .iter()
+            .map(|(segments, _)| segments.iter().map(Segment::plain_text).collect())
+            .collect();
+        assert_eq!(texts, [vec!["fetched"], vec!["tok:37%", "mcp:12%"], vec!["fetched"]]);
+    }
+
+    #[cfg(feature = "network")]
+    #[test]
+    fn test_outcomes() {
+        let config = StatuslineConfig::default();
+        let ctx = en_ctx(&config);
+        let resolve = |providers: &[&dyn Provider], base_url: &str, auth_token: &str| {
+            let (segments, report) = resolve(providers, base_url, auth_token, None, &ctx);
+            (segments.iter().map(Segment::plain_text).collect::<Vec<_>>(), report)
+        };
+        let report = |provider, outcome| ProviderReport { provider, outcome };
+
+        let unauthorized = FailingProvider(ProviderError::Unauthorized);
+        let (segments, outcome) = resolve(&[&unauthorized], "https://other.example.com", "token");
+        assert!(segments.is_empty());
+        assert_eq!(outcome, report(None, ProviderOutcome::NotMatched));
+
+        let (_, outcome) = resolve(&[&StaleProvider], "https://stale.example.com", "");
+        assert_eq!(outcome, report(Some("stale"), ProviderOutcome::NoCredentials));
+
+        let (_, outcome) = resolve(&[&FakeProvider], "https://zhipu.example.com", "");
+        assert_eq!(outcome, report(Some("zhipu"), ProviderOutcome::Cached { segments: 2 }));
+
+        let (segments, outcome) = resolve(&[&StaleProvider], "https://stale.example.com", "token");
+        assert_eq!(segments, ["[STALE] https://stale.example.com"]);
+        assert_eq!(outcome, report(Some("stale"), ProviderOutcome::Fetched { segments: 1 }));
+
+        // 缓存失效且请求失败，失败时的段照常显示
+        let (segments, outcome) = resolve(&[&unauthorized], "https://failing.example.com", "token");
+        assert_eq!(segments, ["[FAIL] unauthorized (token expired and refresh failed)"]);
+        let failed = |error| ProviderOutcome::Failed { error };
+        assert_eq!(outcome, report(Some("failing"), failed(ProviderError::Unauthorized)));
+
+        let unparsable = FailingProvider(ProviderError::parse("/data/limits", "missing"));
+        let (_, outcome) = resolve(&[&unparsable, &StaleProvider], "https://failing.example.com", "token");
+        assert_eq!(outcome.outcome, failed(ProviderError::parse("/data/limits", "missing")));
+
+        let json = serde_json::to_value(&outcome).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "provider": "failing",
+                "status": "failed",
+                "error": {"kind": "parse", "path": "/data/limits", "message": "missing"}
+            })
+        );
+    }
+
+    #[cfg(feature = "network")]
+    #[test]
+    fn test_pinned_provider() {
+        let config = StatuslineConfig::default();
+        let ctx = en_ctx(&config);
+        let unauthorized = FailingProvider(ProviderError::Unauthorized);
+        let providers: [&dyn Provider; 2] = [&StaleProvider, &unauthorized];
+
+        // 未固定时使用第一个匹配的数据源
+        let (_, report) = resolve(&providers, "https://failing.example.com", "token", None, &ctx);
+        assert_eq!(report.provider, Some("stale"));
+
+        // 固定后跳过 baseURL 匹配
+        let (_, report) = resolve(&providers, "https://proxy.example.com", "token", Some("failing"), &ctx);
+        assert_eq!(report.provider, Some("failing"));
+        assert_eq!(report.outcome, ProviderOutcome::Failed { error: ProviderError::Unauthorized });
+
+        let (segments, report) = resolve(&providers, "https://stale.example.com", "token", Some("unknown"), &ctx);
+        assert!(segments.is_empty());
+        assert_eq!(report, ProviderReport { provider: None, outcome: ProviderOutcome::NotMatched });
+    }
+
+    #[cfg(feature = "network")]
+    #[test]
+    fn test_multiple_matches() {
+        let resolve = |config: &StatuslineConfig, auth_token: &str| {
+            let ctx = en_ctx(config);
+            let providers: [&dyn Provider; 2] = [&FakeProvider, &StaleProvider];
+            let (segments, report) = resolve(&providers, "https://both.example.com", auth_token, None, &ctx);
+            (segments.iter().map(Segment::plain_text).collect::<Vec<_>>(), report)
+        };
+
+        // 默认只显示第一个有段的数据源
+        let mut config = StatuslineConfig::default();
+        let (segments, report) = resolve(&config, "token");
+        assert_eq!(segments, ["[ZAI] tok:37%", "[ZAI] mcp:12%"]);
+        assert_eq!(report.provider, Some("zhipu"));
+
+        // position 调整顺序
+        let first = ProviderOptions { position: Some(ProviderPosition::First), ..Default::default() };
+        config.providers.insert("stale".to_string(), first);
+        let (segments, report) = resolve(&config, "token");
+        assert_eq!(segments, ["[STALE] https://both.example.com"]);
+        assert_eq!(report.provider, Some("stale"));
+
+        // 排在前面的数据源没有段时继续匹配下一个
+        let (segments, report) = resolve(&config, "");
+        assert_eq!(segments, ["[ZAI] tok:37%", "[ZAI] mcp:12%"]);
+        assert_eq!(report.provider, Some("zhipu"));
+        assert_eq!(report.outcome, ProviderOutcome::Cached { segments: 2 });
+
+        // 关闭后全部显示，按顺序排列
+        config.first_match_only = Some(false);
+        let (segments, _) = resolve(&config, "token");
+        assert_eq!(segments, ["[STALE] https://both.example.com", "[ZAI] tok:37%", "[ZAI] mcp:12%"]);
+    }
+
+    #[cfg(feature = "network")]
+    #[test]
+    fn test_run_checks() {
+        let config = StatuslineConfig::default();
+        let ctx = en_ctx(&config);
+        let unauthorized = FailingProvider(ProviderError::Unauthorized);
+        let providers: [&dyn Provider; 3] = [&FakeProvider, &StaleProvider, &unauthorized];
+        let results = |base_url: &str, pin: Option<&str>| {
+            run_checks(&providers, base_url, "token", pin, &ctx)
+                .into_iter()
+                .map(|check| (check.provider, check.result))
+                .collect::<Vec<_>>()
+        };
+
+        // 不读缓存：有缓存的数据源也请求一次
+        assert_eq!(
+            results("https://failing.example.com", None),
+            [("zhipu", Ok(0)), ("stale", Ok(1)), ("failing", Err(ProviderError::Unauthorized))]
+        );
+        assert_eq!(results("https://other.example.com", None), [("zhipu", Ok(0)), ("stale", Ok(1))]);
+        assert_eq!(
+            results("https://other.example.com", Some("failing")),
+            [("failing", Err(ProviderError::Unauthorized))]
+        );
+
+        // 未开启的数据源即使被固定也不请求
+        let opt_in = |config: &StatuslineConfig| {
+            run_checks(&[&OptInProvider], "", "token", Some("opt_in"), &en_ctx(config))
+        };
+        assert!(opt_in(&config).is_empty());
+        let enabled = StatuslineConfig { openai_usage: true, ..Default::default() };
+        assert_eq!(opt_in(&enabled)[0].result, Ok(0));
+    }
+}
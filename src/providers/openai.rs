@@ -0,0 +1,195 @@
+use chrono::{DateTime, Datelike, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::cache::{is_fresh, read_cache, write_cache};
+use super::{http, AuthScheme, Fetch, Provider, ProviderContext, ProviderError};
+use crate::colors;
+use crate::i18n::Labels;
+use crate::render::Segment;
+
+pub(crate) const CACHE_FILE: &str = ".openai_usage_cache.json";
+
+/// 依次尝试的用量接口，第一个返回可识别结构的为准
+const USAGE_PATHS: [&str; 2] = ["/v1/usage", "/dashboard/billing/usage"];
+
+/// OpenAI 兼容网关的用量缓存；接口都不可用时 `spent_usd` 为 None，同样缓存以免每次渲染重试
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct OpenaiUsageCache {
+    pub spent_usd: Option<f64>,
+    pub timestamp: DateTime<Utc>,
+}
+
+pub struct OpenaiProvider;
+
+impl OpenaiProvider {
+    fn read_cache(&self, auth_token: &str) -> Option<OpenaiUsageCache> {
+        read_cache::<OpenaiUsageCache>(CACHE_FILE, auth_token)
+            .filter(|cache| is_fresh(cache.timestamp, chrono::Duration::minutes(5)))
+    }
+
+    /// 解析 OpenAI 风格的用量响应：`{"object": "list", "total_usage": 1234.5}`，金额单位为美分
+    fn parse_usage(&self, body: &str) -> Result<f64, ProviderError> {
+        let total_usage: f64 = http::field(&http::parse_body(body)?, "/total_usage")?;
+        if !total_usage.is_finite() || total_usage < 0.0 {
+            return Err(ProviderError::parse("/total_usage", "negative amount"));
+        }
+        Ok(total_usage / 100.0)
+    }
+
+    /// 按顺序请求各个用量接口（`base_domain` 为 `scheme://host[:port]`），都失败时返回最后一个接口的错误
+    async fn fetch_usage(&self, base_domain: &str, auth_token: &str) -> Result<f64, ProviderError> {
+        // 旧版 billing 接口要求日期范围，取本月至今
+        let today = Utc::now().date_naive();
+        let query = format!(
+            "?start_date={}&end_date={}",
+            today.with_day(1).unwrap_or(today),
+            today.succ_opt().unwrap_or(today)
+        );
+
+        let mut last_error = ProviderError::InvalidUrl;
+        for path in USAGE_PATHS {
+            let url = format!("{}{}{}", base_domain, path, query);
+            let request = http::get(&url, self.auth_scheme(), auth_token)?.header("Accept", "application/json");
+            match http::send(request).await.and_then(|body| self.parse_usage(&body)) {
+                Ok(spent) => return Ok(spent),
+                Err(error) => last_error = error,
+            }
+        }
+        Err(last_error)
+    }
+
+    pub(crate) fn render_segments(&self, usage: &OpenaiUsageCache) -> Vec<Segment> {
+        usage
+            .spent_usd
+            .map(|spent| Segment::new("openai_spend", colors::DIM, format!("${}", crate::format_cost(spent))))
+            .into_iter()
+            .collect()
+    }
+}
+
+impl Provider for OpenaiProvider {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    fn label(&self, labels: &'static Labels) -> &'static str {
+        labels.openai_tag
+    }
+
+    /// 兜底数据源：排在最后，任何非官方 API 的 baseURL 都会尝试
+    fn matches(&self, base_url: &str) -> bool {
+        !base_url.contains("api.anthropic.com")
+    }
+
+    fn enabled(&self, ctx: &ProviderContext) -> bool {
+        ctx.config.openai_usage
+    }
+
+    fn auth_scheme(&self) -> AuthScheme {
+        AuthScheme::Bearer
+    }
+
+    fn cache_updated_at(&self, auth_token: &str) -> Option<DateTime<Utc>> {
+        read_cache::<OpenaiUsageCache>(CACHE_FILE, auth_token).map(|cache| cache.timestamp)
+    }
+
+    fn cached_segments(&self, auth_token: &str, _ctx: &ProviderContext) -> Option<Vec<Segment>> {
+        Some(self.render_segments(&self.read_cache(auth_token)?))
+    }
+
+    fn fetch_segments<'a>(
+        &'a self,
+        base_url: &'a str,
+        auth_token: &'a str,
+        ctx: &'a ProviderContext,
+    ) -> Fetch<'a> {
+        Box::pin(async move {
+            let base_domain =
+                http::base_domain(base_url, &ctx.config.plain_http_hosts).ok_or(ProviderError::InvalidUrl)?;
+            let result = self.fetch_usage(&base_domain, auth_token).await;
+            // 接口都不可用时同样写入缓存，避免每次渲染都重试
+            let usage = OpenaiUsageCache {
+                spent_usd: result.as_ref().ok().copied(),
+                timestamp: Utc::now(),
+            };
+            write_cache(CACHE_FILE, auth_token, &usage);
+            result.map(|_| self.render_segments(&usage))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::http::mock_server;
+
+    /// 收到的请求路径（不含查询参数）
+    fn paths(requests: Vec<String>) -> Vec<String> {
+        let path = |request: &str| request.split_whitespace().nth(1)?.split('?').next().map(String::from);
+        requests.iter().filter_map(|request| path(request)).collect()
+    }
+
+    fn fetch(base_domain: &str) -> Result<f64, ProviderError> {
+        http::fetch_all(vec![OpenaiProvider.fetch_usage(base_domain, "sk-test")]).pop().flatten().unwrap()
+    }
+
+    #[test]
+    fn test_parse_usage() {
+        assert_eq!(OpenaiProvider.parse_usage(r#"{"object":"list","total_usage":1234.5}"#), Ok(12.345));
+        assert_eq!(OpenaiProvider.parse_usage(r#"{"total_usage":0}"#), Ok(0.0));
+        // 结构不符时不显示，错误中带出错的路径
+        let missing = OpenaiProvider.parse_usage(r#"{"data":[]}"#).unwrap_err();
+        assert_eq!(missing, ProviderError::parse("/total_usage", "missing"));
+        let negative = OpenaiProvider.parse_usage(r#"{"total_usage":-5}"#).unwrap_err();
+        assert_eq!(negative, ProviderError::parse("/total_usage", "negative amount"));
+        let html = OpenaiProvider.parse_usage("<html>").unwrap_err();
+        assert!(matches!(html, ProviderError::Parse { ref path, .. } if path.is_empty()), "{:?}", html);
+    }
+
+    #[test]
+    fn test_falls_back_to_billing_usage() {
+        let (address, server) = mock_server(vec![
+            (404, r#"{"error":"not found"}"#),
+            (200, r#"{"object":"list","total_usage":4217}"#),
+        ]);
+        assert_eq!(fetch(&address), Ok(42.17));
+        assert_eq!(paths(server.join().unwrap()), ["/v1/usage", "/dashboard/billing/usage"]);
+
+        let usage = OpenaiUsageCache {
+            spent_usd: Some(42.17),
+            timestamp: Utc::now(),
+        };
+        let segments = OpenaiProvider.render_segments(&usage);
+        assert_eq!(segments[0].plain_text(), "$42.17");
+    }
+
+    #[test]
+    fn test_unrecognized_shape_renders_nothing() {
+        let (address, server) = mock_server(vec![(200, r#"{"usage":[]}"#), (200, r#"{"total_usage":"n/a"}"#)]);
+        let error = fetch(&address).unwrap_err();
+        assert!(matches!(error, ProviderError::Parse { ref path, .. } if path == "/total_usage"), "{:?}", error);
+        assert_eq!(server.join().unwrap().len(), 2);
+
+        let (address, server) = mock_server(vec![(404, "{}"), (503, "{}")]);
+        assert_eq!(fetch(&address), Err(ProviderError::Status { status: 503 }));
+        assert_eq!(server.join().unwrap().len(), 2);
+
+        let usage = OpenaiUsageCache {
+            spent_usd: None,
+            timestamp: Utc::now(),
+        };
+        assert!(OpenaiProvider.render_segments(&usage).is_empty());
+    }
+
+    #[test]
+    fn test_matches_only_when_enabled() {
+        let mut config = crate::config::StatuslineConfig::default();
+        let ctx = crate::providers::en_ctx(&config);
+        assert!(OpenaiProvider.matches("https://gateway.example.com/v1"));
+        assert!(!OpenaiProvider.matches("https://api.anthropic.com"));
+        assert!(!OpenaiProvider.enabled(&ctx));
+
+        config.openai_usage = true;
+        assert!(OpenaiProvider.enabled(&crate::providers::en_ctx(&config)));
+    }
+}
@@ -0,0 +1,188 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use super::cache::{is_fresh, read_cache, write_cache};
+use super::{http, AuthScheme, Fetch, Provider, ProviderContext, ProviderError};
+use crate::i18n::Labels;
+use crate::render::Segment;
+
+pub(crate) const CACHE_FILE: &str = ".packycode_cache.json";
+
+/// 容错的数字解析：接受数字或数字字符串（部分 API 以字符串返回金额）
+fn deserialize_tolerant_f64<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Option::<serde_json::Value>::deserialize(deserializer)?;
+    Ok(match value {
+        Some(serde_json::Value::Number(n)) => n.as_f64(),
+        Some(serde_json::Value::String(s)) => s.trim().parse().ok(),
+        _ => None,
+    })
+}
+
+/// 格式化美元金额，整数不显示小数
+fn format_usd(amount: f64) -> String {
+    if amount.fract() == 0.0 {
+        format!("${:.0}", amount)
+    } else {
+        format!("${:.2}", amount)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PackycodeUsageCache {
+    pub daily_budget_usd: Option<f64>,
+    pub daily_spent_usd: Option<f64>,
+    pub timestamp: DateTime<Utc>,
+}
+
+pub struct PackycodeProvider;
+
+impl PackycodeProvider {
+    fn read_cache(&self, auth_token: &str) -> Option<PackycodeUsageCache> {
+        read_cache::<PackycodeUsageCache>(CACHE_FILE, auth_token)
+            .filter(|cache| is_fresh(cache.timestamp, chrono::Duration::minutes(1)))
+    }
+
+    /// 解析 `/api/backend/users/info` 的响应
+    fn parse_user_info(&self, body: &str) -> Result<PackycodeUsageCache, ProviderError> {
+        #[derive(Deserialize)]
+        struct ApiResponse {
+            #[serde(default, deserialize_with = "deserialize_tolerant_f64")]
+            daily_budget_usd: Option<f64>,
+            #[serde(default, deserialize_with = "deserialize_tolerant_f64")]
+            daily_spent_usd: Option<f64>,
+        }
+
+        let api_response: ApiResponse = http::field(&http::parse_body(body)?, "")?;
+        Ok(PackycodeUsageCache {
+            daily_budget_usd: api_response.daily_budget_usd,
+            daily_spent_usd: api_response.daily_spent_usd,
+            timestamp: Utc::now(),
+        })
+    }
+
+    async fn fetch_usage(
+        &self,
+        base_url: &str,
+        auth_token: &str,
+        plain_http_hosts: &[String],
+    ) -> Result<PackycodeUsageCache, ProviderError> {
+        let base_domain = http::base_domain(base_url, plain_http_hosts).ok_or(ProviderError::InvalidUrl)?;
+        let api_url = format!("{}/api/backend/users/info", base_domain);
+
+        let request = http::get(&api_url, self.auth_scheme(), auth_token)?.header("Accept", "application/json");
+        let cache = self.parse_user_info(&http::send(request).await?)?;
+        write_cache(CACHE_FILE, auth_token, &cache);
+        Ok(cache)
+    }
+
+
+    pub(crate) fn render_segments(&self, usage: &PackycodeUsageCache, ctx: &ProviderContext) -> Vec<Segment> {
+        let mut segments = Vec::new();
+
+        if let (Some(budget), Some(spent)) = (usage.daily_budget_usd, usage.daily_spent_usd) {
+            let used_pct = if budget > 0.0 { spent / budget * 100.0 } else { 0.0 };
+            segments.push(Segment::graded(
+                "packycode_quota",
+                ctx.quota_severity(used_pct),
+                format!("{}/{} ({:.0}%)", format_usd(spent), format_usd(budget), used_pct),
+            ));
+        }
+
+        segments
+    }
+}
+
+impl Provider for PackycodeProvider {
+    fn name(&self) -> &'static str {
+        "packycode"
+    }
+
+    fn label(&self, labels: &'static Labels) -> &'static str {
+        labels.packycode_tag
+    }
+
+    fn matches(&self, base_url: &str) -> bool {
+        base_url.contains("packycode.com")
+    }
+
+    fn auth_scheme(&self) -> AuthScheme {
+        AuthScheme::Bearer
+    }
+
+    fn cache_updated_at(&self, auth_token: &str) -> Option<DateTime<Utc>> {
+        read_cache::<PackycodeUsageCache>(CACHE_FILE, auth_token).map(|cache| cache.timestamp)
+    }
+
+    fn cached_segments(&self, auth_token: &str, ctx: &ProviderContext) -> Option<Vec<Segment>> {
+        Some(self.render_segments(&self.read_cache(auth_token)?, ctx))
+    }
+
+    fn fetch_segments<'a>(
+        &'a self,
+        base_url: &'a str,
+        auth_token: &'a str,
+        ctx: &'a ProviderContext,
+    ) -> Fetch<'a> {
+        Box::pin(async move {
+            let usage = self.fetch_usage(base_url, auth_token, &ctx.config.plain_http_hosts).await?;
+            Ok(self.render_segments(&usage, ctx))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colors;
+    use crate::config::StatuslineConfig;
+    use crate::providers::en_ctx;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn fixture(name: &str) -> String {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join("providers")
+            .join(name);
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn test_packycode_parse_string_numbers() {
+        let usage = PackycodeProvider
+            .parse_user_info(&fixture("packycode_user_info.json"))
+            .unwrap();
+        assert_eq!(usage.daily_budget_usd, Some(10.0));
+        assert_eq!(usage.daily_spent_usd, Some(3.2));
+
+        let config = StatuslineConfig::default();
+        let segments = PackycodeProvider.render_segments(&usage, &en_ctx(&config));
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].plain_text(), "$3.20/$10 (32%)");
+        assert_eq!(segments[0].color(), colors::GREEN);
+    }
+
+    #[test]
+    fn test_packycode_tolerates_missing_and_invalid_fields() {
+        let usage = PackycodeProvider
+            .parse_user_info(r#"{"daily_budget_usd": 20, "daily_spent_usd": "n/a"}"#)
+            .unwrap();
+        assert_eq!(usage.daily_budget_usd, Some(20.0));
+        assert_eq!(usage.daily_spent_usd, None);
+        let config = StatuslineConfig::default();
+        assert!(PackycodeProvider.render_segments(&usage, &en_ctx(&config)).is_empty());
+
+        let error = PackycodeProvider.parse_user_info("<html>").unwrap_err();
+        assert!(matches!(error, ProviderError::Parse { ref path, .. } if path.is_empty()), "{:?}", error);
+    }
+
+    #[test]
+    fn test_packycode_matches() {
+        assert!(PackycodeProvider.matches("https://api.packycode.com/v1"));
+        assert!(!PackycodeProvider.matches("https://api.z.ai/api/anthropic"));
+    }
+}
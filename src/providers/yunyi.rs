@@ -0,0 +1,492 @@
+use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::cache::{is_fresh, read_cache, write_cache};
+use super::{http, AuthScheme, Fetch, Provider, ProviderContext, ProviderError};
+use crate::colors;
+use crate::i18n::Labels;
+use crate::render::Segment;
+
+pub(crate) const CACHE_FILE: &str = ".yunyi_cache.json";
+
+/// 账号段中账号的最大显示宽度
+const ACCOUNT_WIDTH: usize = 24;
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct YunyiUsageCache {
+    pub daily_used: Option<u64>,
+    pub daily_quota: Option<u64>,
+    pub daily_spent: Option<u64>,
+    pub daily_total_spent: Option<u64>,
+    pub expires_at: Option<String>,
+    pub request_count: Option<u64>,
+    pub daily_request_count: Option<u64>,
+    // 额度包字段
+    pub quota_pack: Option<u64>,
+    pub quota_pack_remaining: Option<u64>,
+    pub quota_pack_expires_at: Option<String>,
+    /// 接口返回的账号（邮箱或用户名），旧缓存中没有
+    pub account: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+pub struct YunyiProvider;
+
+impl YunyiProvider {
+    fn read_cache(&self, auth_token: &str) -> Option<YunyiUsageCache> {
+        read_cache::<YunyiUsageCache>(CACHE_FILE, auth_token)
+            .filter(|cache| is_fresh(cache.timestamp, chrono::Duration::minutes(1)))
+    }
+
+    async fn fetch_usage(&self, auth_token: &str) -> Result<YunyiUsageCache, ProviderError> {
+        let api_url = "https://yunyi.cfd/user/api/v1/me";
+
+        let request = http::get(api_url, self.auth_scheme(), auth_token)?
+            .header("Accept", "application/json")
+            .header("Accept-Language", "en,zh-CN;q=0.9,zh;q=0.8");
+        let body = http::parse_body(&http::send(request).await?)?;
+
+        #[derive(Deserialize)]
+        struct ApiQuota {
+            daily_quota: Option<u64>,
+            daily_spent: Option<u64>,
+            daily_used: Option<u64>,
+            daily_total_spent: Option<u64>,
+            // 额度包字段
+            quota_pack: Option<u64>,
+            quota_pack_remaining: Option<u64>,
+            quota_pack_expires_at: Option<String>,
+        }
+
+        #[derive(Deserialize)]
+        struct ApiUsage {
+            request_count: Option<u64>,
+            daily_request_count: Option<u64>,
+            daily_spent: Option<u64>,
+        }
+
+        #[derive(Deserialize)]
+        struct ApiTimestamps {
+            expires_at: Option<String>,
+        }
+
+        let quota: ApiQuota = http::field(&body, "/quota")?;
+        let usage: ApiUsage = http::field(&body, "/usage")?;
+        let timestamps: ApiTimestamps = http::field(&body, "/timestamps")?;
+
+        let cache = YunyiUsageCache {
+            daily_used: quota.daily_used,
+            daily_quota: quota.daily_quota,
+            daily_spent: quota.daily_spent.or(usage.daily_spent),
+            daily_total_spent: quota.daily_total_spent,
+            expires_at: timestamps.expires_at,
+            request_count: usage.request_count,
+            daily_request_count: usage.daily_request_count,
+            quota_pack: quota.quota_pack,
+            quota_pack_remaining: quota.quota_pack_remaining,
+            quota_pack_expires_at: quota.quota_pack_expires_at,
+            account: parse_account(&body),
+            timestamp: Utc::now(),
+        };
+
+        write_cache(CACHE_FILE, auth_token, &cache);
+        Ok(cache)
+    }
+
+}
+
+impl Provider for YunyiProvider {
+    fn name(&self) -> &'static str {
+        "yunyi"
+    }
+
+    fn label(&self, labels: &'static Labels) -> &'static str {
+        labels.yunyi_tag
+    }
+
+    fn matches(&self, base_url: &str) -> bool {
+        base_url.contains("yunyi.rdzhvip.com") || base_url.contains("yunyi.cfd")
+    }
+
+    fn auth_scheme(&self) -> AuthScheme {
+        AuthScheme::Bearer
+    }
+
+    fn cache_updated_at(&self, auth_token: &str) -> Option<DateTime<Utc>> {
+        read_cache::<YunyiUsageCache>(CACHE_FILE, auth_token).map(|cache| cache.timestamp)
+    }
+
+    fn cached_segments(&self, auth_token: &str, ctx: &ProviderContext) -> Option<Vec<Segment>> {
+        Some(self.render_segments(&self.read_cache(auth_token)?, Utc::now(), ctx))
+    }
+
+    fn fetch_segments<'a>(
+        &'a self,
+        _base_url: &'a str,
+        auth_token: &'a str,
+        ctx: &'a ProviderContext,
+    ) -> Fetch<'a> {
+        Box::pin(async move {
+            let usage = self.fetch_usage(auth_token).await?;
+            Ok(self.render_segments(&usage, Utc::now(), ctx))
+        })
+    }
+}
+
+/// `/me` 响应中的账号：依次取邮箱和用户名，都没有时为 None
+fn parse_account(body: &Value) -> Option<String> {
+    ["/email", "/user/email", "/username", "/user/username"]
+        .iter()
+        .filter_map(|pointer| body.pointer(pointer)?.as_str())
+        .map(str::trim)
+        .find(|account| !account.is_empty())
+        .map(String::from)
+}
+
+/// 解析过期时间：依次尝试 RFC3339、RFC2822 和不带时区的 `YYYY-MM-DD HH:MM:SS`
+///
+/// 不带时区的时间按 `offset`（配置的时区）解释，未配置时按本地时区，与显示时使用的时区一致。
+fn parse_expiry(s: &str, offset: Option<FixedOffset>) -> Option<DateTime<Utc>> {
+    let s = s.trim();
+    chrono::DateTime::parse_from_rfc3339(s)
+        .or_else(|_| chrono::DateTime::parse_from_rfc2822(s))
+        .map(|dt| dt.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            let naive = chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").ok()?;
+            let local = match offset {
+                Some(offset) => offset.from_local_datetime(&naive).earliest()?.with_timezone(&Utc),
+                None => chrono::Local.from_local_datetime(&naive).earliest()?.with_timezone(&Utc),
+            };
+            Some(local)
+        })
+}
+
+impl YunyiProvider {
+    pub(crate) fn render_segments(
+        &self,
+        usage: &YunyiUsageCache,
+        now: DateTime<Utc>,
+        ctx: &ProviderContext,
+    ) -> Vec<Segment> {
+        let labels = ctx.labels;
+        let mut segments = Vec::new();
+
+        // 开启 `show_account` 时先显示当前使用的账号，过长时截断中间部分
+        let show_account = ctx.options(self.name()).is_some_and(|options| options.show_account);
+        if let Some(account) = usage.account.as_deref().filter(|_| show_account) {
+            let account = crate::text::truncate_middle(account, ACCOUNT_WIDTH);
+            let text = format!("{}{}", labels.account, account);
+            segments.push(Segment::new("yunyi_account", colors::DIM, text));
+        }
+
+        // 计算总剩余额度 = 每日剩余 + 额度包剩余
+        if let (Some(quota), Some(total_spent)) = (usage.daily_quota, usage.daily_total_spent) {
+            let daily_remaining = quota.saturating_sub(total_spent);
+            let pack_remaining = usage.quota_pack_remaining.unwrap_or(0);
+            let total_remaining = daily_remaining + pack_remaining;
+
+            // 计算总额度用于百分比
+            let total_quota = quota + usage.quota_pack.unwrap_or(0);
+            let remaining_pct = if total_quota > 0 {
+                (total_remaining as f64 / total_quota as f64) * 100.0
+            } else {
+                0.0
+            };
+            // 剩余 40% 及以下为警告，20% 及以下为严重
+            let severity = ctx.quota_severity(100.0 - remaining_pct);
+            let remaining_usd = total_remaining as f64 / 100.0;
+            let daily_remaining_usd = daily_remaining as f64 / 100.0;
+            let quota_detail = if pack_remaining > 0 {
+                let pack_remaining_usd = pack_remaining as f64 / 100.0;
+                format!(
+                    "({}${:.2}+{}${:.2})",
+                    labels.yunyi_daily, daily_remaining_usd, labels.yunyi_pack, pack_remaining_usd
+                )
+            } else {
+                String::new()
+            };
+            // 百分比单独一段文本，开启 `provider_bars` 时画成进度条，金额保留
+            let pct = format!("{}{:.0}%", labels.yunyi_remaining, remaining_pct);
+            let segment = Segment::graded("yunyi_quota", severity, pct)
+                .with_graded(severity, format!(" ${:.2}{}", remaining_usd, quota_detail))
+                .bar_capable(remaining_pct);
+            segments.push(segment);
+        }
+
+        // 过期时间合并显示：未过期时显示剩余时间，已过期时显示时刻（使用配置的时区，未配置时使用本地时区）；
+        // 无法解析的时间不显示
+        let offset = ctx.config.timezone_offset();
+        let format_time = |s: &str| -> Option<String> {
+            let dt = parse_expiry(s, offset)?;
+            if let Ok(remaining) = dt.signed_duration_since(now).to_std() {
+                let remaining = crate::format_duration(remaining.as_millis() as u64);
+                return Some(format!("{}{}", labels.yunyi_in, remaining));
+            }
+            Some(match offset {
+                Some(offset) => dt.with_timezone(&offset).format("%m-%d %H:%M").to_string(),
+                None => dt.with_timezone(&chrono::Local).format("%m-%d %H:%M").to_string(),
+            })
+        };
+
+        let exp_str = usage.expires_at.as_deref().and_then(format_time);
+        let pack_exp_str = usage.quota_pack_expires_at.as_deref().and_then(format_time);
+
+        let expiry = match (exp_str, pack_exp_str) {
+            (Some(exp), Some(pack_exp)) => Some(format!(
+                "{}:{} {}:{}",
+                labels.yunyi_expires, exp, labels.yunyi_pack, pack_exp
+            )),
+            (Some(exp), None) => Some(format!("{}:{}", labels.yunyi_expires, exp)),
+            (None, Some(pack_exp)) => Some(format!("{}:{}", labels.yunyi_pack_expires, pack_exp)),
+            (None, None) => None,
+        };
+        if let Some(expiry) = expiry {
+            segments.push(Segment::new("yunyi_expiry", colors::DIM, expiry));
+        }
+
+        segments
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ProviderOptions, StatuslineConfig};
+    use crate::providers::en_ctx;
+
+    fn time(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_auth_scheme() {
+        assert_eq!(YunyiProvider.auth_scheme(), AuthScheme::Bearer);
+    }
+
+    #[test]
+    fn test_parse_expiry_formats() {
+        let expected = time("2026-01-10T04:30:00Z");
+        let utc = FixedOffset::east_opt(0);
+        assert_eq!(parse_expiry("2026-01-10T04:30:00Z", utc), Some(expected));
+        assert_eq!(parse_expiry("2026-01-10T12:30:00+08:00", utc), Some(expected));
+        assert_eq!(parse_expiry("Sat, 10 Jan 2026 04:30:00 +0000", utc), Some(expected));
+        assert_eq!(parse_expiry("2026-01-10 04:30:00", utc), Some(expected));
+        assert_eq!(parse_expiry("next tuesday", utc), None);
+        assert_eq!(parse_expiry("", utc), None);
+    }
+
+    #[test]
+    fn test_parse_naive_expiry_in_configured_timezone() {
+        let expected = time("2026-01-10T04:30:00Z");
+        // 不带时区的时间按配置的时区解释，带时区的不受影响
+        assert_eq!(parse_expiry("2026-01-10 12:30:00", FixedOffset::east_opt(8 * 3600)), Some(expected));
+        assert_eq!(parse_expiry("2026-01-10T04:30:00Z", FixedOffset::east_opt(8 * 3600)), Some(expected));
+        // 未配置时按本地时区
+        let local = chrono::Local.from_local_datetime(&expected.naive_utc()).earliest().unwrap();
+        assert_eq!(parse_expiry("2026-01-10 04:30:00", None), Some(local.with_timezone(&Utc)));
+
+        // 倒计时与显示的时刻使用同一时区，不会差出时区偏移
+        let usage = YunyiUsageCache {
+            daily_used: None,
+            daily_quota: None,
+            daily_spent: None,
+            daily_total_spent: None,
+            expires_at: Some("2026-01-10 12:30:00".to_string()),
+            request_count: None,
+            daily_request_count: None,
+            quota_pack: None,
+            quota_pack_remaining: None,
+            quota_pack_expires_at: None,
+            account: None,
+            timestamp: Utc::now(),
+        };
+        let config = StatuslineConfig {
+            timezone: Some("+08:00".to_string()),
+            ..Default::default()
+        };
+        let now = time("2026-01-10T02:30:00Z");
+        let segments = YunyiProvider.render_segments(&usage, now, &en_ctx(&config));
+        assert_eq!(segments[0].plain_text(), "Exp:in 2h0m");
+    }
+
+    #[test]
+    fn test_unparsable_expiry_is_omitted() {
+        let usage = |expires_at: &str, pack_expires_at: Option<&str>| YunyiUsageCache {
+            daily_used: None,
+            daily_quota: None,
+            daily_spent: None,
+            daily_total_spent: None,
+            expires_at: Some(expires_at.to_string()),
+            request_count: None,
+            daily_request_count: None,
+            quota_pack: None,
+            quota_pack_remaining: None,
+            quota_pack_expires_at: pack_expires_at.map(String::from),
+            account: None,
+            timestamp: Utc::now(),
+        };
+        let config = StatuslineConfig {
+            timezone: Some("UTC".to_string()),
+            ..Default::default()
+        };
+        let ctx = en_ctx(&config);
+        let now = time("2026-03-01T00:00:00Z");
+
+        assert!(YunyiProvider.render_segments(&usage("soon", None), now, &ctx).is_empty());
+        // 只有一项能解析时只显示该项
+        let usage = usage("soon", Some("2026-02-01 00:00:00"));
+        let segments = YunyiProvider.render_segments(&usage, now, &ctx);
+        assert_eq!(segments[0].plain_text(), "PackExp:02-01 00:00");
+    }
+
+    #[test]
+    fn test_render_without_pack() {
+        let usage = YunyiUsageCache {
+            daily_used: None,
+            daily_quota: Some(1000),
+            daily_spent: None,
+            daily_total_spent: Some(900),
+            expires_at: Some("2026-01-10T04:30:00Z".to_string()),
+            request_count: None,
+            daily_request_count: None,
+            quota_pack: None,
+            quota_pack_remaining: None,
+            quota_pack_expires_at: None,
+            account: None,
+            timestamp: Utc::now(),
+        };
+        let mut config = StatuslineConfig {
+            timezone: Some("+08:00".to_string()),
+            ..Default::default()
+        };
+        // 已经过期，显示过期时刻
+        let now = time("2026-03-01T00:00:00Z");
+        let segments = YunyiProvider.render_segments(&usage, now, &en_ctx(&config));
+        assert_eq!(segments[0].plain_text(), "Rem:10% $1.00");
+        assert_eq!(segments[0].color(), colors::RED);
+        assert_eq!(segments[1].plain_text(), "Exp:01-10 12:30");
+
+        config.timezone = Some("-05:00".to_string());
+        let segments = YunyiProvider.render_segments(&usage, now, &en_ctx(&config));
+        assert_eq!(segments[1].plain_text(), "Exp:01-09 23:30");
+
+        // 未配置时按本地时区显示
+        config.timezone = None;
+        let local = chrono::DateTime::parse_from_rfc3339("2026-01-10T04:30:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Local);
+        let segments = YunyiProvider.render_segments(&usage, now, &en_ctx(&config));
+        assert_eq!(segments[1].plain_text(), format!("Exp:{}", local.format("%m-%d %H:%M")));
+    }
+
+    #[test]
+    fn test_provider_bars() {
+        let usage = YunyiUsageCache {
+            daily_used: None,
+            daily_quota: Some(1000),
+            daily_spent: None,
+            daily_total_spent: Some(620),
+            expires_at: None,
+            request_count: None,
+            daily_request_count: None,
+            quota_pack: None,
+            quota_pack_remaining: None,
+            quota_pack_expires_at: None,
+            account: None,
+            timestamp: Utc::now(),
+        };
+        let mut config = StatuslineConfig::default();
+        let render = |config: &StatuslineConfig| {
+            let ctx = en_ctx(config);
+            let metrics = YunyiProvider.render_segments(&usage, Utc::now(), &ctx);
+            ctx.decorate(&YunyiProvider, metrics).iter().map(Segment::plain_text).collect::<Vec<_>>()
+        };
+        // 默认显示百分比
+        assert_eq!(render(&config), ["[YUNYI] Rem:38% $3.80"]);
+
+        config.provider_bars = true;
+        assert_eq!(render(&config), ["[YUNYI] ▮▮▯▯▯ $3.80"]);
+    }
+
+    #[test]
+    fn test_render_countdown() {
+        let usage = YunyiUsageCache {
+            daily_used: None,
+            daily_quota: None,
+            daily_spent: None,
+            daily_total_spent: None,
+            expires_at: Some("2026-01-10T04:30:00Z".to_string()),
+            request_count: None,
+            daily_request_count: None,
+            quota_pack: None,
+            quota_pack_remaining: None,
+            quota_pack_expires_at: Some("2026-02-01T00:00:00Z".to_string()),
+            account: None,
+            timestamp: Utc::now(),
+        };
+        let config = StatuslineConfig {
+            timezone: Some("UTC".to_string()),
+            ..Default::default()
+        };
+        let render = |now: &str| {
+            YunyiProvider.render_segments(&usage, time(now), &en_ctx(&config))[0].plain_text()
+        };
+
+        assert_eq!(render("2026-01-10T01:10:00Z"), "Exp:in 3h20m pack:in 21d22h");
+        assert_eq!(render("2026-01-10T04:29:30Z"), "Exp:in 30s pack:in 21d19h");
+        // 已过期的一项显示过期时刻
+        assert_eq!(render("2026-01-10T04:30:01Z"), "Exp:01-10 04:30 pack:in 21d19h");
+        assert_eq!(render("2026-03-01T00:00:00Z"), "Exp:01-10 04:30 pack:02-01 00:00");
+    }
+
+    #[test]
+    fn test_parse_account() {
+        let body = |json: &str| serde_json::from_str::<Value>(json).unwrap();
+        let account = parse_account(&body(r#"{"email": "dev@example.com"}"#));
+        assert_eq!(account.as_deref(), Some("dev@example.com"));
+        assert_eq!(parse_account(&body(r#"{"user": {"username": "dev"}}"#)).as_deref(), Some("dev"));
+        assert_eq!(parse_account(&body(r#"{"email": " ", "username": "dev"}"#)).as_deref(), Some("dev"));
+        assert_eq!(parse_account(&body(r#"{"quota": {}}"#)), None);
+    }
+
+    #[test]
+    fn test_account_segment() {
+        let usage = YunyiUsageCache {
+            daily_used: None,
+            daily_quota: Some(1000),
+            daily_spent: None,
+            daily_total_spent: Some(100),
+            expires_at: None,
+            request_count: None,
+            daily_request_count: None,
+            quota_pack: None,
+            quota_pack_remaining: None,
+            quota_pack_expires_at: None,
+            account: Some("dev@example.com".to_string()),
+            timestamp: Utc::now(),
+        };
+        let now = time("2026-03-01T00:00:00Z");
+        let mut config = StatuslineConfig::default();
+        // 默认不显示
+        let segments = YunyiProvider.render_segments(&usage, now, &en_ctx(&config));
+        assert_eq!(segments[0].plain_text(), "Rem:90% $9.00");
+
+        config.providers.insert(
+            "yunyi".to_string(),
+            ProviderOptions {
+                show_account: true,
+                ..Default::default()
+            },
+        );
+        let segments = YunyiProvider.render_segments(&usage, now, &en_ctx(&config));
+        assert_eq!(segments[0].plain_text(), "acct:dev@example.com");
+        assert_eq!(segments[1].plain_text(), "Rem:90% $9.00");
+
+        // 没有账号时跳过
+        let usage = YunyiUsageCache { account: None, ..usage };
+        let segments = YunyiProvider.render_segments(&usage, now, &en_ctx(&config));
+        assert_eq!(segments.len(), 1);
+    }
+}
@@ -0,0 +1,360 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::cache::{is_fresh, read_cache, write_cache};
+use super::{http, AuthScheme, Fetch, Provider, ProviderContext, ProviderError};
+use crate::colors;
+use crate::config::{QuotaDisplay, TokenFormat};
+use crate::i18n::Labels;
+use crate::render::Segment;
+
+pub(crate) const CACHE_FILE: &str = ".zhipu_cache.json";
+
+/// 质普配额限制信息
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct QuotaLimit {
+    #[serde(rename = "type")]
+    pub limit_type: String,
+    pub percentage: f64,
+    #[serde(rename = "currentValue")]
+    pub current_value: Option<u64>,
+    pub usage: Option<u64>,
+}
+
+/// 某一时刻的 token 额度使用率
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub struct QuotaSample {
+    pub percentage: f64,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// 质普使用情况缓存
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ZhipuUsageCache {
+    pub token_limit: Option<QuotaLimit>,
+    pub mcp_limit: Option<QuotaLimit>,
+    pub timestamp: DateTime<Utc>,
+    /// 上一次请求时的 token 额度使用率，用于判断是否上升过快；刷新缓存时由旧缓存得到
+    pub previous_token: Option<QuotaSample>,
+    /// 再往前一次请求时的 token 额度使用率，上一次距今不足采样间隔时用它计算
+    pub earlier_token: Option<QuotaSample>,
+}
+
+impl ZhipuUsageCache {
+    /// 本次请求时的 token 额度使用率
+    fn token_sample(&self) -> Option<QuotaSample> {
+        self.token_limit.as_ref().map(|limit| QuotaSample {
+            percentage: limit.percentage,
+            timestamp: self.timestamp,
+        })
+    }
+
+    /// 本次使用率每 10 分钟上升的百分点，用距今至少 `MIN_RISE_INTERVAL` 的最近一次采样计算
+    fn token_rise(&self) -> Option<f64> {
+        let current = self.token_sample()?;
+        let previous = [self.previous_token, self.earlier_token].into_iter().flatten().find(|previous| {
+            current.timestamp.signed_duration_since(previous.timestamp) >= MIN_RISE_INTERVAL
+        })?;
+        rise_per_10m(&previous, &current)
+    }
+}
+
+/// 默认的上升速度阈值：每 10 分钟 10 个百分点
+const DEFAULT_RISE_ALERT: f64 = 10.0;
+
+/// 计算上升速度的最短采样间隔；缓存每 3 分钟刷新，间隔太短时少量波动会被放大成告警
+const MIN_RISE_INTERVAL: chrono::Duration = chrono::Duration::minutes(5);
+
+pub struct ZhipuProvider;
+
+impl ZhipuProvider {
+    fn read_cache(&self, auth_token: &str) -> Option<ZhipuUsageCache> {
+        // 检查缓存是否过期（3分钟）
+        read_cache::<ZhipuUsageCache>(CACHE_FILE, auth_token)
+            .filter(|cache| is_fresh(cache.timestamp, chrono::Duration::minutes(3)))
+    }
+
+    async fn fetch_usage(
+        &self,
+        base_url: &str,
+        auth_token: &str,
+        plain_http_hosts: &[String],
+    ) -> Result<ZhipuUsageCache, ProviderError> {
+        let base_domain = http::base_domain(base_url, plain_http_hosts).ok_or(ProviderError::InvalidUrl)?;
+        let quota_url = format!("{}/api/monitor/usage/quota/limit", base_domain);
+
+        let request = http::get(&quota_url, self.auth_scheme(), auth_token)?
+            .header("Accept-Language", "en-US,en")
+            .header("Content-Type", "application/json");
+        let body = http::send(request).await?;
+        let limits: Vec<QuotaLimit> = http::items(&http::parse_body(&body)?, "/data/limits")?;
+
+        let mut token_limit = None;
+        let mut mcp_limit = None;
+
+        for limit in limits {
+            match limit.limit_type.as_str() {
+                "TOKENS_LIMIT" => token_limit = Some(limit),
+                "TIME_LIMIT" => mcp_limit = Some(limit),
+                _ => {}
+            }
+        }
+
+        // 旧缓存中的采样依次后移，不论是否过期，最多保留两次
+        let (previous_token, earlier_token) = read_cache::<ZhipuUsageCache>(CACHE_FILE, auth_token)
+            .map(|old| (old.token_sample(), old.previous_token))
+            .unwrap_or_default();
+        let cache = ZhipuUsageCache {
+            token_limit,
+            mcp_limit,
+            timestamp: Utc::now(),
+            previous_token,
+            earlier_token,
+        };
+
+        write_cache(CACHE_FILE, auth_token, &cache);
+        Ok(cache)
+    }
+}
+
+impl Provider for ZhipuProvider {
+    fn name(&self) -> &'static str {
+        "zhipu"
+    }
+
+    fn label(&self, labels: &'static Labels) -> &'static str {
+        labels.zhipu_tag
+    }
+
+    fn matches(&self, base_url: &str) -> bool {
+        base_url.contains("bigmodel.cn") || base_url.contains("z.ai")
+    }
+
+    fn auth_scheme(&self) -> AuthScheme {
+        AuthScheme::Raw
+    }
+
+    fn cache_updated_at(&self, auth_token: &str) -> Option<DateTime<Utc>> {
+        read_cache::<ZhipuUsageCache>(CACHE_FILE, auth_token).map(|cache| cache.timestamp)
+    }
+
+    fn cached_segments(&self, auth_token: &str, ctx: &ProviderContext) -> Option<Vec<Segment>> {
+        Some(self.render_segments(&self.read_cache(auth_token)?, ctx))
+    }
+
+    fn fetch_segments<'a>(
+        &'a self,
+        base_url: &'a str,
+        auth_token: &'a str,
+        ctx: &'a ProviderContext,
+    ) -> Fetch<'a> {
+        Box::pin(async move {
+            let usage = self.fetch_usage(base_url, auth_token, &ctx.config.plain_http_hosts).await?;
+            Ok(self.render_segments(&usage, ctx))
+        })
+    }
+}
+
+impl ZhipuProvider {
+    pub(crate) fn render_segments(&self, zhipu_usage: &ZhipuUsageCache, ctx: &ProviderContext) -> Vec<Segment> {
+        let labels = ctx.labels;
+        let display = ctx.options(self.name()).map(|options| options.quota_display).unwrap_or_default();
+        let tokens = ctx.config.token_format;
+        let mut segments = Vec::new();
+
+        if let Some(ref token_limit) = zhipu_usage.token_limit {
+            let severity = ctx.quota_severity(token_limit.percentage);
+            let mut segment = Segment::graded(
+                "zhipu_token",
+                severity,
+                format!("{}:{}", labels.zhipu_token, format_limit(token_limit, display, tokens)),
+            );
+            // 使用率上升过快（如智能体陷入循环）时加上粗体的 ↑↑
+            let threshold = ctx
+                .options(self.name())
+                .and_then(|options| options.rise_alert)
+                .unwrap_or(DEFAULT_RISE_ALERT);
+            if threshold > 0.0 && zhipu_usage.token_rise().is_some_and(|rise| rise >= threshold) {
+                segment = segment.with(colors::BOLD, " ↑↑");
+            }
+            segments.push(segment);
+        }
+
+        if let Some(ref mcp_limit) = zhipu_usage.mcp_limit {
+            let severity = ctx.quota_severity(mcp_limit.percentage);
+            segments.push(Segment::graded(
+                "zhipu_mcp",
+                severity,
+                format!("{}:{}", labels.zhipu_mcp, format_limit(mcp_limit, display, tokens)),
+            ));
+        }
+
+        segments
+    }
+}
+
+/// 两次采样之间使用率每 10 分钟上升的百分点；间隔无效或使用率下降（额度窗口重置）时为 None
+fn rise_per_10m(previous: &QuotaSample, current: &QuotaSample) -> Option<f64> {
+    let elapsed = current.timestamp.signed_duration_since(previous.timestamp).num_seconds();
+    let delta = current.percentage - previous.percentage;
+    (elapsed > 0 && delta > 0.0).then(|| delta * 600.0 / elapsed as f64)
+}
+
+/// 按显示方式格式化一项额度；`currentValue`（已用量）或 `usage`（总量）缺失时只显示使用率
+fn format_limit(limit: &QuotaLimit, display: QuotaDisplay, tokens: TokenFormat) -> String {
+    let percentage = format!("{:.0}%", limit.percentage);
+    let absolute = match (limit.current_value, limit.usage) {
+        (Some(used), Some(total)) => {
+            format!("{}/{}", crate::format_tokens(used, tokens), crate::format_tokens(total, tokens))
+        }
+        _ => return percentage,
+    };
+    match display {
+        QuotaDisplay::Percent => percentage,
+        QuotaDisplay::Absolute => absolute,
+        QuotaDisplay::Both => format!("{} ({})", absolute, percentage),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::StatuslineConfig;
+    use crate::providers::en_ctx;
+
+    #[test]
+    fn test_auth_scheme() {
+        assert_eq!(ZhipuProvider.auth_scheme(), AuthScheme::Raw);
+    }
+
+    #[test]
+    fn test_label_override() {
+        let mut config = StatuslineConfig::default();
+        config.providers.insert(
+            "zhipu".to_string(),
+            crate::config::ProviderOptions {
+                label: Some("Z.ai".to_string()),
+                ..Default::default()
+            },
+        );
+        let usage = ZhipuUsageCache {
+            token_limit: Some(QuotaLimit {
+                limit_type: "TOKENS_LIMIT".to_string(),
+                percentage: 85.0,
+                current_value: None,
+                usage: None,
+            }),
+            mcp_limit: None,
+            timestamp: Utc::now(),
+            previous_token: None,
+            earlier_token: None,
+        };
+        let ctx = en_ctx(&config);
+        let segments = ctx.decorate(&ZhipuProvider, ZhipuProvider.render_segments(&usage, &ctx));
+        assert_eq!(segments[0].plain_text(), "[Z.ai] Token(5h):85%");
+        assert_eq!(segments[0].color(), colors::RED);
+    }
+
+    #[test]
+    fn test_quota_display() {
+        let limit = |limit_type: &str, current_value: Option<u64>, usage: Option<u64>| QuotaLimit {
+            limit_type: limit_type.to_string(),
+            percentage: 24.0,
+            current_value,
+            usage,
+        };
+        let usage = ZhipuUsageCache {
+            token_limit: Some(limit("TOKENS_LIMIT", Some(1_200), Some(5_000))),
+            // 没有用量数据时只能显示使用率
+            mcp_limit: Some(limit("TIME_LIMIT", Some(30), None)),
+            timestamp: Utc::now(),
+            previous_token: None,
+            earlier_token: None,
+        };
+        let render = |display: QuotaDisplay| -> Vec<String> {
+            let mut config = StatuslineConfig::default();
+            config.providers.insert(
+                "zhipu".to_string(),
+                crate::config::ProviderOptions {
+                    quota_display: display,
+                    ..Default::default()
+                },
+            );
+            let segments = ZhipuProvider.render_segments(&usage, &en_ctx(&config));
+            segments.iter().map(Segment::plain_text).collect()
+        };
+
+        assert_eq!(render(QuotaDisplay::Percent), ["Token(5h):24%", "MCP(1mo):24%"]);
+        assert_eq!(render(QuotaDisplay::Absolute), ["Token(5h):1.2k/5.0k", "MCP(1mo):24%"]);
+        assert_eq!(render(QuotaDisplay::Both), ["Token(5h):1.2k/5.0k (24%)", "MCP(1mo):24%"]);
+    }
+
+    fn sample(percentage: f64, timestamp: &str) -> QuotaSample {
+        QuotaSample {
+            percentage,
+            timestamp: timestamp.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_rise_per_10m() {
+        let previous = sample(20.0, "2026-01-10T04:00:00Z");
+        assert_eq!(rise_per_10m(&previous, &sample(32.0, "2026-01-10T04:10:00Z")), Some(12.0));
+        assert_eq!(rise_per_10m(&previous, &sample(23.0, "2026-01-10T04:03:00Z")), Some(10.0));
+        assert_eq!(rise_per_10m(&previous, &sample(29.0, "2026-01-10T04:30:00Z")), Some(3.0));
+        // 窗口重置后使用率下降，不算上升
+        assert_eq!(rise_per_10m(&previous, &sample(2.0, "2026-01-10T04:05:00Z")), None);
+        assert_eq!(rise_per_10m(&previous, &sample(20.0, "2026-01-10T04:05:00Z")), None);
+        assert_eq!(rise_per_10m(&previous, &sample(40.0, "2026-01-10T04:00:00Z")), None);
+    }
+
+    #[test]
+    fn test_rise_alert() {
+        let usage = |percentage: f64, previous: QuotaSample| ZhipuUsageCache {
+            earlier_token: None,
+            token_limit: Some(QuotaLimit {
+                limit_type: "TOKENS_LIMIT".to_string(),
+                percentage,
+                current_value: None,
+                usage: None,
+            }),
+            mcp_limit: None,
+            timestamp: "2026-01-10T04:10:00Z".parse().unwrap(),
+            previous_token: Some(previous),
+        };
+        let with_earlier = |usage: &ZhipuUsageCache, earlier: QuotaSample| ZhipuUsageCache {
+            earlier_token: Some(earlier),
+            ..usage.clone()
+        };
+        let mut config = StatuslineConfig::default();
+        let render = |usage: &ZhipuUsageCache, config: &StatuslineConfig| {
+            ZhipuProvider.render_segments(usage, &en_ctx(config))[0].clone()
+        };
+
+        let fast = usage(45.0, sample(30.0, "2026-01-10T04:00:00Z"));
+        let segment = render(&fast, &config);
+        assert_eq!(segment.plain_text(), "Token(5h):45% ↑↑");
+        assert_eq!(segment.spans[1].color, colors::BOLD);
+        // 低于阈值、窗口重置时不显示
+        let slow = usage(38.0, sample(30.0, "2026-01-10T04:00:00Z"));
+        assert_eq!(render(&slow, &config).plain_text(), "Token(5h):38%");
+        let reset = usage(5.0, sample(95.0, "2026-01-10T04:00:00Z"));
+        assert_eq!(render(&reset, &config).plain_text(), "Token(5h):5%");
+        // 上一次采样不足 5 分钟时用再往前一次的采样，没有时不判断
+        let recent = usage(36.0, sample(30.0, "2026-01-10T04:07:00Z"));
+        assert_eq!(render(&recent, &config).plain_text(), "Token(5h):36%");
+        let steady = with_earlier(&recent, sample(33.0, "2026-01-10T04:00:00Z"));
+        assert_eq!(render(&steady, &config).plain_text(), "Token(5h):36%");
+        let climbing = with_earlier(&recent, sample(20.0, "2026-01-10T04:00:00Z"));
+        assert_eq!(render(&climbing, &config).plain_text(), "Token(5h):36% ↑↑");
+
+        config.providers.insert(
+            "zhipu".to_string(),
+            crate::config::ProviderOptions {
+                rise_alert: Some(20.0),
+                ..Default::default()
+            },
+        );
+        assert_eq!(render(&fast, &config).plain_text(), "Token(5h):45%");
+    }
+}
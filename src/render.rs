@@ -0,0 +1,254 @@
+use crate::colors;
+
+/// 段内一段同色文本
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub color: &'static str,
+    pub text: String,
+    /// 按阈值着色的文本的级别，其余文本为 None；输出时的颜色由主题的配色按级别决定
+    pub severity: Option<colors::Severity>,
+}
+
+/// statusline 中的一段（如模型、分支、上下文使用率）
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    /// 段名称，如 `model`、`git`
+    pub name: &'static str,
+    pub bold: bool,
+    pub spans: Vec<Span>,
+    /// 可以画成进度条的百分比；开启 `provider_bars` 时第一段文本替换为进度条
+    pub bar: Option<f64>,
+}
+
+/// 进度条的格数
+pub const BAR_CELLS: usize = 5;
+
+/// 画成 `cells` 格的进度条，如 64% → `▮▮▮▯▯`；按四舍五入计算填充的格数
+pub fn bar(pct: f64, cells: usize) -> String {
+    let filled = ((pct.clamp(0.0, 100.0) / 100.0) * cells as f64).round() as usize;
+    format!("{}{}", "▮".repeat(filled), "▯".repeat(cells - filled))
+}
+
+impl Segment {
+    pub fn new(name: &'static str, color: &'static str, text: impl Into<String>) -> Self {
+        Segment {
+            name,
+            bold: false,
+            spans: vec![Span {
+                color,
+                text: text.into(),
+                severity: None,
+            }],
+            bar: None,
+        }
+    }
+
+    /// 按阈值级别着色的段；颜色先取默认配色中该级别的颜色，渲染时换成主题配色中的颜色
+    pub fn graded(name: &'static str, severity: colors::Severity, text: impl Into<String>) -> Self {
+        let mut segment = Segment::new(name, colors::Palette::dark().color(severity), text);
+        segment.spans[0].severity = Some(severity);
+        segment
+    }
+
+    /// 标记第一段文本是百分比，可以画成进度条
+    #[cfg_attr(not(feature = "provider-yunyi"), allow(dead_code))]
+    pub fn bar_capable(mut self, pct: f64) -> Self {
+        self.bar = Some(pct);
+        self
+    }
+
+    /// 第一段文本替换为进度条
+    pub fn draw_bar(mut self) -> Self {
+        if let (Some(pct), Some(first)) = (self.bar, self.spans.first_mut()) {
+            first.text = bar(pct, BAR_CELLS);
+        }
+        self
+    }
+
+    pub fn bold(mut self) -> Self {
+        self.bold = true;
+        self
+    }
+
+    /// 追加一段不同颜色的文本
+    pub fn with(mut self, color: &'static str, text: impl Into<String>) -> Self {
+        self.spans.push(Span {
+            color,
+            text: text.into(),
+            severity: None,
+        });
+        self
+    }
+
+    /// 追加一段按阈值级别着色的文本
+    pub fn with_graded(mut self, severity: colors::Severity, text: impl Into<String>) -> Self {
+        self.spans.push(Span {
+            color: colors::Palette::dark().color(severity),
+            text: text.into(),
+            severity: Some(severity),
+        });
+        self
+    }
+
+    /// 段的主颜色（第一段文本的颜色）
+    pub fn color(&self) -> &'static str {
+        self.spans.first().map(|span| span.color).unwrap_or(colors::RESET)
+    }
+
+    /// 去掉颜色后的文本
+    pub fn plain_text(&self) -> String {
+        self.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+}
+
+/// 普通模式默认的段分隔符
+pub const SEPARATOR: &str = " │ ";
+
+/// 普通模式：各段按自身颜色输出，以 ` │ ` 分隔
+pub fn render_plain(segments: &[Segment]) -> String {
+    render_plain_with(segments, SEPARATOR)
+}
+
+/// 普通模式，使用自定义的分隔符（可以为空字符串）
+pub fn render_plain_with(segments: &[Segment], separator: &str) -> String {
+    segments
+        .iter()
+        .map(|segment| {
+            let bold = if segment.bold { colors::BOLD } else { "" };
+            segment
+                .spans
+                .iter()
+                .map(|span| format!("{}{}{}{}", bold, span.color, span.text, colors::RESET))
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+/// Powerline 分隔符
+const POWERLINE_ARROW: &str = "\u{e0b0}";
+
+/// Powerline 模式：各段以主颜色为背景，箭头的前景色衔接上一段的背景色
+pub fn render_powerline(segments: &[Segment]) -> String {
+    let mut out = String::new();
+    for (i, segment) in segments.iter().enumerate() {
+        let bg = colors::background(segment.color());
+        if i > 0 {
+            let prev_fg = colors::foreground(colors::background(segments[i - 1].color()));
+            out.push_str(&format!("{}{}{}", prev_fg, bg, POWERLINE_ARROW));
+            out.push_str(colors::RESET);
+        }
+        let bold = if segment.bold { colors::BOLD } else { "" };
+        out.push_str(&format!(
+            "{}{}{} {} {}",
+            bg,
+            colors::BLACK,
+            bold,
+            segment.plain_text(),
+            colors::RESET
+        ));
+    }
+    if let Some(last) = segments.last() {
+        let last_fg = colors::foreground(colors::background(last.color()));
+        out.push_str(&format!("{}{}{}", last_fg, POWERLINE_ARROW, colors::RESET));
+    }
+    out
+}
+
+/// Powerline 模式在禁用颜色时的降级：不输出箭头字形，以 ` | ` 分隔
+pub fn render_powerline_no_color(segments: &[Segment]) -> String {
+    segments
+        .iter()
+        .map(Segment::plain_text)
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text;
+
+    fn fixture() -> Vec<Segment> {
+        vec![
+            Segment::new("model", colors::MAGENTA, "[Opus]").bold(),
+            Segment::new("git", colors::BLUE, "main").with(colors::RED, " !merge"),
+        ]
+    }
+
+    #[test]
+    fn test_bar_cells() {
+        assert_eq!(bar(0.0, 5), "▯▯▯▯▯");
+        assert_eq!(bar(64.0, 5), "▮▮▮▯▯");
+        // 四舍五入：50% 的 5 格是 2.5 格
+        assert_eq!(bar(50.0, 5), "▮▮▮▯▯");
+        assert_eq!(bar(9.0, 5), "▯▯▯▯▯");
+        assert_eq!(bar(10.0, 5), "▮▯▯▯▯");
+        assert_eq!(bar(100.0, 5), "▮▮▮▮▮");
+        // 超出范围时截断
+        assert_eq!(bar(130.0, 5), "▮▮▮▮▮");
+        assert_eq!(bar(-5.0, 5), "▯▯▯▯▯");
+        assert_eq!(bar(64.0, 10), "▮▮▮▮▮▮▯▯▯▯");
+
+        let segment = Segment::new("quota", colors::GREEN, "Rem:64%").with(colors::GREEN, " $3.80");
+        assert_eq!(segment.clone().draw_bar().plain_text(), "Rem:64% $3.80");
+        assert_eq!(segment.bar_capable(64.0).draw_bar().plain_text(), "▮▮▮▯▯ $3.80");
+    }
+
+    #[test]
+    fn test_render_plain() {
+        assert_eq!(
+            render_plain(&fixture()),
+            "\x1b[1m\x1b[35m[Opus]\x1b[0m │ \x1b[34mmain\x1b[0m\x1b[31m !merge\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_render_plain_with_separator() {
+        let plain = |separator| text::strip_ansi(&render_plain_with(&fixture(), separator));
+        assert_eq!(plain(" \u{e0b1} "), "[Opus] \u{e0b1} main !merge");
+        assert_eq!(plain(""), "[Opus]main !merge");
+        assert_eq!(render_plain_with(&fixture()[..1], "|"), render_plain(&fixture()[..1]));
+    }
+
+    #[test]
+    fn test_render_powerline() {
+        assert_eq!(
+            render_powerline(&fixture()),
+            concat!(
+                "\x1b[45m\x1b[30m\x1b[1m [Opus] \x1b[0m",
+                "\x1b[35m\x1b[44m\u{e0b0}\x1b[0m",
+                "\x1b[44m\x1b[30m main !merge \x1b[0m",
+                "\x1b[34m\u{e0b0}\x1b[0m"
+            )
+        );
+        assert_eq!(render_powerline(&[]), "");
+    }
+
+    #[test]
+    fn test_render_powerline_three_segments() {
+        let segments = vec![
+            Segment::new("dir", colors::CYAN, "project"),
+            Segment::new("ctx", colors::YELLOW, "ctx:65%"),
+            Segment::new("tokens", colors::DIM, "in:1.2k"),
+        ];
+        // 每段结束都 RESET，箭头前景色为上一段背景色，最后以单独的箭头收尾
+        assert_eq!(
+            render_powerline(&segments),
+            concat!(
+                "\x1b[46m\x1b[30m project \x1b[0m",
+                "\x1b[36m\x1b[43m\u{e0b0}\x1b[0m",
+                "\x1b[43m\x1b[30m ctx:65% \x1b[0m",
+                "\x1b[33m\x1b[100m\u{e0b0}\x1b[0m",
+                "\x1b[100m\x1b[30m in:1.2k \x1b[0m",
+                "\x1b[90m\u{e0b0}\x1b[0m"
+            )
+        );
+        assert!(render_powerline(&segments).ends_with(colors::RESET));
+    }
+
+    #[test]
+    fn test_render_powerline_no_color() {
+        assert_eq!(render_powerline_no_color(&fixture()), "[Opus] | main !merge");
+    }
+}
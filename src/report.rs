@@ -0,0 +1,256 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::BufRead;
+
+use crate::config::TokenFormat;
+use crate::history::HistoryRecord;
+
+/// 默认统计最近的天数
+pub const DEFAULT_DAYS: u32 = 7;
+
+/// 没有记录项目目录的会话（第 1 版历史记录）归入的项目名
+const UNKNOWN_PROJECT: &str = "(unknown)";
+
+/// `report` 的输出格式
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    #[default]
+    Table,
+    Json,
+    Csv,
+}
+
+impl ReportFormat {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "table" => Ok(ReportFormat::Table),
+            "json" => Ok(ReportFormat::Json),
+            "csv" => Ok(ReportFormat::Csv),
+            _ => Err(format!("unknown report format: {}", value)),
+        }
+    }
+}
+
+/// 单个项目在统计范围内的汇总
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ProjectReport {
+    pub project: String,
+    pub cost_usd: f64,
+    pub total_tokens: u64,
+    pub sessions: usize,
+    /// 有时长记录的会话的平均时长，都没有时为 None
+    pub avg_duration_ms: Option<u64>,
+}
+
+/// 按项目汇总 `since` 之后的会话，按成本从高到低排列
+///
+/// 每个会话只取文件中最后一条记录（成本、token 数和时长都是累计值）；无法解析的行跳过。
+pub fn build<R: BufRead>(reader: R, since: DateTime<Utc>) -> Vec<ProjectReport> {
+    let mut latest: HashMap<String, HistoryRecord> = HashMap::new();
+    for line in reader.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if let Ok(record) = serde_json::from_str::<HistoryRecord>(&line) {
+            latest.insert(record.session_id.clone(), record.migrate());
+        }
+    }
+
+    let mut projects: HashMap<String, (ProjectReport, Vec<u64>)> = HashMap::new();
+    for record in latest.into_values().filter(|record| record.timestamp >= since) {
+        let project = record.project_dir.unwrap_or_else(|| UNKNOWN_PROJECT.to_string());
+        let (report, durations) = projects.entry(project.clone()).or_insert_with(|| {
+            let report = ProjectReport {
+                project,
+                cost_usd: 0.0,
+                total_tokens: 0,
+                sessions: 0,
+                avg_duration_ms: None,
+            };
+            (report, Vec::new())
+        });
+        report.cost_usd += record.cost_usd;
+        report.total_tokens += record.total_tokens.unwrap_or(0);
+        report.sessions += 1;
+        durations.extend(record.duration_ms);
+    }
+
+    let mut reports: Vec<ProjectReport> = projects
+        .into_values()
+        .map(|(mut report, durations)| {
+            if !durations.is_empty() {
+                report.avg_duration_ms = Some(durations.iter().sum::<u64>() / durations.len() as u64);
+            }
+            report
+        })
+        .collect();
+    reports.sort_by(|a, b| b.cost_usd.total_cmp(&a.cost_usd).then_with(|| a.project.cmp(&b.project)));
+    reports
+}
+
+/// 按格式输出报告
+pub fn format(reports: &[ProjectReport], format: ReportFormat) -> String {
+    match format {
+        ReportFormat::Table => format_table(reports),
+        ReportFormat::Json => serde_json::to_string_pretty(reports).unwrap_or_default(),
+        ReportFormat::Csv => format_csv(reports),
+    }
+}
+
+fn format_table(reports: &[ProjectReport]) -> String {
+    let width = reports.iter().map(|report| report.project.chars().count()).max().unwrap_or(0).max(7);
+    let mut lines = vec![format!(
+        "{:<width$} {:>10} {:>10} {:>8} {:>10}",
+        "project", "cost", "tokens", "sessions", "avg time"
+    )];
+    for report in reports {
+        lines.push(format!(
+            "{:<width$} {:>10} {:>10} {:>8} {:>10}",
+            report.project,
+            format!("${:.2}", report.cost_usd),
+            crate::format_tokens(report.total_tokens, TokenFormat::Abbrev),
+            report.sessions,
+            report.avg_duration_ms.map(crate::format_duration).unwrap_or_else(|| "-".to_string()),
+        ));
+    }
+    lines.join("\n")
+}
+
+fn format_csv(reports: &[ProjectReport]) -> String {
+    let mut lines = vec!["project,cost_usd,total_tokens,sessions,avg_duration_ms".to_string()];
+    for report in reports {
+        lines.push(format!(
+            "{},{:.2},{},{},{}",
+            csv_field(&report.project),
+            report.cost_usd,
+            report.total_tokens,
+            report.sessions,
+            report.avg_duration_ms.map(|ms| ms.to_string()).unwrap_or_default()
+        ));
+    }
+    lines.join("\n")
+}
+
+/// 包含逗号、引号或换行时加引号，引号转义为两个引号
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(
+        timestamp: &str,
+        session_id: &str,
+        cost_usd: f64,
+        project_dir: &str,
+        total_tokens: u64,
+        duration_ms: Option<u64>,
+    ) -> String {
+        serde_json::json!({
+            "version": 2,
+            "timestamp": timestamp,
+            "session_id": session_id,
+            "cost_usd": cost_usd,
+            "project_dir": project_dir,
+            "total_tokens": total_tokens,
+            "duration_ms": duration_ms,
+        })
+        .to_string()
+    }
+
+    fn history() -> String {
+        [
+            // a 的最后一条记录才计入
+            record("2026-03-10T09:00:00Z", "a", 1.0, "/work/api", 10_000, Some(600_000)),
+            record("2026-03-10T10:00:00Z", "a", 2.5, "/work/api", 40_000, Some(1_800_000)),
+            record("2026-03-11T10:00:00Z", "b", 1.5, "/work/api", 20_000, None),
+            record("2026-03-11T12:00:00Z", "c", 3.0, "/work/web, v2", 1_500_000, Some(3_600_000)),
+            // 第 1 版记录没有项目目录
+            r#"{"timestamp":"2026-03-12T08:00:00Z","session_id":"d","cost_usd":0.25}"#.to_string(),
+            // 超出统计范围
+            record("2026-02-01T08:00:00Z", "e", 9.0, "/work/old", 0, None),
+            "not json".to_string(),
+        ]
+        .join("\n")
+    }
+
+    fn since() -> DateTime<Utc> {
+        "2026-03-06T00:00:00Z".parse().unwrap()
+    }
+
+    #[test]
+    fn test_build_aggregates_per_project() {
+        let reports = build(history().as_bytes(), since());
+        assert_eq!(
+            reports,
+            vec![
+                ProjectReport {
+                    project: "/work/api".to_string(),
+                    cost_usd: 4.0,
+                    total_tokens: 60_000,
+                    sessions: 2,
+                    avg_duration_ms: Some(1_800_000),
+                },
+                ProjectReport {
+                    project: "/work/web, v2".to_string(),
+                    cost_usd: 3.0,
+                    total_tokens: 1_500_000,
+                    sessions: 1,
+                    avg_duration_ms: Some(3_600_000),
+                },
+                ProjectReport {
+                    project: "(unknown)".to_string(),
+                    cost_usd: 0.25,
+                    total_tokens: 0,
+                    sessions: 1,
+                    avg_duration_ms: None,
+                },
+            ]
+        );
+        assert!(build("".as_bytes(), since()).is_empty());
+    }
+
+    #[test]
+    fn test_formats() {
+        let reports = build(history().as_bytes(), since());
+
+        let table = format(&reports, ReportFormat::Table);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("project "));
+        assert!(lines[0].ends_with("avg time"));
+        assert_eq!(lines[1], "/work/api          $4.00      60.0k        2        30m");
+        assert_eq!(lines[3], "(unknown)          $0.25          0        1          -");
+
+        let json: serde_json::Value = serde_json::from_str(&format(&reports, ReportFormat::Json)).unwrap();
+        assert_eq!(json[0]["project"], "/work/api");
+        assert_eq!(json[0]["sessions"], 2);
+        assert_eq!(json[2]["avg_duration_ms"], serde_json::Value::Null);
+
+        assert_eq!(
+            format(&reports, ReportFormat::Csv),
+            [
+                "project,cost_usd,total_tokens,sessions,avg_duration_ms",
+                "/work/api,4.00,60000,2,1800000",
+                "\"/work/web, v2\",3.00,1500000,1,3600000",
+                "(unknown),0.25,0,1,",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!(ReportFormat::parse("table"), Ok(ReportFormat::Table));
+        assert_eq!(ReportFormat::parse("json"), Ok(ReportFormat::Json));
+        assert_eq!(ReportFormat::parse("csv"), Ok(ReportFormat::Csv));
+        assert!(ReportFormat::parse("ansi").is_err());
+    }
+}
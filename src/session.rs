@@ -0,0 +1,261 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::error::StatuslineError;
+use crate::paths::{self, sessions_dir};
+
+/// 上一次渲染的结果
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct LastRender {
+    pub rendered_at: DateTime<Utc>,
+    pub output: String,
+}
+
+/// 按 session_id 保存的会话状态，新增字段需带默认值以兼容旧文件
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct SessionState {
+    pub last_render: Option<LastRender>,
+    /// 上次记录的上下文 token 总数（输入 + 输出）
+    pub context_tokens: Option<u64>,
+    /// 最近一次上下文增长量，总数不变时继续显示
+    pub context_delta: Option<u64>,
+    /// 本会话的渲染次数（开启 `heartbeat` 时记录）
+    pub renders: u64,
+    /// 最近一次渲染的时间（开启 `heartbeat` 时记录）
+    pub rendered_at: Option<DateTime<Utc>>,
+    /// 上一次渲染结果（不含心跳符号）的哈希，用于判断输出是否变化
+    pub line_hash: Option<u64>,
+    /// 最近一次写入历史记录的会话成本
+    pub recorded_cost: Option<f64>,
+    /// 上次渲染时的会话成本（开启 `turn_cost` 时记录）
+    pub cost_total: Option<f64>,
+    /// 最近一轮的成本增量，成本不变时继续显示
+    pub cost_delta: Option<f64>,
+}
+
+/// 会话状态文件路径；session_id 中只保留安全字符，避免写到目录外
+fn state_path(session_id: &str) -> Option<PathBuf> {
+    let name: String = session_id
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect();
+    if name.is_empty() {
+        return None;
+    }
+    Some(sessions_dir().join(format!("{}.json", name)))
+}
+
+/// 读取会话状态，文件不存在或损坏时返回默认值
+pub fn load(session_id: &str) -> SessionState {
+    state_path(session_id)
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// 保存会话状态（经 [`paths::write_atomic`] 写入；失败时只在调试模式下提示）
+pub fn save(session_id: &str, state: &SessionState) {
+    if !paths::persistence_enabled() {
+        return;
+    }
+    let Some(path) = state_path(session_id) else {
+        return;
+    };
+    let Ok(json) = serde_json::to_string(state) else {
+        return;
+    };
+    let result = fs::create_dir_all(sessions_dir()).and_then(|_| paths::write_atomic(&path, json));
+    if let Err(err) = result {
+        paths::write_failed(StatuslineError::write(&path, err));
+    }
+}
+
+/// 上次渲染距今不足 `min_interval_ms` 时返回上次的输出
+pub fn reusable_output(state: &SessionState, now: DateTime<Utc>, min_interval_ms: u64) -> Option<&str> {
+    let last = state.last_render.as_ref()?;
+    let elapsed = now.signed_duration_since(last.rendered_at).num_milliseconds();
+    // 时钟回拨时不复用
+    if elapsed < 0 || elapsed as u64 >= min_interval_ms {
+        return None;
+    }
+    Some(&last.output)
+}
+
+/// 记录本次的上下文 token 总数并更新增长量
+///
+/// 会话首次渲染没有基准，不显示增长；总数下降说明发生了压缩，重新开始计算。
+pub fn update_context(state: &mut SessionState, total: u64) {
+    state.context_delta = match state.context_tokens {
+        Some(previous) if total > previous => Some(total - previous),
+        Some(previous) if total == previous => state.context_delta,
+        _ => None,
+    };
+    state.context_tokens = Some(total);
+}
+
+/// 记录本次的会话成本并更新本轮增量
+///
+/// 会话首次渲染没有基准，不显示增量；成本下降（部分中转站会出现）时不显示，并以新值为基准。
+pub fn update_cost(state: &mut SessionState, total: f64) {
+    state.cost_delta = match state.cost_total {
+        Some(previous) if total > previous => Some(total - previous),
+        Some(previous) if total == previous => state.cost_delta,
+        _ => None,
+    };
+    state.cost_total = Some(total);
+}
+
+/// 记录本次渲染结果的哈希，返回与上一次相比是否变化（没有上一次时视为变化）
+pub fn update_line_hash(state: &mut SessionState, hash: u64) -> bool {
+    let changed = state.line_hash != Some(hash);
+    state.line_hash = Some(hash);
+    changed
+}
+
+/// 记录本次要写入历史的会话成本，返回与上次写入的相比是否变化
+pub fn update_recorded_cost(state: &mut SessionState, cost_usd: f64) -> bool {
+    let changed = state.recorded_cost != Some(cost_usd);
+    state.recorded_cost = Some(cost_usd);
+    changed
+}
+
+/// 记录一次渲染；只有输出变化时心跳才前进，停止刷新和输出不变都不会让心跳符号变化
+pub fn record_render(state: &mut SessionState, now: DateTime<Utc>, changed: bool) {
+    if changed {
+        state.renders += 1;
+    }
+    state.rendered_at = Some(now);
+}
+
+/// 心跳符号，相邻两次渲染交替显示 `·` 和 `∘`；还没有渲染记录时为 None
+pub fn heartbeat(state: &SessionState) -> Option<&'static str> {
+    match state.renders {
+        0 => None,
+        n if n % 2 == 1 => Some("·"),
+        _ => Some("∘"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    fn rendered(at: &str) -> SessionState {
+        SessionState {
+            last_render: Some(LastRender {
+                rendered_at: time(at),
+                output: "[Opus] │ ctx:42%".to_string(),
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_reuse_within_interval() {
+        let state = rendered("2026-05-01T10:00:00.000Z");
+        let now = time("2026-05-01T10:00:00.250Z");
+        assert_eq!(reusable_output(&state, now, 300), Some("[Opus] │ ctx:42%"));
+        assert_eq!(reusable_output(&state, now, 250), None);
+        assert_eq!(reusable_output(&state, now, 0), None);
+    }
+
+    #[test]
+    fn test_no_reuse_without_state_or_on_clock_skew() {
+        let now = time("2026-05-01T10:00:00Z");
+        assert_eq!(reusable_output(&SessionState::default(), now, 1000), None);
+        let future = rendered("2026-05-01T10:00:01Z");
+        assert_eq!(reusable_output(&future, now, 5000), None);
+    }
+
+    #[test]
+    fn test_context_growth() {
+        let mut state = SessionState::default();
+        // 首次渲染
+        update_context(&mut state, 120_000);
+        assert_eq!(state.context_delta, None);
+
+        update_context(&mut state, 123_100);
+        assert_eq!(state.context_delta, Some(3_100));
+        // 流式输出期间总数不变时保留上次的增长量
+        update_context(&mut state, 123_100);
+        assert_eq!(state.context_delta, Some(3_100));
+
+        // 压缩后重新开始
+        update_context(&mut state, 18_000);
+        assert_eq!(state.context_delta, None);
+        update_context(&mut state, 18_500);
+        assert_eq!(state.context_delta, Some(500));
+    }
+
+    #[test]
+    fn test_turn_cost() {
+        let mut state = SessionState::default();
+        // 首次渲染
+        update_cost(&mut state, 1.20);
+        assert_eq!(state.cost_delta, None);
+
+        update_cost(&mut state, 1.25);
+        assert!((state.cost_delta.unwrap() - 0.05).abs() < 1e-9);
+        // 同一轮中多次渲染时保留增量
+        update_cost(&mut state, 1.25);
+        assert!((state.cost_delta.unwrap() - 0.05).abs() < 1e-9);
+
+        // 成本倒退时不显示增量，之后以新值为基准
+        update_cost(&mut state, 0.90);
+        assert_eq!(state.cost_delta, None);
+        assert_eq!(state.cost_total, Some(0.90));
+        update_cost(&mut state, 1.70);
+        assert!((state.cost_delta.unwrap() - 0.80).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_heartbeat_alternates() {
+        let mut state = SessionState::default();
+        assert_eq!(heartbeat(&state), None);
+
+        let now = time("2026-05-01T10:00:00Z");
+        let glyphs: Vec<_> = (0..4)
+            .map(|_| {
+                record_render(&mut state, now, true);
+                heartbeat(&state).unwrap()
+            })
+            .collect();
+        assert_eq!(glyphs, ["·", "∘", "·", "∘"]);
+        assert_eq!(state.renders, 4);
+        assert_eq!(state.rendered_at, Some(now));
+
+        // 输出没有变化时只更新渲染时间
+        let later = time("2026-05-01T10:00:05Z");
+        record_render(&mut state, later, false);
+        assert_eq!(heartbeat(&state), Some("∘"));
+        assert_eq!(state.rendered_at, Some(later));
+    }
+
+    #[test]
+    fn test_change_detection() {
+        let mut state = SessionState::default();
+        // 首次渲染视为变化
+        assert!(update_line_hash(&mut state, 1));
+        assert!(!update_line_hash(&mut state, 1));
+        assert!(update_line_hash(&mut state, 2));
+        assert_eq!(state.line_hash, Some(2));
+
+        assert!(update_recorded_cost(&mut state, 1.25));
+        assert!(!update_recorded_cost(&mut state, 1.25));
+        assert!(update_recorded_cost(&mut state, 1.5));
+    }
+
+    #[test]
+    fn test_state_path_sanitized() {
+        let path = state_path("../../etc/passwd").unwrap();
+        assert_eq!(path.file_name().unwrap(), "etcpasswd.json");
+        assert!(state_path("../").is_none());
+    }
+}
@@ -0,0 +1,275 @@
+use crate::colors;
+
+/// 文本片段：普通文本或一个完整的 ANSI 转义序列
+enum Token<'a> {
+    Text(char),
+    Escape(&'a str),
+}
+
+/// 按 ANSI 转义序列切分字符串
+fn tokenize(s: &str) -> impl Iterator<Item = Token<'_>> {
+    let mut rest = s;
+    std::iter::from_fn(move || {
+        let c = rest.chars().next()?;
+        if c == '\x1b' {
+            // CSI 序列以字母结尾，如 "\x1b[31m"
+            let end = rest[1..]
+                .find(|c: char| c.is_ascii_alphabetic())
+                .map(|i| i + 2)
+                .unwrap_or(rest.len());
+            let (escape, tail) = rest.split_at(end);
+            rest = tail;
+            Some(Token::Escape(escape))
+        } else {
+            rest = &rest[c.len_utf8()..];
+            Some(Token::Text(c))
+        }
+    })
+}
+
+/// 去掉 ANSI 转义序列
+pub fn strip_ansi(s: &str) -> String {
+    tokenize(s)
+        .filter_map(|token| match token {
+            Token::Text(c) => Some(c),
+            Token::Escape(_) => None,
+        })
+        .collect()
+}
+
+/// 去掉控制字符（包括 ESC）和零宽、双向文本控制字符，用于分支名、目录名等来自外部的文本
+pub fn strip_control(s: &str) -> String {
+    // 零宽字符和双向文本控制字符
+    let invisible = |c: char| {
+        matches!(c as u32, 0x200B..=0x200F | 0x202A..=0x202E | 0x2060..=0x2069 | 0xFEFF)
+    };
+    s.chars().filter(|&c| !c.is_control() && !invisible(c)).collect()
+}
+
+/// 字符在终端中占用的列数（中日韩文字、全角符号和大部分 emoji 占两列）
+fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+/// 可见宽度：忽略 ANSI 转义序列，宽字符按两列计算
+pub fn visible_width(s: &str) -> usize {
+    tokenize(s)
+        .map(|token| match token {
+            Token::Text(c) => char_width(c),
+            Token::Escape(_) => 0,
+        })
+        .sum()
+}
+
+/// 从中间截断纯文本使其不超过 `max_width` 列，中间以 `…` 代替
+pub fn truncate_middle(s: &str, max_width: usize) -> String {
+    if visible_width(s) <= max_width {
+        return s.to_string();
+    }
+    if max_width == 0 {
+        return String::new();
+    }
+
+    // 省略号占一列，剩余宽度前半部分多留一列
+    let budget = max_width - 1;
+    let head_budget = budget - budget / 2;
+    let tail_budget = budget / 2;
+
+    let mut head = String::new();
+    let mut width = 0;
+    for c in s.chars() {
+        if width + char_width(c) > head_budget {
+            break;
+        }
+        width += char_width(c);
+        head.push(c);
+    }
+
+    let mut tail = Vec::new();
+    let mut width = 0;
+    for c in s.chars().rev() {
+        if width + char_width(c) > tail_budget {
+            break;
+        }
+        width += char_width(c);
+        tail.push(c);
+    }
+
+    format!("{}…{}", head, tail.into_iter().rev().collect::<String>())
+}
+
+/// 截断带 ANSI 颜色的文本使其不超过 `max_width` 列，末尾加上 `…` 并重置颜色
+pub fn truncate_end(s: &str, max_width: usize) -> String {
+    if visible_width(s) <= max_width {
+        return s.to_string();
+    }
+    if max_width == 0 {
+        return String::new();
+    }
+    // 省略号占一列
+    let budget = max_width - 1;
+    let mut out = String::new();
+    let mut width = 0;
+    for token in tokenize(s) {
+        match token {
+            Token::Escape(escape) => out.push_str(escape),
+            Token::Text(c) => {
+                if width + char_width(c) > budget {
+                    break;
+                }
+                width += char_width(c);
+                out.push(c);
+            }
+        }
+    }
+    out.push('…');
+    out.push_str(colors::RESET);
+    out
+}
+
+/// ANSI 颜色代码对应的 tmux 格式指令
+fn tmux_style(escape: &str) -> Option<&'static str> {
+    match escape {
+        colors::RESET => Some("#[default,none]"),
+        colors::BOLD => Some("#[bold]"),
+        colors::DIM => Some("#[dim]"),
+        colors::RED => Some("#[fg=red]"),
+        colors::GREEN => Some("#[fg=green]"),
+        colors::YELLOW => Some("#[fg=yellow]"),
+        colors::BLUE => Some("#[fg=blue]"),
+        colors::MAGENTA => Some("#[fg=magenta]"),
+        colors::CYAN => Some("#[fg=cyan]"),
+        colors::BLACK => Some("#[fg=black]"),
+        colors::GRAY => Some("#[fg=brightblack]"),
+        colors::ORANGE => Some("#[fg=colour208]"),
+        colors::OKABE_BLUE => Some("#[fg=colour25]"),
+        colors::OKABE_ORANGE => Some("#[fg=colour214]"),
+        colors::VERMILLION => Some("#[fg=colour166]"),
+        colors::OKABE_BLUE_RGB => Some("#[fg=#0072b2]"),
+        colors::OKABE_ORANGE_RGB => Some("#[fg=#e69f00]"),
+        colors::VERMILLION_RGB => Some("#[fg=#d55e00]"),
+        colors::DARK_GREEN => Some("#[fg=colour28]"),
+        colors::DARK_YELLOW => Some("#[fg=colour136]"),
+        colors::DARK_CYAN => Some("#[fg=colour30]"),
+        colors::DARK_GRAY => Some("#[fg=colour240]"),
+        colors::BG_RED => Some("#[bg=red]"),
+        colors::BG_GREEN => Some("#[bg=green]"),
+        colors::BG_YELLOW => Some("#[bg=yellow]"),
+        colors::BG_BLUE => Some("#[bg=blue]"),
+        colors::BG_MAGENTA => Some("#[bg=magenta]"),
+        colors::BG_CYAN => Some("#[bg=cyan]"),
+        colors::BG_GRAY => Some("#[bg=brightblack]"),
+        colors::BG_ORANGE => Some("#[bg=colour208]"),
+        colors::BG_OKABE_BLUE => Some("#[bg=colour25]"),
+        colors::BG_OKABE_ORANGE => Some("#[bg=colour214]"),
+        colors::BG_VERMILLION => Some("#[bg=colour166]"),
+        colors::BG_OKABE_BLUE_RGB => Some("#[bg=#0072b2]"),
+        colors::BG_OKABE_ORANGE_RGB => Some("#[bg=#e69f00]"),
+        colors::BG_VERMILLION_RGB => Some("#[bg=#d55e00]"),
+        colors::BG_DARK_GREEN => Some("#[bg=colour28]"),
+        colors::BG_DARK_YELLOW => Some("#[bg=colour136]"),
+        colors::BG_DARK_CYAN => Some("#[bg=colour30]"),
+        _ => None,
+    }
+}
+
+/// 将 ANSI 颜色转换为 tmux 的 `#[fg=...]` 格式，文本中的 `#` 转义为 `##`
+pub fn to_tmux(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for token in tokenize(s) {
+        match token {
+            Token::Text('#') => out.push_str("##"),
+            Token::Text(c) => out.push(c),
+            Token::Escape(escape) => {
+                if let Some(style) = tmux_style(escape) {
+                    out.push_str(style);
+                }
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(strip_ansi("\x1b[1m\x1b[35m[Opus]\x1b[0m │ main"), "[Opus] │ main");
+        assert_eq!(strip_ansi("plain"), "plain");
+        assert_eq!(strip_ansi("中文\x1b[2m"), "中文");
+    }
+
+    #[test]
+    fn test_strip_control() {
+        assert_eq!(strip_control("feat/\x1b[31mred\x1b[0m"), "feat/[31mred[0m");
+        assert_eq!(strip_control("a\r\nb\tc\x7f\u{9b}d"), "abcd");
+        assert_eq!(strip_control("zero\u{200B}width\u{202E}rtl\u{FEFF}"), "zerowidthrtl");
+        assert_eq!(strip_control("功能/中文-🚀"), "功能/中文-🚀");
+    }
+
+    #[test]
+    fn test_visible_width() {
+        assert_eq!(visible_width("\x1b[31mctx:42%\x1b[0m"), 7);
+        assert_eq!(visible_width("MCP(1月)"), 8);
+        assert_eq!(visible_width("剩余"), 4);
+        assert_eq!(visible_width(" │ "), 3);
+    }
+
+    #[test]
+    fn test_truncate_middle() {
+        assert_eq!(truncate_middle("feature/long-branch", 9), "feat…anch");
+        assert_eq!(truncate_middle("short", 9), "short");
+        // 宽字符不能拆开，宽度可能略小于上限
+        assert_eq!(truncate_middle("项目名称很长", 7), "项…长");
+        assert_eq!(truncate_middle("项目名称很长", 8), "项目…长");
+        assert_eq!(truncate_middle("abc", 1), "…");
+    }
+
+    #[test]
+    fn test_truncate_end() {
+        let (magenta, blue, reset) = (colors::MAGENTA, colors::BLUE, colors::RESET);
+        let line = format!("{}[Opus]{} │ {}main{}", magenta, reset, blue, reset);
+        assert_eq!(truncate_end(&line, 13), line);
+        let truncated = truncate_end(&line, 11);
+        assert_eq!(visible_width(&truncated), 11);
+        assert_eq!(truncated, format!("{}[Opus]{} │ {}m…{}", magenta, reset, blue, reset));
+        // 宽字符放不下时整个丢弃
+        assert_eq!(truncate_end("剩余额度", 4), format!("剩…{}", colors::RESET));
+        assert_eq!(truncate_end("abc", 1), format!("…{}", colors::RESET));
+        assert_eq!(truncate_end("abc", 0), "");
+    }
+
+    #[test]
+    fn test_to_tmux() {
+        let line = format!(
+            "{}{}[Opus]{} │ {}fix#12{}",
+            colors::BOLD,
+            colors::MAGENTA,
+            colors::RESET,
+            colors::BLUE,
+            colors::RESET
+        );
+        assert_eq!(
+            to_tmux(&line),
+            "#[bold]#[fg=magenta][Opus]#[default,none] │ #[fg=blue]fix##12#[default,none]"
+        );
+    }
+}
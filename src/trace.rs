@@ -0,0 +1,229 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::error::StatuslineError;
+use crate::paths;
+
+/// 设置为 `1` 时记录每次渲染各阶段的耗时
+pub const TRACE_ENV: &str = "CC_STATUSLINE_TRACE";
+
+/// 耗时记录文件最多保留的行数，超出时丢弃最旧的记录
+pub const MAX_LINES: usize = 1000;
+
+/// 是否记录耗时
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// 本次渲染已记录的阶段及耗时（毫秒）
+static PHASES: Mutex<Vec<(String, f64)>> = Mutex::new(Vec::new());
+
+/// 每次渲染追加到 `statusline_trace.jsonl` 的一行，同名阶段的耗时累加
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct TraceRecord {
+    pub timestamp: DateTime<Utc>,
+    pub phases: BTreeMap<String, f64>,
+}
+
+/// 某个阶段在所有记录中的耗时分布
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhaseSummary {
+    pub phase: String,
+    pub count: usize,
+    pub p50: f64,
+    pub p95: f64,
+}
+
+/// 按环境变量决定是否记录耗时
+pub fn init() {
+    let enabled = std::env::var(TRACE_ENV).is_ok_and(|value| value == "1");
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// 执行 `f` 并记录耗时；未开启时直接执行，不计时
+pub fn phase<T>(name: &str, f: impl FnOnce() -> T) -> T {
+    if !enabled() {
+        return f();
+    }
+    let start = Instant::now();
+    let value = f();
+    record(name, start.elapsed());
+    value
+}
+
+/// 记录一个阶段的耗时（用于无法包成闭包的异步请求）
+pub fn record(name: &str, elapsed: Duration) {
+    if !enabled() {
+        return;
+    }
+    // 精确到微秒
+    let ms = (elapsed.as_secs_f64() * 1_000_000.0).round() / 1000.0;
+    if let Ok(mut phases) = PHASES.lock() {
+        phases.push((name.to_string(), ms));
+    }
+}
+
+/// 把本次渲染记录的耗时写成一行追加到 `path`；未开启或没有记录时不写入
+pub fn finish(path: &Path) {
+    if !enabled() || !paths::persistence_enabled() {
+        return;
+    }
+    let phases = PHASES.lock().map(|mut phases| std::mem::take(&mut *phases)).unwrap_or_default();
+    if phases.is_empty() {
+        return;
+    }
+    let mut record = TraceRecord {
+        timestamp: Utc::now(),
+        phases: BTreeMap::new(),
+    };
+    for (name, ms) in phases {
+        *record.phases.entry(name).or_insert(0.0) += ms;
+    }
+    let Ok(line) = serde_json::to_string(&record) else {
+        return;
+    };
+    if let Err(err) = append_capped(path, &line, MAX_LINES) {
+        paths::write_failed(err);
+    }
+}
+
+/// 追加一行，文件已有 `max_lines` 行时只保留最新的 `max_lines - 1` 行再追加
+fn append_capped(path: &Path, line: &str, max_lines: usize) -> Result<(), StatuslineError> {
+    let existing = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(StatuslineError::read(path, err)),
+    };
+    let lines: Vec<&str> = existing.lines().collect();
+    if lines.len() < max_lines {
+        // 整行一次写入，多个会话同时追加时不会交错
+        return OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(format!("{}\n", line).as_bytes()))
+            .map_err(|err| StatuslineError::write(path, err));
+    }
+    let mut content = String::new();
+    for kept in &lines[lines.len() + 1 - max_lines.max(1)..] {
+        content.push_str(kept);
+        content.push('\n');
+    }
+    content.push_str(line);
+    content.push('\n');
+    fs::write(path, content).map_err(|err| StatuslineError::write(path, err))
+}
+
+/// 按最近秩法取百分位数，`sorted` 需已升序排列且非空
+fn percentile(sorted: &[f64], pct: f64) -> f64 {
+    let rank = (pct / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// 统计每个阶段耗时的 p50/p95，按阶段名排序；无法解析的行跳过
+pub fn summarize(content: &str) -> Vec<PhaseSummary> {
+    let mut samples: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+    for line in content.lines() {
+        let Ok(record) = serde_json::from_str::<TraceRecord>(line) else {
+            continue;
+        };
+        for (phase, ms) in record.phases {
+            samples.entry(phase).or_default().push(ms);
+        }
+    }
+    samples
+        .into_iter()
+        .map(|(phase, mut values)| {
+            values.sort_by(f64::total_cmp);
+            PhaseSummary {
+                phase,
+                count: values.len(),
+                p50: percentile(&values, 50.0),
+                p95: percentile(&values, 95.0),
+            }
+        })
+        .collect()
+}
+
+/// `trace summarize` 的输出表格
+pub fn format_summary(summaries: &[PhaseSummary]) -> String {
+    let mut lines = vec![format!("{:<24} {:>6} {:>10} {:>10}", "phase", "n", "p50 ms", "p95 ms")];
+    for summary in summaries {
+        lines.push(format!(
+            "{:<24} {:>6} {:>10.3} {:>10.3}",
+            summary.phase, summary.count, summary.p50, summary.p95
+        ));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record_line(phases: &[(&str, f64)]) -> String {
+        serde_json::to_string(&TraceRecord {
+            timestamp: "2025-01-15T10:00:00Z".parse().unwrap(),
+            phases: phases.iter().map(|(name, ms)| (name.to_string(), *ms)).collect(),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_append_capped_rotates() {
+        let dir = std::env::temp_dir().join(format!("cc-statusline-trace-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("trace.jsonl");
+        let _ = std::fs::remove_file(&path);
+
+        for i in 0..8 {
+            append_capped(&path, &i.to_string(), 5).unwrap();
+        }
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content, "3\n4\n5\n6\n7\n");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_percentile() {
+        let values: Vec<f64> = (1..=20).map(f64::from).collect();
+        assert_eq!(percentile(&values, 50.0), 10.0);
+        assert_eq!(percentile(&values, 95.0), 19.0);
+        assert_eq!(percentile(&[1.0, 2.0, 3.0, 4.0], 50.0), 2.0);
+        assert_eq!(percentile(&[1.0, 2.0, 3.0, 4.0], 95.0), 4.0);
+        assert_eq!(percentile(&[7.0], 95.0), 7.0);
+    }
+
+    #[test]
+    fn test_summarize() {
+        let mut content = String::new();
+        for ms in [5.0, 1.0, 3.0, 2.0, 4.0] {
+            content.push_str(&record_line(&[("parse", ms), ("git:branch", ms * 10.0)]));
+            content.push('\n');
+        }
+        content.push_str("not json\n");
+        content.push_str(&record_line(&[("provider:zhipu", 120.0)]));
+
+        let summaries = summarize(&content);
+        assert_eq!(
+            summaries,
+            vec![
+                PhaseSummary { phase: "git:branch".to_string(), count: 5, p50: 30.0, p95: 50.0 },
+                PhaseSummary { phase: "parse".to_string(), count: 5, p50: 3.0, p95: 5.0 },
+                PhaseSummary { phase: "provider:zhipu".to_string(), count: 1, p50: 120.0, p95: 120.0 },
+            ]
+        );
+        let table = format_summary(&summaries);
+        assert!(table.lines().next().unwrap().starts_with("phase"));
+        assert!(table.contains("parse                         5      3.000      5.000"));
+    }
+}
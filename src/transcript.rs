@@ -0,0 +1,308 @@
+use serde_json::Value;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// 只读取 transcript 末尾的字节数，避免大文件拖慢渲染
+const TAIL_BYTES: u64 = 256 * 1024;
+
+/// 末尾窗口内没有完整条目时（Claude Code 正在写入），等待后重读一次
+const RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// 统计消息数时解析整个 transcript 的时间上限，超时后只显示已统计的部分
+pub const COUNT_BUDGET: Duration = Duration::from_millis(50);
+
+/// 按角色统计的消息数
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MessageCounts {
+    pub user: usize,
+    pub assistant: usize,
+    /// 超过时间上限，只统计了前面的部分
+    pub partial: bool,
+}
+
+/// 路径是否为 gzip 压缩的 transcript（`.jsonl.gz`）
+fn is_gzip(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "gz")
+}
+
+/// 打开 transcript，`.gz` 文件边读边解压；未编译 `transcript-gzip` 时不读取压缩文件
+fn open(path: &Path) -> Option<Box<dyn Read>> {
+    let file = File::open(path).ok()?;
+    if !is_gzip(path) {
+        return Some(Box::new(file));
+    }
+    #[cfg(feature = "transcript-gzip")]
+    return Some(Box::new(flate2::read::MultiGzDecoder::new(file)));
+    #[cfg(not(feature = "transcript-gzip"))]
+    None
+}
+
+/// 读取文件末尾 `max_bytes` 字节，返回内容和是否从中间开始；压缩文件无法定位，解压全部后截取
+fn read_tail(path: &Path, max_bytes: u64) -> Option<(Vec<u8>, bool)> {
+    if is_gzip(path) {
+        let mut buf = Vec::new();
+        open(path)?.read_to_end(&mut buf).ok()?;
+        let start = buf.len().saturating_sub(max_bytes as usize);
+        buf.drain(..start);
+        return Some((buf, start > 0));
+    }
+    let mut file = File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    let start = len.saturating_sub(max_bytes);
+    file.seek(SeekFrom::Start(start)).ok()?;
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf).ok()?;
+    Some((buf, start > 0))
+}
+
+/// 读取 JSONL 文件末尾的若干行（文件读取完毕后立即关闭）
+pub fn read_tail_lines(path: &Path, max_bytes: u64) -> Option<Vec<String>> {
+    let buf = {
+        let (mut buf, truncated) = read_tail(path, max_bytes)?;
+        if truncated {
+            // 从文件中间开始读取时，第一行是不完整的
+            let first_newline = buf.iter().position(|&b| b == b'\n')?;
+            buf.drain(..=first_newline);
+        }
+        buf
+    };
+
+    let content = String::from_utf8_lossy(&buf);
+    Some(
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(String::from)
+            .collect(),
+    )
+}
+
+/// 读取 transcript 末尾并解析为 JSON 条目
+///
+/// 文件正在被追加写入，最后一行经常只写了一半：无法解析的行被当作不存在。
+/// 窗口内一条完整条目都没有时等待 `RETRY_DELAY` 后重读一次；文件不存在时直接返回空。
+pub fn read_tail_entries(path: &Path) -> Vec<Value> {
+    let Some(entries) = read_tail_lines(path, TAIL_BYTES).map(|lines| parse_entries(&lines)) else {
+        return Vec::new();
+    };
+    if !entries.is_empty() {
+        return entries;
+    }
+    std::thread::sleep(RETRY_DELAY);
+    read_tail_lines(path, TAIL_BYTES)
+        .map(|lines| parse_entries(&lines))
+        .unwrap_or_default()
+}
+
+/// 解析各行，跳过无法解析的行（包括写了一半的最后一行）
+fn parse_entries(lines: &[String]) -> Vec<Value> {
+    lines.iter().filter_map(|line| serde_json::from_str(line).ok()).collect()
+}
+
+/// 条目中 message.content 的各个块
+fn content_blocks(entry: &Value) -> impl Iterator<Item = &Value> {
+    entry
+        .pointer("/message/content")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+}
+
+/// 按 `role` 统计 transcript 中用户和助手的消息数
+///
+/// 角色取 `message.role`，没有时取顶层的 `role`；只包含工具结果的用户消息不是用户发言，不计入。
+/// 无法解析为 JSON 对象的行跳过；解析时间超过 `budget` 时停止并标记为部分结果。
+/// `.gz` 文件边读边解压。
+pub fn count_messages(path: &Path, budget: Duration) -> Option<MessageCounts> {
+    let file = open(path)?;
+    let start = Instant::now();
+    let mut counts = MessageCounts::default();
+    for line in BufReader::new(file).lines() {
+        if start.elapsed() >= budget {
+            counts.partial = true;
+            break;
+        }
+        let Ok(line) = line else {
+            continue;
+        };
+        let Ok(entry) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+        let role = entry
+            .pointer("/message/role")
+            .or_else(|| entry.get("role"))
+            .and_then(Value::as_str);
+        match role {
+            Some("user") if !only_tool_results(&entry) => counts.user += 1,
+            Some("assistant") => counts.assistant += 1,
+            _ => {}
+        }
+    }
+    Some(counts)
+}
+
+/// 条目的内容是否全部是工具结果
+fn only_tool_results(entry: &Value) -> bool {
+    let mut blocks = content_blocks(entry).peekable();
+    blocks.peek().is_some()
+        && blocks.all(|block| block.get("type").and_then(Value::as_str) == Some("tool_result"))
+}
+
+/// 查找最近一个尚未完成的 Task 调用的子代理类型
+pub fn active_subagent(entries: &[Value]) -> Option<String> {
+    let mut finished = std::collections::HashSet::new();
+
+    // 倒序扫描：先看到的 tool_result 表示对应的调用已完成
+    for entry in entries.iter().rev() {
+        for block in content_blocks(entry) {
+            match block.get("type").and_then(Value::as_str) {
+                Some("tool_result") => {
+                    if let Some(id) = block.get("tool_use_id").and_then(Value::as_str) {
+                        finished.insert(id);
+                    }
+                }
+                Some("tool_use") if block.get("name").and_then(Value::as_str) == Some("Task") => {
+                    let id = block.get("id").and_then(Value::as_str).unwrap_or_default();
+                    if finished.contains(id) {
+                        continue;
+                    }
+                    if let Some(subagent) = block.pointer("/input/subagent_type").and_then(Value::as_str) {
+                        return Some(subagent.to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn fixture(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join("transcripts")
+            .join(name)
+    }
+
+    #[test]
+    fn test_active_subagent_running() {
+        let entries = read_tail_entries(&fixture("task_running.jsonl"));
+        assert_eq!(active_subagent(&entries), Some("code-reviewer".to_string()));
+    }
+
+    #[test]
+    fn test_active_subagent_completed() {
+        let entries = read_tail_entries(&fixture("task_completed.jsonl"));
+        assert_eq!(active_subagent(&entries), None);
+    }
+
+    #[test]
+    fn test_active_subagent_no_tasks() {
+        let entries = read_tail_entries(&fixture("no_tasks.jsonl"));
+        assert!(!entries.is_empty());
+        assert_eq!(active_subagent(&entries), None);
+    }
+
+    /// 在临时目录写入内容，返回文件路径
+    fn temp_transcript(name: &str, content: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cc-statusline-transcript-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_read_tail_entries_partial_last_line() {
+        let complete = r#"{"type":"user","message":{"content":"hi"}}"#;
+        let path = temp_transcript("partial.jsonl", &format!("{complete}\n{{\"type\":\"assistant\",\"mess"));
+        let entries = read_tail_entries(&path);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0]["type"], "user");
+
+        // 只有半个对象时重试一次后仍然为空，不报错
+        let path = temp_transcript("only_partial.jsonl", r#"{"type":"assistant","message":{"con"#);
+        assert!(read_tail_entries(&path).is_empty());
+    }
+
+    #[test]
+    fn test_read_tail_entries_ends_at_newline() {
+        let content = "{\"type\":\"user\"}\n{\"type\":\"assistant\"}\n";
+        let path = temp_transcript("complete.jsonl", content);
+        let entries = read_tail_entries(&path);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1]["type"], "assistant");
+
+        assert!(read_tail_entries(&path.with_file_name("missing.jsonl")).is_empty());
+    }
+
+    #[test]
+    fn test_count_messages() {
+        let content = [
+            r#"{"type":"user","message":{"role":"user","content":"fix the bug"}}"#,
+            r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"ok"}]}}"#,
+            r#"{"type":"user","message":{"role":"user","content":[{"type":"tool_result"}]}}"#,
+            r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"tool_use"}]}}"#,
+            r#"{"role":"user","content":"top-level role"}"#,
+            r#"{"type":"summary","summary":"earlier work"}"#,
+            r#"["not", "an", "object"]"#,
+            "not json",
+            r#"{"type":"assistant","message":{"role":"assi"#,
+        ]
+        .join("\n");
+        let path = temp_transcript("roles.jsonl", &content);
+        let counts = count_messages(&path, COUNT_BUDGET).unwrap();
+        assert_eq!(counts, MessageCounts { user: 2, assistant: 2, partial: false });
+
+        // 超时后停止统计并标记为部分结果
+        let counts = count_messages(&path, Duration::ZERO).unwrap();
+        assert_eq!(counts, MessageCounts { user: 0, assistant: 0, partial: true });
+
+        assert_eq!(count_messages(&path.with_file_name("missing.jsonl"), COUNT_BUDGET), None);
+    }
+
+    #[cfg(feature = "transcript-gzip")]
+    #[test]
+    fn test_gzip_transcript() {
+        use std::io::Write;
+
+        let content = [
+            r#"{"type":"user","message":{"role":"user","content":"hi"}}"#,
+            r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"ok"}]}}"#,
+        ]
+        .join("\n");
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(content.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+        let dir = temp_transcript("plain.jsonl", &content).with_file_name("");
+        let path = dir.join("compressed.jsonl.gz");
+        std::fs::write(&path, compressed).unwrap();
+
+        let counts = count_messages(&path, COUNT_BUDGET).unwrap();
+        assert_eq!(counts, MessageCounts { user: 1, assistant: 1, partial: false });
+        let entries = read_tail_entries(&path);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1]["type"], "assistant");
+
+        // 末尾窗口小于解压后的内容时，丢弃不完整的第一行
+        assert_eq!(read_tail_lines(&path, content.len() as u64 - 5).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_read_tail_lines_skips_partial_first_line() {
+        let path = fixture("no_tasks.jsonl");
+        let len = std::fs::metadata(&path).unwrap().len();
+        let all = read_tail_lines(&path, len).unwrap();
+        let tail = read_tail_lines(&path, len - 5).unwrap();
+        assert_eq!(tail.len(), all.len() - 1);
+        assert_eq!(tail, all[1..]);
+    }
+}
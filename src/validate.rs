@@ -0,0 +1,256 @@
+use serde_json::Value;
+use std::fmt;
+
+use crate::StatusInput;
+
+/// 字段期望的 JSON 类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    String,
+    /// 任意数字
+    Number,
+    /// 非负整数（对应 `u64`）
+    Integer,
+    Bool,
+    Object,
+    /// 不检查类型，也不检查其中的字段
+    Any,
+}
+
+impl Kind {
+    fn matches(self, value: &Value) -> bool {
+        match self {
+            Kind::String => value.is_string(),
+            Kind::Number => value.is_number(),
+            Kind::Integer => value.is_u64(),
+            Kind::Bool => value.is_boolean(),
+            Kind::Object => value.is_object(),
+            Kind::Any => true,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Kind::String => "string",
+            Kind::Number => "number",
+            Kind::Integer => "non-negative integer",
+            Kind::Bool => "boolean",
+            Kind::Object => "object",
+            Kind::Any => "any",
+        }
+    }
+}
+
+/// [`StatusInput`] 的字段及类型，按路径排列，父对象排在子字段之前
+const FIELDS: &[(&str, Kind)] = &[
+    ("hook_event_name", Kind::String),
+    ("session_id", Kind::String),
+    ("transcript_path", Kind::String),
+    ("cwd", Kind::String),
+    ("version", Kind::String),
+    ("model", Kind::Object),
+    ("model.id", Kind::String),
+    ("model.display_name", Kind::String),
+    ("workspace", Kind::Object),
+    ("workspace.current_dir", Kind::String),
+    ("workspace.project_dir", Kind::String),
+    ("cost", Kind::Object),
+    ("cost.total_cost_usd", Kind::Number),
+    ("cost.total_duration_ms", Kind::Integer),
+    ("cost.total_api_duration_ms", Kind::Integer),
+    ("cost.total_lines_added", Kind::Integer),
+    ("cost.total_lines_removed", Kind::Integer),
+    ("context_window", Kind::Object),
+    ("context_window.total_input_tokens", Kind::Integer),
+    ("context_window.total_output_tokens", Kind::Integer),
+    ("context_window.context_window_size", Kind::Integer),
+    ("context_window.used_percentage", Kind::Number),
+    ("context_window.remaining_percentage", Kind::Number),
+    ("context_window.current_usage", Kind::Object),
+    ("context_window.current_usage.input_tokens", Kind::Integer),
+    ("context_window.current_usage.output_tokens", Kind::Integer),
+    ("context_window.current_usage.cache_creation_input_tokens", Kind::Integer),
+    ("context_window.current_usage.cache_read_input_tokens", Kind::Integer),
+    ("output_style", Kind::Object),
+    ("output_style.name", Kind::String),
+    ("errors", Kind::Object),
+    ("errors.count", Kind::Integer),
+    ("errors.last_message", Kind::String),
+    ("agent", Kind::Any),
+    ("exceeds_200k_tokens", Kind::Bool),
+    ("thinking", Kind::Any),
+];
+
+/// `--validate` 的检查结果
+#[derive(Debug, Default)]
+pub struct Report {
+    /// 存在且类型正确的字段
+    pub present: Vec<&'static str>,
+    /// 缺失或为 null 的字段（父对象缺失时只列出父对象）
+    pub missing: Vec<&'static str>,
+    /// 类型不符的字段，如 `cost.total_cost_usd: expected number, got string`
+    pub mismatches: Vec<String>,
+    /// 无法识别的字段，渲染时忽略
+    pub unknown: Vec<String>,
+    /// 无法解析为 JSON，或反序列化为 [`StatusInput`] 失败
+    pub error: Option<String>,
+}
+
+impl Report {
+    /// 输入能否正常渲染：反序列化成功且没有类型不符的字段
+    pub fn is_valid(&self) -> bool {
+        self.error.is_none() && self.mismatches.is_empty()
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// 按字段逐个检查输入；serde 对缺失字段很宽松，这里单独列出缺失和类型不符的字段
+pub fn validate(input: &str) -> Report {
+    let mut report = Report::default();
+    let value: Value = match serde_json::from_str(input) {
+        Ok(value) => value,
+        Err(err) => {
+            report.error = Some(format!("invalid JSON: {}", err));
+            return report;
+        }
+    };
+    if !value.is_object() {
+        report.error = Some(format!("expected a JSON object, got {}", type_name(&value)));
+        return report;
+    }
+
+    for &(path, kind) in FIELDS {
+        let (parent, key) = path.rsplit_once('.').unwrap_or(("", path));
+        // 父对象缺失或类型不符时已经报告过，不再列出其中的字段
+        let parent = if parent.is_empty() { Some(&value) } else { lookup(&value, parent) };
+        let Some(parent) = parent.filter(|parent| parent.is_object()) else {
+            continue;
+        };
+        match parent.get(key).filter(|field| !field.is_null()) {
+            None => report.missing.push(path),
+            Some(field) if kind.matches(field) => report.present.push(path),
+            Some(field) => report.mismatches.push(format!(
+                "{}: expected {}, got {}",
+                path,
+                kind.name(),
+                type_name(field)
+            )),
+        }
+    }
+
+    collect_unknown(&value, "", &mut report.unknown);
+    if let Err(err) = serde_json::from_str::<StatusInput>(input) {
+        report.error = Some(err.to_string());
+    }
+    report
+}
+
+/// 按 `a.b.c` 路径取值
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(value, |value, key| value.get(key))
+}
+
+/// 列出对象中不在 [`FIELDS`] 里的字段，只检查已知的对象
+fn collect_unknown(value: &Value, prefix: &str, unknown: &mut Vec<String>) {
+    let Some(object) = value.as_object() else {
+        return;
+    };
+    for (key, field) in object {
+        let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+        match FIELDS.iter().find(|(name, _)| *name == path) {
+            Some((_, Kind::Object)) => collect_unknown(field, &path, unknown),
+            Some(_) => {}
+            None => unknown.push(path),
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "valid: {}", if self.is_valid() { "yes" } else { "no" })?;
+        if let Some(ref error) = self.error {
+            writeln!(f, "error: {}", error)?;
+        }
+        for mismatch in &self.mismatches {
+            writeln!(f, "type mismatch: {}", mismatch)?;
+        }
+        writeln!(f, "present ({}): {}", self.present.len(), self.present.join(", "))?;
+        writeln!(f, "missing ({}): {}", self.missing.len(), self.missing.join(", "))?;
+        write!(f, "unknown, ignored ({}): {}", self.unknown.len(), self.unknown.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 收集序列化结果中所有字段的路径
+    fn paths(value: &Value, prefix: &str, out: &mut Vec<String>) {
+        for (key, field) in value.as_object().unwrap() {
+            let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+            if field.is_object() {
+                paths(field, &path, out);
+            }
+            out.push(path);
+        }
+    }
+
+    #[test]
+    fn test_fields_cover_status_input() {
+        let mut input = StatusInput::default();
+        input.context_window.current_usage = Some(Default::default());
+        let mut all = Vec::new();
+        paths(&serde_json::to_value(&input).unwrap(), "", &mut all);
+        for path in &all {
+            assert!(FIELDS.iter().any(|(name, _)| name == path), "{} missing from FIELDS", path);
+        }
+        assert_eq!(all.len(), FIELDS.len());
+    }
+
+    #[test]
+    fn test_validate() {
+        let report = validate(
+            r#"{"session_id": "abc", "cwd": null, "model": {"id": "opus", "extra": 1},
+                "cost": {"total_cost_usd": "1.5"}, "context_window": {"total_input_tokens": -1},
+                "future": true}"#,
+        );
+        assert!(!report.is_valid());
+        assert_eq!(
+            report.mismatches,
+            [
+                "cost.total_cost_usd: expected number, got string",
+                "context_window.total_input_tokens: expected non-negative integer, got number"
+            ]
+        );
+        assert!(report.present.contains(&"session_id"));
+        assert!(report.present.contains(&"model.id"));
+        // 父对象缺失时只列出父对象
+        assert!(report.missing.contains(&"cwd"));
+        assert!(report.missing.contains(&"workspace"));
+        assert!(!report.missing.contains(&"workspace.current_dir"));
+        assert_eq!(report.unknown, ["future", "model.extra"]);
+        assert!(report.error.is_some());
+    }
+
+    #[test]
+    fn test_validate_hard_failures() {
+        assert!(validate("{").error.unwrap().starts_with("invalid JSON"));
+        assert_eq!(validate("[]").error.unwrap(), "expected a JSON object, got array");
+        let report = validate(r#"{"model": "opus"}"#);
+        assert_eq!(report.mismatches, ["model: expected object, got string"]);
+
+        let report = validate(r#"{"model": {"display_name": "Opus"}}"#);
+        assert!(report.is_valid());
+        assert!(report.to_string().starts_with("valid: yes\npresent (2): model, model.display_name\n"));
+    }
+}
@@ -0,0 +1,673 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+fn fixture(name: &str) -> String {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// 在隔离的环境中运行二进制（不读取真实的 ~/.claude 配置）
+fn run(args: &[&str], stdin: &str) -> Output {
+    run_with_env(args, stdin, &[])
+}
+
+fn run_with_env(args: &[&str], stdin: &str, envs: &[(&str, &str)]) -> Output {
+    let home = std::env::temp_dir().join("cc-statusline-cli-home");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_cc-statusline"))
+        .args(args)
+        .env("HOME", &home)
+        .env("LANG", "en_US.UTF-8")
+        .env_remove("USERPROFILE")
+        .env_remove("CC_STATUSLINE_HOME")
+        .env_remove("CC_STATUSLINE_STATE_DIR")
+        .env_remove("NO_COLOR")
+        .env_remove("COLUMNS")
+        .env_remove("COLORTERM")
+        .env_remove("COLORFGBG")
+        .env_remove("CC_STATUSLINE_TRACE")
+        .env_remove("ANTHROPIC_BASE_URL")
+        .env_remove("ANTHROPIC_AUTH_TOKEN")
+        .env_remove("ANTHROPIC_API_KEY")
+        .envs(envs.iter().copied())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).trim_end().to_string()
+}
+
+#[test]
+fn renders_from_input_file() {
+    let output = run(&["--input", &fixture("basic.json"), "--no-color"], "");
+    assert_eq!(stdout(&output), "[Opus] │ ctx:42% │ in:15.2k │ cache:86%");
+}
+
+#[test]
+fn dash_reads_stdin() {
+    let payload = std::fs::read_to_string(fixture("basic.json")).unwrap();
+    let output = run(&["--input", "-", "--no-color"], &payload);
+    assert_eq!(stdout(&output), "[Opus] │ ctx:42% │ in:15.2k │ cache:86%");
+}
+
+#[test]
+fn prefix_and_suffix_wrap_the_line() {
+    let args = ["--input", &fixture("basic.json"), "--no-color"];
+    let output = run_with_env(&args, "", &[("STATUSLINE_PREFIX", "「"), ("STATUSLINE_SUFFIX", "」")]);
+    assert_eq!(stdout(&output), "「[Opus] │ ctx:42% │ in:15.2k │ cache:86%」");
+    // 转义的换行把 statusline 放到第二行
+    let output = run_with_env(&args, "", &[("STATUSLINE_PREFIX", r"\n")]);
+    assert_eq!(stdout(&output), "\n[Opus] │ ctx:42% │ in:15.2k │ cache:86%");
+    // max_len 截断时保留后缀
+    let envs = [("STATUSLINE_PREFIX", "「"), ("STATUSLINE_SUFFIX", "」"), ("STATUSLINE_MAX_LEN", "16")];
+    assert_eq!(stdout(&run_with_env(&args, "", &envs)), "「[Opus] │ ct…」");
+}
+
+#[test]
+fn color_is_kept_by_default() {
+    let output = run(&["--input", &fixture("basic.json")], "");
+    assert!(stdout(&output).contains("\x1b["));
+}
+
+#[test]
+fn threshold_colors_can_be_remapped() {
+    let payload = r#"{"context_window":{"used_percentage":90}}"#;
+    assert_eq!(stdout(&run(&[], payload)), "\x1b[31mctx:90%\x1b[0m");
+    let output = run_with_env(&[], payload, &[("STATUSLINE_COLORS__CRIT", "magenta")]);
+    assert_eq!(stdout(&output), "\x1b[35mctx:90%\x1b[0m");
+}
+
+#[test]
+fn colorblind_palette_adds_text_markers() {
+    let env = [("STATUSLINE_PALETTE", "colorblind")];
+    let output = run_with_env(&[], r#"{"context_window":{"used_percentage":90}}"#, &env);
+    assert_eq!(stdout(&output), "\x1b[38;5;166mctx:90%✗\x1b[0m");
+    let output = run_with_env(&["--no-color"], r#"{"context_window":{"used_percentage":65}}"#, &env);
+    assert_eq!(stdout(&output), "ctx:65%!");
+    let env = [("STATUSLINE_PALETTE", "colorblind"), ("COLORTERM", "truecolor")];
+    let output = run_with_env(&[], r#"{"context_window":{"used_percentage":30}}"#, &env);
+    assert_eq!(stdout(&output), "\x1b[38;2;0;114;178mctx:30%✓\x1b[0m");
+}
+
+#[cfg(feature = "provider-zhipu")]
+#[test]
+fn json_report_includes_provider_outcome() {
+    let payload = r#"{"model":{"display_name":"Opus"}}"#;
+    let env = [("ANTHROPIC_BASE_URL", "https://open.bigmodel.cn/api/anthropic"), ("ANTHROPIC_AUTH_TOKEN", "")];
+    let output = run_with_env(&["--json", "--no-color"], payload, &env);
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["line"], "[Opus]");
+    assert_eq!(report["provider"], serde_json::json!({"provider": "zhipu", "status": "no_credentials"}));
+
+    let env = [("ANTHROPIC_BASE_URL", "https://gateway.example.com"), ("ANTHROPIC_AUTH_TOKEN", "sk-test")];
+    let output = run_with_env(&["--json"], payload, &env);
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["provider"], serde_json::json!({"provider": null, "status": "not_matched"}));
+}
+
+#[cfg(all(feature = "provider-zhipu", feature = "provider-packycode"))]
+#[test]
+fn project_settings_select_provider_per_project() {
+    let root = std::env::temp_dir().join(format!("cc-statusline-projects-{}", std::process::id()));
+    let project = |name: &str, settings: &str| {
+        let dir = root.join(name);
+        std::fs::create_dir_all(dir.join(".claude")).unwrap();
+        std::fs::write(dir.join(".claude").join("settings.json"), settings).unwrap();
+        dir.to_string_lossy().into_owned()
+    };
+    let zhipu = project("zhipu", r#"{"baseURL": "https://open.bigmodel.cn/api/anthropic", "authToken": ""}"#);
+    // baseURL 是自建代理时，用 provider 固定数据源
+    let pinned = project("pinned", r#"{"baseURL": "https://proxy.example.com", "authToken": "", "provider": "packycode"}"#);
+
+    let env = [("ANTHROPIC_BASE_URL", "https://gateway.example.com"), ("ANTHROPIC_AUTH_TOKEN", "sk-test")];
+    for (dir, provider) in [(&zhipu, "zhipu"), (&pinned, "packycode")] {
+        let payload = serde_json::json!({"model": {"display_name": "Opus"}, "workspace": {"project_dir": dir}});
+        let output = run_with_env(&["--json"], &payload.to_string(), &env);
+        let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(report["provider"], serde_json::json!({"provider": provider, "status": "no_credentials"}));
+    }
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+/// 对每个连接返回 `status` 的本地 HTTP 服务
+#[cfg(feature = "provider-zhipu")]
+fn failing_server(status: u16) -> String {
+    use std::io::{BufRead, BufReader};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = format!("http://{}", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut reader = BufReader::new(stream.unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap_or(0) > 2 {
+                line.clear();
+            }
+            let response = format!("HTTP/1.1 {} X\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status);
+            let _ = reader.get_mut().write_all(response.as_bytes());
+        }
+    });
+    address
+}
+
+#[test]
+fn themes_previews_every_theme() {
+    let output = run_with_env(&["themes", "--no-color"], "", &[]);
+    assert!(output.status.success());
+    let gallery = stdout(&output);
+    let headers: Vec<&str> = gallery.lines().filter(|line| line.contains(r#"{"style": "#)).collect();
+    assert!(headers[0].starts_with("default  "), "{}", gallery);
+    assert!(headers.iter().any(|line| line.starts_with("powerline-colorblind  ")));
+    assert!(!gallery.contains('\x1b'));
+}
+
+#[cfg(all(feature = "provider-zhipu", feature = "provider-yunyi", feature = "provider-openai"))]
+#[test]
+fn doctor_diagnoses_each_provider() {
+    let address = failing_server(502);
+    let env = [
+        ("ANTHROPIC_BASE_URL", address.as_str()),
+        ("ANTHROPIC_AUTH_TOKEN", "sk-test"),
+        ("STATUSLINE_PROVIDERS__ZHIPU__DOMAINS", r#"["127.0.0.1"]"#),
+        ("STATUSLINE_PLAIN_HTTP_HOSTS", r#"["127.0.0.1"]"#),
+    ];
+    let output = run_with_env(&["doctor"], "", &env);
+    let report = stdout(&output);
+    let lines: Vec<&str> = report.lines().collect();
+    assert_eq!(lines[0], format!("base URL: {} (environment)", address));
+    assert_eq!(lines[1], "auth token: set (environment)");
+    assert!(lines.contains(&"zhipu: matched, but the request failed: HTTP 502"), "{}", report);
+    assert!(lines.contains(&"yunyi: not matched"), "{}", report);
+    assert!(lines.contains(&"openai: disabled in config"), "{}", report);
+
+    let output = run(&["doctor"], "");
+    assert!(stdout(&output).starts_with("base URL: not configured, using https://api.anthropic.com"));
+}
+
+#[cfg(feature = "provider-zhipu")]
+#[test]
+fn check_providers_reports_status_and_latency() {
+    let address = failing_server(502);
+    let env = [
+        ("ANTHROPIC_BASE_URL", address.as_str()),
+        ("ANTHROPIC_AUTH_TOKEN", "sk-test"),
+        ("STATUSLINE_PROVIDERS__ZHIPU__DOMAINS", r#"["127.0.0.1"]"#),
+        ("STATUSLINE_PLAIN_HTTP_HOSTS", r#"["127.0.0.1"]"#),
+    ];
+    let output = run_with_env(&["--check-providers"], "", &env);
+    assert_eq!(output.status.code(), Some(1));
+    let report = stdout(&output);
+    let lines: Vec<&str> = report.lines().collect();
+    assert_eq!(lines[0], format!("base URL: {}", address));
+    assert_eq!(lines.len(), 2, "{}", report);
+    let latency = lines[1].strip_prefix("zhipu: failed, HTTP 502, ").and_then(|rest| rest.strip_suffix("ms"));
+    assert!(latency.is_some_and(|ms| ms.parse::<u64>().is_ok()), "{}", report);
+
+    let env = [env[0], env[1]];
+    let output = run_with_env(&["--check-providers"], "", &env);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).ends_with("no provider matches the base URL"));
+}
+
+#[test]
+fn config_errors_are_reported_in_debug_and_doctor() {
+    let dir = std::env::temp_dir().join(format!("cc-statusline-broken-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("settings.json"), "{\"statusline\": ").unwrap();
+    let dir_str = dir.to_string_lossy().into_owned();
+    let envs = [("CC_STATUSLINE_HOME", dir_str.as_str()), ("STATUSLINE_CTX_DECIMALS", "many")];
+
+    // 渲染照常进行，只在调试模式下提示
+    let payload = r#"{"context_window":{"used_percentage":42}}"#;
+    let output = run_with_env(&["--no-color"], payload, &envs);
+    assert_eq!(stdout(&output), "ctx:42%");
+    assert!(output.stderr.is_empty());
+
+    let debug = [envs[0], envs[1], ("STATUSLINE_DEBUG", "true")];
+    let stderr = String::from_utf8(run_with_env(&["--no-color"], payload, &debug).stderr).unwrap();
+    assert!(stderr.contains("cc-statusline: invalid JSON in "), "{}", stderr);
+    assert!(stderr.contains("STATUSLINE_CTX_DECIMALS: invalid value \"many\""), "{}", stderr);
+
+    let report = stdout(&run_with_env(&["doctor"], "", &envs));
+    let config_lines: Vec<&str> = report.lines().filter(|line| line.starts_with("config: ")).collect();
+    assert_eq!(config_lines.len(), 2, "{}", report);
+    assert!(config_lines[0].starts_with("config: invalid JSON in "));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn dump_input_writes_pretty_json_to_stderr() {
+    let output = run(&["--input", &fixture("basic.json"), "--dump-input", "--no-color"], "");
+    let dumped: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(dumped["model"]["display_name"], "Opus");
+    assert_eq!(dumped["session_id"], "abc123");
+    // 缺失的字段也会以 null 形式列出，方便排查
+    assert!(dumped["workspace"]["current_dir"].is_null());
+}
+
+#[test]
+fn invalid_payload_reports_parse_error() {
+    let output = run(&["--input", &fixture("invalid.json")], "");
+    assert_eq!(output.status.code(), Some(2));
+    // 状态栏只得到空行，原因在 stderr
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "\n");
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("Error parsing JSON: "));
+
+    let output = run(&["--input", "/nonexistent/payload.json"], "");
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "\n");
+}
+
+#[test]
+fn osc_title_precedes_the_line_when_enabled() {
+    let payload = r#"{
+        "model": {"display_name": "Opus"},
+        "workspace": {"project_dir": "/work/myproject"},
+        "context_window": {"used_percentage": 91}
+    }"#;
+    let envs = [("STATUSLINE_OSC_TITLE", "true")];
+    let output = run_with_env(&[], payload, &envs);
+    assert_eq!(
+        stdout(&output),
+        "\x1b]0;⚠ ctx:91% — myproject\x07\x1b[1m\x1b[35m[Opus]\x1b[0m │ \x1b[31mctx:91%\x1b[0m"
+    );
+
+    // 未开启、关闭颜色或 tmux 格式时不输出 OSC 序列
+    assert!(!stdout(&run(&[], payload)).contains("\x1b]"));
+    let output = run_with_env(&["--no-color"], payload, &envs);
+    assert_eq!(stdout(&output), "[Opus] │ ctx:91%");
+    let no_color = [("STATUSLINE_OSC_TITLE", "true"), ("NO_COLOR", "1")];
+    assert!(!stdout(&run_with_env(&[], payload, &no_color)).contains("\x1b]"));
+    assert!(!stdout(&run_with_env(&["--format", "tmux"], payload, &envs)).contains("\x1b]"));
+}
+
+#[test]
+fn state_dir_redirects_state_files() {
+    let dir = std::env::temp_dir().join(format!("cc-statusline-state-dir-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let dir_str = dir.to_string_lossy().into_owned();
+    let envs = [("CC_STATUSLINE_STATE_DIR", dir_str.as_str()), ("STATUSLINE_MIN_INTERVAL_MS", "60000")];
+    let payload = r#"{"session_id":"redirected","context_window":{"used_percentage":42}}"#;
+    assert_eq!(stdout(&run_with_env(&["--no-color"], payload, &envs)), "ctx:42%");
+    assert!(dir.join("statusline_sessions").join("redirected.json").is_file());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn read_only_state_dir_disables_persistence() {
+    let dir = std::env::temp_dir().join(format!("cc-statusline-read-only-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let writable = std::fs::metadata(&dir).unwrap().permissions();
+    let mut read_only = writable.clone();
+    read_only.set_readonly(true);
+    std::fs::set_permissions(&dir, read_only).unwrap();
+    // root 不受权限限制，此时把状态目录放到普通文件下面，同样无法写入
+    let state = if std::fs::write(dir.join("probe"), "").is_ok() {
+        std::fs::write(dir.join("file"), "").unwrap();
+        dir.join("file").join("state")
+    } else {
+        dir.clone()
+    };
+    let state_str = state.to_string_lossy().into_owned();
+    let dir_str = dir.to_string_lossy().into_owned();
+    let envs = [
+        ("CC_STATUSLINE_STATE_DIR", state_str.as_str()),
+        ("STATUSLINE_DEBUG", "true"),
+        ("STATUSLINE_MIN_INTERVAL_MS", "60000"),
+        ("STATUSLINE_CTX_GROWTH", "true"),
+    ];
+    let payload = serde_json::json!({
+        "session_id": "read-only",
+        "model": {"display_name": "Opus"},
+        "workspace": {"current_dir": dir_str},
+        "cost": {"total_cost_usd": 1.5},
+        "context_window": {"used_percentage": 42}
+    })
+    .to_string();
+
+    // 每次渲染都照常输出（只是不复用上次的结果），并且只提示一次
+    for _ in 0..2 {
+        let output = run_with_env(&["--no-color"], &payload, &envs);
+        let name = dir.file_name().unwrap().to_string_lossy();
+        assert_eq!(stdout(&output), format!("[Opus] │ {} │ ctx:42%", name));
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert_eq!(stderr.lines().count(), 1, "{}", stderr);
+        assert!(stderr.contains("state will not be saved during this run"), "{}", stderr);
+    }
+
+    assert!(!state.join("statusline_sessions").exists());
+    std::fs::set_permissions(&dir, writable).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn config_error_exits_with_status_3() {
+    let output = run(&["--input", &fixture("basic.json"), "--no-color"], "");
+    assert_eq!(output.status.code(), Some(0));
+
+    // 无效的值被忽略，仍按默认值输出
+    let envs = [("STATUSLINE_CTX_DECIMALS", "many")];
+    let output = run_with_env(&["--input", &fixture("basic.json"), "--no-color"], "", &envs);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(stdout(&output).contains("ctx:42%"));
+}
+
+#[test]
+fn unknown_arguments_print_an_empty_line() {
+    for args in [&["--bogus"][..], &["--format", "html"]] {
+        let output = run(args, "");
+        assert_eq!(output.status.code(), Some(4));
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "\n");
+        assert!(!output.stderr.is_empty());
+    }
+}
+
+#[test]
+fn validate_reports_fields_and_exit_status() {
+    let output = run(&["--validate", "--input", &fixture("basic.json")], "");
+    assert!(output.status.success());
+    assert!(stdout(&output).starts_with("valid: yes\n"));
+    assert!(stdout(&output).contains("model.display_name"));
+
+    let output = run(&["--validate"], r#"{"cost": {"total_cost_usd": "1.5"}}"#);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stdout(&output).contains("type mismatch: cost.total_cost_usd: expected number, got string"));
+}
+
+#[test]
+fn tmux_format_uses_style_directives() {
+    let output = run(&["--input", &fixture("basic.json"), "--format=tmux"], "");
+    let line = stdout(&output);
+    assert!(line.starts_with("#[bold]#[fg=magenta][Opus]#[default,none]"));
+    assert!(!line.contains('\x1b'));
+}
+
+#[test]
+fn thinking_and_200k_badges_follow_the_model() {
+    let output = run(&["--input", &fixture("thinking.json"), "--no-color"], "");
+    assert_eq!(stdout(&output), "[Opus] │ think │ >200k │ ctx:42%");
+    let output = run(&["--input", &fixture("thinking.json")], "");
+    assert!(stdout(&output).contains("\x1b[35mthink\x1b[0m │ \x1b[33m>200k\x1b[0m"), "{}", stdout(&output));
+    // 没有新字段的输入不显示标记
+    let output = run(&["--input", &fixture("basic.json"), "--no-color"], "");
+    assert!(!stdout(&output).contains("think") && !stdout(&output).contains(">200k"));
+}
+
+#[test]
+fn running_task_renders_agent_segment() {
+    let output = run(&["--input", &fixture("agent.json"), "--no-color"], "");
+    assert_eq!(stdout(&output), "[Opus] │ agent:code-reviewer");
+}
+
+#[test]
+fn powerline_without_color_uses_pipe_separators() {
+    let output = run_with_env(
+        &["--input", &fixture("basic.json"), "--no-color"],
+        "",
+        &[("STATUSLINE_STYLE", "powerline")],
+    );
+    assert_eq!(stdout(&output), "[Opus] | ctx:42% | in:15.2k | cache:86%");
+}
+
+#[test]
+fn max_len_hard_truncates_the_line() {
+    let envs = [("STATUSLINE_MAX_LEN", "16")];
+    let output = run_with_env(&["--input", &fixture("basic.json"), "--no-color"], "", &envs);
+    assert_eq!(stdout(&output), "[Opus] │ ctx:42…");
+
+    let output = run_with_env(&["--input", &fixture("basic.json")], "", &envs);
+    assert!(stdout(&output).ends_with("…\x1b[0m"));
+}
+
+#[test]
+fn segments_env_selects_and_orders_segments() {
+    let envs = [("STATUSLINE_SEGMENTS", "ctx,model")];
+    let output = run_with_env(&["--input", &fixture("basic.json"), "--no-color"], "", &envs);
+    assert_eq!(stdout(&output), "ctx:42% │ [Opus]");
+}
+
+#[test]
+fn min_interval_reuses_last_output_per_session() {
+    let envs = [("STATUSLINE_MIN_INTERVAL_MS", "60000")];
+    let session = format!("interval-{}", std::process::id());
+    let sessions = std::env::temp_dir().join("cc-statusline-cli-home/.claude/statusline/statusline_sessions");
+    let _ = std::fs::remove_file(sessions.join(format!("{}.json", session)));
+    let _ = std::fs::remove_file(sessions.join(format!("{}-b.json", session)));
+    let payload = |pct: u32, session: &str| {
+        format!(r#"{{"session_id":"{}","context_window":{{"used_percentage":{}}}}}"#, session, pct)
+    };
+
+    let first = run_with_env(&["--no-color"], &payload(42, &session), &envs);
+    assert_eq!(stdout(&first), "ctx:42%");
+    let second = run_with_env(&["--no-color"], &payload(43, &session), &envs);
+    assert_eq!(stdout(&second), "ctx:42%");
+
+    // 其他会话不受影响
+    let other = run_with_env(&["--no-color"], &payload(43, &format!("{}-b", session)), &envs);
+    assert_eq!(stdout(&other), "ctx:43%");
+}
+
+#[test]
+fn ctx_growth_tracks_totals_per_session() {
+    let envs = [("STATUSLINE_CTX_GROWTH", "true")];
+    let session = format!("growth-{}", std::process::id());
+    let sessions = std::env::temp_dir().join("cc-statusline-cli-home/.claude/statusline/statusline_sessions");
+    let _ = std::fs::remove_file(sessions.join(format!("{}.json", session)));
+    let payload = |total_in: u64| {
+        let context = format!(
+            r#"{{"used_percentage":50,"total_input_tokens":{},"total_output_tokens":1000}}"#,
+            total_in
+        );
+        format!(r#"{{"session_id":"{}","context_window":{}}}"#, session, context)
+    };
+
+    // 没有 current_usage，token 段显示会话累计值
+    let render = |total_in| stdout(&run_with_env(&["--no-color"], &payload(total_in), &envs));
+    assert_eq!(render(90_000), "ctx:50% │ Σ in:90.0k out:1.0k");
+    assert_eq!(render(94_500), "ctx:50% (+4.5k) │ Σ in:94.5k out:1.0k");
+    // 压缩后不显示增长量
+    assert_eq!(render(20_000), "ctx:50% │ Σ in:20.0k out:1.0k");
+}
+
+#[test]
+fn heartbeat_alternates_when_output_changes() {
+    let envs = [("STATUSLINE_HEARTBEAT", "true")];
+    let session = format!("heartbeat-{}", std::process::id());
+    let sessions = std::env::temp_dir().join("cc-statusline-cli-home/.claude/statusline/statusline_sessions");
+    let _ = std::fs::remove_file(sessions.join(format!("{}.json", session)));
+    let payload = |pct: u32| {
+        format!(r#"{{"session_id":"{}","context_window":{{"used_percentage":{}}}}}"#, session, pct)
+    };
+
+    assert_eq!(stdout(&run_with_env(&["--no-color"], &payload(50), &envs)), "ctx:50% │ ·");
+    assert_eq!(stdout(&run_with_env(&["--no-color"], &payload(51), &envs)), "ctx:51% │ ∘");
+    // 输出没有变化时心跳不前进
+    assert_eq!(stdout(&run_with_env(&["--no-color"], &payload(51), &envs)), "ctx:51% │ ∘");
+    assert_eq!(stdout(&run_with_env(&["--no-color"], &payload(52), &envs)), "ctx:52% │ ·");
+
+    let state: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(sessions.join(format!("{}.json", session))).unwrap())
+            .unwrap();
+    assert_eq!(state["renders"], 3);
+    assert!(state["rendered_at"].is_string());
+}
+
+#[test]
+fn history_is_appended_only_when_cost_changes() {
+    let dir = std::env::temp_dir().join(format!("cc-statusline-history-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let dir_str = dir.to_string_lossy().into_owned();
+    let envs = [("CC_STATUSLINE_HOME", dir_str.as_str()), ("STATUSLINE_WEEK_SPEND", "true")];
+    let payload = |cost: f64| format!(r#"{{"session_id":"history","cost":{{"total_cost_usd":{}}}}}"#, cost);
+
+    assert!(stdout(&run_with_env(&["--no-color"], &payload(1.25), &envs)).ends_with("wk:$1.25"));
+    assert!(stdout(&run_with_env(&["--no-color"], &payload(1.25), &envs)).ends_with("wk:$1.25"));
+    let history = dir.join("statusline").join("statusline_history.jsonl");
+    let lines = || std::fs::read_to_string(&history).unwrap().lines().count();
+    assert_eq!(lines(), 1);
+
+    assert!(stdout(&run_with_env(&["--no-color"], &payload(2.5), &envs)).ends_with("wk:$2.50"));
+    assert_eq!(lines(), 2);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn report_summarizes_history_per_project() {
+    let dir = std::env::temp_dir().join(format!("cc-statusline-report-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let dir_str = dir.to_string_lossy().into_owned();
+    let envs = [("CC_STATUSLINE_HOME", dir_str.as_str()), ("STATUSLINE_WEEK_SPEND", "true")];
+    let payload = |session: &str, project: &str, cost: f64| {
+        serde_json::json!({
+            "session_id": session,
+            "workspace": {"project_dir": project},
+            "cost": {"total_cost_usd": cost, "total_duration_ms": 600_000},
+            "context_window": {"total_input_tokens": 9_000, "total_output_tokens": 1_000}
+        })
+        .to_string()
+    };
+    run_with_env(&["--no-color"], &payload("a", "/work/api", 1.0), &envs);
+    run_with_env(&["--no-color"], &payload("a", "/work/api", 2.5), &envs);
+    run_with_env(&["--no-color"], &payload("b", "/work/web", 0.5), &envs);
+
+    let output = run_with_env(&["report", "--days", "7", "--format", "csv"], "", &envs);
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        [
+            "project,cost_usd,total_tokens,sessions,avg_duration_ms",
+            "/work/api,2.50,10000,1,600000",
+            "/work/web,0.50,10000,1,600000",
+        ]
+        .join("\n")
+    );
+    let table = stdout(&run_with_env(&["report"], "", &envs));
+    assert!(table.starts_with("project"));
+    assert!(table.contains("/work/api      $2.50"), "{}", table);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn debug_log_is_skipped_when_output_is_unchanged() {
+    let dir = std::env::temp_dir().join(format!("cc-statusline-debug-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let dir_str = dir.to_string_lossy().into_owned();
+    let envs = [
+        ("CC_STATUSLINE_HOME", dir_str.as_str()),
+        ("STATUSLINE_WEEK_SPEND", "true"),
+        ("STATUSLINE_DEBUG", "true"),
+    ];
+    // 负数成本在汇总花费时会产生一条警告
+    let payload = r#"{"session_id":"debug","model":{"display_name":"Opus"},"cost":{"total_cost_usd":-1}}"#;
+    let stderr = |output: Output| String::from_utf8_lossy(&output.stderr).into_owned();
+
+    assert!(stderr(run_with_env(&[], payload, &envs)).contains("invalid cost"));
+    let second = run_with_env(&[], payload, &envs);
+    assert!(stdout(&second).contains("wk:$0.00"));
+    assert_eq!(stderr(second), "");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn statusline_home_override_is_created_on_demand() {
+    let dir = std::env::temp_dir().join(format!("cc-statusline-home-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let dir_str = dir.to_string_lossy().into_owned();
+    let envs = [("CC_STATUSLINE_HOME", dir_str.as_str()), ("STATUSLINE_WEEK_SPEND", "true")];
+
+    let payload = r#"{"session_id":"home","cost":{"total_cost_usd":1.25}}"#;
+    let output = run_with_env(&["--no-color"], payload, &envs);
+    assert!(stdout(&output).ends_with("wk:$1.25"), "{}", stdout(&output));
+    // 历史记录写入覆盖目录，而不是 HOME 下的 .claude
+    assert!(dir.join("statusline").join("statusline_history.jsonl").is_file());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn clear_cache_removes_provider_caches() {
+    let dir = std::env::temp_dir().join(format!("cc-statusline-clear-cli-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    // 旧版本放在 .claude 下的缓存先迁移到 statusline 子目录，再被删除
+    for name in [".zhipu_cache.json", ".yunyi_cache.0123456789abcdef.json", "settings.json"] {
+        std::fs::write(dir.join(name), "{}").unwrap();
+    }
+    let dir_str = dir.to_string_lossy().into_owned();
+    let envs = [("CC_STATUSLINE_HOME", dir_str.as_str())];
+
+    let output = run_with_env(&["--clear-cache"], "", &envs);
+    let canonical = std::fs::canonicalize(&dir).unwrap();
+    let state_dir = canonical.join("statusline");
+    assert_eq!(stdout(&output), format!("removed 2 cache file(s) from {}", state_dir.display()));
+    assert!(!dir.join(".zhipu_cache.json").exists());
+    assert!(!state_dir.join(".zhipu_cache.json").exists());
+    assert!(dir.join("settings.json").is_file());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn metrics_log_appends_one_line_per_render() {
+    let path = std::env::temp_dir().join(format!("cc-statusline-metrics-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let path_str = path.to_string_lossy().into_owned();
+    let envs = [("STATUSLINE_LOG", path_str.as_str())];
+
+    let first = run_with_env(&["--input", &fixture("basic.json"), "--no-color"], "", &envs);
+    // 不影响 stdout
+    assert_eq!(stdout(&first), "[Opus] │ ctx:42% │ in:15.2k │ cache:86%");
+    run_with_env(&["--input", &fixture("basic.json"), "--no-color"], "", &envs);
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    let lines: Vec<serde_json::Value> =
+        content.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["session_id"], "abc123");
+    assert_eq!(lines[0]["model"], "claude-opus-4-1");
+    assert_eq!(lines[0]["context_pct"], 42.5);
+    assert!(lines[0]["timestamp"].is_string());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn trace_records_phases_and_summarizes() {
+    let dir = std::env::temp_dir().join(format!("cc-statusline-trace-cli-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let dir_str = dir.to_string_lossy().into_owned();
+    let home = [("CC_STATUSLINE_HOME", dir_str.as_str())];
+
+    let output = run_with_env(&["trace", "summarize"], "", &home);
+    assert!(stdout(&output).starts_with("no trace records in "));
+    // 未开启时不写入
+    run_with_env(&["--input", &fixture("basic.json"), "--no-color"], "", &home);
+    let trace = dir.join("statusline").join("statusline_trace.jsonl");
+    assert!(!trace.exists());
+
+    let envs = [home[0], ("CC_STATUSLINE_TRACE", "1")];
+    let first = run_with_env(&["--input", &fixture("basic.json"), "--no-color"], "", &envs);
+    assert_eq!(stdout(&first), "[Opus] │ ctx:42% │ in:15.2k │ cache:86%");
+    run_with_env(&["--input", &fixture("basic.json"), "--no-color"], "", &envs);
+
+    let content = std::fs::read_to_string(&trace).unwrap();
+    let lines: Vec<serde_json::Value> =
+        content.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(lines.len(), 2);
+    for phase in ["stdin", "parse", "config", "render", "format"] {
+        assert!(lines[0]["phases"][phase].is_number(), "missing phase {}", phase);
+    }
+
+    let summary = stdout(&run_with_env(&["trace", "summarize"], "", &home));
+    assert!(summary.starts_with("phase"));
+    assert!(summary.lines().any(|line| line.starts_with("parse") && line.contains(" 2 ")));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,3 @@
+#!/bin/sh
+echo "should not show"
+exit 3
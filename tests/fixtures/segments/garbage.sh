@@ -0,0 +1,4 @@
+#!/bin/sh
+# 带颜色、换行、控制字符和超长文本的输出
+printf '\033[31mred\033[0m\tline1\nline2\r\n\001{not json'
+head -c 300 /dev/zero | tr '\0' 'x'
@@ -0,0 +1,4 @@
+#!/bin/sh
+# 从 stdin 的 StatusInput 中取出 session_id，输出 JSON 格式的段
+session=$(sed -n 's/.*"session_id":"\([^"]*\)".*/\1/p')
+printf '{"text": "PROJ-42 %s", "severity": "warn"}\n' "$session"